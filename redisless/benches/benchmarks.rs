@@ -9,7 +9,7 @@ use redisless::storage::in_memory::InMemoryStorage;
 fn criterion_benchmarks(c: &mut Criterion) {
     let port = 3335;
     let server = Server::new(InMemoryStorage::new(), port);
-    assert_eq!(server.start(), Some(ServerState::Started));
+    assert_eq!(server.start(), Ok(ServerState::Started));
 
     let mut stream = TcpStream::connect(format!("localhost:{}", port)).unwrap();
 
@@ -35,7 +35,7 @@ fn criterion_benchmarks(c: &mut Criterion) {
         });
     });
 
-    assert_eq!(server.stop(), Some(ServerState::Stopped));
+    assert_eq!(server.stop(), Ok(ServerState::Stopped));
 }
 
 criterion_group!(benches, criterion_benchmarks);
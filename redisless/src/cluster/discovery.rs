@@ -0,0 +1,86 @@
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Result, Write};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+
+use uuid::Uuid;
+
+use crate::cluster::peer::{Peer, Peers, PeersDiscovery};
+
+/// Peers discovery through a file shared by all the instances running on the same host
+/// (possibly in different processes). Each instance registers itself by appending a
+/// `<node_id> <socket_addr>` line, and discovers the others by reading the whole file.
+#[derive(Debug, Clone)]
+pub struct FileDiscovery {
+    path: PathBuf,
+}
+
+impl FileDiscovery {
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        FileDiscovery { path: path.into() }
+    }
+
+    /// make the node `node_id` listening on `socket_addr` discoverable by the other instances
+    pub fn register(&self, node_id: &str, socket_addr: SocketAddr) -> Result<()> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+
+        // a single small write in append mode is atomic - concurrent instances can't interleave
+        file.write_all(format!("{} {}\n", node_id, socket_addr).as_bytes())
+    }
+
+    /// remove the node `node_id` from the shared file, which is replaced at once so that the other
+    /// instances never read it partially written
+    pub fn unregister(&self, node_id: &str) -> Result<()> {
+        let content = match fs::read_to_string(&self.path) {
+            Ok(content) => content,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(()),
+            Err(err) => return Err(err),
+        };
+
+        let content = content
+            .lines()
+            .filter(|line| line.split(' ').next() != Some(node_id))
+            .fold(String::new(), |mut content, line| {
+                content.push_str(line);
+                content.push('\n');
+                content
+            });
+
+        // the temporary file is in the same directory, renaming it does not copy anything
+        let mut temp_path = self.path.clone().into_os_string();
+        temp_path.push(format!(".{}.tmp", Uuid::new_v4()));
+
+        if let Err(err) = fs::write(&temp_path, content) {
+            let _ = fs::remove_file(&temp_path);
+            return Err(err);
+        }
+
+        fs::rename(&temp_path, &self.path)
+    }
+
+    /// return all the registered peers - malformed lines are ignored
+    pub fn peers(&self) -> Peers {
+        let content = match fs::read_to_string(&self.path) {
+            Ok(content) => content,
+            Err(_) => return vec![],
+        };
+
+        content
+            .lines()
+            .filter_map(|line| {
+                let mut parts = line.split(' ');
+                let node_id = parts.next()?;
+                let socket_addr = parts.next()?.parse::<SocketAddr>().ok()?;
+
+                Some(Peer::new(
+                    node_id,
+                    PeersDiscovery::File(self.path.clone()),
+                    socket_addr,
+                ))
+            })
+            .collect()
+    }
+}
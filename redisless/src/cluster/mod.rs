@@ -1,4 +1,6 @@
+pub mod discovery;
 mod node;
 pub mod peer;
+#[cfg(test)]
 mod tests;
 mod util;
@@ -1,3 +1,4 @@
+use std::io::Result;
 use std::net::SocketAddr;
 
 use rand::rngs::OsRng;
@@ -5,11 +6,9 @@ use rand::rngs::OsRng;
 use raft::log::memory::InMemoryLog;
 use raft::node::Node;
 
-use crate::cluster::peer::{Peer, Peers, PeersDiscovery, DEFAULT_NODE_LISTENING_PORT};
-use crate::cluster::util::{get_ip_addresses, get_local_network_ip_addresses, scan_ip_range};
+use crate::cluster::discovery::FileDiscovery;
+use crate::cluster::peer::{Peer, PeersDiscovery};
 use crossbeam_channel::{unbounded, Receiver, Sender};
-use std::borrow::Borrow;
-use std::collections::{HashSet, LinkedList};
 use std::thread;
 use std::time::Duration;
 
@@ -19,9 +18,11 @@ pub const GETINFO_RESPONSE: &[u8; 9] = b"redisless";
 
 type RaftNode = Node<InMemoryLog, OsRng, String>;
 
+#[allow(dead_code)]
 pub struct ClusterNode {
     node: RaftNode,
     listening_socket_addr: SocketAddr,
+    peers_discovery: PeersDiscovery,
     peer_receiver: Receiver<Peer>,
     listener_started: bool,
     search_peers_started: bool,
@@ -38,6 +39,7 @@ impl ClusterNode {
         let mut cn = ClusterNode {
             node,
             listening_socket_addr,
+            peers_discovery: peers_discovery.clone(),
             peer_receiver: rx,
             listener_started: false,
             search_peers_started: false,
@@ -54,9 +56,9 @@ impl ClusterNode {
             return;
         }
 
-        let _ = match peers_discovery {
+        match &peers_discovery {
             PeersDiscovery::Manual(_) => return, // in this case - search peers is not useful
-            PeersDiscovery::Automatic(_) => {}
+            PeersDiscovery::Automatic(_) | PeersDiscovery::File(_) => {}
         };
 
         let _ = thread::spawn(move || {
@@ -78,24 +80,37 @@ impl ClusterNode {
     }
 
     // start TCP socket listener to handle incoming message from peers
-    pub fn start_listener(&mut self) {
+    pub fn start_listener(&mut self) -> Result<()> {
         if self.listener_started {
-            return;
+            return Ok(());
+        }
+
+        if let PeersDiscovery::File(path) = &self.peers_discovery {
+            // make the current node discoverable by the other instances sharing the file
+            FileDiscovery::new(path.clone())
+                .register(self.node.node_id(), self.listening_socket_addr)?;
         }
 
         self.listener_started = true;
 
         // TODO
+        Ok(())
     }
 
     // stop TCP socket listener to handle incoming message from peers
-    pub fn stop_listener(&mut self) {
+    pub fn stop_listener(&mut self) -> Result<()> {
         if !self.listener_started {
-            return;
+            return Ok(());
         }
 
         self.listener_started = false;
 
+        if let PeersDiscovery::File(path) = &self.peers_discovery {
+            // the other instances must not try to reach the current node anymore
+            FileDiscovery::new(path.clone()).unregister(self.node.node_id())?;
+        }
+
         // TODO
+        Ok(())
     }
 }
@@ -1,3 +1,4 @@
+use crate::cluster::discovery::FileDiscovery;
 use crate::cluster::node::ClusterNode;
 use crate::cluster::util::{get_ip_addresses, get_local_network_ip_addresses, scan_ip_range};
 use raft::log::memory::InMemoryLog;
@@ -5,6 +6,7 @@ use raft::node::{Config, Node};
 use rand::rngs::OsRng;
 use std::collections::BTreeSet;
 use std::net::SocketAddr;
+use std::path::PathBuf;
 
 pub const DEFAULT_NODE_LISTENING_PORT: u16 = 8686;
 
@@ -36,13 +38,21 @@ impl Peer {
         }
     }
 
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn listening_socket_addr(&self) -> SocketAddr {
+        self.listening_socket_addr
+    }
+
     pub fn into_cluster_node(self) -> ClusterNode {
         ClusterNode::new(
             Node::new(
                 self.id,
                 BTreeSet::new(),
                 InMemoryLog::new_unbounded(),
-                OsRng::default(),
+                OsRng,
                 CONFIG,
             ),
             self.peers_discovery,
@@ -59,6 +69,8 @@ pub enum PeersDiscovery {
     Manual(Peers),
     // search peers in the same local network
     Automatic(ListeningPort),
+    // peers register themselves in a file shared by all the instances of the same host
+    File(PathBuf),
 }
 
 impl PeersDiscovery {
@@ -66,6 +78,7 @@ impl PeersDiscovery {
         match self {
             PeersDiscovery::Manual(peers) => peers.clone(),
             PeersDiscovery::Automatic(listening_port) => search_peers(*listening_port),
+            PeersDiscovery::File(path) => FileDiscovery::new(path.clone()).peers(),
         }
     }
 }
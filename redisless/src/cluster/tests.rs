@@ -1,5 +1,6 @@
 use std::net::{IpAddr, Ipv4Addr, SocketAddr, SocketAddrV4};
 
+use crate::cluster::discovery::FileDiscovery;
 use crate::cluster::node::ClusterNode;
 use crate::cluster::peer::{Peer, PeersDiscovery, DEFAULT_NODE_LISTENING_PORT};
 use crate::cluster::util::{
@@ -15,7 +16,7 @@ fn start_cluster() {
 #[test]
 fn list_ip_addresses() {
     let ip_addresses = get_ip_addresses();
-    assert!(ip_addresses.len() > 0);
+    assert!(!ip_addresses.is_empty());
 }
 
 #[test]
@@ -119,15 +120,42 @@ fn test_scan_ip_range_with_4_peers() {
 
     // start nodes
     for node in nodes.iter_mut() {
-        node.start_listener();
+        node.start_listener().unwrap();
     }
 
     //assert_eq!(opened_sockets.len(), 4);
 
     // stop nodes
     for node in nodes.iter_mut() {
-        node.stop_listener();
+        node.stop_listener().unwrap();
     }
 
     //assert_eq!(opened_sockets.len(), 0);
 }
+
+#[test]
+fn file_discovery() {
+    let path = std::env::temp_dir().join(format!("redisless-peers-{}", uuid::Uuid::new_v4()));
+    let discovery = FileDiscovery::new(path.clone());
+
+    assert_eq!(discovery.peers().len(), 0);
+
+    let socket_addr_1 = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 8686));
+    let socket_addr_2 = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 8687));
+    discovery.register("node-1", socket_addr_1).unwrap();
+    discovery.register("node-2", socket_addr_2).unwrap();
+
+    // another instance sharing the same file sees both peers
+    let peers = FileDiscovery::new(path.clone()).peers();
+    assert_eq!(peers.len(), 2);
+    assert_eq!(peers[0].id(), "node-1");
+    assert_eq!(peers[0].listening_socket_addr(), socket_addr_1);
+    assert_eq!(peers[1].id(), "node-2");
+
+    discovery.unregister("node-1").unwrap();
+    let peers = PeersDiscovery::File(path.clone()).peers();
+    assert_eq!(peers.len(), 1);
+    assert_eq!(peers[0].id(), "node-2");
+
+    let _ = std::fs::remove_file(path);
+}
@@ -0,0 +1,235 @@
+/// ACL categories, in the order listed by Redis
+pub const CATEGORIES: [&str; 21] = [
+    "keyspace",
+    "read",
+    "write",
+    "set",
+    "sortedset",
+    "list",
+    "hash",
+    "string",
+    "bitmap",
+    "hyperloglog",
+    "geo",
+    "stream",
+    "pubsub",
+    "admin",
+    "fast",
+    "slow",
+    "blocking",
+    "dangerous",
+    "connection",
+    "transaction",
+    "scripting",
+];
+
+/// lowercase names of the supported commands along with their ACL categories
+const COMMAND_CATEGORIES: &[(&str, &[&str])] = &[
+    ("acl", &["slow"]),
+    ("append", &["write", "string", "fast"]),
+    ("auth", &["fast", "connection"]),
+    ("bitcount", &["read", "bitmap", "slow"]),
+    ("bitfield", &["write", "bitmap", "slow"]),
+    ("bitop", &["write", "bitmap", "slow"]),
+    ("bitpos", &["read", "bitmap", "slow"]),
+    ("blmove", &["write", "list", "slow", "blocking"]),
+    ("blmpop", &["write", "list", "slow", "blocking"]),
+    ("blpop", &["write", "list", "slow", "blocking"]),
+    ("brpop", &["write", "list", "slow", "blocking"]),
+    ("brpoplpush", &["write", "list", "slow", "blocking"]),
+    ("bzmpop", &["write", "sortedset", "slow", "blocking"]),
+    ("bzpopmax", &["write", "sortedset", "fast", "blocking"]),
+    ("bzpopmin", &["write", "sortedset", "fast", "blocking"]),
+    ("client", &["admin", "slow", "dangerous", "connection"]),
+    ("config", &["admin", "slow", "dangerous"]),
+    ("copy", &["keyspace", "write", "slow"]),
+    ("dbsize", &["keyspace", "read", "fast"]),
+    ("del", &["keyspace", "write", "slow"]),
+    ("discard", &["fast", "transaction"]),
+    ("dump", &["keyspace", "read", "slow"]),
+    ("echo", &["fast", "connection"]),
+    ("eval", &["slow", "scripting"]),
+    ("evalsha", &["slow", "scripting"]),
+    ("exec", &["slow", "transaction"]),
+    ("exists", &["keyspace", "read", "fast"]),
+    ("expire", &["keyspace", "write", "fast"]),
+    ("fcall", &["slow", "scripting"]),
+    ("fcall_ro", &["slow", "scripting"]),
+    ("flushall", &["keyspace", "write", "slow", "dangerous"]),
+    ("flushdb", &["keyspace", "write", "slow", "dangerous"]),
+    ("geoadd", &["write", "geo", "slow"]),
+    ("geodist", &["read", "geo", "slow"]),
+    ("geohash", &["read", "geo", "slow"]),
+    ("geopos", &["read", "geo", "slow"]),
+    ("geosearch", &["read", "geo", "slow"]),
+    ("geosearchstore", &["write", "geo", "slow"]),
+    ("function", &["slow", "scripting"]),
+    ("get", &["read", "string", "fast"]),
+    ("getbit", &["read", "bitmap", "fast"]),
+    ("getset", &["write", "string", "fast"]),
+    ("hdel", &["write", "hash", "fast"]),
+    ("hexists", &["read", "hash", "fast"]),
+    ("hget", &["read", "hash", "fast"]),
+    ("hgetall", &["read", "hash", "slow"]),
+    ("hincrby", &["write", "hash", "fast"]),
+    ("hincrbyfloat", &["write", "hash", "fast"]),
+    ("hkeys", &["read", "hash", "slow"]),
+    ("hlen", &["read", "hash", "fast"]),
+    ("hmget", &["read", "hash", "fast"]),
+    ("hmset", &["write", "hash", "fast"]),
+    ("hrandfield", &["read", "hash", "slow"]),
+    ("hscan", &["read", "hash", "slow"]),
+    ("hset", &["write", "hash", "fast"]),
+    ("hsetnx", &["write", "hash", "fast"]),
+    ("hstrlen", &["read", "hash", "fast"]),
+    ("hvals", &["read", "hash", "slow"]),
+    ("incr", &["write", "string", "fast"]),
+    ("incrby", &["write", "string", "fast"]),
+    ("info", &["slow", "dangerous"]),
+    ("keys", &["keyspace", "read", "slow", "dangerous"]),
+    ("latency", &["admin", "slow", "dangerous"]),
+    ("lindex", &["read", "list", "slow"]),
+    ("lmove", &["write", "list", "slow"]),
+    ("lmpop", &["write", "list", "slow"]),
+    ("linsert", &["write", "list", "slow"]),
+    ("llen", &["read", "list", "fast"]),
+    ("lpop", &["write", "list", "fast"]),
+    ("lpos", &["read", "list", "slow"]),
+    ("lpush", &["write", "list", "fast"]),
+    ("lrange", &["read", "list", "slow"]),
+    ("lrem", &["write", "list", "slow"]),
+    ("lset", &["write", "list", "slow"]),
+    ("ltrim", &["write", "list", "slow"]),
+    ("mget", &["read", "string", "fast"]),
+    ("move", &["keyspace", "write", "fast"]),
+    ("mset", &["write", "string", "slow"]),
+    ("msetnx", &["write", "string", "slow"]),
+    ("multi", &["fast", "transaction"]),
+    ("object", &["keyspace", "read", "slow"]),
+    ("pexpire", &["keyspace", "write", "fast"]),
+    ("pfadd", &["write", "hyperloglog", "fast"]),
+    ("pfcount", &["read", "hyperloglog", "slow"]),
+    ("pfmerge", &["write", "hyperloglog", "slow"]),
+    ("ping", &["fast", "connection"]),
+    ("psetex", &["write", "string", "slow"]),
+    ("psubscribe", &["pubsub", "slow"]),
+    ("pttl", &["keyspace", "read", "fast"]),
+    ("publish", &["pubsub", "fast"]),
+    ("pubsub", &["slow"]),
+    ("punsubscribe", &["pubsub", "slow"]),
+    ("quit", &["fast", "connection"]),
+    ("randomkey", &["keyspace", "read", "slow"]),
+    ("rename", &["keyspace", "write", "slow"]),
+    ("renamenx", &["keyspace", "write", "fast"]),
+    ("reset", &["fast", "connection"]),
+    ("restore", &["keyspace", "write", "slow", "dangerous"]),
+    ("rpop", &["write", "list", "fast"]),
+    ("rpoplpush", &["write", "list", "slow"]),
+    ("rpush", &["write", "list", "fast"]),
+    ("sadd", &["write", "set", "fast"]),
+    ("scard", &["read", "set", "fast"]),
+    ("scan", &["keyspace", "read", "slow"]),
+    ("script", &["slow", "scripting"]),
+    ("sdiff", &["read", "set", "slow"]),
+    ("sdiffstore", &["write", "set", "slow"]),
+    ("select", &["fast", "connection"]),
+    ("set", &["write", "string", "slow"]),
+    ("setbit", &["write", "bitmap", "slow"]),
+    ("setex", &["write", "string", "slow"]),
+    ("setnx", &["write", "string", "fast"]),
+    ("sinter", &["read", "set", "slow"]),
+    ("sintercard", &["read", "set", "slow"]),
+    ("sinterstore", &["write", "set", "slow"]),
+    ("sismember", &["read", "set", "fast"]),
+    ("smembers", &["read", "set", "slow"]),
+    ("smismember", &["read", "set", "fast"]),
+    ("smove", &["write", "set", "fast"]),
+    (
+        "sort",
+        &["write", "set", "sortedset", "list", "slow", "dangerous"],
+    ),
+    (
+        "sort_ro",
+        &["read", "set", "sortedset", "list", "slow", "dangerous"],
+    ),
+    ("spop", &["write", "set", "fast"]),
+    ("srandmember", &["read", "set", "slow"]),
+    ("spublish", &["pubsub", "fast"]),
+    ("srem", &["write", "set", "fast"]),
+    ("sscan", &["read", "set", "slow"]),
+    ("ssubscribe", &["pubsub", "slow"]),
+    ("subscribe", &["pubsub", "slow"]),
+    ("sunion", &["read", "set", "slow"]),
+    ("sunsubscribe", &["pubsub", "slow"]),
+    ("sunionstore", &["write", "set", "slow"]),
+    ("swapdb", &["keyspace", "write", "fast", "dangerous"]),
+    ("time", &["fast"]),
+    ("touch", &["keyspace", "read", "fast"]),
+    ("ttl", &["keyspace", "read", "fast"]),
+    ("type", &["keyspace", "read", "fast"]),
+    ("unlink", &["keyspace", "write", "fast"]),
+    ("unsubscribe", &["pubsub", "slow"]),
+    ("unwatch", &["fast", "transaction"]),
+    ("watch", &["fast", "transaction"]),
+    ("xack", &["write", "stream", "fast"]),
+    ("xadd", &["write", "stream", "fast"]),
+    ("xautoclaim", &["write", "stream", "fast"]),
+    ("xclaim", &["write", "stream", "fast"]),
+    ("xdel", &["write", "stream", "fast"]),
+    ("xgroup", &["write", "stream", "slow"]),
+    ("xinfo", &["read", "stream", "slow"]),
+    ("xlen", &["read", "stream", "fast"]),
+    ("xpending", &["read", "stream", "slow"]),
+    ("xrange", &["read", "stream", "slow"]),
+    ("xread", &["read", "stream", "slow", "blocking"]),
+    ("xreadgroup", &["write", "stream", "slow", "blocking"]),
+    ("xrevrange", &["read", "stream", "slow"]),
+    ("xsetid", &["write", "stream", "fast"]),
+    ("xtrim", &["write", "stream", "slow"]),
+    ("zadd", &["write", "sortedset", "fast"]),
+    ("zcard", &["read", "sortedset", "fast"]),
+    ("zcount", &["read", "sortedset", "fast"]),
+    ("zdiff", &["read", "sortedset", "slow"]),
+    ("zdiffstore", &["write", "sortedset", "slow"]),
+    ("zincrby", &["write", "sortedset", "fast"]),
+    ("zinter", &["read", "sortedset", "slow"]),
+    ("zinterstore", &["write", "sortedset", "slow"]),
+    ("zlexcount", &["read", "sortedset", "fast"]),
+    ("zmpop", &["write", "sortedset", "slow"]),
+    ("zmscore", &["read", "sortedset", "fast"]),
+    ("zpopmax", &["write", "sortedset", "fast"]),
+    ("zpopmin", &["write", "sortedset", "fast"]),
+    ("zrandmember", &["read", "sortedset", "slow"]),
+    ("zrange", &["read", "sortedset", "slow"]),
+    ("zrangebylex", &["read", "sortedset", "slow"]),
+    ("zrangebyscore", &["read", "sortedset", "slow"]),
+    ("zrangestore", &["write", "sortedset", "slow"]),
+    ("zrank", &["read", "sortedset", "fast"]),
+    ("zrem", &["write", "sortedset", "fast"]),
+    ("zrevrange", &["read", "sortedset", "slow"]),
+    ("zrevrangebylex", &["read", "sortedset", "slow"]),
+    ("zrevrangebyscore", &["read", "sortedset", "slow"]),
+    ("zrevrank", &["read", "sortedset", "fast"]),
+    ("zscan", &["read", "sortedset", "slow"]),
+    ("zscore", &["read", "sortedset", "fast"]),
+    ("zunion", &["read", "sortedset", "slow"]),
+    ("zunionstore", &["write", "sortedset", "slow"]),
+];
+
+pub fn is_category(category: &str) -> bool {
+    CATEGORIES.contains(&category)
+}
+
+/// lowercase names of the supported commands
+pub fn command_names() -> impl Iterator<Item = &'static str> {
+    COMMAND_CATEGORIES.iter().map(|(name, _)| *name)
+}
+
+/// lowercase names of the supported commands belonging to `category`
+pub fn category_commands(category: &str) -> Vec<&'static str> {
+    COMMAND_CATEGORIES
+        .iter()
+        .filter(|(_, categories)| categories.contains(&category))
+        .map(|(name, _)| *name)
+        .collect()
+}
@@ -5,6 +5,7 @@ use std::{
 };
 
 use crate::protocol::error::RedisError;
+use crate::storage::dump::PayloadError;
 use crate::storage::models::expiry::TimeOverflow;
 
 #[derive(Debug)]
@@ -22,9 +23,242 @@ pub enum RedisCommandError {
     ProtocolParse(RedisError),
     InvalidCommand,
     CommandNotFound,
+    // Unknown option or malformed arguments
+    Syntax,
+    // Invalid cursor passed to a SCAN family command
+    InvalidCursor,
+    // The key does not exist
+    NoSuchKey,
+    // Source and destination keys are the same
+    SameObject,
+    // The database does not exist
+    DbIndexOutOfRange,
+    // More keys announced than there are arguments left
+    TooManyKeys,
+    // Not a number or out of the range of a 64 bits integer
+    NotInteger,
+    // the command panicked while being run
+    CommandPanicked,
+    // a blocking timeout too large to be represented
+    TimeoutOutOfRange,
+    // A count is negative
+    NotPositive,
+    // A count is too big to be replied that many elements
+    ValueOutOfRange,
+    // No element at the given list index
+    IndexOutOfRange,
+    // LPOS was given a RANK of 0
+    RankZero,
+    // The named option was given a negative value
+    NegativeOption(&'static str),
+    // The named argument must be strictly positive
+    NotGreaterThanZero(&'static str),
+    // The timeout of a blocking command is not a number
+    TimeoutNotFloat,
+    // The timeout of a blocking command is not an integer
+    TimeoutNotInteger,
+    // The timeout of a blocking command is negative
+    NegativeTimeout,
+    // A bound of a score range is not a number
+    MinMaxNotFloat,
+    // A weight of ZUNION or ZINTER is not a number
+    WeightNotFloat,
+    // The named command was given no key to combine
+    NoInputKey(&'static str),
+    // A bound of a lexicographic range is malformed
+    MinMaxNotLexRange,
+    // ZRANGE was given both BYLEX and WITHSCORES
+    WithScoresByLex,
+    // ZRANGE was given a LIMIT for a range of indexes
+    LimitWithoutScoreOrLex,
+    // Options which can not be given together
+    IncompatibleOptions(&'static str),
+    // ZADD INCR was given several score/member pairs
+    IncrSinglePair,
+    // The new score of a sorted set member is not a number
+    ScoreNaN,
+    // SORT compares elements or weights which are not numbers without ALPHA
+    SortNotFloat,
+    // A bit offset is negative or beyond the maximum string size
+    BitOffset,
+    // SETBIT was given a value other than 0 or 1
+    BitValue,
+    // BITPOS was given a bit other than 0 or 1
+    BitArgument,
+    // BITOP NOT was given several source keys
+    BitOpNotSingleSource,
+    // A BITFIELD type is not a signed or unsigned integer of a supported width
+    BitFieldType,
+    // BITFIELD was given an unknown OVERFLOW behavior
+    OverflowType,
+    // A string value is not a HyperLogLog
+    InvalidHyperLogLog,
+    // DUMP was given a stream, whose encoding is not supported
+    DumpStream,
+    // A stream ID is malformed
+    InvalidStreamId,
+    // XADD was given an ID which is not greater than the last one of the stream
+    StreamIdTooSmall,
+    // XADD is run against a stream whose last ID is the maximum one
+    StreamExhausted,
+    // XADD was given the 0-0 ID
+    StreamIdZero,
+    // An exclusive bound of a stream range can not be moved past the named end
+    StreamIntervalId(&'static str),
+    // The streams of XREAD are not given an ID each
+    UnbalancedStreams(&'static str),
+    // An argument of a stream command is negative, holds the argument
+    NegativeArgument(&'static str),
+    // LIMIT is given to the exact trimming of a stream
+    LimitWithoutApproximation,
+    // LIMIT is given to XADD or XTRIM without MAXLEN nor MINID
+    LimitWithoutTrimStrategy,
+    // XSETID is given an ID less than the one of the last entry of the stream
+    SetIdTooSmall,
+    // XSETID is given a number of added entries less than the length of the stream
+    SetIdEntriesAdded,
+    // XSETID is given a greatest deleted ID greater than the last ID
+    SetIdMaxDeleted,
+    // XREADGROUP is given `$` as ID
+    StreamReadGroupLastId,
+    // A consumer group or its stream does not exist, holds the key and the group
+    NoGroup(String, String),
+    // A consumer group is created with the name of an existing one
+    BusyGroup,
+    // XGROUP is run on a key which does not exist
+    GroupKeyMissing,
+    // A position is out of the range of geohashes, holds the longitude and the latitude
+    InvalidCoordinates(f64, f64),
+    // The unit of a distance is not one of m, km, mi and ft
+    UnsupportedUnit,
+    // GEOSEARCH is not given a single center, holds the command
+    GeoSearchOrigin(&'static str),
+    // GEOSEARCH is not given a single area, holds the command
+    GeoSearchShape(&'static str),
+    // A size of the area of a search is negative, holds the size
+    NegativeSize(&'static str),
+    // ANY is given to GEOSEARCH without COUNT
+    AnyWithoutCount,
+    // The center of a search is a member missing from the set
+    UnknownGeoMember,
+    // CONFIG SET is given a parameter it does not know, holds the parameter
+    UnsupportedConfig(String),
+    // notify-keyspace-events is set to something else than classes of events
+    InvalidKeyspaceEvents,
+    // A subscribed client runs another command than the pub/sub ones, holds the command
+    NotAllowedWhenSubscribed(String),
+    // MULTI is called within a transaction
+    NestedMulti,
+    // EXEC or DISCARD is called outside of a transaction, holds the command
+    WithoutMulti(&'static str),
+    // EXEC is called after a command could not be queued
+    ExecAbort,
+    // WATCH is called within a transaction
+    WatchInMulti,
+    // The number of keys given to a script is negative
+    NegativeKeyCount,
+    // No script has the SHA1 digest given to EVALSHA
+    NoScript,
+    // SCRIPT KILL is called while no script is running
+    NotBusy,
+    // SCRIPT KILL is called while the script has already written to the data
+    Unkillable,
+    // A script has run for longer than the busy threshold
+    BusyScript,
+    // The script has been stopped by SCRIPT KILL
+    #[cfg(feature = "lua")]
+    ScriptKilled,
+    // A numeric parameter of CONFIG SET is given something else, holds the parameter
+    InvalidConfigInteger(String),
+    // The code of a library does not start with its metadata
+    MissingLibraryMetadata,
+    // The metadata of a library names an unknown engine
+    EngineNotFound(String),
+    // The metadata of a library has an unknown field
+    InvalidMetadata(String),
+    // The metadata of a library does not name it
+    LibraryNameMissing,
+    // The name of a library or of a function has other characters than letters, numbers and
+    // underscores, holds what it names
+    InvalidName(&'static str),
+    // A library does not register any function
+    NoFunctionsRegistered,
+    // A library of the same name is loaded without REPLACE
+    LibraryExists(String),
+    // A function of a library is already registered by another one
+    FunctionExists(String),
+    LibraryNotFound,
+    FunctionNotFound,
+    // FCALL_RO calls a function without the no-writes flag
+    WriteFlagInReadOnly,
+    // A read only script calls a write command
+    #[cfg(feature = "lua")]
+    WriteFromReadOnlyScript,
+    // A script calls a command it can not run
+    #[cfg(feature = "lua")]
+    NotAllowedFromScript,
+    // A script calls a command which does not exist
+    #[cfg(feature = "lua")]
+    UnknownCommandFromScript,
+    // A script failed or replied an error, holds the whole message
+    #[cfg(feature = "lua")]
+    Script(String),
+    // Not a floating point number
+    NotFloat,
+    // The hash field holds something else than an integer
+    HashValueNotInteger,
+    // The hash field holds something else than a floating point number
+    HashValueNotFloat,
+    // The result of an increment does not fit in a 64 bits integer
+    IncrementOverflow,
+    // The result of a floating point increment is not a number
+    NanOrInfinity,
+    // Negative or malformed TTL
+    InvalidTtl,
+    // Negative IDLETIME of RESTORE
+    InvalidIdleTime,
+    // FREQ of RESTORE out of the range of the access frequencies
+    InvalidFrequency,
+    // The key holds a value of another type
+    WrongType,
+    // The target key already exists
+    BusyKey,
+    // DUMP payload from a newer RDB version or with a wrong checksum
+    BadDumpPayload,
+    // DUMP payload that can not be decoded
+    BadDataFormat,
+    // The command queue is full
+    Busy,
+    // ACL CAT with a category that does not exist
+    UnknownCategory(String),
+    // ACL GENPASS bits count is not in 1..=4096
+    InvalidGenPassBits,
+    // A password is required and the client has not authenticated yet
+    NoAuth,
+    // AUTH is given a wrong username or password
+    WrongPass,
+    // AUTH is given a password while none is required
+    AuthWithoutPassword,
+    // ACL SETUSER is given an invalid rule, holds the rule and why it is invalid
+    InvalidAclRule(String, &'static str),
+    // The user of the client can not run the command, holds the user and the command
+    NoCommandPermission(String, String),
+    // The user of the client can not access one of the keys of the command
+    NoKeyPermission,
+    // ACL DELUSER is given the default user
+    DefaultUserRemoval,
+    // CLIENT KILL is given an ID which is not a positive integer
+    InvalidClientId,
+    // CLIENT KILL is given a TYPE which does not exist, holds the type
+    UnknownClientType(String),
+    // CLIENT KILL is given a USER which does not exist, holds the user
+    NoSuchUser(String),
+    // CLIENT KILL is given the address of no client
+    NoSuchClient,
 }
 
 impl RedisCommandError {
+    #[allow(clippy::wrong_self_convention)]
     pub fn to_vec(self) -> Vec<u8> {
         format!("-{}\r\n", self).as_bytes().to_vec()
     }
@@ -36,7 +270,7 @@ impl Display for RedisCommandError {
             Self::ArgNumber => {
                 write!(f, "wrong number of arguments for command")
             }
-            Self::TimeOverflow(e) => write!(f, "{:?}", e),
+            Self::TimeOverflow(_) => write!(f, "ERR invalid expire time"),
             Self::BadString(e) => write!(f, "{}", e),
             Self::IntParse(e) => write!(f, "{}", e),
             Self::NotSupported(cmd) => {
@@ -45,6 +279,292 @@ impl Display for RedisCommandError {
             Self::ProtocolParse(err) => write!(f, "{}", err),
             Self::InvalidCommand => write!(f, "invalid command"),
             Self::CommandNotFound => write!(f, "command not found"),
+            Self::Syntax => write!(f, "ERR syntax error"),
+            Self::InvalidCursor => write!(f, "ERR invalid cursor"),
+            Self::NoSuchKey => write!(f, "ERR no such key"),
+            Self::SameObject => write!(f, "ERR source and destination objects are the same"),
+            Self::DbIndexOutOfRange => write!(f, "ERR DB index is out of range"),
+            Self::TooManyKeys => {
+                write!(f, "ERR Number of keys can't be greater than number of args")
+            }
+            Self::NotInteger => write!(f, "ERR value is not an integer or out of range"),
+            Self::CommandPanicked => write!(f, "ERR the command failed unexpectedly"),
+            Self::TimeoutOutOfRange => write!(f, "ERR timeout is out of range"),
+            Self::IndexOutOfRange => write!(f, "ERR index out of range"),
+            Self::RankZero => write!(
+                f,
+                "ERR RANK can't be zero: use 1 to start from the first match, 2 from the second ... \
+                 or use negative to start from the last match"
+            ),
+            Self::TimeoutNotFloat => write!(f, "ERR timeout is not a float or out of range"),
+            Self::TimeoutNotInteger => {
+                write!(f, "ERR timeout is not an integer or out of range")
+            }
+            Self::NegativeTimeout => write!(f, "ERR timeout is negative"),
+            Self::NotGreaterThanZero(argument) => {
+                write!(f, "ERR {} should be greater than 0", argument)
+            }
+            Self::NegativeOption(option) => write!(f, "ERR {} can't be negative", option),
+            Self::NotPositive => write!(f, "ERR value is out of range, must be positive"),
+            Self::ValueOutOfRange => write!(f, "ERR value is out of range"),
+            Self::MinMaxNotFloat => write!(f, "ERR min or max is not a float"),
+            Self::WeightNotFloat => write!(f, "ERR weight value is not a float"),
+            Self::NoInputKey(command) => write!(
+                f,
+                "ERR at least 1 input key is needed for '{}' command",
+                command
+            ),
+            Self::NegativeArgument(argument) => {
+                write!(f, "ERR The {} argument must be >= 0.", argument)
+            }
+            Self::LimitWithoutApproximation => write!(
+                f,
+                "ERR syntax error, LIMIT cannot be used without the special ~ option"
+            ),
+            Self::LimitWithoutTrimStrategy => write!(
+                f,
+                "ERR syntax error, LIMIT cannot be used without specifying a trimming strategy"
+            ),
+            Self::SetIdTooSmall => write!(
+                f,
+                "ERR The ID specified in XSETID is smaller than the target stream top item"
+            ),
+            Self::SetIdEntriesAdded => write!(
+                f,
+                "ERR The entries_added specified in XSETID is smaller than the target stream length"
+            ),
+            Self::SetIdMaxDeleted => write!(
+                f,
+                "ERR The ID specified in XSETID is smaller than the provided max_deleted_entry_id"
+            ),
+            Self::StreamReadGroupLastId => write!(
+                f,
+                "ERR The $ ID is meaningless in the context of XREADGROUP: you want to read the \
+                 history of this consumer by specifying a proper ID, or use the > ID to get new \
+                 messages. The $ ID would just return an empty result set."
+            ),
+            Self::NoGroup(key, group) => write!(
+                f,
+                "NOGROUP No such key '{}' or consumer group '{}'",
+                key, group
+            ),
+            Self::BusyGroup => write!(f, "BUSYGROUP Consumer Group name already exists"),
+            Self::GroupKeyMissing => write!(
+                f,
+                "ERR The XGROUP subcommand requires the key to exist. Note that for CREATE you \
+                 may want to use the MKSTREAM option to create an empty stream automatically."
+            ),
+            Self::MinMaxNotLexRange => write!(f, "ERR min or max not valid string range item"),
+            Self::WithScoresByLex => write!(
+                f,
+                "ERR syntax error, WITHSCORES not supported in combination with BYLEX"
+            ),
+            Self::LimitWithoutScoreOrLex => write!(
+                f,
+                "ERR syntax error, LIMIT is only supported in combination with either BYSCORE or BYLEX"
+            ),
+            Self::IncompatibleOptions(options) => {
+                write!(f, "ERR {} options at the same time are not compatible", options)
+            }
+            Self::IncrSinglePair => {
+                write!(f, "ERR INCR option supports a single increment-element pair")
+            }
+            Self::ScoreNaN => write!(f, "ERR resulting score is not a number (NaN)"),
+            Self::SortNotFloat => {
+                write!(f, "ERR One or more scores can't be converted into double")
+            }
+            Self::BitOffset => write!(f, "ERR bit offset is not an integer or out of range"),
+            Self::BitValue => write!(f, "ERR bit is not an integer or out of range"),
+            Self::BitArgument => write!(f, "ERR The bit argument must be 1 or 0."),
+            Self::BitOpNotSingleSource => {
+                write!(f, "ERR BITOP NOT must be called with a single source key.")
+            }
+            Self::BitFieldType => write!(
+                f,
+                "ERR Invalid bitfield type. Use something like i16 u8. Note that u64 is not \
+                 supported but i64 is."
+            ),
+            Self::OverflowType => write!(f, "ERR Invalid OVERFLOW type specified"),
+            Self::InvalidHyperLogLog => write!(
+                f,
+                "WRONGTYPE Key is not a valid HyperLogLog string value."
+            ),
+            Self::DumpStream => write!(f, "ERR DUMP is not supported for stream values"),
+            Self::InvalidStreamId => write!(
+                f,
+                "ERR Invalid stream ID specified as stream command argument"
+            ),
+            Self::StreamIdTooSmall => write!(
+                f,
+                "ERR The ID specified in XADD is equal or smaller than the target stream top item"
+            ),
+            Self::StreamExhausted => write!(
+                f,
+                "ERR The stream has exhausted the last possible ID, unable to add more items"
+            ),
+            Self::StreamIdZero => {
+                write!(f, "ERR The ID specified in XADD must be greater than 0-0")
+            }
+            Self::StreamIntervalId(end) => write!(f, "ERR invalid {} ID for the interval", end),
+            Self::UnbalancedStreams(command) => write!(
+                f,
+                "ERR Unbalanced '{}' list of streams: for each stream key an ID or '$' must be specified.",
+                command
+            ),
+            Self::InvalidCoordinates(longitude, latitude) => write!(
+                f,
+                "ERR invalid longitude,latitude pair {:.6},{:.6}",
+                longitude, latitude
+            ),
+            Self::UnsupportedUnit => write!(
+                f,
+                "ERR unsupported unit provided. please use M, KM, FT, MI"
+            ),
+            Self::GeoSearchOrigin(command) => write!(
+                f,
+                "ERR exactly one of FROMMEMBER or FROMLONLAT can be specified for {}",
+                command
+            ),
+            Self::GeoSearchShape(command) => write!(
+                f,
+                "ERR exactly one of BYRADIUS and BYBOX can be specified for {}",
+                command
+            ),
+            Self::NegativeSize(size) => write!(f, "ERR {} cannot be negative", size),
+            Self::AnyWithoutCount => write!(f, "ERR the ANY argument requires COUNT argument"),
+            Self::UnknownGeoMember => write!(f, "ERR could not decode requested zset member"),
+            Self::UnsupportedConfig(parameter) => write!(
+                f,
+                "ERR Unknown option or number of arguments for CONFIG SET - '{}'",
+                parameter
+            ),
+            Self::InvalidKeyspaceEvents => write!(
+                f,
+                "ERR CONFIG SET failed (possibly related to argument 'notify-keyspace-events') - \
+                 Invalid event class character. Use 'Ag$lshzxeKEtmdn'."
+            ),
+            Self::NotAllowedWhenSubscribed(command) => write!(
+                f,
+                "ERR Can't execute '{}': only (P|S)SUBSCRIBE / (P|S)UNSUBSCRIBE / PING / QUIT / \
+                 RESET are allowed in this context",
+                command
+            ),
+            Self::NestedMulti => write!(f, "ERR MULTI calls can not be nested"),
+            Self::WithoutMulti(command) => write!(f, "ERR {} without MULTI", command),
+            Self::ExecAbort => write!(
+                f,
+                "EXECABORT Transaction discarded because of previous errors."
+            ),
+            Self::WatchInMulti => write!(f, "ERR WATCH inside MULTI is not allowed"),
+            Self::NegativeKeyCount => write!(f, "ERR Number of keys can't be negative"),
+            Self::NoScript => write!(f, "NOSCRIPT No matching script. Please use EVAL."),
+            Self::NotBusy => write!(f, "NOTBUSY No scripts in execution right now."),
+            Self::Unkillable => write!(
+                f,
+                "UNKILLABLE Sorry the script already executed write commands against the dataset. \
+                 You can either wait the script termination or kill the server in a hard way."
+            ),
+            Self::BusyScript => write!(
+                f,
+                "BUSY Redis is busy running a script. You can only call SCRIPT KILL."
+            ),
+            #[cfg(feature = "lua")]
+            Self::ScriptKilled => write!(f, "ERR Script killed by user with SCRIPT KILL..."),
+            Self::InvalidConfigInteger(parameter) => write!(
+                f,
+                "ERR CONFIG SET failed (possibly related to argument '{}') - argument couldn't \
+                 be parsed into an integer",
+                parameter
+            ),
+            Self::MissingLibraryMetadata => write!(f, "ERR Missing library metadata"),
+            Self::EngineNotFound(engine) => write!(f, "ERR Engine '{}' not found", engine),
+            Self::InvalidMetadata(field) => {
+                write!(f, "ERR Invalid metadata value given: {}", field)
+            }
+            Self::LibraryNameMissing => write!(f, "ERR Library name was not given"),
+            Self::InvalidName(kind) => write!(
+                f,
+                "ERR {} names can only contain letters, numbers, or underscores(_) and must be at least one character long",
+                kind
+            ),
+            Self::NoFunctionsRegistered => write!(f, "ERR No functions registered"),
+            Self::LibraryExists(library) => write!(f, "ERR Library '{}' already exists", library),
+            Self::FunctionExists(function) => {
+                write!(f, "ERR Function {} already exists", function)
+            }
+            Self::LibraryNotFound => write!(f, "ERR Library not found"),
+            Self::FunctionNotFound => write!(f, "ERR Function not found"),
+            Self::WriteFlagInReadOnly => write!(
+                f,
+                "ERR Can not execute a script with write flag using *_ro command."
+            ),
+            #[cfg(feature = "lua")]
+            Self::WriteFromReadOnlyScript => write!(
+                f,
+                "ERR Write commands are not allowed from read-only scripts."
+            ),
+            #[cfg(feature = "lua")]
+            Self::NotAllowedFromScript => {
+                write!(f, "ERR This Redis command is not allowed from script")
+            }
+            #[cfg(feature = "lua")]
+            Self::UnknownCommandFromScript => {
+                write!(f, "ERR Unknown Redis command called from script")
+            }
+            #[cfg(feature = "lua")]
+            Self::Script(message) => write!(f, "{}", message),
+            Self::NotFloat => write!(f, "ERR value is not a valid float"),
+            Self::HashValueNotInteger => write!(f, "ERR hash value is not an integer"),
+            Self::HashValueNotFloat => write!(f, "ERR hash value is not a float"),
+            Self::IncrementOverflow => write!(f, "ERR increment or decrement would overflow"),
+            Self::NanOrInfinity => write!(f, "ERR increment would produce NaN or Infinity"),
+            Self::InvalidTtl => write!(f, "ERR Invalid TTL value, must be >= 0"),
+            Self::InvalidIdleTime => write!(f, "ERR Invalid IDLETIME value, must be >= 0"),
+            Self::InvalidFrequency => {
+                write!(f, "ERR Invalid FREQ value, must be >= 0 and <= 255")
+            }
+            Self::WrongType => write!(
+                f,
+                "WRONGTYPE Operation against a key holding the wrong kind of value"
+            ),
+            Self::BusyKey => write!(f, "BUSYKEY Target key name already exists."),
+            Self::BadDumpPayload => write!(f, "ERR DUMP payload version or checksum are wrong"),
+            Self::BadDataFormat => write!(f, "ERR Bad data format"),
+            Self::Busy => write!(f, "BUSY the command queue is full, try again later"),
+            Self::UnknownCategory(category) => {
+                write!(f, "ERR Unknown category '{}'", category)
+            }
+            Self::InvalidGenPassBits => write!(
+                f,
+                "ERR ACL GENPASS argument must be the number of bits for the output password, \
+                 a positive number up to 4096"
+            ),
+            Self::NoAuth => write!(f, "NOAUTH Authentication required."),
+            Self::WrongPass => write!(
+                f,
+                "WRONGPASS invalid username-password pair or user is disabled."
+            ),
+            Self::AuthWithoutPassword => write!(
+                f,
+                "ERR AUTH <password> called without any password configured for the default \
+                 user. Are you sure your configuration is correct?"
+            ),
+            Self::InvalidAclRule(rule, reason) => {
+                write!(f, "ERR Error in ACL SETUSER modifier '{}': {}", rule, reason)
+            }
+            Self::NoCommandPermission(user, command) => write!(
+                f,
+                "NOPERM User {} has no permissions to run the '{}' command",
+                user, command
+            ),
+            Self::NoKeyPermission => write!(f, "NOPERM No permissions to access a key"),
+            Self::DefaultUserRemoval => write!(f, "ERR The 'default' user cannot be removed"),
+            Self::InvalidClientId => write!(f, "ERR client-id should be greater than 0"),
+            Self::UnknownClientType(client_type) => {
+                write!(f, "ERR Unknown client type '{}'", client_type)
+            }
+            Self::NoSuchUser(user) => write!(f, "ERR No such user '{}'", user),
+            Self::NoSuchClient => write!(f, "ERR No such client"),
         }
     }
 }
@@ -66,3 +586,12 @@ impl From<ParseIntError> for RedisCommandError {
         Self::IntParse(err)
     }
 }
+
+impl From<PayloadError> for RedisCommandError {
+    fn from(err: PayloadError) -> Self {
+        match err {
+            PayloadError::VersionOrChecksum => Self::BadDumpPayload,
+            PayloadError::BadDataFormat => Self::BadDataFormat,
+        }
+    }
+}
@@ -1,23 +1,165 @@
 #[cfg(test)]
 mod tests;
 
+pub mod category;
 pub mod command_error;
 mod util;
 
+use std::time::Duration;
+
 use crate::protocol::Resp;
-use crate::storage::models::Expiry;
+use crate::storage::models::bitmap::{BitFieldOperation, BitOperation, BitRange, BitUnit};
+use crate::storage::models::geo::{self, GeoQuery, GeoUnit};
+use crate::storage::models::{
+    Aggregate, ClaimOptions, Expiry, LexBound, ListEnd, NewStreamId, ScoreBound, ScoreEnd,
+    StreamId, StreamTrim, ZRangeBy, ZRangeQuery,
+};
+use crate::storage::scan::DEFAULT_SCAN_COUNT;
 use command_error::RedisCommandError;
 
 use super::storage::models::RedisString;
 
+/// ACL GENPASS output size when no bits count is given
+const DEFAULT_GENPASS_BITS: u64 = 256;
+const MAX_GENPASS_BITS: u64 = 4096;
+
 type Key = RedisString;
 type Value = RedisString;
 type Items = Vec<(Key, Value)>;
 type Keys = Vec<Key>;
+type Values = Vec<Value>;
+type ScoredMembers = Vec<(f64, Value)>;
+
+/// Flags of ZADD
+#[derive(Debug, PartialEq, Default, Clone, Copy)]
+pub struct ZAddOptions {
+    // only add new members
+    pub nx: bool,
+    // only update existing members
+    pub xx: bool,
+    // only update a score when the new one is greater
+    pub gt: bool,
+    // only update a score when the new one is less
+    pub lt: bool,
+    // count the updated members along with the added ones
+    pub ch: bool,
+    // increment the score of a single member and reply with the new score
+    pub incr: bool,
+}
+
+/// Options of SORT and SORT_RO
+#[derive(Debug, PartialEq, Default, Clone)]
+pub struct SortOptions {
+    // pattern of the keys (or `key->field` hash fields) holding the weights of the elements, which
+    // are not sorted at all when it has no `*`
+    pub by: Option<Value>,
+    // offset and count of the sorted elements to reply, a negative count replying them all
+    pub limit: Option<(i64, i64)>,
+    // patterns of the values replied instead of each element, `#` being the element itself
+    pub get: Values,
+    pub desc: bool,
+    // compare the elements (or their weights) as strings rather than as numbers
+    pub alpha: bool,
+}
+
+/// Type of a client, as CLIENT KILL filters it
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ClientType {
+    Normal,
+    Master,
+    Replica,
+    PubSub,
+}
+
+/// Filters of CLIENT KILL, a client being killed when it matches all of them
+#[derive(Debug, PartialEq, Clone)]
+pub struct ClientKillFilter {
+    pub id: Option<u64>,
+    // `ip:port` of the client, and of the server side of its connection
+    pub addr: Option<String>,
+    pub laddr: Option<String>,
+    pub client_type: Option<ClientType>,
+    pub user: Option<Value>,
+    // the client sending CLIENT KILL is not killed
+    pub skip_me: bool,
+}
+
+impl Default for ClientKillFilter {
+    fn default() -> Self {
+        ClientKillFilter {
+            id: None,
+            addr: None,
+            laddr: None,
+            client_type: None,
+            user: None,
+            skip_me: true,
+        }
+    }
+}
 
 #[derive(Debug, PartialEq)]
 pub enum Command {
     Append(Key, Value),
+    SetBit(Key, u64, u8),
+    GetBit(Key, u64),
+    BitCount(Key, Option<BitRange>),
+    BitPos(Key, u8, i64, Option<i64>, BitUnit),
+    BitOp(BitOperation, Key, Keys),
+    BitField(Key, Vec<BitFieldOperation>),
+    PfAdd(Key, Values),
+    PfCount(Keys),
+    PfMerge(Key, Keys),
+    XAdd(Key, bool, Option<StreamTrim>, NewStreamId, Items),
+    XTrim(Key, StreamTrim),
+    XDel(Key, Vec<StreamId>),
+    XLen(Key),
+    XRange(Key, StreamId, StreamId, Option<usize>),
+    XRevRange(Key, StreamId, StreamId, Option<usize>),
+    XRead(
+        Keys,
+        Vec<Option<StreamId>>,
+        Option<usize>,
+        Option<Option<Duration>>,
+    ),
+    XGroupCreate(Key, Value, Option<StreamId>, bool),
+    XGroupSetId(Key, Value, Option<StreamId>),
+    XGroupDestroy(Key, Value),
+    XGroupCreateConsumer(Key, Value, Value),
+    XGroupDelConsumer(Key, Value, Value),
+    XReadGroup(
+        Value,
+        Value,
+        Keys,
+        Vec<Option<StreamId>>,
+        Option<usize>,
+        Option<Option<Duration>>,
+        bool,
+    ),
+    XAck(Key, Value, Vec<StreamId>),
+    XInfoStream(Key),
+    XInfoStreamFull(Key, Option<usize>),
+    XInfoGroups(Key),
+    XInfoConsumers(Key, Value),
+    XSetId(Key, StreamId, Option<u64>, Option<StreamId>),
+    XPending(Key, Value),
+    XPendingRange(
+        Key,
+        Value,
+        StreamId,
+        StreamId,
+        usize,
+        Option<u64>,
+        Option<Value>,
+    ),
+    XClaim(
+        Key,
+        Value,
+        Value,
+        Vec<StreamId>,
+        ClaimOptions,
+        Option<StreamId>,
+    ),
+    XAutoClaim(Key, Value, Value, StreamId, usize, ClaimOptions),
     Set(Key, Value),
     Setnx(Key, Value),
     Setex(Key, Expiry, Value),
@@ -30,20 +172,407 @@ pub enum Command {
     GetSet(Key, Value),
     MGet(Keys),
     HSet(Key, Items),
+    HMSet(Key, Items),
     HGet(Key, Key),
+    HDel(Key, Keys),
+    HGetAll(Key),
+    HMGet(Key, Keys),
+    HSetnx(Key, Key, Value),
+    HKeys(Key),
+    HVals(Key),
+    HLen(Key),
+    HExists(Key, Key),
+    HScan(Key, u64, Option<Key>, usize, bool),
+    HRandField(Key, Option<i64>, bool),
+    HStrLen(Key, Key),
+    LPush(Key, Values),
+    RPush(Key, Values),
+    LPop(Key, Option<usize>),
+    RPop(Key, Option<usize>),
+    LLen(Key),
+    LRange(Key, i64, i64),
+    LIndex(Key, i64),
+    LSet(Key, i64, Value),
+    LTrim(Key, i64, i64),
+    LInsert(Key, bool, Value, Value),
+    LRem(Key, i64, Value),
+    LPos(Key, Value, i64, Option<usize>, usize),
+    LMove(Key, Key, ListEnd, ListEnd),
+    BLPop(Keys, Option<Duration>),
+    BRPop(Keys, Option<Duration>),
+    BLMove(Key, Key, ListEnd, ListEnd, Option<Duration>),
+    LMPop(Keys, ListEnd, usize),
+    BLMPop(Option<Duration>, Keys, ListEnd, usize),
+    SAdd(Key, Values),
+    SRem(Key, Values),
+    SMembers(Key),
+    SIsMember(Key, Value),
+    SMIsMember(Key, Values),
+    SCard(Key),
+    SPop(Key, Option<usize>),
+    SRandMember(Key, Option<i64>),
+    SMove(Key, Key, Value),
+    SInter(Keys),
+    SUnion(Keys),
+    SDiff(Keys),
+    SInterCard(Keys, usize),
+    SScan(Key, u64, Option<Key>, usize),
+    SInterStore(Key, Keys),
+    SUnionStore(Key, Keys),
+    SDiffStore(Key, Keys),
+    ZAdd(Key, ZAddOptions, ScoredMembers),
+    ZScore(Key, Value),
+    ZMScore(Key, Values),
+    GeoPos(Key, Values),
+    GeoDist(Key, Value, Value, GeoUnit),
+    GeoHash(Key, Values),
+    GeoSearch(Key, GeoQuery),
+    GeoSearchStore(Key, Key, GeoQuery, bool),
+    ZRem(Key, Values),
+    ZCard(Key),
+    ZRandMember(Key, Option<i64>, bool),
+    ZScan(Key, u64, Option<Key>, usize),
+    ZRange(Key, ZRangeQuery, bool),
+    ZRangeStore(Key, Key, ZRangeQuery),
+    ZCount(Key, ScoreBound, ScoreBound),
+    ZLexCount(Key, LexBound, LexBound),
+    ZIncrBy(Key, f64, Value),
+    ZPopMin(Key, Option<usize>),
+    ZPopMax(Key, Option<usize>),
+    ZMPop(Keys, ScoreEnd, usize),
+    BZPopMin(Keys, Option<Duration>),
+    BZPopMax(Keys, Option<Duration>),
+    BZMPop(Option<Duration>, Keys, ScoreEnd, usize),
+    ZRank(Key, Value, bool),
+    ZRevRank(Key, Value, bool),
+    ZUnion(Keys, Vec<f64>, Aggregate, bool),
+    ZInter(Keys, Vec<f64>, Aggregate, bool),
+    ZDiff(Keys, bool),
+    ZUnionStore(Key, Keys, Vec<f64>, Aggregate),
+    ZInterStore(Key, Keys, Vec<f64>, Aggregate),
+    ZDiffStore(Key, Keys),
+    HIncrBy(Key, Key, i64),
+    HIncrByFloat(Key, Key, f64),
     Del(Key),
+    Unlink(Keys),
     Incr(Key),
     IncrBy(Key, i64),
     Exists(Key),
+    Touch(Keys),
+    Type(Key),
+    Sort(Key, SortOptions, Option<Key>),
+    SortRo(Key, SortOptions),
+    Keys(Key),
+    Rename(Key, Key),
+    RenameNx(Key, Key),
+    Copy(Key, Key, Option<u64>, bool),
+    Dump(Key),
+    Restore(Key, u64, Value, bool, bool),
+    Scan(u64, Option<Key>, usize, Option<Value>),
+    RandomKey,
+    ObjectEncoding(Key),
+    ObjectRefCount(Key),
+    ObjectIdleTime(Key),
+    ObjectFreq(Key),
     Ttl(Key),
     Pttl(Key),
     Info,
-    Ping,
+    Ping(Option<Value>),
+    Echo(Value),
     Quit,
+    Reset,
+    Auth(Option<Value>, Value),
+    Multi,
+    Exec,
+    Discard,
+    Watch(Keys),
+    Unwatch,
+    Eval(Value, Keys, Values),
+    EvalSha(Value, Keys, Values),
+    ScriptLoad(Value),
+    ScriptExists(Values),
+    ScriptFlush,
+    ScriptKill,
+    FunctionLoad(Value, bool),
+    FunctionList(Option<Value>, bool),
+    FunctionDelete(Value),
+    FunctionDump,
+    FCall(Value, Keys, Values, bool),
+    Subscribe(Values),
+    Unsubscribe(Values),
+    PSubscribe(Values),
+    PUnsubscribe(Values),
+    Publish(Value, Value),
+    PubSubChannels(Option<Value>),
+    PubSubNumSub(Values),
+    PubSubNumPat,
+    SSubscribe(Values),
+    SUnsubscribe(Values),
+    SPublish(Value, Value),
+    PubSubShardChannels(Option<Value>),
+    PubSubShardNumSub(Values),
     Dbsize,
+    Time,
+    FlushDb(bool),
+    FlushAll(bool),
+    Select(u64),
+    Move(Key, u64),
+    SwapDb(u64, u64),
+    LatencyHistogram(Keys),
+    ConfigGet(Values),
+    ConfigSet(Items),
+    AclCat(Option<Value>),
+    AclGenPass(u64),
+    AclSetUser(Value, Values),
+    AclGetUser(Value),
+    AclDelUser(Values),
+    AclList,
+    AclWhoAmI,
+    ClientId,
+    // the former form of CLIENT KILL, with the address of the client only
+    ClientKillAddr(Value),
+    ClientKill(ClientKillFilter),
 }
 
 impl Command {
+    /// whether a client subscribed to channels can still run the command
+    pub fn is_allowed_when_subscribed(&self) -> bool {
+        matches!(
+            self,
+            Command::Subscribe(_)
+                | Command::Unsubscribe(_)
+                | Command::PSubscribe(_)
+                | Command::PUnsubscribe(_)
+                | Command::SSubscribe(_)
+                | Command::SUnsubscribe(_)
+                | Command::Ping(_)
+                | Command::Quit
+                | Command::Reset
+        )
+    }
+
+    /// whether a script can call the command
+    #[cfg(feature = "lua")]
+    pub fn is_allowed_in_script(&self) -> bool {
+        !matches!(
+            self,
+            Command::Multi
+                | Command::Exec
+                | Command::Discard
+                | Command::Watch(_)
+                | Command::Unwatch
+                | Command::Eval(..)
+                | Command::EvalSha(..)
+                | Command::ScriptLoad(_)
+                | Command::ScriptExists(_)
+                | Command::ScriptFlush
+                | Command::ScriptKill
+                | Command::FunctionLoad(..)
+                | Command::FunctionList(..)
+                | Command::FunctionDelete(_)
+                | Command::FunctionDump
+                | Command::FCall(..)
+                | Command::Subscribe(_)
+                | Command::Unsubscribe(_)
+                | Command::PSubscribe(_)
+                | Command::PUnsubscribe(_)
+                | Command::SSubscribe(_)
+                | Command::SUnsubscribe(_)
+                | Command::Quit
+                | Command::Reset
+                | Command::Auth(..)
+                | Command::ClientId
+                | Command::ClientKillAddr(_)
+                | Command::ClientKill(_)
+        )
+    }
+
+    /// whether a client which has not authenticated yet can run the command
+    pub fn is_allowed_without_auth(&self) -> bool {
+        matches!(self, Command::Auth(..) | Command::Quit | Command::Reset)
+    }
+
+    /// keys the command accesses, which the user of the client must be allowed to
+    pub fn keys(&self) -> Vec<&Key> {
+        match self {
+            Command::Append(key, _)
+            | Command::SetBit(key, ..)
+            | Command::GetBit(key, _)
+            | Command::BitCount(key, _)
+            | Command::BitPos(key, ..)
+            | Command::BitField(key, _)
+            | Command::PfAdd(key, _)
+            | Command::XAdd(key, ..)
+            | Command::XTrim(key, _)
+            | Command::XDel(key, _)
+            | Command::XLen(key)
+            | Command::XRange(key, ..)
+            | Command::XRevRange(key, ..)
+            | Command::XGroupCreate(key, ..)
+            | Command::XGroupSetId(key, ..)
+            | Command::XGroupDestroy(key, _)
+            | Command::XGroupCreateConsumer(key, ..)
+            | Command::XGroupDelConsumer(key, ..)
+            | Command::XAck(key, ..)
+            | Command::XInfoStream(key)
+            | Command::XInfoStreamFull(key, _)
+            | Command::XInfoGroups(key)
+            | Command::XInfoConsumers(key, _)
+            | Command::XSetId(key, ..)
+            | Command::XPending(key, _)
+            | Command::XPendingRange(key, ..)
+            | Command::XClaim(key, ..)
+            | Command::XAutoClaim(key, ..)
+            | Command::Set(key, _)
+            | Command::Setnx(key, _)
+            | Command::Setex(key, ..)
+            | Command::PSetex(key, ..)
+            | Command::Expire(key, _)
+            | Command::PExpire(key, _)
+            | Command::Get(key)
+            | Command::GetSet(key, _)
+            | Command::HSet(key, _)
+            | Command::HMSet(key, _)
+            | Command::HGet(key, _)
+            | Command::HDel(key, _)
+            | Command::HGetAll(key)
+            | Command::HMGet(key, _)
+            | Command::HSetnx(key, ..)
+            | Command::HKeys(key)
+            | Command::HVals(key)
+            | Command::HLen(key)
+            | Command::HExists(key, _)
+            | Command::HScan(key, ..)
+            | Command::HRandField(key, ..)
+            | Command::HStrLen(key, _)
+            | Command::HIncrBy(key, ..)
+            | Command::HIncrByFloat(key, ..)
+            | Command::LPush(key, _)
+            | Command::RPush(key, _)
+            | Command::LPop(key, _)
+            | Command::RPop(key, _)
+            | Command::LLen(key)
+            | Command::LRange(key, ..)
+            | Command::LIndex(key, _)
+            | Command::LSet(key, ..)
+            | Command::LTrim(key, ..)
+            | Command::LInsert(key, ..)
+            | Command::LRem(key, ..)
+            | Command::LPos(key, ..)
+            | Command::SAdd(key, _)
+            | Command::SRem(key, _)
+            | Command::SMembers(key)
+            | Command::SIsMember(key, _)
+            | Command::SMIsMember(key, _)
+            | Command::SCard(key)
+            | Command::SPop(key, _)
+            | Command::SRandMember(key, _)
+            | Command::SScan(key, ..)
+            | Command::ZAdd(key, ..)
+            | Command::ZScore(key, _)
+            | Command::ZMScore(key, _)
+            | Command::GeoPos(key, _)
+            | Command::GeoDist(key, ..)
+            | Command::GeoHash(key, _)
+            | Command::GeoSearch(key, _)
+            | Command::ZRem(key, _)
+            | Command::ZCard(key)
+            | Command::ZRandMember(key, ..)
+            | Command::ZScan(key, ..)
+            | Command::ZRange(key, ..)
+            | Command::ZCount(key, ..)
+            | Command::ZLexCount(key, ..)
+            | Command::ZIncrBy(key, ..)
+            | Command::ZPopMin(key, _)
+            | Command::ZPopMax(key, _)
+            | Command::ZRank(key, ..)
+            | Command::ZRevRank(key, ..)
+            | Command::Del(key)
+            | Command::Incr(key)
+            | Command::IncrBy(key, _)
+            | Command::Exists(key)
+            | Command::Type(key)
+            | Command::SortRo(key, _)
+            | Command::Dump(key)
+            | Command::Restore(key, ..)
+            | Command::ObjectEncoding(key)
+            | Command::ObjectRefCount(key)
+            | Command::ObjectIdleTime(key)
+            | Command::ObjectFreq(key)
+            | Command::Ttl(key)
+            | Command::Pttl(key)
+            | Command::Move(key, _) => vec![key],
+            Command::LMove(key, other_key, ..)
+            | Command::BLMove(key, other_key, ..)
+            | Command::SMove(key, other_key, _)
+            | Command::GeoSearchStore(key, other_key, ..)
+            | Command::ZRangeStore(key, other_key, _)
+            | Command::Rename(key, other_key)
+            | Command::RenameNx(key, other_key)
+            | Command::Copy(key, other_key, ..) => vec![key, other_key],
+            Command::Sort(key, _, destination) => std::iter::once(key).chain(destination).collect(),
+            Command::BitOp(_, key, keys)
+            | Command::PfMerge(key, keys)
+            | Command::SInterStore(key, keys)
+            | Command::SUnionStore(key, keys)
+            | Command::SDiffStore(key, keys)
+            | Command::ZUnionStore(key, keys, ..)
+            | Command::ZInterStore(key, keys, ..)
+            | Command::ZDiffStore(key, keys) => std::iter::once(key).chain(keys).collect(),
+            Command::PfCount(keys)
+            | Command::XRead(keys, ..)
+            | Command::XReadGroup(_, _, keys, ..)
+            | Command::MGet(keys)
+            | Command::BLPop(keys, _)
+            | Command::BRPop(keys, _)
+            | Command::LMPop(keys, ..)
+            | Command::BLMPop(_, keys, ..)
+            | Command::SInter(keys)
+            | Command::SUnion(keys)
+            | Command::SDiff(keys)
+            | Command::SInterCard(keys, _)
+            | Command::ZMPop(keys, ..)
+            | Command::BZPopMin(keys, _)
+            | Command::BZPopMax(keys, _)
+            | Command::BZMPop(_, keys, ..)
+            | Command::ZUnion(keys, ..)
+            | Command::ZInter(keys, ..)
+            | Command::ZDiff(keys, _)
+            | Command::Unlink(keys)
+            | Command::Touch(keys)
+            | Command::Watch(keys)
+            | Command::Eval(_, keys, _)
+            | Command::EvalSha(_, keys, _)
+            | Command::FCall(_, keys, ..) => keys.iter().collect(),
+            Command::MSet(items) | Command::MSetnx(items) => {
+                items.iter().map(|(key, _)| key).collect()
+            }
+            _ => vec![],
+        }
+    }
+
+    /// whether the command runs without any command of another client in between, as
+    /// transactions and scripts do
+    pub fn is_exclusive(&self) -> bool {
+        matches!(
+            self,
+            Command::Exec | Command::Eval(..) | Command::EvalSha(..) | Command::FCall(..)
+        )
+    }
+
+    /// whether the command is queued rather than run right away once MULTI has been called
+    pub fn is_queued_in_transaction(&self) -> bool {
+        !matches!(
+            self,
+            Command::Multi
+                | Command::Exec
+                | Command::Discard
+                | Command::Watch(_)
+                | Command::Quit
+                | Command::Reset
+        )
+    }
+
     pub fn parse(v: Vec<Resp>) -> Result<Self, RedisCommandError> {
         use util::*;
         use Command::*;
@@ -63,6 +592,456 @@ impl Command {
 
                     Ok(Append(key, value))
                 }
+                b"SETBIT" | b"setbit" | b"SetBit" | b"Setbit" => {
+                    let key = get_bytes_vec(v.get(1))?;
+                    let offset = get_bytes_vec(v.get(2)).and_then(parse_bit_offset)?;
+                    let bit = get_bytes_vec(v.get(3)).and_then(parse_bit)?;
+
+                    Ok(SetBit(key, offset, bit))
+                }
+                b"GETBIT" | b"getbit" | b"GetBit" | b"Getbit" => {
+                    let key = get_bytes_vec(v.get(1))?;
+                    let offset = get_bytes_vec(v.get(2)).and_then(parse_bit_offset)?;
+
+                    Ok(GetBit(key, offset))
+                }
+                b"BITCOUNT" | b"bitcount" | b"BitCount" | b"Bitcount" => {
+                    let key = get_bytes_vec(v.get(1))?;
+                    let range = match &v[2.min(v.len())..] {
+                        [] => None,
+                        [start, end] | [start, end, _] => Some(BitRange {
+                            start: get_bytes_vec(Some(start)).and_then(parse_integer)?,
+                            end: get_bytes_vec(Some(end)).and_then(parse_integer)?,
+                            unit: match v.get(4) {
+                                Some(unit) => get_bytes_vec(Some(unit)).and_then(parse_bit_unit)?,
+                                None => BitUnit::Byte,
+                            },
+                        }),
+                        _ => return Err(Syntax),
+                    };
+
+                    Ok(BitCount(key, range))
+                }
+                b"BITPOS" | b"bitpos" | b"BitPos" | b"Bitpos" => {
+                    let key = get_bytes_vec(v.get(1))?;
+                    let bit = match get_bytes_vec(v.get(2))?.as_slice() {
+                        b"0" => 0,
+                        b"1" => 1,
+                        _ => return Err(BitArgument),
+                    };
+                    let start = match v.get(3) {
+                        Some(start) => get_bytes_vec(Some(start)).and_then(parse_integer)?,
+                        None => 0,
+                    };
+                    let end = match v.get(4) {
+                        Some(end) => Some(get_bytes_vec(Some(end)).and_then(parse_integer)?),
+                        None => None,
+                    };
+                    let unit = match &v[5.min(v.len())..] {
+                        [] => BitUnit::Byte,
+                        [unit] => get_bytes_vec(Some(unit)).and_then(parse_bit_unit)?,
+                        _ => return Err(Syntax),
+                    };
+
+                    Ok(BitPos(key, bit, start, end, unit))
+                }
+                b"BITOP" | b"bitop" | b"BitOp" | b"Bitop" => {
+                    let operation = match get_bytes_vec(v.get(1))?.to_ascii_uppercase().as_slice() {
+                        b"AND" => BitOperation::And,
+                        b"OR" => BitOperation::Or,
+                        b"XOR" => BitOperation::Xor,
+                        b"NOT" => BitOperation::Not,
+                        _ => return Err(Syntax),
+                    };
+                    let destination = get_bytes_vec(v.get(2))?;
+                    let keys = &v[3.min(v.len())..];
+                    if keys.is_empty() {
+                        return Err(ArgNumber);
+                    }
+                    if operation == BitOperation::Not && keys.len() > 1 {
+                        return Err(BitOpNotSingleSource);
+                    }
+
+                    let mut source_keys = Vec::<Key>::with_capacity(keys.len());
+                    for key in keys {
+                        source_keys.push(get_bytes_vec(Some(key))?);
+                    }
+
+                    Ok(BitOp(operation, destination, source_keys))
+                }
+                b"BITFIELD" | b"bitfield" | b"BitField" | b"Bitfield" => {
+                    let key = get_bytes_vec(v.get(1))?;
+                    let operations = parse_bitfield_operations(&v[2.min(v.len())..])?;
+
+                    Ok(BitField(key, operations))
+                }
+                b"PFADD" | b"pfadd" | b"PfAdd" | b"Pfadd" => {
+                    let key = get_bytes_vec(v.get(1))?;
+
+                    let mut elements = Values::with_capacity(v.len().saturating_sub(2));
+                    for element in &v[2.min(v.len())..] {
+                        elements.push(get_bytes_vec(Some(element))?);
+                    }
+
+                    Ok(PfAdd(key, elements))
+                }
+                b"PFCOUNT" | b"pfcount" | b"PfCount" | b"Pfcount" => {
+                    let keys = &v[1..];
+                    if keys.is_empty() {
+                        return Err(ArgNumber);
+                    }
+
+                    let mut hll_keys = Vec::<Key>::with_capacity(keys.len());
+                    for key in keys {
+                        hll_keys.push(get_bytes_vec(Some(key))?);
+                    }
+
+                    Ok(PfCount(hll_keys))
+                }
+                b"PFMERGE" | b"pfmerge" | b"PfMerge" | b"Pfmerge" => {
+                    let destination = get_bytes_vec(v.get(1))?;
+
+                    let mut source_keys = Vec::<Key>::with_capacity(v.len().saturating_sub(2));
+                    for key in &v[2..] {
+                        source_keys.push(get_bytes_vec(Some(key))?);
+                    }
+
+                    Ok(PfMerge(destination, source_keys))
+                }
+                b"XADD" | b"xadd" | b"XAdd" | b"Xadd" => {
+                    let stream_key = get_bytes_vec(v.get(1))?;
+                    let mut arguments = v[2.min(v.len())..].iter().peekable();
+
+                    // NOMKSTREAM leaves a missing stream alone
+                    let mut no_mkstream = false;
+                    let mut trim = None;
+                    let mut limit = None;
+                    let id = loop {
+                        let argument = get_bytes_vec(arguments.next())?;
+                        match argument.to_ascii_uppercase().as_slice() {
+                            b"NOMKSTREAM" => no_mkstream = true,
+                            strategy @ (b"MAXLEN" | b"MINID") => {
+                                trim = Some(parse_stream_trim(strategy, &mut arguments)?)
+                            }
+                            b"LIMIT" => limit = Some(parse_stream_trim_limit(&mut arguments)?),
+                            _ => break parse_new_stream_id(argument)?,
+                        }
+                    };
+                    let trim = limit_stream_trim(trim, limit)?;
+
+                    let pairs = arguments.collect::<Vec<_>>();
+                    if pairs.is_empty() || !pairs.len().is_multiple_of(2) {
+                        return Err(ArgNumber);
+                    }
+
+                    let mut fields = Items::with_capacity(pairs.len() / 2);
+                    for pair in pairs.chunks_exact(2) {
+                        let field = get_bytes_vec(Some(pair[0]))?;
+                        let value = get_bytes_vec(Some(pair[1]))?;
+                        fields.push((field, value));
+                    }
+
+                    Ok(XAdd(stream_key, no_mkstream, trim, id, fields))
+                }
+                b"XTRIM" | b"xtrim" | b"XTrim" | b"Xtrim" => {
+                    let stream_key = get_bytes_vec(v.get(1))?;
+                    let mut arguments = v[2.min(v.len())..].iter().peekable();
+                    if arguments.peek().is_none() {
+                        return Err(ArgNumber);
+                    }
+
+                    let mut trim = None;
+                    let mut limit = None;
+                    while let Some(argument) = arguments.next() {
+                        let argument = get_bytes_vec(Some(argument))?;
+                        match argument.to_ascii_uppercase().as_slice() {
+                            strategy @ (b"MAXLEN" | b"MINID") => {
+                                trim = Some(parse_stream_trim(strategy, &mut arguments)?)
+                            }
+                            b"LIMIT" => limit = Some(parse_stream_trim_limit(&mut arguments)?),
+                            _ => return Err(Syntax),
+                        }
+                    }
+
+                    match limit_stream_trim(trim, limit)? {
+                        Some(trim) => Ok(XTrim(stream_key, trim)),
+                        None => Err(Syntax),
+                    }
+                }
+                b"XDEL" | b"xdel" | b"XDel" | b"Xdel" => {
+                    let stream_key = get_bytes_vec(v.get(1))?;
+                    if v.len() < 3 {
+                        return Err(ArgNumber);
+                    }
+
+                    let mut ids = Vec::with_capacity(v.len() - 2);
+                    for id in &v[2..] {
+                        ids.push(parse_stream_id(&get_bytes_vec(Some(id))?, 0)?);
+                    }
+                    Ok(XDel(stream_key, ids))
+                }
+                b"XLEN" | b"xlen" | b"XLen" | b"Xlen" => {
+                    let stream_key = get_bytes_vec(v.get(1))?;
+                    Ok(XLen(stream_key))
+                }
+                b"XRANGE" | b"xrange" | b"XRange" | b"Xrange" | b"XREVRANGE" | b"xrevrange"
+                | b"XRevRange" | b"Xrevrange" => {
+                    let rev = command.eq_ignore_ascii_case(b"XREVRANGE");
+                    let stream_key = get_bytes_vec(v.get(1))?;
+                    // XREVRANGE takes the end of the range first
+                    let (start, end) = match rev {
+                        true => (v.get(3), v.get(2)),
+                        false => (v.get(2), v.get(3)),
+                    };
+                    let start = get_bytes_vec(start).and_then(parse_stream_start)?;
+                    let end = get_bytes_vec(end).and_then(parse_stream_end)?;
+
+                    let count = match &v[4.min(v.len())..] {
+                        [] => None,
+                        [option, count]
+                            if get_bytes_vec(Some(option))?.eq_ignore_ascii_case(b"COUNT") =>
+                        {
+                            // a negative count is the same as 0
+                            let count = get_bytes_vec(Some(count)).and_then(parse_integer)?;
+                            Some(count.max(0) as usize)
+                        }
+                        _ => return Err(Syntax),
+                    };
+
+                    match rev {
+                        true => Ok(XRevRange(stream_key, start, end, count)),
+                        false => Ok(XRange(stream_key, start, end, count)),
+                    }
+                }
+                b"XREAD" | b"xread" | b"XRead" | b"Xread" => {
+                    let arguments = parse_stream_read_arguments(&v[1..], "xread")?;
+                    let ids = arguments
+                        .ids
+                        .into_iter()
+                        .map(parse_stream_read_id)
+                        .collect::<Result<Vec<_>, _>>()?;
+                    Ok(XRead(arguments.keys, ids, arguments.count, arguments.block))
+                }
+                b"XGROUP" | b"xgroup" | b"XGroup" | b"Xgroup" => {
+                    let subcommand = get_bytes_vec(v.get(1))?;
+                    let stream_key = get_bytes_vec(v.get(2))?;
+                    let group = get_bytes_vec(v.get(3))?;
+                    match subcommand.to_ascii_uppercase().as_slice() {
+                        b"CREATE" => {
+                            let id = get_bytes_vec(v.get(4)).and_then(parse_stream_read_id)?;
+                            let mk_stream = match &v[5.min(v.len())..] {
+                                [] => false,
+                                [option]
+                                    if get_bytes_vec(Some(option))?
+                                        .eq_ignore_ascii_case(b"MKSTREAM") =>
+                                {
+                                    true
+                                }
+                                _ => return Err(Syntax),
+                            };
+                            Ok(XGroupCreate(stream_key, group, id, mk_stream))
+                        }
+                        b"SETID" => {
+                            let id = get_bytes_vec(v.get(4)).and_then(parse_stream_read_id)?;
+                            Ok(XGroupSetId(stream_key, group, id))
+                        }
+                        b"DESTROY" => Ok(XGroupDestroy(stream_key, group)),
+                        b"CREATECONSUMER" => {
+                            let consumer = get_bytes_vec(v.get(4))?;
+                            Ok(XGroupCreateConsumer(stream_key, group, consumer))
+                        }
+                        b"DELCONSUMER" => {
+                            let consumer = get_bytes_vec(v.get(4))?;
+                            Ok(XGroupDelConsumer(stream_key, group, consumer))
+                        }
+                        _ => Err(NotSupported(format!(
+                            "XGROUP {}",
+                            String::from_utf8_lossy(&subcommand)
+                        ))),
+                    }
+                }
+                b"XREADGROUP" | b"xreadgroup" | b"XReadGroup" | b"Xreadgroup" => {
+                    match get_bytes_vec(v.get(1)) {
+                        Ok(option) if option.eq_ignore_ascii_case(b"GROUP") => {}
+                        _ => return Err(Syntax),
+                    }
+                    let group = get_bytes_vec(v.get(2))?;
+                    let consumer = get_bytes_vec(v.get(3))?;
+
+                    let arguments =
+                        parse_stream_read_arguments(&v[4.min(v.len())..], "xreadgroup")?;
+                    let ids = arguments
+                        .ids
+                        .into_iter()
+                        .map(parse_stream_read_group_id)
+                        .collect::<Result<Vec<_>, _>>()?;
+                    Ok(XReadGroup(
+                        group,
+                        consumer,
+                        arguments.keys,
+                        ids,
+                        arguments.count,
+                        arguments.block,
+                        arguments.no_ack,
+                    ))
+                }
+                b"XACK" | b"xack" | b"XAck" | b"Xack" => {
+                    let stream_key = get_bytes_vec(v.get(1))?;
+                    let group = get_bytes_vec(v.get(2))?;
+                    if v.len() < 4 {
+                        return Err(ArgNumber);
+                    }
+
+                    let mut ids = Vec::with_capacity(v.len() - 3);
+                    for id in &v[3..] {
+                        ids.push(parse_stream_id(&get_bytes_vec(Some(id))?, 0)?);
+                    }
+                    Ok(XAck(stream_key, group, ids))
+                }
+                b"XINFO" | b"xinfo" | b"XInfo" | b"Xinfo" => {
+                    let subcommand = get_bytes_vec(v.get(1))?;
+                    let stream_key = get_bytes_vec(v.get(2))?;
+                    match subcommand.to_ascii_uppercase().as_slice() {
+                        b"STREAM" => match &v[3..] {
+                            [] => Ok(XInfoStream(stream_key)),
+                            [option]
+                                if get_bytes_vec(Some(option))?.eq_ignore_ascii_case(b"FULL") =>
+                            {
+                                Ok(XInfoStreamFull(stream_key, Some(10)))
+                            }
+                            [option, count_option, count]
+                                if get_bytes_vec(Some(option))?.eq_ignore_ascii_case(b"FULL")
+                                    && get_bytes_vec(Some(count_option))?
+                                        .eq_ignore_ascii_case(b"COUNT") =>
+                            {
+                                // a count of 0 means every entry
+                                let count = get_bytes_vec(Some(count)).and_then(parse_integer)?;
+                                let count = Some(count.max(0) as usize).filter(|count| *count > 0);
+                                Ok(XInfoStreamFull(stream_key, count))
+                            }
+                            _ => Err(Syntax),
+                        },
+                        b"GROUPS" => Ok(XInfoGroups(stream_key)),
+                        b"CONSUMERS" => {
+                            let group = get_bytes_vec(v.get(3))?;
+                            Ok(XInfoConsumers(stream_key, group))
+                        }
+                        _ => Err(NotSupported(format!(
+                            "XINFO {}",
+                            String::from_utf8_lossy(&subcommand)
+                        ))),
+                    }
+                }
+                b"XSETID" | b"xsetid" | b"XSetId" | b"Xsetid" => {
+                    let stream_key = get_bytes_vec(v.get(1))?;
+                    let last_id = parse_stream_id(&get_bytes_vec(v.get(2))?, 0)?;
+
+                    let mut entries_added = None;
+                    let mut max_deleted_id = None;
+                    let mut arguments = v[3.min(v.len())..].iter();
+                    while let Some(option) = arguments.next() {
+                        match get_bytes_vec(Some(option))?.to_ascii_uppercase().as_slice() {
+                            b"ENTRIESADDED" => {
+                                let count =
+                                    get_bytes_vec(arguments.next()).and_then(parse_integer)?;
+                                if count < 0 {
+                                    return Err(NegativeArgument("ENTRIESADDED"));
+                                }
+                                entries_added = Some(count as u64);
+                            }
+                            b"MAXDELETEDID" => {
+                                let id = get_bytes_vec(arguments.next())?;
+                                max_deleted_id = Some(parse_stream_id(&id, 0)?);
+                            }
+                            _ => return Err(Syntax),
+                        }
+                    }
+
+                    Ok(XSetId(stream_key, last_id, entries_added, max_deleted_id))
+                }
+                b"XPENDING" | b"xpending" | b"XPending" | b"Xpending" => {
+                    let stream_key = get_bytes_vec(v.get(1))?;
+                    let group = get_bytes_vec(v.get(2))?;
+                    let mut arguments = &v[3.min(v.len())..];
+                    if arguments.is_empty() {
+                        return Ok(XPending(stream_key, group));
+                    }
+
+                    let mut min_idle = None;
+                    if get_bytes_vec(arguments.first())?.eq_ignore_ascii_case(b"IDLE") {
+                        let idle = get_bytes_vec(arguments.get(1)).and_then(parse_integer)?;
+                        min_idle = Some(idle.max(0) as u64);
+                        arguments = &arguments[2.min(arguments.len())..];
+                    }
+
+                    let start = get_bytes_vec(arguments.first())
+                        .map_err(|_| Syntax)
+                        .and_then(parse_stream_start)?;
+                    let end = get_bytes_vec(arguments.get(1))
+                        .map_err(|_| Syntax)
+                        .and_then(parse_stream_end)?;
+                    // a negative count is the same as 0
+                    let count = get_bytes_vec(arguments.get(2))
+                        .map_err(|_| Syntax)
+                        .and_then(parse_integer)?
+                        .max(0) as usize;
+                    let consumer = match &arguments[3.min(arguments.len())..] {
+                        [] => None,
+                        [consumer] => Some(get_bytes_vec(Some(consumer))?),
+                        _ => return Err(Syntax),
+                    };
+
+                    Ok(XPendingRange(
+                        stream_key, group, start, end, count, min_idle, consumer,
+                    ))
+                }
+                b"XCLAIM" | b"xclaim" | b"XClaim" | b"Xclaim" => {
+                    let stream_key = get_bytes_vec(v.get(1))?;
+                    let group = get_bytes_vec(v.get(2))?;
+                    let consumer = get_bytes_vec(v.get(3))?;
+                    // a negative idle time is the same as 0
+                    let min_idle = get_bytes_vec(v.get(4)).and_then(parse_integer)?;
+                    let mut options = ClaimOptions {
+                        min_idle: min_idle.max(0) as u64,
+                        ..ClaimOptions::default()
+                    };
+
+                    let (ids, last_id) =
+                        parse_xclaim_arguments(&v[5.min(v.len())..], &mut options)?;
+                    Ok(XClaim(stream_key, group, consumer, ids, options, last_id))
+                }
+                b"XAUTOCLAIM" | b"xautoclaim" | b"XAutoClaim" | b"Xautoclaim" => {
+                    let stream_key = get_bytes_vec(v.get(1))?;
+                    let group = get_bytes_vec(v.get(2))?;
+                    let consumer = get_bytes_vec(v.get(3))?;
+                    let min_idle = get_bytes_vec(v.get(4)).and_then(parse_integer)?;
+                    let mut options = ClaimOptions {
+                        min_idle: min_idle.max(0) as u64,
+                        ..ClaimOptions::default()
+                    };
+                    let start = parse_stream_id(&get_bytes_vec(v.get(5))?, 0)?;
+
+                    let mut count = 100;
+                    let mut arguments = v[6.min(v.len())..].iter();
+                    while let Some(option) = arguments.next() {
+                        match get_bytes_vec(Some(option))?.to_ascii_uppercase().as_slice() {
+                            b"COUNT" => {
+                                count = match get_bytes_vec(arguments.next())
+                                    .and_then(parse_integer)?
+                                {
+                                    count if count <= 0 => return Err(NotGreaterThanZero("COUNT")),
+                                    count => count as usize,
+                                };
+                            }
+                            b"JUSTID" => options.just_id = true,
+                            _ => return Err(Syntax),
+                        }
+                    }
+
+                    Ok(XAutoClaim(
+                        stream_key, group, consumer, start, count, options,
+                    ))
+                }
                 b"SETEX" | b"setex" | b"SetEx" | b"Setex" => {
                     let key = get_bytes_vec(v.get(1))?;
                     let duration = get_bytes_vec(v.get(2)).and_then(parse_duration)?;
@@ -86,7 +1065,7 @@ impl Command {
                     // We want [(key, value), (key, value), (key, value), ..]
                     let pairs = &v[1..];
                     let chunk_size = 2_usize;
-                    if pairs.is_empty() || pairs.len() % chunk_size != 0 {
+                    if pairs.is_empty() || !pairs.len().is_multiple_of(chunk_size) {
                         return Err(ArgNumber);
                     }
 
@@ -94,8 +1073,8 @@ impl Command {
                     for pair in pairs.chunks_exact(chunk_size) {
                         match pair {
                             [key, value] => {
-                                let key = get_bytes_vec(Some(&key))?;
-                                let value = get_bytes_vec(Some(&value))?;
+                                let key = get_bytes_vec(Some(key))?;
+                                let value = get_bytes_vec(Some(value))?;
                                 items.push((key, value));
                             }
                             _ => unreachable!(), // pairs has even length so each chunk will have len 2
@@ -107,7 +1086,7 @@ impl Command {
                     let pairs = &v[1..];
 
                     let chunk_size = 2_usize;
-                    if pairs.is_empty() || pairs.len() % chunk_size != 0 {
+                    if pairs.is_empty() || !pairs.len().is_multiple_of(chunk_size) {
                         return Err(ArgNumber);
                     }
 
@@ -115,8 +1094,8 @@ impl Command {
                     for pair in pairs.chunks_exact(chunk_size) {
                         match pair {
                             [key, value] => {
-                                let key = get_bytes_vec(Some(&key))?;
-                                let value = get_bytes_vec(Some(&value))?;
+                                let key = get_bytes_vec(Some(key))?;
+                                let value = get_bytes_vec(Some(value))?;
                                 items.push((key, value));
                             }
                             _ => unreachable!(),
@@ -161,7 +1140,7 @@ impl Command {
                         return Err(ArgNumber);
                     }
 
-                    let mut keys_vec = Keys::with_capacity(keys.len());
+                    let mut keys_vec = Vec::<Key>::with_capacity(keys.len());
                     for key in keys {
                         let key = get_bytes_vec(Some(key))?;
                         keys_vec.push(key);
@@ -174,7 +1153,7 @@ impl Command {
                     let pairs = &v[2..];
 
                     let chunk_size = 2_usize;
-                    if pairs.is_empty() || pairs.len() % chunk_size != 0 {
+                    if pairs.is_empty() || !pairs.len().is_multiple_of(chunk_size) {
                         return Err(ArgNumber);
                     }
 
@@ -182,14 +1161,26 @@ impl Command {
                     for pair in pairs.chunks_exact(chunk_size) {
                         match pair {
                             [key, value] => {
-                                let key = get_bytes_vec(Some(&key))?;
-                                let value = get_bytes_vec(Some(&value))?;
+                                let key = get_bytes_vec(Some(key))?;
+                                let value = get_bytes_vec(Some(value))?;
                                 items.push((key, value));
                             }
                             _ => unreachable!(),
                         }
                     }
-                    Ok(HSet(hash_key, items))
+
+                    // HMSET is the legacy form of HSET, replying +OK
+                    match command.eq_ignore_ascii_case(b"HMSET") {
+                        true => Ok(HMSet(hash_key, items)),
+                        false => Ok(HSet(hash_key, items)),
+                    }
+                }
+                b"HSETNX" | b"hsetnx" | b"HSetnx" | b"Hsetnx" => {
+                    let hash_key = get_bytes_vec(v.get(1))?;
+                    let field_key = get_bytes_vec(v.get(2))?;
+                    let value = get_bytes_vec(v.get(3))?;
+
+                    Ok(HSetnx(hash_key, field_key, value))
                 }
                 b"HGET" | b"hget" => {
                     //HGet(Key, Key),
@@ -198,39 +1189,1361 @@ impl Command {
 
                     Ok(HGet(hash_key, field_key))
                 }
-                b"DEL" | b"del" | b"Del" => {
-                    let key = get_bytes_vec(v.get(1))?;
-                    Ok(Del(key))
+                b"HDEL" | b"hdel" | b"HDel" | b"Hdel" => {
+                    let hash_key = get_bytes_vec(v.get(1))?;
+                    let fields = &v[2..];
+                    if fields.is_empty() {
+                        return Err(ArgNumber);
+                    }
+
+                    let mut field_keys = Vec::<Key>::with_capacity(fields.len());
+                    for field in fields {
+                        field_keys.push(get_bytes_vec(Some(field))?);
+                    }
+
+                    Ok(HDel(hash_key, field_keys))
                 }
-                b"INCR" | b"incr" | b"Incr" => {
-                    let key = get_bytes_vec(v.get(1))?;
-                    Ok(Incr(key))
+                b"HMGET" | b"hmget" | b"HMGet" | b"Hmget" => {
+                    let hash_key = get_bytes_vec(v.get(1))?;
+                    let fields = &v[2..];
+                    if fields.is_empty() {
+                        return Err(ArgNumber);
+                    }
+
+                    let mut field_keys = Vec::<Key>::with_capacity(fields.len());
+                    for field in fields {
+                        field_keys.push(get_bytes_vec(Some(field))?);
+                    }
+
+                    Ok(HMGet(hash_key, field_keys))
                 }
-                b"INCRBY" | b"incrby" | b"IncrBy" => {
-                    let key = get_bytes_vec(v.get(1))?;
-                    let increment = get_bytes_vec(v.get(2)).and_then(parse_increment)?;
-                    Ok(IncrBy(key, increment))
+                b"HINCRBY" | b"hincrby" | b"HIncrBy" | b"Hincrby" => {
+                    let hash_key = get_bytes_vec(v.get(1))?;
+                    let field_key = get_bytes_vec(v.get(2))?;
+                    let increment = get_bytes_vec(v.get(3)).and_then(parse_integer)?;
+                    Ok(HIncrBy(hash_key, field_key, increment))
                 }
-                b"EXISTS" | b"exists" | b"Exists" => {
-                    let key = get_bytes_vec(v.get(1))?;
-                    Ok(Exists(key))
+                b"HINCRBYFLOAT" | b"hincrbyfloat" | b"HIncrByFloat" | b"Hincrbyfloat" => {
+                    let hash_key = get_bytes_vec(v.get(1))?;
+                    let field_key = get_bytes_vec(v.get(2))?;
+                    let increment = get_bytes_vec(v.get(3)).and_then(parse_float)?;
+                    Ok(HIncrByFloat(hash_key, field_key, increment))
                 }
-                b"TTL" | b"ttl" | b"Ttl" => {
-                    let key = get_bytes_vec(v.get(1))?;
-                    Ok(Ttl(key))
+                b"HGETALL" | b"hgetall" | b"HGetAll" | b"Hgetall" => {
+                    let hash_key = get_bytes_vec(v.get(1))?;
+                    Ok(HGetAll(hash_key))
                 }
-                b"PTTL" | b"pttl" | b"Pttl" => {
-                    let key = get_bytes_vec(v.get(1))?;
-                    Ok(Pttl(key))
+                b"HKEYS" | b"hkeys" | b"HKeys" | b"Hkeys" => {
+                    let hash_key = get_bytes_vec(v.get(1))?;
+                    Ok(HKeys(hash_key))
+                }
+                b"HVALS" | b"hvals" | b"HVals" | b"Hvals" => {
+                    let hash_key = get_bytes_vec(v.get(1))?;
+                    Ok(HVals(hash_key))
+                }
+                b"HLEN" | b"hlen" | b"HLen" | b"Hlen" => {
+                    let hash_key = get_bytes_vec(v.get(1))?;
+                    Ok(HLen(hash_key))
+                }
+                b"HEXISTS" | b"hexists" | b"HExists" | b"Hexists" => {
+                    let hash_key = get_bytes_vec(v.get(1))?;
+                    let field_key = get_bytes_vec(v.get(2))?;
+                    Ok(HExists(hash_key, field_key))
+                }
+                b"LPUSH" | b"lpush" | b"LPush" | b"Lpush" | b"RPUSH" | b"rpush" | b"RPush"
+                | b"Rpush" => {
+                    let list_key = get_bytes_vec(v.get(1))?;
+                    let elements = &v[2..];
+                    if elements.is_empty() {
+                        return Err(ArgNumber);
+                    }
+
+                    let mut values = Values::with_capacity(elements.len());
+                    for element in elements {
+                        values.push(get_bytes_vec(Some(element))?);
+                    }
+
+                    match command.eq_ignore_ascii_case(b"LPUSH") {
+                        true => Ok(LPush(list_key, values)),
+                        false => Ok(RPush(list_key, values)),
+                    }
+                }
+                b"LPOP" | b"lpop" | b"LPop" | b"Lpop" | b"RPOP" | b"rpop" | b"RPop" | b"Rpop" => {
+                    let list_key = get_bytes_vec(v.get(1))?;
+                    let count = match v.get(2) {
+                        Some(count) => Some(get_bytes_vec(Some(count)).and_then(parse_count)?),
+                        None => None,
+                    };
+                    if v.len() > 3 {
+                        return Err(ArgNumber);
+                    }
+
+                    match command.eq_ignore_ascii_case(b"LPOP") {
+                        true => Ok(LPop(list_key, count)),
+                        false => Ok(RPop(list_key, count)),
+                    }
+                }
+                b"LLEN" | b"llen" | b"LLen" | b"Llen" => {
+                    let list_key = get_bytes_vec(v.get(1))?;
+                    Ok(LLen(list_key))
+                }
+                b"LRANGE" | b"lrange" | b"LRange" | b"Lrange" => {
+                    let list_key = get_bytes_vec(v.get(1))?;
+                    let start = get_bytes_vec(v.get(2)).and_then(parse_integer)?;
+                    let stop = get_bytes_vec(v.get(3)).and_then(parse_integer)?;
+                    Ok(LRange(list_key, start, stop))
+                }
+                b"LINDEX" | b"lindex" | b"LIndex" | b"Lindex" => {
+                    let list_key = get_bytes_vec(v.get(1))?;
+                    let index = get_bytes_vec(v.get(2)).and_then(parse_integer)?;
+                    Ok(LIndex(list_key, index))
+                }
+                b"LSET" | b"lset" | b"LSet" | b"Lset" => {
+                    let list_key = get_bytes_vec(v.get(1))?;
+                    let index = get_bytes_vec(v.get(2)).and_then(parse_integer)?;
+                    let value = get_bytes_vec(v.get(3))?;
+                    Ok(LSet(list_key, index, value))
+                }
+                b"LTRIM" | b"ltrim" | b"LTrim" | b"Ltrim" => {
+                    let list_key = get_bytes_vec(v.get(1))?;
+                    let start = get_bytes_vec(v.get(2)).and_then(parse_integer)?;
+                    let stop = get_bytes_vec(v.get(3)).and_then(parse_integer)?;
+                    Ok(LTrim(list_key, start, stop))
+                }
+                b"LINSERT" | b"linsert" | b"LInsert" | b"Linsert" => {
+                    let list_key = get_bytes_vec(v.get(1))?;
+                    let before = match get_bytes_vec(v.get(2))?.to_ascii_uppercase().as_slice() {
+                        b"BEFORE" => true,
+                        b"AFTER" => false,
+                        _ => return Err(Syntax),
+                    };
+                    let pivot = get_bytes_vec(v.get(3))?;
+                    let value = get_bytes_vec(v.get(4))?;
+                    Ok(LInsert(list_key, before, pivot, value))
+                }
+                b"LREM" | b"lrem" | b"LRem" | b"Lrem" => {
+                    let list_key = get_bytes_vec(v.get(1))?;
+                    let count = get_bytes_vec(v.get(2)).and_then(parse_integer)?;
+                    let value = get_bytes_vec(v.get(3))?;
+                    Ok(LRem(list_key, count, value))
+                }
+                b"LPOS" | b"lpos" | b"LPos" | b"Lpos" => {
+                    let list_key = get_bytes_vec(v.get(1))?;
+                    let value = get_bytes_vec(v.get(2))?;
+                    let mut rank = 1;
+                    let mut count = None;
+                    let mut max_len = 0;
+
+                    let mut options = v[3..].iter();
+                    while let Some(option) = options.next() {
+                        let option = get_bytes_vec(Some(option))?.to_ascii_uppercase();
+                        let value = get_bytes_vec(options.next()).map_err(|_| Syntax)?;
+                        let value = parse_integer(value)?;
+                        match option.as_slice() {
+                            b"RANK" if value == 0 => return Err(RankZero),
+                            b"RANK" => rank = value,
+                            b"COUNT" if value < 0 => return Err(NegativeOption("COUNT")),
+                            b"COUNT" => count = Some(value as usize),
+                            b"MAXLEN" if value < 0 => return Err(NegativeOption("MAXLEN")),
+                            b"MAXLEN" => max_len = value as usize,
+                            _ => return Err(Syntax),
+                        }
+                    }
+
+                    Ok(LPos(list_key, value, rank, count, max_len))
+                }
+                b"LMOVE" | b"lmove" | b"LMove" | b"Lmove" => {
+                    let source = get_bytes_vec(v.get(1))?;
+                    let destination = get_bytes_vec(v.get(2))?;
+                    let from = get_bytes_vec(v.get(3)).and_then(parse_list_end)?;
+                    let to = get_bytes_vec(v.get(4)).and_then(parse_list_end)?;
+                    Ok(LMove(source, destination, from, to))
+                }
+                b"RPOPLPUSH" | b"rpoplpush" | b"RPopLPush" | b"Rpoplpush" => {
+                    let source = get_bytes_vec(v.get(1))?;
+                    let destination = get_bytes_vec(v.get(2))?;
+                    Ok(LMove(source, destination, ListEnd::Right, ListEnd::Left))
+                }
+                b"BLPOP" | b"blpop" | b"BLPop" | b"Blpop" | b"BRPOP" | b"brpop" | b"BRPop"
+                | b"Brpop" => {
+                    if v.len() < 3 {
+                        return Err(ArgNumber);
+                    }
+
+                    let mut list_keys = Vec::<Key>::with_capacity(v.len() - 2);
+                    for key in &v[1..v.len() - 1] {
+                        list_keys.push(get_bytes_vec(Some(key))?);
+                    }
+                    let timeout = get_bytes_vec(v.last()).and_then(parse_timeout)?;
+
+                    match command.eq_ignore_ascii_case(b"BLPOP") {
+                        true => Ok(BLPop(list_keys, timeout)),
+                        false => Ok(BRPop(list_keys, timeout)),
+                    }
+                }
+                b"BLMOVE" | b"blmove" | b"BLMove" | b"Blmove" => {
+                    let source = get_bytes_vec(v.get(1))?;
+                    let destination = get_bytes_vec(v.get(2))?;
+                    let from = get_bytes_vec(v.get(3)).and_then(parse_list_end)?;
+                    let to = get_bytes_vec(v.get(4)).and_then(parse_list_end)?;
+                    let timeout = get_bytes_vec(v.get(5)).and_then(parse_timeout)?;
+                    Ok(BLMove(source, destination, from, to, timeout))
+                }
+                b"BRPOPLPUSH" | b"brpoplpush" | b"BRPopLPush" | b"Brpoplpush" => {
+                    let source = get_bytes_vec(v.get(1))?;
+                    let destination = get_bytes_vec(v.get(2))?;
+                    let timeout = get_bytes_vec(v.get(3)).and_then(parse_timeout)?;
+                    Ok(BLMove(
+                        source,
+                        destination,
+                        ListEnd::Right,
+                        ListEnd::Left,
+                        timeout,
+                    ))
+                }
+                b"LMPOP" | b"lmpop" | b"LMPop" | b"Lmpop" => {
+                    let (list_keys, end, count) = parse_mpop_arguments(&v[1..], parse_list_end)?;
+                    Ok(LMPop(list_keys, end, count))
+                }
+                b"BLMPOP" | b"blmpop" | b"BLMPop" | b"Blmpop" => {
+                    let timeout = get_bytes_vec(v.get(1)).and_then(parse_timeout)?;
+                    let (list_keys, end, count) =
+                        parse_mpop_arguments(&v[2.min(v.len())..], parse_list_end)?;
+                    Ok(BLMPop(timeout, list_keys, end, count))
+                }
+                b"SADD" | b"sadd" | b"SAdd" | b"Sadd" | b"SREM" | b"srem" | b"SRem" | b"Srem" => {
+                    let set_key = get_bytes_vec(v.get(1))?;
+                    let elements = &v[2..];
+                    if elements.is_empty() {
+                        return Err(ArgNumber);
+                    }
+
+                    let mut members = Values::with_capacity(elements.len());
+                    for element in elements {
+                        members.push(get_bytes_vec(Some(element))?);
+                    }
+
+                    match command.eq_ignore_ascii_case(b"SADD") {
+                        true => Ok(SAdd(set_key, members)),
+                        false => Ok(SRem(set_key, members)),
+                    }
+                }
+                b"SMEMBERS" | b"smembers" | b"SMembers" | b"Smembers" => {
+                    let set_key = get_bytes_vec(v.get(1))?;
+                    Ok(SMembers(set_key))
+                }
+                b"SISMEMBER" | b"sismember" | b"SIsMember" | b"Sismember" => {
+                    let set_key = get_bytes_vec(v.get(1))?;
+                    let member = get_bytes_vec(v.get(2))?;
+                    Ok(SIsMember(set_key, member))
+                }
+                b"SMISMEMBER" | b"smismember" | b"SMIsMember" | b"Smismember" => {
+                    let set_key = get_bytes_vec(v.get(1))?;
+                    let elements = &v[2..];
+                    if elements.is_empty() {
+                        return Err(ArgNumber);
+                    }
+
+                    let mut members = Values::with_capacity(elements.len());
+                    for element in elements {
+                        members.push(get_bytes_vec(Some(element))?);
+                    }
+                    Ok(SMIsMember(set_key, members))
+                }
+                b"SCARD" | b"scard" | b"SCard" | b"Scard" => {
+                    let set_key = get_bytes_vec(v.get(1))?;
+                    Ok(SCard(set_key))
+                }
+                b"SPOP" | b"spop" | b"SPop" | b"Spop" => {
+                    let set_key = get_bytes_vec(v.get(1))?;
+                    let count = match v.get(2) {
+                        Some(count) => Some(get_bytes_vec(Some(count)).and_then(parse_count)?),
+                        None => None,
+                    };
+                    if v.len() > 3 {
+                        return Err(Syntax);
+                    }
+                    Ok(SPop(set_key, count))
+                }
+                b"SRANDMEMBER" | b"srandmember" | b"SRandMember" | b"Srandmember" => {
+                    let set_key = get_bytes_vec(v.get(1))?;
+                    let count = match v.get(2) {
+                        Some(count) => {
+                            Some(get_bytes_vec(Some(count)).and_then(parse_random_count)?)
+                        }
+                        None => None,
+                    };
+                    if v.len() > 3 {
+                        return Err(Syntax);
+                    }
+                    Ok(SRandMember(set_key, count))
+                }
+                b"SMOVE" | b"smove" | b"SMove" | b"Smove" => {
+                    let source = get_bytes_vec(v.get(1))?;
+                    let destination = get_bytes_vec(v.get(2))?;
+                    let member = get_bytes_vec(v.get(3))?;
+                    Ok(SMove(source, destination, member))
+                }
+                b"SINTER" | b"sinter" | b"SInter" | b"Sinter" | b"SUNION" | b"sunion"
+                | b"SUnion" | b"Sunion" | b"SDIFF" | b"sdiff" | b"SDiff" | b"Sdiff" => {
+                    let keys = &v[1..];
+                    if keys.is_empty() {
+                        return Err(ArgNumber);
+                    }
+
+                    let mut set_keys = Vec::<Key>::with_capacity(keys.len());
+                    for key in keys {
+                        set_keys.push(get_bytes_vec(Some(key))?);
+                    }
+
+                    match command.to_ascii_uppercase().as_slice() {
+                        b"SINTER" => Ok(SInter(set_keys)),
+                        b"SUNION" => Ok(SUnion(set_keys)),
+                        _ => Ok(SDiff(set_keys)),
+                    }
+                }
+                b"SINTERCARD" | b"sintercard" | b"SInterCard" | b"Sintercard" => {
+                    let num_keys = get_bytes_vec(v.get(1)).and_then(parse_integer)?;
+                    if num_keys <= 0 {
+                        return Err(NotGreaterThanZero("numkeys"));
+                    }
+
+                    let num_keys = num_keys as usize;
+                    let keys = match v.get(2..).and_then(|arguments| arguments.get(..num_keys)) {
+                        Some(keys) => keys,
+                        None => return Err(TooManyKeys),
+                    };
+
+                    let mut set_keys = Vec::<Key>::with_capacity(keys.len());
+                    for key in keys {
+                        set_keys.push(get_bytes_vec(Some(key))?);
+                    }
+
+                    // 0 for no limit
+                    let limit = match &v[2 + num_keys..] {
+                        [] => 0,
+                        [option, limit]
+                            if get_bytes_vec(Some(option))?.eq_ignore_ascii_case(b"LIMIT") =>
+                        {
+                            match get_bytes_vec(Some(limit)).and_then(parse_integer)? {
+                                limit if limit < 0 => return Err(NegativeOption("LIMIT")),
+                                limit => limit as usize,
+                            }
+                        }
+                        _ => return Err(Syntax),
+                    };
+
+                    Ok(SInterCard(set_keys, limit))
+                }
+                b"SINTERSTORE" | b"sinterstore" | b"SInterStore" | b"Sinterstore"
+                | b"SUNIONSTORE" | b"sunionstore" | b"SUnionStore" | b"Sunionstore"
+                | b"SDIFFSTORE" | b"sdiffstore" | b"SDiffStore" | b"Sdiffstore" => {
+                    let destination = get_bytes_vec(v.get(1))?;
+                    let keys = &v[2..];
+                    if keys.is_empty() {
+                        return Err(ArgNumber);
+                    }
+
+                    let mut set_keys = Vec::<Key>::with_capacity(keys.len());
+                    for key in keys {
+                        set_keys.push(get_bytes_vec(Some(key))?);
+                    }
+
+                    match command.to_ascii_uppercase().as_slice() {
+                        b"SINTERSTORE" => Ok(SInterStore(destination, set_keys)),
+                        b"SUNIONSTORE" => Ok(SUnionStore(destination, set_keys)),
+                        _ => Ok(SDiffStore(destination, set_keys)),
+                    }
+                }
+                b"ZADD" | b"zadd" | b"ZAdd" | b"Zadd" => {
+                    let zset_key = get_bytes_vec(v.get(1))?;
+                    let mut arguments = &v[2..];
+                    if arguments.is_empty() {
+                        return Err(ArgNumber);
+                    }
+
+                    let mut options = ZAddOptions::default();
+                    while let Some(option) = arguments.first() {
+                        match get_bytes_vec(Some(option))?.to_ascii_uppercase().as_slice() {
+                            b"NX" => options.nx = true,
+                            b"XX" => options.xx = true,
+                            b"GT" => options.gt = true,
+                            b"LT" => options.lt = true,
+                            b"CH" => options.ch = true,
+                            b"INCR" => options.incr = true,
+                            _ => break,
+                        }
+                        arguments = &arguments[1..];
+                    }
+
+                    if options.nx && options.xx {
+                        return Err(IncompatibleOptions("XX and NX"));
+                    }
+                    if [options.nx, options.gt, options.lt]
+                        .iter()
+                        .filter(|option| **option)
+                        .count()
+                        > 1
+                    {
+                        return Err(IncompatibleOptions("GT, LT, and/or NX"));
+                    }
+                    if arguments.is_empty() || !arguments.len().is_multiple_of(2) {
+                        return Err(Syntax);
+                    }
+                    if options.incr && arguments.len() > 2 {
+                        return Err(IncrSinglePair);
+                    }
+
+                    let mut members = ScoredMembers::with_capacity(arguments.len() / 2);
+                    for pair in arguments.chunks(2) {
+                        let score = get_bytes_vec(pair.first()).and_then(parse_float)?;
+                        let member = get_bytes_vec(pair.get(1))?;
+                        members.push((score, member));
+                    }
+
+                    Ok(ZAdd(zset_key, options, members))
+                }
+                b"GEOADD" | b"geoadd" | b"GeoAdd" | b"Geoadd" => {
+                    let zset_key = get_bytes_vec(v.get(1))?;
+                    let mut arguments = &v[2.min(v.len())..];
+
+                    let mut options = ZAddOptions::default();
+                    while let Some(option) = arguments.first() {
+                        match get_bytes_vec(Some(option))?.to_ascii_uppercase().as_slice() {
+                            b"NX" => options.nx = true,
+                            b"XX" => options.xx = true,
+                            b"CH" => options.ch = true,
+                            _ => break,
+                        }
+                        arguments = &arguments[1..];
+                    }
+
+                    if options.nx && options.xx {
+                        return Err(IncompatibleOptions("XX and NX"));
+                    }
+                    if arguments.is_empty() || !arguments.len().is_multiple_of(3) {
+                        return Err(Syntax);
+                    }
+
+                    // positions are added as members scored with their geohash
+                    let mut members = ScoredMembers::with_capacity(arguments.len() / 3);
+                    for position in arguments.chunks(3) {
+                        let (longitude, latitude) = parse_coordinates(
+                            get_bytes_vec(Some(&position[0]))?,
+                            get_bytes_vec(Some(&position[1]))?,
+                        )?;
+                        let member = get_bytes_vec(Some(&position[2]))?;
+                        members.push((geo::encode(longitude, latitude) as f64, member));
+                    }
+
+                    Ok(ZAdd(zset_key, options, members))
+                }
+                b"GEOPOS" | b"geopos" | b"GeoPos" | b"Geopos" | b"GEOHASH" | b"geohash"
+                | b"GeoHash" | b"Geohash" => {
+                    let zset_key = get_bytes_vec(v.get(1))?;
+                    let mut members = Values::with_capacity(v.len().saturating_sub(2));
+                    for member in &v[2.min(v.len())..] {
+                        members.push(get_bytes_vec(Some(member))?);
+                    }
+
+                    match command.eq_ignore_ascii_case(b"GEOPOS") {
+                        true => Ok(GeoPos(zset_key, members)),
+                        false => Ok(GeoHash(zset_key, members)),
+                    }
+                }
+                b"GEODIST" | b"geodist" | b"GeoDist" | b"Geodist" => {
+                    let zset_key = get_bytes_vec(v.get(1))?;
+                    let member = get_bytes_vec(v.get(2))?;
+                    let other = get_bytes_vec(v.get(3))?;
+                    let unit = match &v[4.min(v.len())..] {
+                        [] => GeoUnit::Meters,
+                        [unit] => get_bytes_vec(Some(unit)).and_then(parse_geo_unit)?,
+                        _ => return Err(Syntax),
+                    };
+                    Ok(GeoDist(zset_key, member, other, unit))
+                }
+                b"GEOSEARCH" | b"geosearch" | b"GeoSearch" | b"Geosearch" => {
+                    let zset_key = get_bytes_vec(v.get(1))?;
+                    let (query, _) = parse_geo_search_arguments(&v[2.min(v.len())..], "geosearch")?;
+                    Ok(GeoSearch(zset_key, query))
+                }
+                b"GEOSEARCHSTORE" | b"geosearchstore" | b"GeoSearchStore" | b"Geosearchstore" => {
+                    let destination = get_bytes_vec(v.get(1))?;
+                    let source = get_bytes_vec(v.get(2))?;
+                    let (query, store_dist) =
+                        parse_geo_search_arguments(&v[3.min(v.len())..], "geosearchstore")?;
+                    Ok(GeoSearchStore(destination, source, query, store_dist))
+                }
+                b"ZSCORE" | b"zscore" | b"ZScore" | b"Zscore" => {
+                    let zset_key = get_bytes_vec(v.get(1))?;
+                    let member = get_bytes_vec(v.get(2))?;
+                    Ok(ZScore(zset_key, member))
+                }
+                b"ZMSCORE" | b"zmscore" | b"ZMScore" | b"Zmscore" => {
+                    let zset_key = get_bytes_vec(v.get(1))?;
+                    let elements = &v[2..];
+                    if elements.is_empty() {
+                        return Err(ArgNumber);
+                    }
+
+                    let mut members = Values::with_capacity(elements.len());
+                    for element in elements {
+                        members.push(get_bytes_vec(Some(element))?);
+                    }
+
+                    Ok(ZMScore(zset_key, members))
+                }
+                b"ZREM" | b"zrem" | b"ZRem" | b"Zrem" => {
+                    let zset_key = get_bytes_vec(v.get(1))?;
+                    let elements = &v[2..];
+                    if elements.is_empty() {
+                        return Err(ArgNumber);
+                    }
+
+                    let mut members = Values::with_capacity(elements.len());
+                    for element in elements {
+                        members.push(get_bytes_vec(Some(element))?);
+                    }
+
+                    Ok(ZRem(zset_key, members))
+                }
+                b"ZRANDMEMBER" | b"zrandmember" | b"ZRandMember" | b"Zrandmember" => {
+                    let zset_key = get_bytes_vec(v.get(1))?;
+                    let count = match v.get(2) {
+                        Some(count) => {
+                            Some(get_bytes_vec(Some(count)).and_then(parse_random_count)?)
+                        }
+                        None => None,
+                    };
+                    let with_scores = match v.get(3) {
+                        Some(option) => {
+                            match get_bytes_vec(Some(option))?.to_ascii_uppercase().as_slice() {
+                                b"WITHSCORES" if v.len() == 4 => true,
+                                _ => return Err(Syntax),
+                            }
+                        }
+                        None => false,
+                    };
+
+                    Ok(ZRandMember(zset_key, count, with_scores))
+                }
+                b"ZCARD" | b"zcard" | b"ZCard" | b"Zcard" => {
+                    let zset_key = get_bytes_vec(v.get(1))?;
+                    Ok(ZCard(zset_key))
+                }
+                b"ZRANGE" | b"zrange" | b"ZRange" | b"Zrange" => {
+                    let zset_key = get_bytes_vec(v.get(1))?;
+                    let (query, with_scores) =
+                        parse_zrange_arguments(&v[2..], ZRangeCommand::Range)?;
+                    Ok(ZRange(zset_key, query, with_scores))
+                }
+                b"ZRANGESTORE" | b"zrangestore" | b"ZRangeStore" | b"Zrangestore" => {
+                    let destination = get_bytes_vec(v.get(1))?;
+                    let source = get_bytes_vec(v.get(2))?;
+                    let (query, with_scores) =
+                        parse_zrange_arguments(&v[3..], ZRangeCommand::Range)?;
+                    if with_scores {
+                        return Err(Syntax);
+                    }
+
+                    Ok(ZRangeStore(destination, source, query))
+                }
+                b"ZRANGEBYSCORE" | b"zrangebyscore" | b"ZRangeByScore" | b"Zrangebyscore" => {
+                    let zset_key = get_bytes_vec(v.get(1))?;
+                    let (query, with_scores) =
+                        parse_zrange_arguments(&v[2..], ZRangeCommand::ByScore)?;
+                    Ok(ZRange(zset_key, query, with_scores))
+                }
+                b"ZREVRANGEBYSCORE" | b"zrevrangebyscore" | b"ZRevRangeByScore"
+                | b"Zrevrangebyscore" => {
+                    let zset_key = get_bytes_vec(v.get(1))?;
+                    let (query, with_scores) =
+                        parse_zrange_arguments(&v[2..], ZRangeCommand::RevByScore)?;
+                    Ok(ZRange(zset_key, query, with_scores))
+                }
+                b"ZRANGEBYLEX" | b"zrangebylex" | b"ZRangeByLex" | b"Zrangebylex" => {
+                    let zset_key = get_bytes_vec(v.get(1))?;
+                    let (query, _) = parse_zrange_arguments(&v[2..], ZRangeCommand::ByLex)?;
+                    Ok(ZRange(zset_key, query, false))
+                }
+                b"ZREVRANGEBYLEX" | b"zrevrangebylex" | b"ZRevRangeByLex" | b"Zrevrangebylex" => {
+                    let zset_key = get_bytes_vec(v.get(1))?;
+                    let (query, _) = parse_zrange_arguments(&v[2..], ZRangeCommand::RevByLex)?;
+                    Ok(ZRange(zset_key, query, false))
+                }
+                b"ZCOUNT" | b"zcount" | b"ZCount" | b"Zcount" => {
+                    let zset_key = get_bytes_vec(v.get(1))?;
+                    let min = get_bytes_vec(v.get(2)).and_then(parse_score_bound)?;
+                    let max = get_bytes_vec(v.get(3)).and_then(parse_score_bound)?;
+                    Ok(ZCount(zset_key, min, max))
+                }
+                b"ZLEXCOUNT" | b"zlexcount" | b"ZLexCount" | b"Zlexcount" => {
+                    let zset_key = get_bytes_vec(v.get(1))?;
+                    let min = get_bytes_vec(v.get(2)).and_then(parse_lex_bound)?;
+                    let max = get_bytes_vec(v.get(3)).and_then(parse_lex_bound)?;
+                    Ok(ZLexCount(zset_key, min, max))
+                }
+                b"ZINCRBY" | b"zincrby" | b"ZIncrBy" | b"Zincrby" => {
+                    let zset_key = get_bytes_vec(v.get(1))?;
+                    let increment = get_bytes_vec(v.get(2)).and_then(parse_float)?;
+                    let member = get_bytes_vec(v.get(3))?;
+                    Ok(ZIncrBy(zset_key, increment, member))
+                }
+                b"ZPOPMIN" | b"zpopmin" | b"ZPopMin" | b"Zpopmin" | b"ZPOPMAX" | b"zpopmax"
+                | b"ZPopMax" | b"Zpopmax" => {
+                    let zset_key = get_bytes_vec(v.get(1))?;
+                    let count = match v.get(2) {
+                        Some(count) => Some(get_bytes_vec(Some(count)).and_then(parse_count)?),
+                        None => None,
+                    };
+
+                    match command.eq_ignore_ascii_case(b"ZPOPMIN") {
+                        true => Ok(ZPopMin(zset_key, count)),
+                        false => Ok(ZPopMax(zset_key, count)),
+                    }
+                }
+                b"ZMPOP" | b"zmpop" | b"ZMPop" | b"Zmpop" => {
+                    let (zset_keys, end, count) = parse_mpop_arguments(&v[1..], parse_score_end)?;
+                    Ok(ZMPop(zset_keys, end, count))
+                }
+                b"BZPOPMIN" | b"bzpopmin" | b"BZPopMin" | b"Bzpopmin" | b"BZPOPMAX"
+                | b"bzpopmax" | b"BZPopMax" | b"Bzpopmax" => {
+                    if v.len() < 3 {
+                        return Err(ArgNumber);
+                    }
+
+                    let mut zset_keys = Vec::<Key>::with_capacity(v.len() - 2);
+                    for key in &v[1..v.len() - 1] {
+                        zset_keys.push(get_bytes_vec(Some(key))?);
+                    }
+                    let timeout = get_bytes_vec(v.last()).and_then(parse_timeout)?;
+
+                    match command.eq_ignore_ascii_case(b"BZPOPMIN") {
+                        true => Ok(BZPopMin(zset_keys, timeout)),
+                        false => Ok(BZPopMax(zset_keys, timeout)),
+                    }
+                }
+                b"BZMPOP" | b"bzmpop" | b"BZMPop" | b"Bzmpop" => {
+                    let timeout = get_bytes_vec(v.get(1)).and_then(parse_timeout)?;
+                    let (zset_keys, end, count) =
+                        parse_mpop_arguments(&v[2.min(v.len())..], parse_score_end)?;
+                    Ok(BZMPop(timeout, zset_keys, end, count))
+                }
+                b"ZRANK" | b"zrank" | b"ZRank" | b"Zrank" | b"ZREVRANK" | b"zrevrank"
+                | b"ZRevRank" | b"Zrevrank" => {
+                    let zset_key = get_bytes_vec(v.get(1))?;
+                    let member = get_bytes_vec(v.get(2))?;
+                    let with_score = match &v[3..] {
+                        [] => false,
+                        [option]
+                            if get_bytes_vec(Some(option))?.eq_ignore_ascii_case(b"WITHSCORE") =>
+                        {
+                            true
+                        }
+                        _ => return Err(Syntax),
+                    };
+
+                    match command.eq_ignore_ascii_case(b"ZRANK") {
+                        true => Ok(ZRank(zset_key, member, with_score)),
+                        false => Ok(ZRevRank(zset_key, member, with_score)),
+                    }
+                }
+                b"ZUNION" | b"zunion" | b"ZUnion" | b"Zunion" | b"ZINTER" | b"zinter"
+                | b"ZInter" | b"Zinter" => {
+                    let union = command.eq_ignore_ascii_case(b"ZUNION");
+                    let name = if union { "zunion" } else { "zinter" };
+                    let (zset_keys, weights, aggregate, with_scores) =
+                        parse_zset_operation_arguments(&v[1..], name, true, true)?;
+
+                    match union {
+                        true => Ok(ZUnion(zset_keys, weights, aggregate, with_scores)),
+                        false => Ok(ZInter(zset_keys, weights, aggregate, with_scores)),
+                    }
+                }
+                b"ZDIFF" | b"zdiff" | b"ZDiff" | b"Zdiff" => {
+                    let (zset_keys, _, _, with_scores) =
+                        parse_zset_operation_arguments(&v[1..], "zdiff", false, true)?;
+                    Ok(ZDiff(zset_keys, with_scores))
+                }
+                b"ZUNIONSTORE" | b"zunionstore" | b"ZUnionStore" | b"Zunionstore"
+                | b"ZINTERSTORE" | b"zinterstore" | b"ZInterStore" | b"Zinterstore" => {
+                    let union = command.eq_ignore_ascii_case(b"ZUNIONSTORE");
+                    let name = if union { "zunionstore" } else { "zinterstore" };
+                    let destination = get_bytes_vec(v.get(1))?;
+                    let (zset_keys, weights, aggregate, _) =
+                        parse_zset_operation_arguments(&v[2..], name, true, false)?;
+
+                    match union {
+                        true => Ok(ZUnionStore(destination, zset_keys, weights, aggregate)),
+                        false => Ok(ZInterStore(destination, zset_keys, weights, aggregate)),
+                    }
+                }
+                b"ZDIFFSTORE" | b"zdiffstore" | b"ZDiffStore" | b"Zdiffstore" => {
+                    let destination = get_bytes_vec(v.get(1))?;
+                    let (zset_keys, _, _, _) =
+                        parse_zset_operation_arguments(&v[2..], "zdiffstore", false, false)?;
+                    Ok(ZDiffStore(destination, zset_keys))
+                }
+                b"ZREVRANGE" | b"zrevrange" | b"ZRevRange" | b"Zrevrange" => {
+                    let zset_key = get_bytes_vec(v.get(1))?;
+                    let start = get_bytes_vec(v.get(2)).and_then(parse_integer)?;
+                    let stop = get_bytes_vec(v.get(3)).and_then(parse_integer)?;
+                    let with_scores = match &v[4..] {
+                        [] => false,
+                        [option]
+                            if get_bytes_vec(Some(option))?.eq_ignore_ascii_case(b"WITHSCORES") =>
+                        {
+                            true
+                        }
+                        _ => return Err(Syntax),
+                    };
+
+                    let query = ZRangeQuery::new(ZRangeBy::Index(start, stop), true);
+                    Ok(ZRange(zset_key, query, with_scores))
+                }
+                b"DEL" | b"del" | b"Del" => {
+                    let key = get_bytes_vec(v.get(1))?;
+                    Ok(Del(key))
+                }
+                b"UNLINK" | b"unlink" | b"Unlink" => {
+                    let keys = &v[1..];
+                    if keys.is_empty() {
+                        return Err(ArgNumber);
+                    }
+
+                    let mut keys_vec = Vec::<Key>::with_capacity(keys.len());
+                    for key in keys {
+                        keys_vec.push(get_bytes_vec(Some(key))?);
+                    }
+
+                    Ok(Unlink(keys_vec))
+                }
+                b"INCR" | b"incr" | b"Incr" => {
+                    let key = get_bytes_vec(v.get(1))?;
+                    Ok(Incr(key))
+                }
+                b"INCRBY" | b"incrby" | b"IncrBy" => {
+                    let key = get_bytes_vec(v.get(1))?;
+                    let increment = get_bytes_vec(v.get(2)).and_then(parse_increment)?;
+                    Ok(IncrBy(key, increment))
+                }
+                b"EXISTS" | b"exists" | b"Exists" => {
+                    let key = get_bytes_vec(v.get(1))?;
+                    Ok(Exists(key))
+                }
+                b"TOUCH" | b"touch" | b"Touch" => {
+                    let keys = &v[1..];
+                    if keys.is_empty() {
+                        return Err(ArgNumber);
+                    }
+
+                    let mut keys_vec = Vec::<Key>::with_capacity(keys.len());
+                    for key in keys {
+                        keys_vec.push(get_bytes_vec(Some(key))?);
+                    }
+
+                    Ok(Touch(keys_vec))
+                }
+                b"TYPE" | b"type" | b"Type" => {
+                    let key = get_bytes_vec(v.get(1))?;
+                    Ok(Type(key))
+                }
+                b"SORT" | b"sort" | b"Sort" | b"SORT_RO" | b"sort_ro" | b"Sort_RO" | b"Sort_ro" => {
+                    let key = get_bytes_vec(v.get(1))?;
+                    let (options, destination) = parse_sort_arguments(&v[2..])?;
+
+                    match (command.eq_ignore_ascii_case(b"SORT"), destination) {
+                        (true, destination) => Ok(Sort(key, options, destination)),
+                        (false, None) => Ok(SortRo(key, options)),
+                        // SORT_RO never writes
+                        (false, Some(_)) => Err(Syntax),
+                    }
+                }
+                b"KEYS" | b"keys" | b"Keys" => {
+                    let pattern = get_bytes_vec(v.get(1))?;
+                    Ok(Keys(pattern))
+                }
+                b"SCAN" | b"scan" | b"Scan" => {
+                    let cursor = get_bytes_vec(v.get(1)).and_then(parse_cursor)?;
+                    let mut pattern = None;
+                    let mut count = DEFAULT_SCAN_COUNT;
+                    let mut data_type = None;
+
+                    let mut options = v[2..].iter();
+                    while let Some(option) = options.next() {
+                        let option = get_bytes_vec(Some(option))?.to_ascii_uppercase();
+                        let value = get_bytes_vec(options.next()).map_err(|_| Syntax)?;
+                        match option.as_slice() {
+                            b"MATCH" => pattern = Some(value),
+                            b"COUNT" => count = parse_scan_count(value)?,
+                            b"TYPE" => data_type = Some(value),
+                            _ => return Err(Syntax),
+                        }
+                    }
+
+                    Ok(Scan(cursor, pattern, count, data_type))
+                }
+                b"HSTRLEN" | b"hstrlen" | b"HStrLen" | b"Hstrlen" => {
+                    let hash_key = get_bytes_vec(v.get(1))?;
+                    let field_key = get_bytes_vec(v.get(2))?;
+                    Ok(HStrLen(hash_key, field_key))
+                }
+                b"HSCAN" | b"hscan" | b"HScan" | b"Hscan" => {
+                    let hash_key = get_bytes_vec(v.get(1))?;
+                    let cursor = get_bytes_vec(v.get(2)).and_then(parse_cursor)?;
+                    let mut pattern = None;
+                    let mut count = DEFAULT_SCAN_COUNT;
+                    let mut no_values = false;
+
+                    let mut options = v[3..].iter();
+                    while let Some(option) = options.next() {
+                        match get_bytes_vec(Some(option))?.to_ascii_uppercase().as_slice() {
+                            b"MATCH" => {
+                                pattern = Some(get_bytes_vec(options.next()).map_err(|_| Syntax)?)
+                            }
+                            b"COUNT" => {
+                                let value = get_bytes_vec(options.next()).map_err(|_| Syntax)?;
+                                count = parse_scan_count(value)?;
+                            }
+                            b"NOVALUES" => no_values = true,
+                            _ => return Err(Syntax),
+                        }
+                    }
+
+                    Ok(HScan(hash_key, cursor, pattern, count, no_values))
+                }
+                b"SSCAN" | b"sscan" | b"SScan" | b"Sscan" | b"ZSCAN" | b"zscan" | b"ZScan"
+                | b"Zscan" => {
+                    let key = get_bytes_vec(v.get(1))?;
+                    let cursor = get_bytes_vec(v.get(2)).and_then(parse_cursor)?;
+                    let mut pattern = None;
+                    let mut count = DEFAULT_SCAN_COUNT;
+
+                    let mut options = v[3..].iter();
+                    while let Some(option) = options.next() {
+                        match get_bytes_vec(Some(option))?.to_ascii_uppercase().as_slice() {
+                            b"MATCH" => {
+                                pattern = Some(get_bytes_vec(options.next()).map_err(|_| Syntax)?)
+                            }
+                            b"COUNT" => {
+                                let value = get_bytes_vec(options.next()).map_err(|_| Syntax)?;
+                                count = parse_scan_count(value)?;
+                            }
+                            _ => return Err(Syntax),
+                        }
+                    }
+
+                    match command.eq_ignore_ascii_case(b"SSCAN") {
+                        true => Ok(SScan(key, cursor, pattern, count)),
+                        false => Ok(ZScan(key, cursor, pattern, count)),
+                    }
+                }
+                b"HRANDFIELD" | b"hrandfield" | b"HRandField" | b"Hrandfield" => {
+                    let hash_key = get_bytes_vec(v.get(1))?;
+                    let count = match v.get(2) {
+                        Some(count) => {
+                            Some(get_bytes_vec(Some(count)).and_then(parse_random_count)?)
+                        }
+                        None => None,
+                    };
+                    let with_values = match v.get(3) {
+                        Some(option) => {
+                            match get_bytes_vec(Some(option))?.to_ascii_uppercase().as_slice() {
+                                b"WITHVALUES" if v.len() == 4 => true,
+                                _ => return Err(Syntax),
+                            }
+                        }
+                        None => false,
+                    };
+
+                    Ok(HRandField(hash_key, count, with_values))
+                }
+                b"RENAME" | b"rename" | b"Rename" => {
+                    let key = get_bytes_vec(v.get(1))?;
+                    let new_key = get_bytes_vec(v.get(2))?;
+                    Ok(Rename(key, new_key))
+                }
+                b"RENAMENX" | b"renamenx" | b"RenameNx" | b"Renamenx" => {
+                    let key = get_bytes_vec(v.get(1))?;
+                    let new_key = get_bytes_vec(v.get(2))?;
+                    Ok(RenameNx(key, new_key))
+                }
+                b"COPY" | b"copy" | b"Copy" => {
+                    let key = get_bytes_vec(v.get(1))?;
+                    let new_key = get_bytes_vec(v.get(2))?;
+                    let mut db = None;
+                    let mut replace = false;
+
+                    let mut options = v[3..].iter();
+                    while let Some(option) = options.next() {
+                        match get_bytes_vec(Some(option))?.to_ascii_uppercase().as_slice() {
+                            b"DB" => {
+                                let index = get_bytes_vec(options.next()).map_err(|_| Syntax)?;
+                                db = Some(parse_db_index(index)?);
+                            }
+                            b"REPLACE" => replace = true,
+                            _ => return Err(Syntax),
+                        }
+                    }
+
+                    Ok(Copy(key, new_key, db, replace))
+                }
+                b"DUMP" | b"dump" | b"Dump" => {
+                    let key = get_bytes_vec(v.get(1))?;
+                    Ok(Dump(key))
+                }
+                b"RESTORE" | b"restore" | b"Restore" => {
+                    let key = get_bytes_vec(v.get(1))?;
+                    let ttl = get_bytes_vec(v.get(2))?;
+                    let ttl = parse_duration(ttl).map_err(|_| InvalidTtl)?;
+                    let payload = get_bytes_vec(v.get(3))?;
+                    let mut replace = false;
+                    let mut absolute_ttl = false;
+                    // the idle time and the access frequency are checked but not restored
+                    let mut idle_time = None;
+                    let mut frequency = None;
+
+                    let mut options = v[4..].iter();
+                    while let Some(option) = options.next() {
+                        match get_bytes_vec(Some(option))?.to_ascii_uppercase().as_slice() {
+                            b"REPLACE" => replace = true,
+                            b"ABSTTL" => absolute_ttl = true,
+                            b"IDLETIME" if frequency.is_none() => {
+                                let value = get_bytes_vec(options.next()).map_err(|_| Syntax)?;
+                                match parse_integer(value).map_err(|_| NotInteger)? {
+                                    value if value < 0 => return Err(InvalidIdleTime),
+                                    value => idle_time = Some(value),
+                                }
+                            }
+                            b"FREQ" if idle_time.is_none() => {
+                                let value = get_bytes_vec(options.next()).map_err(|_| Syntax)?;
+                                match parse_integer(value).map_err(|_| NotInteger)? {
+                                    value if !(0..=255).contains(&value) => {
+                                        return Err(InvalidFrequency)
+                                    }
+                                    value => frequency = Some(value),
+                                }
+                            }
+                            _ => return Err(Syntax),
+                        }
+                    }
+
+                    Ok(Restore(key, ttl, payload, replace, absolute_ttl))
+                }
+                b"OBJECT" | b"object" | b"Object" => {
+                    let subcommand = get_bytes_vec(v.get(1))?;
+                    let key = get_bytes_vec(v.get(2))?;
+                    match subcommand.to_ascii_uppercase().as_slice() {
+                        b"ENCODING" => Ok(ObjectEncoding(key)),
+                        b"REFCOUNT" => Ok(ObjectRefCount(key)),
+                        b"IDLETIME" => Ok(ObjectIdleTime(key)),
+                        b"FREQ" => Ok(ObjectFreq(key)),
+                        _ => Err(NotSupported(format!(
+                            "OBJECT {}",
+                            String::from_utf8_lossy(&subcommand)
+                        ))),
+                    }
+                }
+                b"TTL" | b"ttl" | b"Ttl" => {
+                    let key = get_bytes_vec(v.get(1))?;
+                    Ok(Ttl(key))
+                }
+                b"PTTL" | b"pttl" | b"Pttl" => {
+                    let key = get_bytes_vec(v.get(1))?;
+                    Ok(Pttl(key))
+                }
+                b"INFO" | b"info" | b"Info" => Ok(Info),
+                b"PING" | b"ping" | b"Ping" => match &v[1..] {
+                    [] => Ok(Ping(None)),
+                    [message] => Ok(Ping(Some(get_bytes_vec(Some(message))?))),
+                    _ => Err(ArgNumber),
+                },
+                b"ECHO" | b"echo" | b"Echo" => match &v[1..] {
+                    [message] => Ok(Echo(get_bytes_vec(Some(message))?)),
+                    _ => Err(ArgNumber),
+                },
+                b"DBSIZE" | b"dbsize" | b"Dbsize" => Ok(Dbsize),
+                b"TIME" | b"time" | b"Time" => Ok(Time),
+                b"RANDOMKEY" | b"randomkey" | b"RandomKey" => Ok(RandomKey),
+                b"FLUSHDB" | b"flushdb" | b"FlushDb" | b"Flushdb" => {
+                    let lazy = parse_flush_mode(v.get(1))?;
+                    Ok(FlushDb(lazy))
+                }
+                b"FLUSHALL" | b"flushall" | b"FlushAll" | b"Flushall" => {
+                    let lazy = parse_flush_mode(v.get(1))?;
+                    Ok(FlushAll(lazy))
+                }
+                b"SELECT" | b"select" | b"Select" => {
+                    let db = get_bytes_vec(v.get(1)).and_then(parse_db_index)?;
+                    Ok(Select(db))
+                }
+                b"MOVE" | b"move" | b"Move" => {
+                    let key = get_bytes_vec(v.get(1))?;
+                    let db = get_bytes_vec(v.get(2)).and_then(parse_db_index)?;
+                    Ok(Move(key, db))
+                }
+                b"SWAPDB" | b"swapdb" | b"SwapDb" | b"Swapdb" => {
+                    let db = get_bytes_vec(v.get(1)).and_then(parse_db_index)?;
+                    let other_db = get_bytes_vec(v.get(2)).and_then(parse_db_index)?;
+                    Ok(SwapDb(db, other_db))
+                }
+                b"QUIT" | b"quit" | b"Quit" => Ok(Quit),
+                b"RESET" | b"reset" | b"Reset" if v.len() == 1 => Ok(Reset),
+                b"RESET" | b"reset" | b"Reset" => Err(ArgNumber),
+                b"AUTH" | b"auth" | b"Auth" => match &v[1..] {
+                    [password] => Ok(Auth(None, get_bytes_vec(Some(password))?)),
+                    [username, password] => Ok(Auth(
+                        Some(get_bytes_vec(Some(username))?),
+                        get_bytes_vec(Some(password))?,
+                    )),
+                    [] => Err(ArgNumber),
+                    _ => Err(Syntax),
+                },
+                b"MULTI" | b"multi" | b"Multi" if v.len() == 1 => Ok(Multi),
+                b"EXEC" | b"exec" | b"Exec" if v.len() == 1 => Ok(Exec),
+                b"DISCARD" | b"discard" | b"Discard" if v.len() == 1 => Ok(Discard),
+                b"MULTI" | b"multi" | b"Multi" | b"EXEC" | b"exec" | b"Exec" | b"DISCARD"
+                | b"discard" | b"Discard" => Err(ArgNumber),
+                b"WATCH" | b"watch" | b"Watch" => {
+                    if v.len() < 2 {
+                        return Err(ArgNumber);
+                    }
+
+                    let mut keys = Vec::<Key>::with_capacity(v.len() - 1);
+                    for key in &v[1..] {
+                        keys.push(get_bytes_vec(Some(key))?);
+                    }
+                    Ok(Watch(keys))
+                }
+                b"UNWATCH" | b"unwatch" | b"Unwatch" if v.len() == 1 => Ok(Unwatch),
+                b"UNWATCH" | b"unwatch" | b"Unwatch" => Err(ArgNumber),
+                b"EVAL" | b"eval" | b"Eval" => {
+                    let script = get_bytes_vec(v.get(1))?;
+                    let (keys, args) = parse_script_arguments(&v[2.min(v.len())..])?;
+
+                    Ok(Eval(script, keys, args))
+                }
+                b"EVALSHA" | b"evalsha" | b"EvalSha" | b"Evalsha" => {
+                    let sha = get_bytes_vec(v.get(1))?;
+                    let (keys, args) = parse_script_arguments(&v[2.min(v.len())..])?;
+
+                    Ok(EvalSha(sha, keys, args))
+                }
+                b"SCRIPT" | b"script" | b"Script" => {
+                    let subcommand = get_bytes_vec(v.get(1))?;
+                    match subcommand.to_ascii_uppercase().as_slice() {
+                        b"LOAD" => match &v[2..] {
+                            [script] => Ok(ScriptLoad(get_bytes_vec(Some(script))?)),
+                            _ => Err(ArgNumber),
+                        },
+                        b"EXISTS" => {
+                            if v.len() < 3 {
+                                return Err(ArgNumber);
+                            }
+
+                            let mut shas = Values::with_capacity(v.len() - 2);
+                            for sha in &v[2..] {
+                                shas.push(get_bytes_vec(Some(sha))?);
+                            }
+                            Ok(ScriptExists(shas))
+                        }
+                        // the cache is always flushed right away
+                        b"FLUSH" => match &v[2..] {
+                            [] => Ok(ScriptFlush),
+                            [mode] => {
+                                match get_bytes_vec(Some(mode))?.to_ascii_uppercase().as_slice() {
+                                    b"ASYNC" | b"SYNC" => Ok(ScriptFlush),
+                                    _ => Err(Syntax),
+                                }
+                            }
+                            _ => Err(Syntax),
+                        },
+                        b"KILL" if v.len() == 2 => Ok(ScriptKill),
+                        b"KILL" => Err(ArgNumber),
+                        _ => Err(NotSupported(format!(
+                            "SCRIPT {}",
+                            String::from_utf8_lossy(&subcommand)
+                        ))),
+                    }
+                }
+                b"FUNCTION" | b"function" | b"Function" => {
+                    let subcommand = get_bytes_vec(v.get(1))?;
+                    match subcommand.to_ascii_uppercase().as_slice() {
+                        b"LOAD" => match &v[2..] {
+                            [code] => Ok(FunctionLoad(get_bytes_vec(Some(code))?, false)),
+                            [option, code]
+                                if get_bytes_vec(Some(option))?
+                                    .eq_ignore_ascii_case(b"REPLACE") =>
+                            {
+                                Ok(FunctionLoad(get_bytes_vec(Some(code))?, true))
+                            }
+                            [] => Err(ArgNumber),
+                            _ => Err(Syntax),
+                        },
+                        b"LIST" => {
+                            let mut pattern = None;
+                            let mut with_code = false;
+                            let mut options = v[2..].iter();
+                            while let Some(option) = options.next() {
+                                match get_bytes_vec(Some(option))?.to_ascii_uppercase().as_slice() {
+                                    b"WITHCODE" => with_code = true,
+                                    b"LIBRARYNAME" => {
+                                        pattern = Some(
+                                            get_bytes_vec(options.next()).map_err(|_| Syntax)?,
+                                        )
+                                    }
+                                    _ => return Err(Syntax),
+                                }
+                            }
+                            Ok(FunctionList(pattern, with_code))
+                        }
+                        b"DELETE" => match &v[2..] {
+                            [library] => Ok(FunctionDelete(get_bytes_vec(Some(library))?)),
+                            _ => Err(ArgNumber),
+                        },
+                        b"DUMP" if v.len() == 2 => Ok(FunctionDump),
+                        b"DUMP" => Err(ArgNumber),
+                        _ => Err(NotSupported(format!(
+                            "FUNCTION {}",
+                            String::from_utf8_lossy(&subcommand)
+                        ))),
+                    }
+                }
+                b"FCALL" | b"fcall" | b"FCall" | b"Fcall" => {
+                    let function = get_bytes_vec(v.get(1))?;
+                    let (keys, args) = parse_script_arguments(&v[2.min(v.len())..])?;
+
+                    Ok(FCall(function, keys, args, false))
+                }
+                b"FCALL_RO" | b"fcall_ro" | b"FCall_RO" | b"Fcall_ro" => {
+                    let function = get_bytes_vec(v.get(1))?;
+                    let (keys, args) = parse_script_arguments(&v[2.min(v.len())..])?;
+
+                    Ok(FCall(function, keys, args, true))
+                }
+                b"SUBSCRIBE" | b"subscribe" | b"Subscribe" => {
+                    if v.len() < 2 {
+                        return Err(ArgNumber);
+                    }
+
+                    let mut channels = Values::with_capacity(v.len() - 1);
+                    for channel in &v[1..] {
+                        channels.push(get_bytes_vec(Some(channel))?);
+                    }
+                    Ok(Subscribe(channels))
+                }
+                b"UNSUBSCRIBE" | b"unsubscribe" | b"Unsubscribe" => {
+                    let mut channels = Values::with_capacity(v.len() - 1);
+                    for channel in &v[1..] {
+                        channels.push(get_bytes_vec(Some(channel))?);
+                    }
+                    Ok(Unsubscribe(channels))
+                }
+                b"PSUBSCRIBE" | b"psubscribe" | b"PSubscribe" | b"Psubscribe" => {
+                    if v.len() < 2 {
+                        return Err(ArgNumber);
+                    }
+
+                    let mut patterns = Values::with_capacity(v.len() - 1);
+                    for pattern in &v[1..] {
+                        patterns.push(get_bytes_vec(Some(pattern))?);
+                    }
+                    Ok(PSubscribe(patterns))
+                }
+                b"PUNSUBSCRIBE" | b"punsubscribe" | b"PUnsubscribe" | b"Punsubscribe" => {
+                    let mut patterns = Values::with_capacity(v.len() - 1);
+                    for pattern in &v[1..] {
+                        patterns.push(get_bytes_vec(Some(pattern))?);
+                    }
+                    Ok(PUnsubscribe(patterns))
+                }
+                b"SSUBSCRIBE" | b"ssubscribe" | b"SSubscribe" | b"Ssubscribe" => {
+                    if v.len() < 2 {
+                        return Err(ArgNumber);
+                    }
+
+                    let mut channels = Values::with_capacity(v.len() - 1);
+                    for channel in &v[1..] {
+                        channels.push(get_bytes_vec(Some(channel))?);
+                    }
+                    Ok(SSubscribe(channels))
+                }
+                b"SUNSUBSCRIBE" | b"sunsubscribe" | b"SUnsubscribe" | b"Sunsubscribe" => {
+                    let mut channels = Values::with_capacity(v.len() - 1);
+                    for channel in &v[1..] {
+                        channels.push(get_bytes_vec(Some(channel))?);
+                    }
+                    Ok(SUnsubscribe(channels))
+                }
+                b"SPUBLISH" | b"spublish" | b"SPublish" | b"Spublish" => {
+                    let channel = get_bytes_vec(v.get(1))?;
+                    let message = get_bytes_vec(v.get(2))?;
+                    Ok(SPublish(channel, message))
+                }
+                b"PUBLISH" | b"publish" | b"Publish" => {
+                    let channel = get_bytes_vec(v.get(1))?;
+                    let message = get_bytes_vec(v.get(2))?;
+                    Ok(Publish(channel, message))
+                }
+                b"PUBSUB" | b"pubsub" | b"PubSub" | b"Pubsub" => {
+                    let subcommand = get_bytes_vec(v.get(1))?;
+                    match subcommand.to_ascii_uppercase().as_slice() {
+                        b"CHANNELS" => match &v[2..] {
+                            [] => Ok(PubSubChannels(None)),
+                            [pattern] => Ok(PubSubChannels(Some(get_bytes_vec(Some(pattern))?))),
+                            _ => Err(ArgNumber),
+                        },
+                        b"NUMSUB" => {
+                            let mut channels = Values::with_capacity(v.len() - 2);
+                            for channel in &v[2..] {
+                                channels.push(get_bytes_vec(Some(channel))?);
+                            }
+                            Ok(PubSubNumSub(channels))
+                        }
+                        b"SHARDCHANNELS" => match &v[2..] {
+                            [] => Ok(PubSubShardChannels(None)),
+                            [pattern] => {
+                                Ok(PubSubShardChannels(Some(get_bytes_vec(Some(pattern))?)))
+                            }
+                            _ => Err(ArgNumber),
+                        },
+                        b"SHARDNUMSUB" => {
+                            let mut channels = Values::with_capacity(v.len() - 2);
+                            for channel in &v[2..] {
+                                channels.push(get_bytes_vec(Some(channel))?);
+                            }
+                            Ok(PubSubShardNumSub(channels))
+                        }
+                        b"NUMPAT" if v.len() == 2 => Ok(PubSubNumPat),
+                        b"NUMPAT" => Err(ArgNumber),
+                        _ => Err(NotSupported(format!(
+                            "PUBSUB {}",
+                            String::from_utf8_lossy(&subcommand)
+                        ))),
+                    }
+                }
+                b"CONFIG" | b"config" | b"Config" => {
+                    let subcommand = get_bytes_vec(v.get(1))?;
+                    match subcommand.to_ascii_uppercase().as_slice() {
+                        b"GET" => {
+                            if v.len() < 3 {
+                                return Err(ArgNumber);
+                            }
+
+                            let mut patterns = Values::with_capacity(v.len() - 2);
+                            for pattern in &v[2..] {
+                                patterns.push(get_bytes_vec(Some(pattern))?);
+                            }
+                            Ok(ConfigGet(patterns))
+                        }
+                        b"SET" => {
+                            let pairs = &v[2..];
+                            if pairs.is_empty() || !pairs.len().is_multiple_of(2) {
+                                return Err(ArgNumber);
+                            }
+
+                            let mut items = Items::with_capacity(pairs.len() / 2);
+                            for pair in pairs.chunks_exact(2) {
+                                items.push((
+                                    get_bytes_vec(pair.first())?,
+                                    get_bytes_vec(pair.get(1))?,
+                                ));
+                            }
+                            Ok(ConfigSet(items))
+                        }
+                        _ => Err(NotSupported(format!(
+                            "CONFIG {}",
+                            String::from_utf8_lossy(&subcommand)
+                        ))),
+                    }
+                }
+                b"LATENCY" | b"latency" | b"Latency" => {
+                    let subcommand = get_bytes_vec(v.get(1))?;
+                    match subcommand.to_ascii_uppercase().as_slice() {
+                        b"HISTOGRAM" => {
+                            let mut command_names = Vec::<Key>::with_capacity(v.len() - 2);
+                            for name in &v[2..] {
+                                command_names.push(get_bytes_vec(Some(name))?);
+                            }
+                            Ok(LatencyHistogram(command_names))
+                        }
+                        _ => Err(NotSupported(format!(
+                            "LATENCY {}",
+                            String::from_utf8_lossy(&subcommand)
+                        ))),
+                    }
+                }
+                b"ACL" | b"acl" | b"Acl" => {
+                    let subcommand = get_bytes_vec(v.get(1))?;
+                    match subcommand.to_ascii_uppercase().as_slice() {
+                        b"CAT" => {
+                            let category = match v.get(2) {
+                                Some(category) => Some(get_bytes_vec(Some(category))?),
+                                None => None,
+                            };
+                            Ok(AclCat(category))
+                        }
+                        b"GENPASS" => {
+                            let bits = match v.get(2) {
+                                Some(bits) => get_bytes_vec(Some(bits))
+                                    .and_then(parse_u64)
+                                    .map_err(|_| InvalidGenPassBits)?,
+                                None => DEFAULT_GENPASS_BITS,
+                            };
+                            if bits == 0 || bits > MAX_GENPASS_BITS {
+                                return Err(InvalidGenPassBits);
+                            }
+                            Ok(AclGenPass(bits))
+                        }
+                        b"SETUSER" => match v.get(2) {
+                            Some(user) => Ok(AclSetUser(
+                                get_bytes_vec(Some(user))?,
+                                v[3..]
+                                    .iter()
+                                    .map(|rule| get_bytes_vec(Some(rule)))
+                                    .collect::<Result<_, _>>()?,
+                            )),
+                            None => Err(ArgNumber),
+                        },
+                        b"GETUSER" => match &v[2..] {
+                            [user] => Ok(AclGetUser(get_bytes_vec(Some(user))?)),
+                            _ => Err(ArgNumber),
+                        },
+                        b"DELUSER" if v.len() > 2 => Ok(AclDelUser(
+                            v[2..]
+                                .iter()
+                                .map(|user| get_bytes_vec(Some(user)))
+                                .collect::<Result<_, _>>()?,
+                        )),
+                        b"DELUSER" => Err(ArgNumber),
+                        b"LIST" if v.len() == 2 => Ok(AclList),
+                        b"LIST" => Err(ArgNumber),
+                        b"WHOAMI" if v.len() == 2 => Ok(AclWhoAmI),
+                        b"WHOAMI" => Err(ArgNumber),
+                        _ => Err(NotSupported(format!(
+                            "ACL {}",
+                            String::from_utf8_lossy(&subcommand)
+                        ))),
+                    }
+                }
+                b"CLIENT" | b"client" | b"Client" => {
+                    let subcommand = get_bytes_vec(v.get(1))?;
+                    match subcommand.to_ascii_uppercase().as_slice() {
+                        b"ID" if v.len() == 2 => Ok(ClientId),
+                        b"ID" => Err(ArgNumber),
+                        b"KILL" => match &v[2..] {
+                            [] => Err(ArgNumber),
+                            [addr] => Ok(ClientKillAddr(get_bytes_vec(Some(addr))?)),
+                            filters => parse_client_kill_filter(filters).map(ClientKill),
+                        },
+                        _ => Err(NotSupported(format!(
+                            "CLIENT {}",
+                            String::from_utf8_lossy(&subcommand)
+                        ))),
+                    }
                 }
-                b"INFO" | b"info" | b"Info" => Ok(Info),
-                b"PING" | b"ping" | b"Ping" => Ok(Ping),
-                b"DBSIZE" | b"dbsize" | b"Dbsize" => Ok(Dbsize),
-                b"QUIT" | b"quit" | b"Quit" => Ok(Quit),
                 unsupported_command => Err(NotSupported(
-                    std::str::from_utf8(unsupported_command)
-                        .unwrap()
-                        .to_string(),
+                    String::from_utf8_lossy(unsupported_command).to_string(),
                 )),
             },
             _ => Err(InvalidCommand),
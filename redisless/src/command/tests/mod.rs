@@ -15,3 +15,254 @@ fn set_command() {
         assert_eq!(command, Command::Set(b"mykey".to_vec(), b"value".to_vec()));
     }
 }
+
+#[test]
+fn acl_genpass_command() {
+    let command = Command::parse(vec![Resp::BulkString(b"ACL"), Resp::BulkString(b"GENPASS")]);
+    assert_eq!(command.unwrap(), Command::AclGenPass(256));
+
+    let command = Command::parse(vec![
+        Resp::BulkString(b"acl"),
+        Resp::BulkString(b"genpass"),
+        Resp::BulkString(b"5"),
+    ]);
+    assert_eq!(command.unwrap(), Command::AclGenPass(5));
+
+    for bits in [&b"0"[..], b"4097", b"abc"] {
+        let command = Command::parse(vec![
+            Resp::BulkString(b"ACL"),
+            Resp::BulkString(b"GENPASS"),
+            Resp::BulkString(bits),
+        ]);
+        assert!(command.is_err());
+    }
+}
+
+#[test]
+fn client_kill_command() {
+    use crate::command::{ClientKillFilter, ClientType};
+
+    let command = Command::parse(vec![
+        Resp::BulkString(b"CLIENT"),
+        Resp::BulkString(b"KILL"),
+        Resp::BulkString(b"127.0.0.1:6379"),
+    ]);
+    assert_eq!(
+        command.unwrap(),
+        Command::ClientKillAddr(b"127.0.0.1:6379".to_vec())
+    );
+
+    let command = Command::parse(vec![
+        Resp::BulkString(b"client"),
+        Resp::BulkString(b"kill"),
+        Resp::BulkString(b"id"),
+        Resp::BulkString(b"3"),
+        Resp::BulkString(b"TYPE"),
+        Resp::BulkString(b"slave"),
+        Resp::BulkString(b"USER"),
+        Resp::BulkString(b"alice"),
+        Resp::BulkString(b"LADDR"),
+        Resp::BulkString(b"127.0.0.1:6379"),
+        Resp::BulkString(b"SKIPME"),
+        Resp::BulkString(b"no"),
+    ]);
+    assert_eq!(
+        command.unwrap(),
+        Command::ClientKill(ClientKillFilter {
+            id: Some(3),
+            laddr: Some("127.0.0.1:6379".to_string()),
+            client_type: Some(ClientType::Replica),
+            user: Some(b"alice".to_vec()),
+            skip_me: false,
+            ..ClientKillFilter::default()
+        })
+    );
+
+    for filters in [
+        &[&b"ID"[..], b"0"][..],
+        &[b"ID", b"abc"],
+        &[b"TYPE", b"other"],
+        &[b"SKIPME", b"maybe"],
+        &[b"ADDR", b"127.0.0.1:6379", b"USER"],
+        &[b"MAXAGE", b"10"],
+    ] {
+        let mut resp = vec![Resp::BulkString(b"CLIENT"), Resp::BulkString(b"KILL")];
+        resp.extend(filters.iter().map(|argument| Resp::BulkString(argument)));
+        assert!(Command::parse(resp).is_err());
+    }
+}
+
+#[test]
+fn hset_and_hmset_commands() {
+    for (cmd, legacy) in [
+        (&b"HSET"[..], false),
+        (b"hset", false),
+        (b"HMSET", true),
+        (b"hmset", true),
+    ] {
+        let resp = vec![
+            Resp::BulkString(cmd),
+            Resp::BulkString(b"hash"),
+            Resp::BulkString(b"field"),
+            Resp::BulkString(b"value"),
+        ];
+
+        let items = vec![(b"field".to_vec(), b"value".to_vec())];
+        let expected = match legacy {
+            true => Command::HMSet(b"hash".to_vec(), items),
+            false => Command::HSet(b"hash".to_vec(), items),
+        };
+        assert_eq!(Command::parse(resp).unwrap(), expected);
+    }
+}
+
+#[test]
+fn hincrby_commands() {
+    let resp = vec![
+        Resp::BulkString(b"HINCRBY"),
+        Resp::BulkString(b"hash"),
+        Resp::BulkString(b"field"),
+        Resp::BulkString(b"-5"),
+    ];
+    let expected = Command::HIncrBy(b"hash".to_vec(), b"field".to_vec(), -5);
+    assert_eq!(Command::parse(resp).unwrap(), expected);
+
+    let resp = vec![
+        Resp::BulkString(b"hincrbyfloat"),
+        Resp::BulkString(b"hash"),
+        Resp::BulkString(b"field"),
+        Resp::BulkString(b"0.5"),
+    ];
+    let expected = Command::HIncrByFloat(b"hash".to_vec(), b"field".to_vec(), 0.5);
+    assert_eq!(Command::parse(resp).unwrap(), expected);
+
+    for (cmd, increment) in [(&b"HINCRBY"[..], &b"1.5"[..]), (b"HINCRBYFLOAT", b"abc")] {
+        let resp = vec![
+            Resp::BulkString(cmd),
+            Resp::BulkString(b"hash"),
+            Resp::BulkString(b"field"),
+            Resp::BulkString(increment),
+        ];
+        assert!(Command::parse(resp).is_err());
+    }
+}
+
+#[test]
+fn sintercard_command() {
+    let resp = vec![
+        Resp::BulkString(b"SINTERCARD"),
+        Resp::BulkString(b"2"),
+        Resp::BulkString(b"s1"),
+        Resp::BulkString(b"s2"),
+        Resp::BulkString(b"limit"),
+        Resp::BulkString(b"3"),
+    ];
+    let expected = Command::SInterCard(vec![b"s1".to_vec(), b"s2".to_vec()], 3);
+    assert_eq!(Command::parse(resp).unwrap(), expected);
+
+    let resp = vec![
+        Resp::BulkString(b"sintercard"),
+        Resp::BulkString(b"1"),
+        Resp::BulkString(b"s1"),
+    ];
+    let expected = Command::SInterCard(vec![b"s1".to_vec()], 0);
+    assert_eq!(Command::parse(resp).unwrap(), expected);
+
+    for arguments in [
+        &[&b"0"[..], b"s1"][..],
+        &[b"3", b"s1", b"s2"],
+        &[b"1", b"s1", b"LIMIT", b"-1"],
+        &[b"1", b"s1", b"LIMIT"],
+    ] {
+        let mut resp = vec![Resp::BulkString(b"SINTERCARD")];
+        resp.extend(arguments.iter().map(|argument| Resp::BulkString(argument)));
+        assert!(Command::parse(resp).is_err());
+    }
+}
+
+#[test]
+fn zadd_options() {
+    use crate::command::ZAddOptions;
+
+    let resp = vec![
+        Resp::BulkString(b"ZADD"),
+        Resp::BulkString(b"zset"),
+        Resp::BulkString(b"xx"),
+        Resp::BulkString(b"GT"),
+        Resp::BulkString(b"Ch"),
+        Resp::BulkString(b"1"),
+        Resp::BulkString(b"a"),
+        Resp::BulkString(b"-2.5"),
+        Resp::BulkString(b"b"),
+    ];
+    let options = ZAddOptions {
+        xx: true,
+        gt: true,
+        ch: true,
+        ..ZAddOptions::default()
+    };
+    let expected = Command::ZAdd(
+        b"zset".to_vec(),
+        options,
+        vec![(1.0, b"a".to_vec()), (-2.5, b"b".to_vec())],
+    );
+    assert_eq!(Command::parse(resp).unwrap(), expected);
+
+    for arguments in [
+        &[&b"NX"[..], b"XX", b"1", b"a"][..],
+        &[b"NX", b"GT", b"1", b"a"],
+        &[b"GT", b"LT", b"1", b"a"],
+        &[b"INCR", b"1", b"a", b"2", b"b"],
+        &[b"NX"],
+        &[b"1", b"a", b"2"],
+    ] {
+        let mut resp = vec![Resp::BulkString(b"ZADD"), Resp::BulkString(b"zset")];
+        resp.extend(arguments.iter().map(|argument| Resp::BulkString(argument)));
+        assert!(Command::parse(resp).is_err());
+    }
+}
+
+#[test]
+fn sort_options() {
+    use crate::command::SortOptions;
+
+    let resp = vec![
+        Resp::BulkString(b"SORT"),
+        Resp::BulkString(b"list"),
+        Resp::BulkString(b"by"),
+        Resp::BulkString(b"weight_*"),
+        Resp::BulkString(b"LIMIT"),
+        Resp::BulkString(b"0"),
+        Resp::BulkString(b"-1"),
+        Resp::BulkString(b"GET"),
+        Resp::BulkString(b"#"),
+        Resp::BulkString(b"get"),
+        Resp::BulkString(b"hash_*->field"),
+        Resp::BulkString(b"Desc"),
+        Resp::BulkString(b"ALPHA"),
+        Resp::BulkString(b"STORE"),
+        Resp::BulkString(b"destination"),
+    ];
+    let options = SortOptions {
+        by: Some(b"weight_*".to_vec()),
+        limit: Some((0, -1)),
+        get: vec![b"#".to_vec(), b"hash_*->field".to_vec()],
+        desc: true,
+        alpha: true,
+    };
+    let expected = Command::Sort(b"list".to_vec(), options, Some(b"destination".to_vec()));
+    assert_eq!(Command::parse(resp).unwrap(), expected);
+
+    for arguments in [
+        &[&b"BY"[..]][..],
+        &[b"LIMIT", b"0"],
+        &[b"LIMIT", b"zero", b"1"],
+        &[b"GET"],
+        &[b"STORE"],
+        &[b"UNKNOWN"],
+    ] {
+        let mut resp = vec![Resp::BulkString(b"SORT"), Resp::BulkString(b"list")];
+        resp.extend(arguments.iter().map(|argument| Resp::BulkString(argument)));
+        assert!(Command::parse(resp).is_err());
+    }
+}
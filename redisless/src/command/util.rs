@@ -1,5 +1,20 @@
+use std::iter::Peekable;
+use std::slice::Iter;
+use std::time::Duration;
+
 use super::command_error::RedisCommandError;
+use super::{ClientKillFilter, ClientType, Keys, SortOptions, Values};
 use crate::protocol::Resp;
+use crate::storage::models::bitmap::{BitFieldOperation, BitFieldType, BitOverflow, BitUnit};
+use crate::storage::models::geo::{self, GeoOrigin, GeoQuery, GeoShape, GeoSort, GeoUnit};
+use crate::storage::models::{
+    Aggregate, ClaimOptions, LexBound, ListEnd, NewStreamId, ScoreBound, ScoreEnd, StreamId,
+    StreamTrim, TrimStrategy, ZRangeBy, ZRangeQuery,
+};
+
+// random elements replied at most for a negative count, as they are gathered while the
+// database is locked
+const MAX_RANDOM_COUNT: i64 = 1_000_000;
 
 pub fn get_bytes_vec(resp: Option<&Resp>) -> Result<Vec<u8>, RedisCommandError> {
     match resp {
@@ -17,3 +32,861 @@ pub fn parse_increment(bytes: Vec<u8>) -> Result<i64, RedisCommandError> {
     let delta = std::str::from_utf8(&bytes[..])?;
     Ok(delta.parse::<i64>()?)
 }
+
+pub fn parse_u64(bytes: Vec<u8>) -> Result<u64, RedisCommandError> {
+    let number = std::str::from_utf8(&bytes[..])?;
+    Ok(number.parse::<u64>()?)
+}
+
+/// parse the number of elements a SCAN-like command should look at, which must be positive
+pub fn parse_scan_count(bytes: Vec<u8>) -> Result<usize, RedisCommandError> {
+    match parse_u64(bytes)? {
+        0 => Err(RedisCommandError::Syntax),
+        count => Ok(count as usize),
+    }
+}
+
+pub fn parse_cursor(bytes: Vec<u8>) -> Result<u64, RedisCommandError> {
+    std::str::from_utf8(&bytes[..])
+        .ok()
+        .and_then(|cursor| cursor.parse::<u64>().ok())
+        .ok_or(RedisCommandError::InvalidCursor)
+}
+
+/// parse a signed 64 bits integer argument
+pub fn parse_integer(bytes: Vec<u8>) -> Result<i64, RedisCommandError> {
+    std::str::from_utf8(&bytes[..])
+        .ok()
+        .and_then(|integer| integer.parse::<i64>().ok())
+        .ok_or(RedisCommandError::NotInteger)
+}
+
+/// parse a count which must not be negative
+pub fn parse_count(bytes: Vec<u8>) -> Result<usize, RedisCommandError> {
+    match parse_integer(bytes)? {
+        count if count < 0 => Err(RedisCommandError::NotPositive),
+        count => Ok(count as usize),
+    }
+}
+
+/// parse the count of random elements to reply, which may be repeated when it is negative
+pub fn parse_random_count(bytes: Vec<u8>) -> Result<i64, RedisCommandError> {
+    match parse_integer(bytes)? {
+        count if count < -MAX_RANDOM_COUNT => Err(RedisCommandError::ValueOutOfRange),
+        count => Ok(count),
+    }
+}
+
+/// parse a floating point argument
+pub fn parse_float(bytes: Vec<u8>) -> Result<f64, RedisCommandError> {
+    std::str::from_utf8(&bytes[..])
+        .ok()
+        .and_then(|float| float.parse::<f64>().ok())
+        .filter(|float| !float.is_nan())
+        .ok_or(RedisCommandError::NotFloat)
+}
+
+/// parse the offset of a bit in a string, which can not go beyond 512MB
+pub fn parse_bit_offset(bytes: Vec<u8>) -> Result<u64, RedisCommandError> {
+    std::str::from_utf8(&bytes[..])
+        .ok()
+        .and_then(|offset| offset.parse::<u64>().ok())
+        .filter(|offset| *offset < 1 << 32)
+        .ok_or(RedisCommandError::BitOffset)
+}
+
+/// parse the value of a bit, either 0 or 1
+pub fn parse_bit(bytes: Vec<u8>) -> Result<u8, RedisCommandError> {
+    match bytes.as_slice() {
+        b"0" => Ok(0),
+        b"1" => Ok(1),
+        _ => Err(RedisCommandError::BitValue),
+    }
+}
+
+/// parse the `BYTE` / `BIT` unit of a bit range
+pub fn parse_bit_unit(bytes: Vec<u8>) -> Result<BitUnit, RedisCommandError> {
+    match bytes.to_ascii_uppercase().as_slice() {
+        b"BYTE" => Ok(BitUnit::Byte),
+        b"BIT" => Ok(BitUnit::Bit),
+        _ => Err(RedisCommandError::Syntax),
+    }
+}
+
+/// parse the type of a BITFIELD field: `i` or `u` followed by its width in bits
+pub fn parse_bitfield_type(bytes: Vec<u8>) -> Result<BitFieldType, RedisCommandError> {
+    let (signed, max_bits) = match bytes.first() {
+        Some(b'i') | Some(b'I') => (true, 64),
+        Some(b'u') | Some(b'U') => (false, 63),
+        _ => return Err(RedisCommandError::BitFieldType),
+    };
+
+    std::str::from_utf8(&bytes[1..])
+        .ok()
+        .and_then(|bits| bits.parse::<u8>().ok())
+        .filter(|bits| (1..=max_bits).contains(bits))
+        .map(|bits| BitFieldType { signed, bits })
+        .ok_or(RedisCommandError::BitFieldType)
+}
+
+/// parse the offset of a BITFIELD field, in bits or in multiples of the field width when
+/// prefixed with `#`, the whole field having to fit in 512MB
+pub fn parse_bitfield_offset(
+    bytes: Vec<u8>,
+    field: BitFieldType,
+) -> Result<u64, RedisCommandError> {
+    let offset = match bytes.strip_prefix(b"#") {
+        Some(index) => parse_bit_offset(index.to_vec())?.checked_mul(field.bits as u64),
+        None => Some(parse_bit_offset(bytes)?),
+    };
+
+    offset
+        .filter(|offset| offset + field.bits as u64 <= 1 << 32)
+        .ok_or(RedisCommandError::BitOffset)
+}
+
+/// parse the `GET`, `SET`, `INCRBY` and `OVERFLOW` subcommands of BITFIELD
+pub fn parse_bitfield_operations(
+    arguments: &[Resp],
+) -> Result<Vec<BitFieldOperation>, RedisCommandError> {
+    let mut operations = vec![];
+
+    let mut arguments = arguments.iter();
+    while let Some(subcommand) = arguments.next() {
+        let mut next = || get_bytes_vec(arguments.next()).map_err(|_| RedisCommandError::Syntax);
+
+        let operation = match get_bytes_vec(Some(subcommand))?
+            .to_ascii_uppercase()
+            .as_slice()
+        {
+            b"OVERFLOW" => match next()?.to_ascii_uppercase().as_slice() {
+                b"WRAP" => BitFieldOperation::Overflow(BitOverflow::Wrap),
+                b"SAT" => BitFieldOperation::Overflow(BitOverflow::Sat),
+                b"FAIL" => BitFieldOperation::Overflow(BitOverflow::Fail),
+                _ => return Err(RedisCommandError::OverflowType),
+            },
+            subcommand @ (b"GET" | b"SET" | b"INCRBY") => {
+                let field = parse_bitfield_type(next()?)?;
+                let offset = parse_bitfield_offset(next()?, field)?;
+                match subcommand {
+                    b"GET" => BitFieldOperation::Get(field, offset),
+                    b"SET" => BitFieldOperation::Set(field, offset, parse_integer(next()?)?),
+                    _ => BitFieldOperation::IncrBy(field, offset, parse_integer(next()?)?),
+                }
+            }
+            _ => return Err(RedisCommandError::Syntax),
+        };
+        operations.push(operation);
+    }
+
+    Ok(operations)
+}
+
+/// parse a database index, negative indexes are out of range
+pub fn parse_db_index(bytes: Vec<u8>) -> Result<u64, RedisCommandError> {
+    let index = std::str::from_utf8(&bytes[..])
+        .ok()
+        .and_then(|index| index.parse::<i64>().ok())
+        .ok_or(RedisCommandError::NotInteger)?;
+
+    match index < 0 {
+        true => Err(RedisCommandError::DbIndexOutOfRange),
+        false => Ok(index as u64),
+    }
+}
+
+/// parse the optional `ASYNC` / `SYNC` argument of FLUSHDB and FLUSHALL, return `true` for `ASYNC`
+pub fn parse_flush_mode(resp: Option<&Resp>) -> Result<bool, RedisCommandError> {
+    match resp {
+        None => Ok(false),
+        Some(resp) => match get_bytes_vec(Some(resp))?.to_ascii_uppercase().as_slice() {
+            b"ASYNC" => Ok(true),
+            b"SYNC" => Ok(false),
+            _ => Err(RedisCommandError::Syntax),
+        },
+    }
+}
+
+/// parse the `LEFT` / `RIGHT` side of a list
+pub fn parse_list_end(bytes: Vec<u8>) -> Result<ListEnd, RedisCommandError> {
+    match bytes.to_ascii_uppercase().as_slice() {
+        b"LEFT" => Ok(ListEnd::Left),
+        b"RIGHT" => Ok(ListEnd::Right),
+        _ => Err(RedisCommandError::Syntax),
+    }
+}
+
+/// parse the timeout in seconds of a blocking command, `None` (for 0) to block forever
+pub fn parse_timeout(bytes: Vec<u8>) -> Result<Option<Duration>, RedisCommandError> {
+    let timeout = std::str::from_utf8(&bytes[..])
+        .ok()
+        .and_then(|timeout| timeout.parse::<f64>().ok())
+        .filter(|timeout| timeout.is_finite())
+        .ok_or(RedisCommandError::TimeoutNotFloat)?;
+
+    match timeout {
+        timeout if timeout < 0.0 => Err(RedisCommandError::NegativeTimeout),
+        0.0 => Ok(None),
+        timeout => Duration::try_from_secs_f64(timeout)
+            .map(Some)
+            .map_err(|_| RedisCommandError::TimeoutOutOfRange),
+    }
+}
+
+/// parse the `MIN` or `MAX` end of a sorted set
+pub fn parse_score_end(bytes: Vec<u8>) -> Result<ScoreEnd, RedisCommandError> {
+    match bytes.to_ascii_uppercase().as_slice() {
+        b"MIN" => Ok(ScoreEnd::Min),
+        b"MAX" => Ok(ScoreEnd::Max),
+        _ => Err(RedisCommandError::Syntax),
+    }
+}
+
+/// parse the `numkeys key [key ...] end [COUNT count]` arguments of LMPOP, ZMPOP and their
+/// blocking forms, the end being parsed by `parse_end`
+pub fn parse_mpop_arguments<E>(
+    arguments: &[Resp],
+    parse_end: fn(Vec<u8>) -> Result<E, RedisCommandError>,
+) -> Result<(Vec<Vec<u8>>, E, usize), RedisCommandError> {
+    let num_keys = get_bytes_vec(arguments.first()).and_then(parse_integer)?;
+    if num_keys <= 0 {
+        return Err(RedisCommandError::NotGreaterThanZero("numkeys"));
+    }
+
+    let num_keys = num_keys as usize;
+    let keys = match arguments.get(1..=num_keys) {
+        Some(keys) => keys,
+        None => return Err(RedisCommandError::Syntax),
+    };
+    let keys = keys
+        .iter()
+        .map(|key| get_bytes_vec(Some(key)))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let end = get_bytes_vec(arguments.get(num_keys + 1))
+        .map_err(|_| RedisCommandError::Syntax)
+        .and_then(parse_end)?;
+
+    let count = match &arguments[(num_keys + 2).min(arguments.len())..] {
+        [] => 1,
+        [option, count] if get_bytes_vec(Some(option))?.eq_ignore_ascii_case(b"COUNT") => {
+            match get_bytes_vec(Some(count)).and_then(parse_integer)? {
+                count if count <= 0 => return Err(RedisCommandError::NotGreaterThanZero("count")),
+                count => count as usize,
+            }
+        }
+        _ => return Err(RedisCommandError::Syntax),
+    };
+
+    Ok((keys, end, count))
+}
+
+/// parse the `[BY pattern] [LIMIT offset count] [GET pattern [GET pattern ...]] [ASC | DESC]
+/// [ALPHA] [STORE destination]` arguments of SORT and SORT_RO, along with the destination
+pub fn parse_sort_arguments(
+    arguments: &[Resp],
+) -> Result<(SortOptions, Option<Vec<u8>>), RedisCommandError> {
+    let mut options = SortOptions::default();
+    let mut destination = None;
+
+    let mut arguments = arguments.iter();
+    while let Some(option) = arguments.next() {
+        let option = get_bytes_vec(Some(option))?.to_ascii_uppercase();
+        let mut value = || get_bytes_vec(arguments.next()).map_err(|_| RedisCommandError::Syntax);
+        match option.as_slice() {
+            b"BY" => options.by = Some(value()?),
+            b"LIMIT" => {
+                let offset = value().and_then(parse_integer)?;
+                let count = value().and_then(parse_integer)?;
+                options.limit = Some((offset, count));
+            }
+            b"GET" => options.get.push(value()?),
+            b"ASC" => options.desc = false,
+            b"DESC" => options.desc = true,
+            b"ALPHA" => options.alpha = true,
+            b"STORE" => destination = Some(value()?),
+            _ => return Err(RedisCommandError::Syntax),
+        }
+    }
+
+    Ok((options, destination))
+}
+
+/// parse the `[ID client-id] [TYPE type] [USER username] [ADDR ip:port] [LADDR ip:port]
+/// [SKIPME yes|no]` filters of CLIENT KILL
+pub fn parse_client_kill_filter(arguments: &[Resp]) -> Result<ClientKillFilter, RedisCommandError> {
+    let mut filter = ClientKillFilter::default();
+
+    let mut arguments = arguments.iter();
+    while let Some(option) = arguments.next() {
+        let option = get_bytes_vec(Some(option))?.to_ascii_uppercase();
+        let value = get_bytes_vec(arguments.next()).map_err(|_| RedisCommandError::Syntax)?;
+        match option.as_slice() {
+            b"ID" => {
+                let id = parse_u64(value)
+                    .ok()
+                    .filter(|id| *id > 0)
+                    .ok_or(RedisCommandError::InvalidClientId)?;
+                filter.id = Some(id);
+            }
+            b"TYPE" => {
+                let client_type = match value.to_ascii_lowercase().as_slice() {
+                    b"normal" => ClientType::Normal,
+                    b"master" => ClientType::Master,
+                    b"replica" | b"slave" => ClientType::Replica,
+                    b"pubsub" => ClientType::PubSub,
+                    _ => {
+                        return Err(RedisCommandError::UnknownClientType(
+                            String::from_utf8_lossy(&value).to_string(),
+                        ))
+                    }
+                };
+                filter.client_type = Some(client_type);
+            }
+            b"USER" => filter.user = Some(value),
+            b"ADDR" => filter.addr = Some(String::from_utf8_lossy(&value).to_string()),
+            b"LADDR" => filter.laddr = Some(String::from_utf8_lossy(&value).to_string()),
+            b"SKIPME" => {
+                filter.skip_me = match value.to_ascii_lowercase().as_slice() {
+                    b"yes" => true,
+                    b"no" => false,
+                    _ => return Err(RedisCommandError::Syntax),
+                }
+            }
+            _ => return Err(RedisCommandError::Syntax),
+        }
+    }
+
+    Ok(filter)
+}
+
+/// parse the `numkeys [key [key ...]] [arg [arg ...]]` arguments of EVAL and EVALSHA
+pub fn parse_script_arguments(arguments: &[Resp]) -> Result<(Keys, Values), RedisCommandError> {
+    let num_keys = get_bytes_vec(arguments.first()).and_then(parse_integer)?;
+    if num_keys < 0 {
+        return Err(RedisCommandError::NegativeKeyCount);
+    }
+
+    let num_keys = num_keys as usize;
+    if num_keys >= arguments.len() {
+        return Err(RedisCommandError::TooManyKeys);
+    }
+
+    let (keys, args) = arguments[1..].split_at(num_keys);
+    let keys = keys
+        .iter()
+        .map(|key| get_bytes_vec(Some(key)))
+        .collect::<Result<Vec<_>, _>>()?;
+    let args = args
+        .iter()
+        .map(|arg| get_bytes_vec(Some(arg)))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok((keys, args))
+}
+
+/// Commands sharing the arguments of ZRANGE
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum ZRangeCommand {
+    // ZRANGE, the kind of range and the direction being given as options
+    Range,
+    ByScore,
+    RevByScore,
+    ByLex,
+    RevByLex,
+}
+
+/// parse the `start stop [BYSCORE|BYLEX] [REV] [LIMIT offset count] [WITHSCORES]` arguments of
+/// ZRANGE and of its dedicated forms, return the query and whether the scores are requested
+pub fn parse_zrange_arguments(
+    arguments: &[Resp],
+    command: ZRangeCommand,
+) -> Result<(ZRangeQuery, bool), RedisCommandError> {
+    use ZRangeCommand::*;
+
+    let start = get_bytes_vec(arguments.first())?;
+    let stop = get_bytes_vec(arguments.get(1))?;
+    let mut by_score = matches!(command, ByScore | RevByScore);
+    let mut by_lex = matches!(command, ByLex | RevByLex);
+    let mut rev = matches!(command, RevByScore | RevByLex);
+    let mut limit = None;
+    let mut with_scores = false;
+
+    let mut options = arguments[2.min(arguments.len())..].iter();
+    while let Some(option) = options.next() {
+        match get_bytes_vec(Some(option))?.to_ascii_uppercase().as_slice() {
+            b"BYSCORE" if command == Range => by_score = true,
+            b"BYLEX" if command == Range => by_lex = true,
+            b"REV" if command == Range => rev = true,
+            b"LIMIT" => {
+                let offset =
+                    get_bytes_vec(options.next()).map_err(|_| RedisCommandError::Syntax)?;
+                let count = get_bytes_vec(options.next()).map_err(|_| RedisCommandError::Syntax)?;
+                limit = Some((parse_integer(offset)?, parse_integer(count)?));
+            }
+            b"WITHSCORES" if !matches!(command, ByLex | RevByLex) => with_scores = true,
+            _ => return Err(RedisCommandError::Syntax),
+        }
+    }
+
+    // the maximum comes first when walking backwards
+    let (min, max) = match rev {
+        true => (stop.clone(), start.clone()),
+        false => (start.clone(), stop.clone()),
+    };
+    let by = match (by_score, by_lex) {
+        (true, true) => return Err(RedisCommandError::Syntax),
+        (true, false) => ZRangeBy::Score(parse_score_bound(min)?, parse_score_bound(max)?),
+        (false, true) if with_scores => return Err(RedisCommandError::WithScoresByLex),
+        (false, true) => ZRangeBy::Lex(parse_lex_bound(min)?, parse_lex_bound(max)?),
+        (false, false) if limit.is_some() => return Err(RedisCommandError::LimitWithoutScoreOrLex),
+        (false, false) => ZRangeBy::Index(parse_integer(start)?, parse_integer(stop)?),
+    };
+
+    Ok((ZRangeQuery { by, rev, limit }, with_scores))
+}
+
+/// parse a lexicographic range bound: `-`, `+`, or a member prefixed with `[` (included) or `(`
+/// (excluded)
+pub fn parse_lex_bound(bytes: Vec<u8>) -> Result<LexBound, RedisCommandError> {
+    match bytes.split_first() {
+        Some((b'-', [])) => Ok(LexBound::Min),
+        Some((b'+', [])) => Ok(LexBound::Max),
+        Some((b'[', member)) => Ok(LexBound::Inclusive(member.to_vec())),
+        Some((b'(', member)) => Ok(LexBound::Exclusive(member.to_vec())),
+        _ => Err(RedisCommandError::MinMaxNotLexRange),
+    }
+}
+
+/// parse a score range bound: a float, `-inf` or `+inf`, excluded when prefixed with `(`
+pub fn parse_score_bound(bytes: Vec<u8>) -> Result<ScoreBound, RedisCommandError> {
+    let (value, exclusive) = match bytes.strip_prefix(b"(") {
+        Some(value) => (value, true),
+        None => (&bytes[..], false),
+    };
+
+    std::str::from_utf8(value)
+        .ok()
+        .and_then(|value| value.parse::<f64>().ok())
+        .filter(|value| !value.is_nan())
+        .map(|value| ScoreBound { value, exclusive })
+        .ok_or(RedisCommandError::MinMaxNotFloat)
+}
+
+/// keys, weights, aggregate function and whether the scores are requested
+type ZSetOperationArguments = (Vec<Vec<u8>>, Vec<f64>, Aggregate, bool);
+
+/// parse the `numkeys key [key ...] [WEIGHTS weight [weight ...]] [AGGREGATE SUM|MIN|MAX]
+/// [WITHSCORES]` arguments of ZUNION, ZINTER, ZDIFF and their storing forms - `weighted` allows
+/// WEIGHTS and AGGREGATE, `with_scores` allows WITHSCORES
+pub fn parse_zset_operation_arguments(
+    arguments: &[Resp],
+    command: &'static str,
+    weighted: bool,
+    with_scores: bool,
+) -> Result<ZSetOperationArguments, RedisCommandError> {
+    let num_keys = get_bytes_vec(arguments.first()).and_then(parse_integer)?;
+    if num_keys <= 0 {
+        return Err(RedisCommandError::NoInputKey(command));
+    }
+
+    let num_keys = num_keys as usize;
+    let keys = match arguments.get(1..=num_keys) {
+        Some(keys) => keys,
+        None => return Err(RedisCommandError::Syntax),
+    };
+    let keys = keys
+        .iter()
+        .map(|key| get_bytes_vec(Some(key)))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut weights = vec![1.0; num_keys];
+    let mut aggregate = Aggregate::Sum;
+    let mut scores_requested = false;
+
+    let mut options = arguments[num_keys + 1..].iter();
+    while let Some(option) = options.next() {
+        match get_bytes_vec(Some(option))?.to_ascii_uppercase().as_slice() {
+            b"WEIGHTS" if weighted => {
+                for weight in weights.iter_mut() {
+                    *weight = get_bytes_vec(options.next())
+                        .map_err(|_| RedisCommandError::Syntax)
+                        .and_then(|weight| {
+                            parse_float(weight).map_err(|_| RedisCommandError::WeightNotFloat)
+                        })?;
+                }
+            }
+            b"AGGREGATE" if weighted => {
+                aggregate = match get_bytes_vec(options.next())
+                    .map_err(|_| RedisCommandError::Syntax)?
+                    .to_ascii_uppercase()
+                    .as_slice()
+                {
+                    b"SUM" => Aggregate::Sum,
+                    b"MIN" => Aggregate::Min,
+                    b"MAX" => Aggregate::Max,
+                    _ => return Err(RedisCommandError::Syntax),
+                }
+            }
+            b"WITHSCORES" if with_scores => scores_requested = true,
+            _ => return Err(RedisCommandError::Syntax),
+        }
+    }
+
+    Ok((keys, weights, aggregate, scores_requested))
+}
+
+/// parse a stream ID, `ms-seq` or `ms` alone with `default_seq` as its sequence number
+pub fn parse_stream_id(bytes: &[u8], default_seq: u64) -> Result<StreamId, RedisCommandError> {
+    let id = std::str::from_utf8(bytes).map_err(|_| RedisCommandError::InvalidStreamId)?;
+    let (ms, seq) = match id.split_once('-') {
+        Some((ms, seq)) => (ms, Some(seq)),
+        None => (id, None),
+    };
+
+    let ms = ms
+        .parse::<u64>()
+        .map_err(|_| RedisCommandError::InvalidStreamId)?;
+    let seq = match seq {
+        Some(seq) => seq
+            .parse::<u64>()
+            .map_err(|_| RedisCommandError::InvalidStreamId)?,
+        None => default_seq,
+    };
+    Ok(StreamId::new(ms, seq))
+}
+
+/// parse the start of a stream range: `-`, an ID, or an ID prefixed with `(` to exclude it
+pub fn parse_stream_start(bytes: Vec<u8>) -> Result<StreamId, RedisCommandError> {
+    match bytes.split_first() {
+        Some((b'-', [])) => Ok(StreamId::MIN),
+        Some((b'(', id)) => parse_stream_id(id, 0)?
+            .next()
+            .ok_or(RedisCommandError::StreamIntervalId("start")),
+        _ => parse_stream_id(&bytes, 0),
+    }
+}
+
+/// parse the end of a stream range: `+`, an ID, or an ID prefixed with `(` to exclude it
+pub fn parse_stream_end(bytes: Vec<u8>) -> Result<StreamId, RedisCommandError> {
+    match bytes.split_first() {
+        Some((b'+', [])) => Ok(StreamId::MAX),
+        Some((b'(', id)) => parse_stream_id(id, u64::MAX)?
+            .previous()
+            .ok_or(RedisCommandError::StreamIntervalId("end")),
+        _ => parse_stream_id(&bytes, u64::MAX),
+    }
+}
+
+/// parse the ID of the entry added by XADD: `*`, `ms-*` or an explicit ID greater than 0-0
+pub fn parse_new_stream_id(bytes: Vec<u8>) -> Result<NewStreamId, RedisCommandError> {
+    if bytes == b"*" {
+        return Ok(NewStreamId::Auto);
+    }
+    if let Some(ms) = bytes.strip_suffix(b"-*") {
+        return Ok(NewStreamId::Partial(parse_stream_id(ms, 0)?.ms));
+    }
+
+    match parse_stream_id(&bytes, 0)? {
+        StreamId::MIN => Err(RedisCommandError::StreamIdZero),
+        id => Ok(NewStreamId::Explicit(id)),
+    }
+}
+
+/// parse the BLOCK timeout of XREAD in milliseconds, `None` to block forever
+pub fn parse_block_timeout(bytes: Vec<u8>) -> Result<Option<Duration>, RedisCommandError> {
+    let timeout = std::str::from_utf8(&bytes[..])
+        .ok()
+        .and_then(|timeout| timeout.parse::<i64>().ok())
+        .ok_or(RedisCommandError::TimeoutNotInteger)?;
+
+    match timeout {
+        timeout if timeout < 0 => Err(RedisCommandError::NegativeTimeout),
+        0 => Ok(None),
+        timeout => Ok(Some(Duration::from_millis(timeout as u64))),
+    }
+}
+
+/// Options and streams of XREAD and XREADGROUP
+#[derive(Debug, PartialEq)]
+pub struct StreamReadArguments {
+    pub count: Option<usize>,
+    // `None` when not blocking, `Some(None)` to block forever
+    pub block: Option<Option<Duration>>,
+    // entries read from a group are not added to its pending entry list, XREADGROUP only
+    pub no_ack: bool,
+    pub keys: Vec<Vec<u8>>,
+    // unparsed IDs, one per key
+    pub ids: Vec<Vec<u8>>,
+}
+
+/// parse the `[COUNT count] [BLOCK milliseconds] [NOACK] STREAMS key [key ...] id [id ...]`
+/// arguments of `command`, NOACK being accepted by XREADGROUP only
+pub fn parse_stream_read_arguments(
+    arguments: &[Resp],
+    command: &'static str,
+) -> Result<StreamReadArguments, RedisCommandError> {
+    let mut count = None;
+    let mut block = None;
+    let mut no_ack = false;
+
+    let mut arguments = arguments.iter();
+    loop {
+        let option = get_bytes_vec(arguments.next()).map_err(|_| RedisCommandError::Syntax)?;
+        match option.to_ascii_uppercase().as_slice() {
+            b"COUNT" => {
+                // a count which is not positive is the same as no count
+                let value = get_bytes_vec(arguments.next()).and_then(parse_integer)?;
+                count = Some(value)
+                    .filter(|count| *count > 0)
+                    .map(|count| count as usize);
+            }
+            b"BLOCK" => {
+                block = Some(get_bytes_vec(arguments.next()).and_then(parse_block_timeout)?);
+            }
+            b"NOACK" if command == "xreadgroup" => no_ack = true,
+            b"STREAMS" => break,
+            _ => return Err(RedisCommandError::Syntax),
+        }
+    }
+
+    let arguments = arguments.collect::<Vec<_>>();
+    if arguments.is_empty() || !arguments.len().is_multiple_of(2) {
+        return Err(RedisCommandError::UnbalancedStreams(command));
+    }
+
+    let mut keys = arguments
+        .into_iter()
+        .map(|argument| get_bytes_vec(Some(argument)))
+        .collect::<Result<Vec<_>, _>>()?;
+    let ids = keys.split_off(keys.len() / 2);
+
+    Ok(StreamReadArguments {
+        count,
+        block,
+        no_ack,
+        keys,
+        ids,
+    })
+}
+
+/// parse an ID of XREAD: `$` for the last ID of the stream, or an ID
+pub fn parse_stream_read_id(bytes: Vec<u8>) -> Result<Option<StreamId>, RedisCommandError> {
+    match bytes.as_slice() {
+        b"$" => Ok(None),
+        id => parse_stream_id(id, 0).map(Some),
+    }
+}
+
+/// parse an ID of XREADGROUP: `>` for the entries never delivered to the group, or an ID to read
+/// the pending entries of the consumer from
+pub fn parse_stream_read_group_id(bytes: Vec<u8>) -> Result<Option<StreamId>, RedisCommandError> {
+    match bytes.as_slice() {
+        b">" => Ok(None),
+        b"$" => Err(RedisCommandError::StreamReadGroupLastId),
+        id => parse_stream_id(id, 0).map(Some),
+    }
+}
+
+/// parse the `id [id ...] [IDLE ms] [TIME ms] [RETRYCOUNT count] [FORCE] [JUSTID] [LASTID id]`
+/// arguments of XCLAIM into the IDs, the options and the last ID
+pub fn parse_xclaim_arguments(
+    arguments: &[Resp],
+    options: &mut ClaimOptions,
+) -> Result<(Vec<StreamId>, Option<StreamId>), RedisCommandError> {
+    let mut arguments = arguments.iter().peekable();
+    let mut ids = vec![parse_stream_id(&get_bytes_vec(arguments.next())?, 0)?];
+    while let Some(id) = arguments.peek() {
+        match parse_stream_id(&get_bytes_vec(Some(id))?, 0) {
+            Ok(id) => ids.push(id),
+            Err(_) => break,
+        }
+        arguments.next();
+    }
+
+    // negative times and counts are the same as 0
+    let parse_value = |arguments: &mut dyn Iterator<Item = &Resp>| {
+        get_bytes_vec(arguments.next())
+            .and_then(parse_integer)
+            .map(|value| value.max(0) as u64)
+    };
+
+    let mut last_id = None;
+    while let Some(option) = arguments.next() {
+        match get_bytes_vec(Some(option))?.to_ascii_uppercase().as_slice() {
+            b"IDLE" => options.idle = Some(parse_value(&mut arguments)?),
+            b"TIME" => options.time = Some(parse_value(&mut arguments)?),
+            b"RETRYCOUNT" => options.retry_count = Some(parse_value(&mut arguments)?),
+            b"FORCE" => options.force = true,
+            b"JUSTID" => options.just_id = true,
+            b"LASTID" => last_id = Some(parse_stream_id(&get_bytes_vec(arguments.next())?, 0)?),
+            _ => return Err(RedisCommandError::Syntax),
+        }
+    }
+
+    Ok((ids, last_id))
+}
+
+/// parse the `[= | ~] threshold` arguments following the MAXLEN or MINID `strategy` of XTRIM and
+/// XADD
+pub fn parse_stream_trim(
+    strategy: &[u8],
+    arguments: &mut Peekable<Iter<Resp>>,
+) -> Result<StreamTrim, RedisCommandError> {
+    let mut threshold = get_bytes_vec(arguments.next())?;
+    let approximate = threshold == b"~";
+    if approximate || threshold == b"=" {
+        threshold = get_bytes_vec(arguments.next())?;
+    }
+
+    let strategy = match strategy {
+        b"MAXLEN" => match parse_integer(threshold)? {
+            max_len if max_len < 0 => return Err(RedisCommandError::NegativeArgument("MAXLEN")),
+            max_len => TrimStrategy::MaxLen(max_len as usize),
+        },
+        _ => TrimStrategy::MinId(parse_stream_id(&threshold, 0)?),
+    };
+
+    Ok(StreamTrim {
+        strategy,
+        approximate,
+        limit: None,
+    })
+}
+
+/// parse the count following the LIMIT option of XTRIM and XADD
+pub fn parse_stream_trim_limit(
+    arguments: &mut Peekable<Iter<Resp>>,
+) -> Result<usize, RedisCommandError> {
+    match get_bytes_vec(arguments.next()).and_then(parse_integer)? {
+        limit if limit < 0 => Err(RedisCommandError::NegativeArgument("LIMIT")),
+        limit => Ok(limit as usize),
+    }
+}
+
+/// give `trim` the LIMIT option, wherever it was among the options of XTRIM and XADD - only an
+/// approximate trimming can be limited
+pub fn limit_stream_trim(
+    trim: Option<StreamTrim>,
+    limit: Option<usize>,
+) -> Result<Option<StreamTrim>, RedisCommandError> {
+    match (trim, limit) {
+        (trim, None) => Ok(trim),
+        (None, Some(_)) => Err(RedisCommandError::LimitWithoutTrimStrategy),
+        (Some(trim), Some(_)) if !trim.approximate => {
+            Err(RedisCommandError::LimitWithoutApproximation)
+        }
+        (Some(trim), limit) => Ok(Some(StreamTrim { limit, ..trim })),
+    }
+}
+
+/// parse a longitude and a latitude, which must be within the limits of geohashes
+pub fn parse_coordinates(
+    longitude: Vec<u8>,
+    latitude: Vec<u8>,
+) -> Result<(f64, f64), RedisCommandError> {
+    let longitude = parse_float(longitude)?;
+    let latitude = parse_float(latitude)?;
+    match geo::is_valid(longitude, latitude) {
+        true => Ok((longitude, latitude)),
+        false => Err(RedisCommandError::InvalidCoordinates(longitude, latitude)),
+    }
+}
+
+/// parse the unit of a distance: `m`, `km`, `mi` or `ft`
+pub fn parse_geo_unit(bytes: Vec<u8>) -> Result<GeoUnit, RedisCommandError> {
+    match bytes.to_ascii_lowercase().as_slice() {
+        b"m" => Ok(GeoUnit::Meters),
+        b"km" => Ok(GeoUnit::Kilometers),
+        b"mi" => Ok(GeoUnit::Miles),
+        b"ft" => Ok(GeoUnit::Feet),
+        _ => Err(RedisCommandError::UnsupportedUnit),
+    }
+}
+
+/// parse the `FROMMEMBER member|FROMLONLAT longitude latitude BYRADIUS radius unit|BYBOX width
+/// height unit [ASC|DESC] [COUNT count [ANY]] [WITHCOORD] [WITHDIST] [WITHHASH]` arguments of
+/// GEOSEARCH, the WITH options being replaced by `[STOREDIST]` for GEOSEARCHSTORE, return the
+/// query and whether the distances are to be stored
+pub fn parse_geo_search_arguments(
+    arguments: &[Resp],
+    command: &'static str,
+) -> Result<(GeoQuery, bool), RedisCommandError> {
+    let store = command == "geosearchstore";
+    let mut origin = None;
+    let mut shape = None;
+    let mut unit = GeoUnit::Meters;
+    let mut sort = None;
+    let mut count = None;
+    let mut any = false;
+    let (mut with_coord, mut with_dist, mut with_hash, mut store_dist) =
+        (false, false, false, false);
+
+    let mut arguments = arguments.iter();
+    let mut next = || get_bytes_vec(arguments.next()).map_err(|_| RedisCommandError::Syntax);
+    while let Ok(option) = next() {
+        match option.to_ascii_uppercase().as_slice() {
+            b"FROMMEMBER" if origin.is_none() => origin = Some(GeoOrigin::Member(next()?)),
+            b"FROMLONLAT" if origin.is_none() => {
+                let (longitude, latitude) = parse_coordinates(next()?, next()?)?;
+                origin = Some(GeoOrigin::Position(longitude, latitude));
+            }
+            b"FROMMEMBER" | b"FROMLONLAT" => {
+                return Err(RedisCommandError::GeoSearchOrigin(command))
+            }
+            b"BYRADIUS" if shape.is_none() => {
+                let radius = parse_float(next()?)?;
+                unit = parse_geo_unit(next()?)?;
+                if radius < 0.0 {
+                    return Err(RedisCommandError::NegativeSize("radius"));
+                }
+                shape = Some(GeoShape::Radius(radius * unit.meters()));
+            }
+            b"BYBOX" if shape.is_none() => {
+                let width = parse_float(next()?)?;
+                let height = parse_float(next()?)?;
+                unit = parse_geo_unit(next()?)?;
+                if width < 0.0 || height < 0.0 {
+                    return Err(RedisCommandError::NegativeSize("height or width"));
+                }
+                shape = Some(GeoShape::Box(width * unit.meters(), height * unit.meters()));
+            }
+            b"BYRADIUS" | b"BYBOX" => return Err(RedisCommandError::GeoSearchShape(command)),
+            b"ASC" => sort = Some(GeoSort::Asc),
+            b"DESC" => sort = Some(GeoSort::Desc),
+            b"COUNT" => {
+                count = match parse_integer(next()?)? {
+                    count if count <= 0 => {
+                        return Err(RedisCommandError::NotGreaterThanZero("COUNT"))
+                    }
+                    count => Some(count as usize),
+                }
+            }
+            b"ANY" => any = true,
+            b"WITHCOORD" if !store => with_coord = true,
+            b"WITHDIST" if !store => with_dist = true,
+            b"WITHHASH" if !store => with_hash = true,
+            b"STOREDIST" if store => store_dist = true,
+            _ => return Err(RedisCommandError::Syntax),
+        }
+    }
+
+    let origin = origin.ok_or(RedisCommandError::GeoSearchOrigin(command))?;
+    let shape = shape.ok_or(RedisCommandError::GeoSearchShape(command))?;
+    if any && count.is_none() {
+        return Err(RedisCommandError::AnyWithoutCount);
+    }
+
+    let query = GeoQuery {
+        origin,
+        shape,
+        unit,
+        sort,
+        count,
+        any,
+        with_coord,
+        with_dist,
+        with_hash,
+    };
+    Ok((query, store_dist))
+}
@@ -1,11 +1,40 @@
-use std::io::{Error, ErrorKind};
+use std::fmt::{Display, Formatter};
+use std::io::{self, ErrorKind};
+use std::sync::PoisonError;
 
-use rayon::ThreadPoolBuildError;
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    // The listening address could not be bound, holds the address and the cause
+    Bind(String, ErrorKind),
+    // I/O failure (socket, thread, file)
+    Io(ErrorKind, String),
+    // Invalid configuration or API misuse
+    Config(String),
+    // Failure of the storage backend
+    Storage(String),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Bind(addr, kind) => write!(f, "can not bind {}: {:?}", addr, kind),
+            Self::Io(kind, message) => write!(f, "{:?}: {}", kind, message),
+            Self::Config(message) => write!(f, "configuration error: {}", message),
+            Self::Storage(message) => write!(f, "storage error: {}", message),
+        }
+    }
+}
 
-pub struct MyThreadPoolBuildError(ThreadPoolBuildError);
+impl std::error::Error for Error {}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err.kind(), err.to_string())
+    }
+}
 
-impl From<MyThreadPoolBuildError> for Error {
-    fn from(err: MyThreadPoolBuildError) -> Self {
-        Error::new(ErrorKind::Other, err.0.to_string())
+impl<T> From<PoisonError<T>> for Error {
+    fn from(_: PoisonError<T>) -> Self {
+        Self::Storage("a command panicked while holding the lock".to_string())
     }
 }
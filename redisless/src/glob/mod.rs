@@ -0,0 +1,112 @@
+#[cfg(test)]
+mod tests;
+
+/// Match `string` against a Redis glob-style `pattern`, byte by byte.
+///
+/// Supported syntax:
+/// - `*` matches any sequence of bytes (including the empty one)
+/// - `?` matches exactly one byte
+/// - `[abc]`, `[a-z]` and `[^abc]` match one byte from (or not from) a set
+/// - `\x` matches `x` literally
+pub fn glob_match(pattern: &[u8], string: &[u8]) -> bool {
+    let mut p = 0_usize;
+    let mut s = 0_usize;
+
+    while p < pattern.len() {
+        match pattern[p] {
+            b'*' => {
+                // collapse consecutive stars
+                while pattern.get(p + 1) == Some(&b'*') {
+                    p += 1;
+                }
+
+                if p + 1 == pattern.len() {
+                    return true; // trailing star matches everything
+                }
+
+                return (s..=string.len()).any(|i| glob_match(&pattern[p + 1..], &string[i..]));
+            }
+            b'?' => {
+                if s >= string.len() {
+                    return false;
+                }
+                s += 1;
+            }
+            b'[' => {
+                let c = match string.get(s) {
+                    Some(c) => *c,
+                    None => return false,
+                };
+
+                let (matched, end) = match_class(pattern, p + 1, c);
+                if !matched {
+                    return false;
+                }
+
+                p = end;
+                s += 1;
+            }
+            b'\\' if p + 1 < pattern.len() => {
+                p += 1;
+                if string.get(s) != Some(&pattern[p]) {
+                    return false;
+                }
+                s += 1;
+            }
+            c => {
+                if string.get(s) != Some(&c) {
+                    return false;
+                }
+                s += 1;
+            }
+        }
+
+        p += 1;
+    }
+
+    s == string.len()
+}
+
+/// Match `c` against the class starting at `start` (right after `[`).
+/// Return whether it matched and the index of the closing `]`
+/// (or of the last byte of the pattern when the class is not closed).
+fn match_class(pattern: &[u8], start: usize, c: u8) -> (bool, usize) {
+    let mut p = start;
+    let not = pattern.get(p) == Some(&b'^');
+    if not {
+        p += 1;
+    }
+
+    let mut matched = false;
+    while p < pattern.len() {
+        match pattern[p] {
+            b'\\' if p + 1 < pattern.len() => {
+                p += 1;
+                if pattern[p] == c {
+                    matched = true;
+                }
+            }
+            b']' => break,
+            low if pattern.get(p + 1) == Some(&b'-') && p + 2 < pattern.len() => {
+                let high = pattern[p + 2];
+                let (low, high) = if low > high { (high, low) } else { (low, high) };
+                if low <= c && c <= high {
+                    matched = true;
+                }
+                p += 2;
+            }
+            x => {
+                if x == c {
+                    matched = true;
+                }
+            }
+        }
+
+        p += 1;
+    }
+
+    // an unclosed class ends at the last byte of the pattern
+    let end = p.min(pattern.len() - 1);
+
+    (matched != not, end)
+}
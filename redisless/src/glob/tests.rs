@@ -0,0 +1,37 @@
+use crate::glob::glob_match;
+
+#[test]
+fn star_and_question_mark() {
+    assert!(glob_match(b"*", b""));
+    assert!(glob_match(b"*", b"anything"));
+    assert!(glob_match(b"h*llo", b"hllo"));
+    assert!(glob_match(b"h*llo", b"heeeello"));
+    assert!(glob_match(b"h?llo", b"hello"));
+    assert!(!glob_match(b"h?llo", b"hllo"));
+    assert!(glob_match(b"user:*:name", b"user:42:name"));
+    assert!(!glob_match(b"user:*:name", b"user:42:email"));
+    assert!(glob_match(b"**a**", b"bab"));
+    assert!(!glob_match(b"", b"a"));
+}
+
+#[test]
+fn classes() {
+    assert!(glob_match(b"h[ae]llo", b"hello"));
+    assert!(glob_match(b"h[ae]llo", b"hallo"));
+    assert!(!glob_match(b"h[ae]llo", b"hillo"));
+    assert!(glob_match(b"h[^e]llo", b"hallo"));
+    assert!(!glob_match(b"h[^e]llo", b"hello"));
+    assert!(glob_match(b"h[a-b]llo", b"hbllo"));
+    assert!(glob_match(b"h[b-a]llo", b"hallo"));
+    assert!(!glob_match(b"h[a-b]llo", b"hcllo"));
+    assert!(glob_match(b"[\\]]", b"]"));
+}
+
+#[test]
+fn escaping_and_binary() {
+    assert!(glob_match(b"h\\*llo", b"h*llo"));
+    assert!(!glob_match(b"h\\*llo", b"hello"));
+    assert!(glob_match(b"h\\?llo", b"h?llo"));
+    assert!(glob_match(b"\xff*", b"\xff\x00\x01"));
+    assert!(!glob_match(b"\xff?", b"\xff"));
+}
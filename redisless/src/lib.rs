@@ -3,29 +3,42 @@
 extern crate serial_test;
 
 use storage::in_memory::InMemoryStorage;
+use storage::models::RedisType;
 
 use crate::server::{Server, ServerState};
 
 #[cfg(test)]
 mod tests;
 
-mod cluster;
+pub mod cluster;
 mod command;
 mod error;
+mod glob;
 mod protocol;
 pub mod server;
 pub mod storage;
 
+pub use error::Error;
+
+/// # Safety
+///
+/// The returned pointer must be released with `redisless_server_free`.
 #[no_mangle]
 pub unsafe extern "C" fn redisless_server_new(port: u16) -> *mut Server {
     Box::into_raw(Box::new(Server::new(InMemoryStorage::new(), port)))
 }
 
+/// # Safety
+///
+/// `server` must be a pointer returned by `redisless_server_new` and must not be used afterwards.
 #[no_mangle]
 pub unsafe extern "C" fn redisless_server_free(server: *mut Server) {
     let _ = Box::from_raw(server);
 }
 
+/// # Safety
+///
+/// `server` must be null or a valid pointer returned by `redisless_server_new`.
 #[no_mangle]
 pub unsafe extern "C" fn redisless_server_start(server: *mut Server) -> bool {
     let server = match server.as_ref() {
@@ -33,12 +46,12 @@ pub unsafe extern "C" fn redisless_server_start(server: *mut Server) -> bool {
         None => return false,
     };
 
-    match server.start() {
-        Some(server_state) => server_state == ServerState::Started,
-        None => false,
-    }
+    matches!(server.start(), Ok(ServerState::Started))
 }
 
+/// # Safety
+///
+/// `server` must be null or a valid pointer returned by `redisless_server_new`.
 #[no_mangle]
 pub unsafe extern "C" fn redisless_server_stop(server: *mut Server) -> bool {
     let server = match server.as_ref() {
@@ -46,8 +59,61 @@ pub unsafe extern "C" fn redisless_server_stop(server: *mut Server) -> bool {
         None => return false,
     };
 
-    match server.stop() {
-        Some(server_state) => server_state == ServerState::Stopped,
-        None => false,
+    matches!(server.stop(), Ok(ServerState::Stopped))
+}
+
+/// Key metadata exposed to non-Rust bindings
+#[repr(C)]
+pub struct RedisLessKeyInfo {
+    /// 0: none, 1: string, 2: list, 3: set, 4: zset, 5: hash, 6: stream
+    pub key_type: u8,
+    /// milliseconds left before the key expires, -1 when it has no expiry, -2 when it does not exist
+    pub ttl: i64,
+    /// number of elements held by the value
+    pub elements: u64,
+    /// approximate number of bytes used by the key and its value
+    pub size: u64,
+}
+
+/// # Safety
+///
+/// `server` must be null or a valid pointer returned by `redisless_server_new`,
+/// `key` must point to `key_len` readable bytes and `out_info` must be null or writable.
+#[no_mangle]
+pub unsafe extern "C" fn redisless_key_info(
+    server: *mut Server,
+    key: *const u8,
+    key_len: usize,
+    out_info: *mut RedisLessKeyInfo,
+) -> bool {
+    let (server, out_info) = match (server.as_ref(), out_info.as_mut()) {
+        (Some(server), Some(out_info)) if !key.is_null() => (server, out_info),
+        _ => return false,
+    };
+
+    let key = std::slice::from_raw_parts(key, key_len);
+
+    match server.key_info(key) {
+        Ok(Some(key_info)) => {
+            out_info.key_type = match key_info.data_type {
+                RedisType::String => 1,
+                RedisType::List => 2,
+                RedisType::Set => 3,
+                RedisType::ZSet => 4,
+                RedisType::Hash => 5,
+                RedisType::Stream => 6,
+            };
+            out_info.ttl = key_info.ttl;
+            out_info.elements = key_info.elements;
+            out_info.size = key_info.size;
+            true
+        }
+        _ => {
+            out_info.key_type = 0;
+            out_info.ttl = -2;
+            out_info.elements = 0;
+            out_info.size = 0;
+            false
+        }
     }
 }
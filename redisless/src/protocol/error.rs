@@ -38,17 +38,31 @@ impl RedisError {
             err_type: RedisErrorType::IncorrectFormat,
         }
     }
+
+    /// whether the input is the beginning of a value, which is parsed once the rest is appended
+    pub fn is_incomplete(&self) -> bool {
+        matches!(
+            self.err_type,
+            RedisErrorType::EmptyInput | RedisErrorType::NoCrlf
+        )
+    }
 }
 
-impl<'a> std::fmt::Display for RedisError {
+impl std::fmt::Display for RedisError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self)
+        match &self.err_type {
+            RedisErrorType::UnknownSymbol => write!(f, "unknown symbol"),
+            RedisErrorType::EmptyInput => write!(f, "empty input"),
+            RedisErrorType::NoCrlf => write!(f, "no CRLF found"),
+            RedisErrorType::IncorrectFormat => write!(f, "incorrect format"),
+            RedisErrorType::Other(err) => write!(f, "{}", err),
+        }
     }
 }
 
-impl<'a> std::error::Error for RedisError {}
+impl std::error::Error for RedisError {}
 
-impl<'a> From<std::str::Utf8Error> for RedisError {
+impl From<std::str::Utf8Error> for RedisError {
     fn from(from: std::str::Utf8Error) -> Self {
         Self {
             err_type: RedisErrorType::Other(Box::new(from)),
@@ -56,7 +70,7 @@ impl<'a> From<std::str::Utf8Error> for RedisError {
     }
 }
 
-impl<'a> From<std::num::ParseIntError> for RedisError {
+impl From<std::num::ParseIntError> for RedisError {
     fn from(from: std::num::ParseIntError) -> Self {
         Self {
             err_type: RedisErrorType::Other(Box::new(from)),
@@ -16,6 +16,7 @@ const LF: u8 = b'\n';
 pub const OK: &[u8; 5] = b"+OK\r\n";
 pub const PONG: &[u8; 7] = b"+PONG\r\n";
 pub const NIL: &[u8; 5] = b"$-1\r\n";
+pub const NIL_ARRAY: &[u8; 5] = b"*-1\r\n";
 
 #[derive(Debug, Eq, PartialEq)]
 pub enum Resp<'a> {
@@ -5,8 +5,8 @@ use super::{CR, LF, NIL_VALUE_SIZE};
 pub struct RedisProtocolParser;
 
 impl RedisProtocolParser {
-    pub fn parse(input: &[u8]) -> Result {
-        if let Some(first) = input.get(0) {
+    pub fn parse(input: &[u8]) -> Result<'_> {
+        if let Some(first) = input.first() {
             let first = *first as char;
             let input = &input[1..];
             let (resp, left) = match first {
@@ -34,19 +34,19 @@ impl RedisProtocolParser {
         Err(RedisError::no_crlf())
     }
 
-    pub fn parse_simple_string(input: &[u8]) -> Result {
+    pub fn parse_simple_string(input: &[u8]) -> Result<'_> {
         RedisProtocolParser::parse_everything_until_crlf(input).map(|(x, y)| (Resp::String(x), y))
     }
 
-    pub fn parse_errors(input: &[u8]) -> Result {
+    pub fn parse_errors(input: &[u8]) -> Result<'_> {
         RedisProtocolParser::parse_everything_until_crlf(input).map(|(x, y)| (Resp::Error(x), y))
     }
 
-    pub fn parse_integers(input: &[u8]) -> Result {
+    pub fn parse_integers(input: &[u8]) -> Result<'_> {
         RedisProtocolParser::parse_everything_until_crlf(input).map(|(x, y)| (Resp::Integer(x), y))
     }
 
-    pub fn parse_bulk_strings(input: &[u8]) -> Result {
+    pub fn parse_bulk_strings(input: &[u8]) -> Result<'_> {
         // Check Null Strings.
         if RedisProtocolParser::check_null_value(input) {
             Ok((Resp::Nil, &input[NIL_VALUE_SIZE..]))
@@ -54,31 +54,34 @@ impl RedisProtocolParser {
             let (size_str, input_after_size) =
                 RedisProtocolParser::parse_everything_until_crlf(input)?;
             let size = std::str::from_utf8(size_str)?.parse::<u64>()? as usize;
-            if RedisProtocolParser::check_crlf_at_index(input_after_size, size) {
-                Ok((
+            let end = size
+                .checked_add(2)
+                .ok_or_else(RedisError::incorrect_format)?;
+            match input_after_size.get(size..end) {
+                Some(crlf) if crlf == [CR, LF] => Ok((
                     Resp::BulkString(&input_after_size[..size]),
-                    &input_after_size[size + 2..],
-                ))
-            } else {
-                Err(RedisError::incorrect_format())
+                    &input_after_size[end..],
+                )),
+                // the end of the string has not been received yet
+                None if input_after_size.get(size).is_none_or(|byte| *byte == CR) => {
+                    Err(RedisError::no_crlf())
+                }
+                _ => Err(RedisError::incorrect_format()),
             }
         }
     }
 
-    fn check_crlf_at_index(input: &[u8], index: usize) -> bool {
-        input.len() >= index && input[index] == CR && input[index + 1] == LF
-    }
-
     fn check_null_value(input: &[u8]) -> bool {
         input.len() >= 4 && input[0] == b'-' && input[1] == b'1' && input[2] == CR && input[3] == LF
     }
 
-    pub fn parse_arrays(input: &[u8]) -> Result {
+    pub fn parse_arrays(input: &[u8]) -> Result<'_> {
         let (size_str, input) = RedisProtocolParser::parse_everything_until_crlf(input)?;
         let size = std::str::from_utf8(size_str)?.parse::<u64>()?;
         let sizes = size as usize;
         let mut left = input;
-        let mut result = Vec::with_capacity(sizes);
+        // the size is not trusted until the elements have been received
+        let mut result = Vec::with_capacity(sizes.min(input.len()));
         for _ in 0..sizes {
             let (element, tmp) = RedisProtocolParser::parse(left)?;
             result.push(element);
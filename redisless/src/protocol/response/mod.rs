@@ -1,13 +1,16 @@
 use prost::bytes::BufMut;
 
-use super::{NIL, OK, PONG};
+use super::{NIL, NIL_ARRAY, OK, PONG};
 use crate::{command::command_error::RedisCommandError, storage::models::RedisString};
 
 pub enum RedisResponseType {
     SimpleString(RedisString),
     BulkString(RedisString),
     Integer(i64),
+    Array(Vec<RedisResponseType>),
     Nil,
+    // the missing array replied by the blocking commands which timed out
+    NilArray,
 }
 
 pub struct RedisResponse {
@@ -17,6 +20,10 @@ pub struct RedisResponse {
 enum RedisResponseInner {
     Single(RedisResponseType),
     Array(Vec<RedisResponseType>),
+    // several replies in a row, as sent to confirm each channel of SUBSCRIBE
+    Multiple(Vec<RedisResponseType>),
+    // complete replies, errors included, gathered in an array as EXEC does
+    Replies(Vec<RedisResponse>),
     Error(RedisCommandError),
     Okay,
     Pong,
@@ -25,12 +32,22 @@ enum RedisResponseInner {
 
 impl RedisResponseType {
     // move out of the enum
+    #[allow(clippy::wrong_self_convention)]
     fn to_vec(self) -> Vec<u8> {
         use RedisResponseType::*;
         match self {
             SimpleString(s) | BulkString(s) => s,
             Integer(num) => num.to_string().as_bytes().to_vec(),
+            Array(responses) => {
+                responses
+                    .into_iter()
+                    .fold(Vec::<u8>::new(), |mut bytes, response| {
+                        bytes.append(&mut response.get_formatted());
+                        bytes
+                    })
+            }
             Nil => NIL.to_vec(),
+            NilArray => NIL_ARRAY.to_vec(),
         }
     }
     /// Move out of self and return bytes analogous to `format!("{}{}{}", symbol, data, CRLF)`
@@ -41,7 +58,15 @@ impl RedisResponseType {
             SimpleString(_) => b'+',
             BulkString(_) => b'$',
             Integer(_) => b':',
-            Nil => return self.to_vec(),
+            Array(responses) => {
+                let mut reply = Vec::<u8>::with_capacity(512);
+                reply.push(b'*');
+                reply.put_slice(responses.len().to_string().as_bytes());
+                reply.put_slice(b"\r\n");
+                reply.append(&mut self.to_vec());
+                return reply;
+            }
+            Nil | NilArray => return self.to_vec(),
         };
         let mut bytes = self.to_vec();
         let mut reply =
@@ -75,10 +100,10 @@ impl RedisResponse {
         }
     }
     pub fn is_quit(&self) -> bool {
-        match self.responses {
-            RedisResponseInner::Quit => true,
-            _ => false,
-        }
+        matches!(self.responses, RedisResponseInner::Quit)
+    }
+    pub fn is_error(&self) -> bool {
+        matches!(self.responses, RedisResponseInner::Error(_))
     }
 
     pub fn single(response: RedisResponseType) -> Self {
@@ -93,6 +118,18 @@ impl RedisResponse {
         }
     }
 
+    pub fn multiple(responses: Vec<RedisResponseType>) -> Self {
+        Self {
+            responses: RedisResponseInner::Multiple(responses),
+        }
+    }
+
+    pub fn replies(responses: Vec<RedisResponse>) -> Self {
+        Self {
+            responses: RedisResponseInner::Replies(responses),
+        }
+    }
+
     pub fn error(error: RedisCommandError) -> Self {
         Self {
             responses: RedisResponseInner::Error(error),
@@ -106,14 +143,15 @@ impl RedisResponse {
             Error(e) => e.to_vec(),
             Pong => PONG.to_vec(),
             Single(single) => single.get_formatted(),
-            Array(responses) => {
-                let mut reply = Vec::<u8>::with_capacity(512);
-                reply.push(b'*');
-                reply.put_slice(&responses.len().to_string().as_bytes().to_vec());
-                reply.put_slice(b"\r\n");
+            Array(responses) => RedisResponseType::Array(responses).get_formatted(),
+            Multiple(responses) => responses
+                .into_iter()
+                .flat_map(RedisResponseType::get_formatted)
+                .collect(),
+            Replies(responses) => {
+                let mut reply = format!("*{}\r\n", responses.len()).into_bytes();
                 for response in responses {
-                    let mut response = response.get_formatted();
-                    reply.append(&mut response);
+                    reply.append(&mut response.reply());
                 }
                 reply
             }
@@ -27,6 +27,15 @@ pub fn test_errors() -> std::result::Result<(), RedisError> {
     let input = "*2\r\n$3\r\nfoo+hello\r\n".as_bytes();
     let err = RedisProtocolParser::parse(input).unwrap_err();
     assert!(matches!(err.err_type, RedisErrorType::IncorrectFormat));
+    let input = "*2\r\n$3\r\nfoo\r\n$3\r\nba".as_bytes();
+    let err = RedisProtocolParser::parse(input).unwrap_err();
+    assert!(err.is_incomplete());
+    let input = "*2\r\n$3\r\nfoo\r\n".as_bytes();
+    let err = RedisProtocolParser::parse(input).unwrap_err();
+    assert!(err.is_incomplete());
+    let input = "*99999999999999\r\n".as_bytes();
+    let err = RedisProtocolParser::parse(input).unwrap_err();
+    assert!(err.is_incomplete());
     Ok(())
 }
 
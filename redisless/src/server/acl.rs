@@ -0,0 +1,392 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Mutex;
+
+use sha2::{Digest, Sha256};
+
+use crate::command::category;
+use crate::command::command_error::RedisCommandError;
+use crate::glob::glob_match;
+use crate::storage::models::RedisString;
+
+/// User the clients are authenticated as when they connect
+pub const DEFAULT_USER: &str = "default";
+
+/// User of the ACL, whose permissions apply to the clients authenticated as it
+#[derive(Clone)]
+pub struct User {
+    pub name: String,
+    pub enabled: bool,
+    // any password is accepted
+    pub nopass: bool,
+    // SHA-256 of the passwords, hex encoded
+    pub passwords: BTreeSet<String>,
+    // set by +@all, the user can run any command, even the ones without a category
+    all_commands: bool,
+    // lowercase names of the commands the user can run, when not `all_commands`
+    commands: BTreeSet<&'static str>,
+    // command rules applied since the last reset, as they are described
+    command_rules: Vec<String>,
+    // glob-style patterns of the keys the user can access
+    pub key_patterns: Vec<RedisString>,
+}
+
+impl User {
+    /// user created by ACL SETUSER, which can not do anything until given rules
+    fn new(name: String) -> Self {
+        User {
+            name,
+            enabled: false,
+            nopass: false,
+            passwords: BTreeSet::new(),
+            all_commands: false,
+            commands: BTreeSet::new(),
+            command_rules: vec!["-@all".to_string()],
+            key_patterns: vec![],
+        }
+    }
+
+    /// whether `password` authenticates as the user
+    fn accepts(&self, password: &[u8]) -> bool {
+        self.enabled && (self.nopass || self.passwords.contains(&hash_password(password)))
+    }
+
+    /// whether the user can run the command `command_name`
+    pub fn can_run(&self, command_name: &str) -> bool {
+        self.all_commands || self.commands.contains(command_name)
+    }
+
+    /// whether `updated` is the user with fewer permissions: disabled, or not allowed some of the
+    /// commands or keys it was
+    fn is_downgraded_to(&self, updated: &User) -> bool {
+        let commands_revoked = !updated.all_commands
+            && (self.all_commands || !self.commands.is_subset(&updated.commands));
+        // a pattern is still granted as is, or by the one of allkeys
+        let keys_revoked = self.key_patterns.iter().any(|pattern| {
+            !updated
+                .key_patterns
+                .iter()
+                .any(|granted| granted == pattern || granted == b"*")
+        });
+        (self.enabled && !updated.enabled) || commands_revoked || keys_revoked
+    }
+
+    /// whether the user can access `key`
+    pub fn can_access(&self, key: &[u8]) -> bool {
+        self.key_patterns
+            .iter()
+            .any(|pattern| glob_match(pattern, key))
+    }
+
+    /// commands the user can run, as the rules which granted them
+    pub fn describe_commands(&self) -> String {
+        self.command_rules.join(" ")
+    }
+
+    /// key patterns of the user, each one prefixed with `~`
+    pub fn describe_keys(&self) -> String {
+        self.key_patterns
+            .iter()
+            .map(|pattern| format!("~{}", String::from_utf8_lossy(pattern)))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// the user as listed by ACL LIST, as the rules which would create it
+    pub fn describe(&self) -> String {
+        let mut rules = vec![
+            format!("user {}", self.name),
+            (if self.enabled { "on" } else { "off" }).to_string(),
+        ];
+        if self.nopass {
+            rules.push("nopass".to_string());
+        }
+        rules.extend(self.passwords.iter().map(|hash| format!("#{}", hash)));
+        if !self.key_patterns.is_empty() {
+            rules.push(self.describe_keys());
+        }
+        rules.push(self.describe_commands());
+        rules.join(" ")
+    }
+
+    /// apply a rule of ACL SETUSER
+    fn apply(&mut self, rule: &[u8]) -> Result<(), RedisCommandError> {
+        let invalid = |reason| {
+            RedisCommandError::InvalidAclRule(String::from_utf8_lossy(rule).to_string(), reason)
+        };
+
+        match rule.to_ascii_lowercase().as_slice() {
+            b"on" => self.enabled = true,
+            b"off" => self.enabled = false,
+            b"nopass" => {
+                self.nopass = true;
+                self.passwords.clear();
+            }
+            b"resetpass" => {
+                self.nopass = false;
+                self.passwords.clear();
+            }
+            b"allkeys" => self.key_patterns = vec![b"*".to_vec()],
+            b"resetkeys" => self.key_patterns.clear(),
+            b"allcommands" => self.allow_category("all"),
+            b"nocommands" => self.deny_category("all"),
+            b"reset" => {
+                *self = User::new(std::mem::take(&mut self.name));
+            }
+            _ => match rule.split_first() {
+                Some((b'>', password)) => {
+                    self.nopass = false;
+                    self.passwords.insert(hash_password(password));
+                }
+                Some((b'<', password)) => {
+                    if !self.passwords.remove(&hash_password(password)) {
+                        return Err(invalid("No such password"));
+                    }
+                }
+                Some((b'#', hash)) => {
+                    let hash = parse_password_hash(hash).ok_or_else(|| {
+                        invalid("The password hash must be 64 lowercase hexadecimal characters")
+                    })?;
+                    self.nopass = false;
+                    self.passwords.insert(hash);
+                }
+                Some((b'!', hash)) => {
+                    let removed = parse_password_hash(hash)
+                        .map(|hash| self.passwords.remove(&hash))
+                        .unwrap_or_default();
+                    if !removed {
+                        return Err(invalid("No such password"));
+                    }
+                }
+                Some((b'~', pattern)) => self.key_patterns.push(pattern.to_vec()),
+                Some((sign @ (b'+' | b'-'), name)) => {
+                    let name = String::from_utf8_lossy(name).to_lowercase();
+                    match name.strip_prefix('@') {
+                        Some(category) if category == "all" || category::is_category(category) => {
+                            match sign {
+                                b'+' => self.allow_category(category),
+                                _ => self.deny_category(category),
+                            }
+                        }
+                        Some(_) => return Err(invalid("Unknown command category")),
+                        None => match category::command_names().find(|known| *known == name) {
+                            Some(command) if *sign == b'+' => {
+                                self.commands.insert(command);
+                                self.command_rules.push(format!("+{}", command));
+                            }
+                            Some(command) => {
+                                self.expand_all_commands();
+                                self.commands.remove(command);
+                                self.command_rules.push(format!("-{}", command));
+                            }
+                            None => return Err(invalid("Unknown command")),
+                        },
+                    }
+                }
+                _ => return Err(invalid("Syntax error")),
+            },
+        }
+
+        Ok(())
+    }
+
+    /// grant the commands of `category`, every one for `all`
+    fn allow_category(&mut self, category: &str) {
+        match category {
+            "all" => {
+                self.all_commands = true;
+                self.commands = category::command_names().collect();
+                self.command_rules = vec!["+@all".to_string()];
+            }
+            _ => {
+                self.commands.extend(category::category_commands(category));
+                self.command_rules.push(format!("+@{}", category));
+            }
+        }
+    }
+
+    /// revoke the commands of `category`, every one for `all`
+    fn deny_category(&mut self, category: &str) {
+        match category {
+            "all" => {
+                self.all_commands = false;
+                self.commands.clear();
+                self.command_rules = vec!["-@all".to_string()];
+            }
+            _ => {
+                self.expand_all_commands();
+                for command in category::category_commands(category) {
+                    self.commands.remove(command);
+                }
+                self.command_rules.push(format!("-@{}", category));
+            }
+        }
+    }
+
+    /// a command being revoked, only the commands with a category are still granted
+    fn expand_all_commands(&mut self) {
+        if self.all_commands {
+            self.all_commands = false;
+            self.commands = category::command_names().collect();
+        }
+    }
+}
+
+/// Users of the ACL, by name
+pub struct Acl {
+    users: Mutex<BTreeMap<String, User>>,
+    // password of the default user set with requirepass, as CONFIG GET replies it
+    requirepass: Mutex<RedisString>,
+}
+
+impl Acl {
+    /// ACL with the default user only, which can do anything and requires `requirepass` if given
+    pub fn new(requirepass: Option<String>) -> Self {
+        let mut default_user = User::new(DEFAULT_USER.to_string());
+        default_user.enabled = true;
+        default_user.nopass = true;
+        default_user.key_patterns = vec![b"*".to_vec()];
+        default_user.allow_category("all");
+
+        let acl = Acl {
+            users: Mutex::new(BTreeMap::from([(DEFAULT_USER.to_string(), default_user)])),
+            requirepass: Mutex::new(vec![]),
+        };
+        acl.set_requirepass(requirepass.map(String::into_bytes).unwrap_or_default());
+        acl
+    }
+
+    pub fn requirepass(&self) -> RedisString {
+        self.requirepass.lock().unwrap().clone()
+    }
+
+    /// make `password` the only one of the default user, any password being accepted when it is
+    /// empty
+    pub fn set_requirepass(&self, password: RedisString) {
+        let mut users = self.users.lock().unwrap();
+        if let Some(default_user) = users.get_mut(DEFAULT_USER) {
+            default_user.passwords.clear();
+            default_user.nopass = password.is_empty();
+            if !password.is_empty() {
+                default_user.passwords.insert(hash_password(&password));
+            }
+        }
+        *self.requirepass.lock().unwrap() = password;
+    }
+
+    /// whether the clients have to authenticate, the default user requiring a password
+    pub fn requires_auth(&self) -> bool {
+        let users = self.users.lock().unwrap();
+        users
+            .get(DEFAULT_USER)
+            .is_none_or(|user| !user.enabled || !user.nopass)
+    }
+
+    /// whether the default user accepts any password
+    pub fn is_default_user_nopass(&self) -> bool {
+        let users = self.users.lock().unwrap();
+        users.get(DEFAULT_USER).is_some_and(|user| user.nopass)
+    }
+
+    /// whether `password` authenticates as the user `name`
+    pub fn authenticate(&self, name: &[u8], password: &[u8]) -> bool {
+        let users = self.users.lock().unwrap();
+        users
+            .get(String::from_utf8_lossy(name).as_ref())
+            .is_some_and(|user| user.accepts(password))
+    }
+
+    pub fn user(&self, name: &[u8]) -> Option<User> {
+        let users = self.users.lock().unwrap();
+        users.get(String::from_utf8_lossy(name).as_ref()).cloned()
+    }
+
+    /// users sorted by name
+    pub fn users(&self) -> Vec<User> {
+        self.users.lock().unwrap().values().cloned().collect()
+    }
+
+    /// create the user `name` or update it with `rules`, left as is if any rule is invalid -
+    /// return whether an existing user has been downgraded, so that its clients are killed
+    pub fn set_user(&self, name: &[u8], rules: &[RedisString]) -> Result<bool, RedisCommandError> {
+        let name = String::from_utf8_lossy(name).to_string();
+        let mut users = self.users.lock().unwrap();
+        let mut user = users
+            .get(&name)
+            .cloned()
+            .unwrap_or_else(|| User::new(name.clone()));
+        for rule in rules {
+            user.apply(rule)?;
+        }
+        let downgraded = users
+            .get(&name)
+            .is_some_and(|existing| existing.is_downgraded_to(&user));
+        users.insert(name, user);
+        Ok(downgraded)
+    }
+
+    /// delete the users `names` unless one of them is the default user, return the ones which
+    /// existed
+    pub fn delete_users(&self, names: &[RedisString]) -> Result<Vec<String>, RedisCommandError> {
+        let names = names
+            .iter()
+            .map(|name| String::from_utf8_lossy(name).to_string())
+            .collect::<Vec<_>>();
+        if names.iter().any(|name| name == DEFAULT_USER) {
+            return Err(RedisCommandError::DefaultUserRemoval);
+        }
+
+        let mut users = self.users.lock().unwrap();
+        Ok(names
+            .into_iter()
+            .filter(|name| users.remove(name).is_some())
+            .collect())
+    }
+
+    /// check that the user `name` can run the command `command_name` on `keys`
+    pub fn check_permissions(
+        &self,
+        name: &str,
+        command_name: &str,
+        keys: &[&RedisString],
+    ) -> Result<(), RedisCommandError> {
+        let users = self.users.lock().unwrap();
+        // the user has been deleted while the client was being killed
+        let user = match users.get(name) {
+            Some(user) => user,
+            None => {
+                return Err(RedisCommandError::NoCommandPermission(
+                    name.to_string(),
+                    command_name.to_string(),
+                ))
+            }
+        };
+
+        if !user.can_run(command_name) {
+            return Err(RedisCommandError::NoCommandPermission(
+                name.to_string(),
+                command_name.to_string(),
+            ));
+        }
+        if !keys.iter().all(|key| user.can_access(key)) {
+            return Err(RedisCommandError::NoKeyPermission);
+        }
+        Ok(())
+    }
+}
+
+/// SHA-256 of `password`, hex encoded
+fn hash_password(password: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(password))
+}
+
+/// password hash given to ACL SETUSER, `None` if it is not a SHA-256 hex encoded
+fn parse_password_hash(hash: &[u8]) -> Option<String> {
+    let valid = hash.len() == 64
+        && hash
+            .iter()
+            .all(|byte| byte.is_ascii_digit() || (b'a'..=b'f').contains(byte));
+    match valid {
+        true => Some(String::from_utf8_lossy(hash).to_string()),
+        false => None,
+    }
+}
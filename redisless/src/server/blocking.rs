@@ -0,0 +1,78 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crossbeam_channel::{bounded, Receiver, Sender};
+
+use crate::storage::models::RedisString;
+
+// a key along with the index of its database
+type DbKey = (usize, RedisString);
+
+/// Registration of a client waiting for an element to be pushed (or a member to be added) to one
+/// of `keys`
+pub struct Waiter {
+    db: usize,
+    keys: Vec<RedisString>,
+    sender: Sender<()>,
+    pub wakeup: Receiver<()>,
+    // `None` to wait forever
+    pub timeout: Option<Duration>,
+}
+
+/// Clients blocked on list or sorted set keys, per database and key
+#[derive(Default)]
+pub struct BlockedClients {
+    waiters: Mutex<HashMap<DbKey, Vec<Sender<()>>>>,
+}
+
+impl BlockedClients {
+    /// register a client waiting for an element to be pushed to one of `keys` of the database `db`
+    pub fn wait(&self, db: usize, keys: &[RedisString], timeout: Option<Duration>) -> Waiter {
+        let (sender, wakeup) = bounded(1);
+
+        let mut waiters = self.waiters.lock().unwrap();
+        for key in keys {
+            waiters
+                .entry((db, key.clone()))
+                .or_default()
+                .push(sender.clone());
+        }
+
+        Waiter {
+            db,
+            keys: keys.to_vec(),
+            sender,
+            wakeup,
+            timeout,
+        }
+    }
+
+    /// wake up all the clients blocked on `key`, they run their command again to pop the element
+    pub fn notify(&self, db: usize, key: &[u8]) {
+        let mut waiters = self.waiters.lock().unwrap();
+        if waiters.is_empty() {
+            return;
+        }
+
+        if let Some(senders) = waiters.remove(&(db, key.to_vec())) {
+            senders.iter().for_each(|sender| {
+                let _ = sender.try_send(());
+            });
+        }
+    }
+
+    /// unregister `waiter` from the keys it has not been woken up by
+    pub fn cancel(&self, waiter: &Waiter) {
+        let mut waiters = self.waiters.lock().unwrap();
+        for key in &waiter.keys {
+            let entry = (waiter.db, key.clone());
+            if let Some(senders) = waiters.get_mut(&entry) {
+                senders.retain(|sender| !sender.same_channel(&waiter.sender));
+                if senders.is_empty() {
+                    waiters.remove(&entry);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,149 @@
+use std::collections::BTreeMap;
+use std::io;
+use std::net::{Shutdown, SocketAddr, TcpStream};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use super::blocking::Waiter;
+use super::pubsub::Subscriptions;
+use super::transaction::Transaction;
+use super::watch::Watch;
+use crate::command::{ClientKillFilter, ClientType};
+use crate::storage::models::StreamId;
+
+/// State of a single client connection
+#[derive(Default)]
+pub struct Client {
+    // set once the client is registered, starting from 1
+    pub id: u64,
+    // database selected with SELECT
+    pub db: usize,
+    // set by a blocking command which could not be served right away
+    pub blocked: Option<Waiter>,
+    // IDs read from by a blocked XREAD, its `$` standing for the last IDs of its first run
+    pub stream_read_ids: Option<Vec<StreamId>>,
+    // channels, patterns and shard channels subscribed to with SUBSCRIBE, PSUBSCRIBE and
+    // SSUBSCRIBE
+    pub subscriptions: Subscriptions,
+    // commands queued since MULTI
+    pub transaction: Option<Transaction>,
+    // keys watched with WATCH until the next EXEC or DISCARD
+    pub watch: Watch,
+    // set by AUTH, or when no password is required as the connection is opened or reset
+    pub authenticated: bool,
+    // ACL user the client is authenticated as
+    pub user: String,
+    // set by CLIENT KILL, or once the ACL user has been deleted or downgraded, the connection
+    // being closed once the current command has been replied
+    pub killed: Arc<AtomicBool>,
+}
+
+/// Connection of a registered client, along with what CLIENT KILL filters it by
+struct Connection {
+    addr: String,
+    laddr: String,
+    // the socket of the client, shut down to wake it up when it is blocked
+    stream: TcpStream,
+    killed: Arc<AtomicBool>,
+    // as of the last command of the client
+    user: String,
+    pubsub: bool,
+}
+
+impl Connection {
+    fn matches(&self, id: u64, filter: &ClientKillFilter) -> bool {
+        let client_type = match self.pubsub {
+            true => ClientType::PubSub,
+            false => ClientType::Normal,
+        };
+        filter.id.is_none_or(|filter_id| filter_id == id)
+            && filter.addr.as_ref().is_none_or(|addr| *addr == self.addr)
+            && filter
+                .laddr
+                .as_ref()
+                .is_none_or(|laddr| *laddr == self.laddr)
+            && filter
+                .client_type
+                .is_none_or(|filter_type| filter_type == client_type)
+            && filter
+                .user
+                .as_ref()
+                .is_none_or(|user| *user == self.user.as_bytes())
+    }
+
+    fn kill(&self) {
+        self.killed.store(true, Ordering::Relaxed);
+        let _ = self.stream.shutdown(Shutdown::Read);
+    }
+}
+
+/// Clients connected to the server, by ID
+#[derive(Default)]
+pub struct Clients {
+    last_id: AtomicU64,
+    connections: Mutex<BTreeMap<u64, Connection>>,
+}
+
+impl Clients {
+    /// give an ID to the client connected through `stream` and register it
+    pub fn register(&self, client: &mut Client, stream: &TcpStream) -> io::Result<()> {
+        let connection = Connection {
+            addr: format_addr(stream.peer_addr()?),
+            laddr: format_addr(stream.local_addr()?),
+            stream: stream.try_clone()?,
+            killed: client.killed.clone(),
+            user: client.user.clone(),
+            pubsub: false,
+        };
+
+        client.id = self.last_id.fetch_add(1, Ordering::Relaxed) + 1;
+        self.connections
+            .lock()
+            .unwrap()
+            .insert(client.id, connection);
+        Ok(())
+    }
+
+    pub fn unregister(&self, client: &Client) {
+        self.connections.lock().unwrap().remove(&client.id);
+    }
+
+    /// keep the user and the type of `client` up to date once it has run a command
+    pub fn update(&self, client: &Client) {
+        if let Some(connection) = self.connections.lock().unwrap().get_mut(&client.id) {
+            if connection.user != client.user {
+                connection.user = client.user.clone();
+            }
+            connection.pubsub = client.subscriptions.is_subscribed();
+        }
+    }
+
+    /// kill the clients matching `filter` - but the client `caller` if it is skipped - return how
+    /// many there were, the ones killed already and not gone yet not counting
+    pub fn kill(&self, filter: &ClientKillFilter, caller: u64) -> usize {
+        let connections = self.connections.lock().unwrap();
+        connections
+            .iter()
+            .filter(|(_, connection)| !connection.killed.load(Ordering::Relaxed))
+            .filter(|(id, connection)| {
+                !(filter.skip_me && **id == caller) && connection.matches(**id, filter)
+            })
+            .inspect(|(_, connection)| connection.kill())
+            .count()
+    }
+
+    /// kill the clients authenticated as `user`, which has been deleted or downgraded
+    pub fn kill_user(&self, user: &str) -> usize {
+        let filter = ClientKillFilter {
+            user: Some(user.as_bytes().to_vec()),
+            skip_me: false,
+            ..ClientKillFilter::default()
+        };
+        self.kill(&filter, 0)
+    }
+}
+
+/// `ip:port`, as Redis lists the addresses of the clients
+fn format_addr(addr: SocketAddr) -> String {
+    format!("{}:{}", addr.ip(), addr.port())
+}
@@ -0,0 +1,116 @@
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use crossbeam_channel::{bounded, Receiver, RecvTimeoutError, Sender, TrySendError};
+
+use crate::error::Error;
+
+pub const DEFAULT_COMMAND_QUEUE_DEPTH: usize = 1024;
+pub const DEFAULT_COMMAND_WORKERS: usize = 4;
+
+// how often idle workers check if the server has been stopped
+const WORKER_STOP_CHECK_MILLIS: u64 = 100;
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// What to do with a command received while the command queue is full
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum QueueFullPolicy {
+    /// stop reading from the connection until there is room in the queue
+    Block,
+    /// reply `-BUSY` right away
+    Busy,
+}
+
+#[derive(Debug, Clone)]
+pub struct CommandQueueOptions {
+    // maximum number of commands waiting to be executed
+    pub depth: usize,
+    // number of threads executing the commands
+    pub workers: usize,
+    pub when_full: QueueFullPolicy,
+}
+
+impl Default for CommandQueueOptions {
+    fn default() -> Self {
+        CommandQueueOptions {
+            depth: DEFAULT_COMMAND_QUEUE_DEPTH,
+            workers: DEFAULT_COMMAND_WORKERS,
+            when_full: QueueFullPolicy::Block,
+        }
+    }
+}
+
+/// Bounded queue of commands, shared by all the connections and executed by a pool of workers
+pub struct CommandQueue {
+    options: CommandQueueOptions,
+    sender: Sender<Job>,
+    receiver: Receiver<Job>,
+    high_watermark: AtomicUsize,
+}
+
+impl CommandQueue {
+    pub fn new(options: CommandQueueOptions) -> Self {
+        let (sender, receiver) = bounded(options.depth.max(1));
+
+        CommandQueue {
+            options,
+            sender,
+            receiver,
+            high_watermark: AtomicUsize::new(0),
+        }
+    }
+
+    /// spawn the workers, they exit once `stopped` is set and the queue is empty
+    pub fn start_workers(&self, stopped: &Arc<AtomicBool>) -> Result<(), Error> {
+        for _ in 0..self.options.workers.max(1) {
+            let receiver = self.receiver.clone();
+            let stopped = stopped.clone();
+
+            let _ = thread::Builder::new()
+                .name("command worker".to_string())
+                .spawn(move || loop {
+                    match receiver.recv_timeout(Duration::from_millis(WORKER_STOP_CHECK_MILLIS)) {
+                        // a panicking job must not take its worker down with it
+                        Ok(job) => {
+                            let _ = panic::catch_unwind(AssertUnwindSafe(job));
+                        }
+                        Err(RecvTimeoutError::Timeout) if !stopped.load(Ordering::Relaxed) => {}
+                        Err(_) => return,
+                    }
+                })?;
+        }
+
+        Ok(())
+    }
+
+    /// queue `job`, return `false` if the queue is full and the policy is to reply `-BUSY`
+    pub fn submit<F: FnOnce() + Send + 'static>(&self, job: F) -> bool {
+        let job: Job = Box::new(job);
+
+        let queued = match self.options.when_full {
+            QueueFullPolicy::Block => self.sender.send(job).is_ok(),
+            QueueFullPolicy::Busy => match self.sender.try_send(job) {
+                Ok(_) => true,
+                Err(TrySendError::Full(_)) | Err(TrySendError::Disconnected(_)) => false,
+            },
+        };
+
+        self.high_watermark
+            .fetch_max(self.receiver.len(), Ordering::Relaxed);
+        queued
+    }
+
+    /// number of commands waiting to be executed
+    pub fn depth(&self) -> usize {
+        self.receiver.len()
+    }
+
+    /// highest depth reached since the server has been created
+    pub fn high_watermark(&self) -> usize {
+        self.high_watermark.load(Ordering::Relaxed)
+    }
+}
@@ -0,0 +1,64 @@
+use std::sync::{Mutex, RwLock};
+
+use super::acl::Acl;
+use super::blocking::BlockedClients;
+use super::client::Clients;
+use super::command_queue::{CommandQueue, CommandQueueOptions};
+use super::functions::Libraries;
+use super::latency::LatencyHistograms;
+use super::miss_injection::MissInjections;
+use super::pubsub::PubSub;
+use super::scripting::{RunningScript, Scripts};
+use super::stats::ServerStats;
+use super::watch::WatchedKeys;
+
+/// State shared by the server and all its connections
+pub struct ServerContext {
+    pub latency_histograms: Mutex<LatencyHistograms>,
+    pub miss_injections: Mutex<MissInjections>,
+    pub command_queue: CommandQueue,
+    pub blocked_clients: BlockedClients,
+    pub clients: Clients,
+    pub pubsub: PubSub,
+    // held for reading by each command and for writing by EXEC and the scripts, so that the
+    // commands of a transaction or of a script are not interleaved with other ones
+    pub exec_lock: RwLock<()>,
+    pub watched_keys: WatchedKeys,
+    pub scripts: Scripts,
+    pub running_script: RunningScript,
+    pub libraries: Libraries,
+    pub acl: Acl,
+    // whether a nested MULTI marks a savepoint rather than failing
+    pub transaction_savepoints: bool,
+}
+
+impl ServerContext {
+    pub fn new(
+        command_queue_options: CommandQueueOptions,
+        requirepass: Option<String>,
+        transaction_savepoints: bool,
+    ) -> Self {
+        ServerContext {
+            latency_histograms: Mutex::new(LatencyHistograms::new()),
+            miss_injections: Mutex::new(MissInjections::default()),
+            command_queue: CommandQueue::new(command_queue_options),
+            blocked_clients: BlockedClients::default(),
+            clients: Clients::default(),
+            pubsub: PubSub::default(),
+            exec_lock: RwLock::new(()),
+            watched_keys: WatchedKeys::default(),
+            scripts: Scripts::default(),
+            running_script: RunningScript::default(),
+            libraries: Libraries::default(),
+            acl: Acl::new(requirepass),
+            transaction_savepoints,
+        }
+    }
+
+    pub fn stats(&self) -> ServerStats {
+        ServerStats {
+            command_queue_depth: self.command_queue.depth(),
+            command_queue_high_watermark: self.command_queue.high_watermark(),
+        }
+    }
+}
@@ -0,0 +1,156 @@
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+use crate::command::command_error::RedisCommandError;
+use crate::glob::glob_match;
+use crate::storage::dump;
+use crate::storage::models::RedisString;
+
+/// Flags a function can be registered with
+#[cfg(feature = "lua")]
+pub const FUNCTION_FLAGS: [&str; 5] = [
+    "no-writes",
+    "allow-oom",
+    "allow-stale",
+    "no-cluster",
+    "allow-cross-slot-keys",
+];
+
+/// Function registered by a library, to be called with FCALL
+#[derive(Clone)]
+pub struct Function {
+    pub name: String,
+    pub description: Option<String>,
+    pub flags: Vec<String>,
+}
+
+impl Function {
+    /// whether the function can only read, so that FCALL_RO can call it
+    pub fn is_read_only(&self) -> bool {
+        self.flags.iter().any(|flag| flag == "no-writes")
+    }
+}
+
+/// Library loaded with FUNCTION LOAD, along with the functions it registered
+#[derive(Clone)]
+pub struct Library {
+    pub name: String,
+    pub code: RedisString,
+    pub functions: Vec<Function>,
+}
+
+/// Libraries of functions, by name
+#[derive(Default)]
+pub struct Libraries {
+    libraries: Mutex<BTreeMap<String, Library>>,
+}
+
+impl Libraries {
+    /// add `library`, replacing the one of the same name if `replace` - none of its functions
+    /// being registered by another library
+    pub fn load(&self, library: Library, replace: bool) -> Result<String, RedisCommandError> {
+        let mut libraries = self.libraries.lock().unwrap();
+        if !replace && libraries.contains_key(&library.name) {
+            return Err(RedisCommandError::LibraryExists(library.name));
+        }
+
+        for function in &library.functions {
+            let registered = libraries
+                .values()
+                .filter(|other| other.name != library.name)
+                .flat_map(|other| &other.functions)
+                .any(|other| other.name == function.name);
+            if registered {
+                return Err(RedisCommandError::FunctionExists(function.name.clone()));
+            }
+        }
+
+        let name = library.name.clone();
+        libraries.insert(name.clone(), library);
+        Ok(name)
+    }
+
+    /// delete the library `name`, false if there is none
+    pub fn delete(&self, name: &[u8]) -> bool {
+        let name = String::from_utf8_lossy(name);
+        self.libraries
+            .lock()
+            .unwrap()
+            .remove(name.as_ref())
+            .is_some()
+    }
+
+    /// function `name`, along with the code of its library
+    pub fn function(&self, name: &[u8]) -> Option<(RedisString, Function)> {
+        let libraries = self.libraries.lock().unwrap();
+        libraries.values().find_map(|library| {
+            library
+                .functions
+                .iter()
+                .find(|function| function.name.as_bytes() == name)
+                .map(|function| (library.code.clone(), function.clone()))
+        })
+    }
+
+    /// libraries sorted by name, the ones whose name matches `pattern` if given
+    pub fn list(&self, pattern: Option<&[u8]>) -> Vec<Library> {
+        let libraries = self.libraries.lock().unwrap();
+        libraries
+            .values()
+            .filter(|library| {
+                pattern.is_none_or(|pattern| glob_match(pattern, library.name.as_bytes()))
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// FUNCTION DUMP payload of all the libraries
+    pub fn dump(&self) -> Vec<u8> {
+        let libraries = self.libraries.lock().unwrap();
+        dump::serialize_functions(libraries.values().map(|library| library.code.as_slice()))
+    }
+}
+
+/// parse the first line of the code of a library, `#!<engine> name=<name>`, return the name
+/// of the library along with its body - starting with the end of the first line so that it
+/// keeps its line numbers
+pub fn parse_metadata(code: &[u8]) -> Result<(String, &[u8]), RedisCommandError> {
+    let metadata = match code.strip_prefix(b"#!") {
+        Some(metadata) => metadata,
+        None => return Err(RedisCommandError::MissingLibraryMetadata),
+    };
+    let end = metadata
+        .iter()
+        .position(|byte| *byte == b'\n')
+        .unwrap_or(metadata.len());
+    let (metadata, body) = metadata.split_at(end);
+
+    let metadata = String::from_utf8_lossy(metadata);
+    let mut fields = metadata.split_ascii_whitespace();
+    let engine = fields.next().unwrap_or_default();
+    if !engine.eq_ignore_ascii_case("lua") {
+        return Err(RedisCommandError::EngineNotFound(engine.to_string()));
+    }
+
+    let mut name = None;
+    for field in fields {
+        match field.strip_prefix("name=") {
+            Some(value) => name = Some(value.to_string()),
+            None => return Err(RedisCommandError::InvalidMetadata(field.to_string())),
+        }
+    }
+
+    match name {
+        Some(name) if is_valid_name(&name) => Ok((name, body)),
+        Some(_) => Err(RedisCommandError::InvalidName("Library")),
+        None => Err(RedisCommandError::LibraryNameMissing),
+    }
+}
+
+/// whether `name` can name a library or a function
+pub fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .bytes()
+            .all(|byte| byte.is_ascii_alphanumeric() || byte == b'_')
+}
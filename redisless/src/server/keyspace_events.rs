@@ -0,0 +1,71 @@
+//! Classes of the keyspace events published to subscribers, as set by `notify-keyspace-events`
+
+pub const KEYSPACE: u32 = 1 << 0;
+pub const KEYEVENT: u32 = 1 << 1;
+pub const GENERIC: u32 = 1 << 2;
+pub const STRING: u32 = 1 << 3;
+pub const LIST: u32 = 1 << 4;
+pub const SET: u32 = 1 << 5;
+pub const HASH: u32 = 1 << 6;
+pub const ZSET: u32 = 1 << 7;
+pub const EXPIRED: u32 = 1 << 8;
+pub const EVICTED: u32 = 1 << 9;
+pub const STREAM: u32 = 1 << 10;
+pub const KEY_MISS: u32 = 1 << 11;
+pub const MODULE: u32 = 1 << 12;
+pub const NEW: u32 = 1 << 13;
+// the classes enabled by `A`
+const ALL: u32 = GENERIC | STRING | LIST | SET | HASH | ZSET | EXPIRED | EVICTED | STREAM | MODULE;
+
+// characters of the classes, in the order they are listed by CONFIG GET
+const CLASSES: [(u8, u32); 10] = [
+    (b'g', GENERIC),
+    (b'$', STRING),
+    (b'l', LIST),
+    (b's', SET),
+    (b'h', HASH),
+    (b'z', ZSET),
+    (b'x', EXPIRED),
+    (b'e', EVICTED),
+    (b't', STREAM),
+    (b'd', MODULE),
+];
+
+/// parse the characters of `notify-keyspace-events`, `None` if one of them is not a class
+pub fn parse(classes: &[u8]) -> Option<u32> {
+    classes.iter().try_fold(0, |flags, class| {
+        let flag = match class {
+            b'A' => ALL,
+            b'K' => KEYSPACE,
+            b'E' => KEYEVENT,
+            b'm' => KEY_MISS,
+            b'n' => NEW,
+            class => CLASSES.iter().find(|(c, _)| c == class)?.1,
+        };
+        Some(flags | flag)
+    })
+}
+
+/// characters of the classes of `flags`, `A` standing for all the classes of keys and events
+pub fn format(flags: u32) -> String {
+    let mut classes = String::new();
+    match flags & ALL == ALL {
+        true => classes.push('A'),
+        false => CLASSES
+            .iter()
+            .filter(|(_, flag)| flags & flag != 0)
+            .for_each(|(class, _)| classes.push(*class as char)),
+    }
+
+    for (class, flag) in [
+        ('K', KEYSPACE),
+        ('E', KEYEVENT),
+        ('m', KEY_MISS),
+        ('n', NEW),
+    ] {
+        if flags & flag != 0 {
+            classes.push(class);
+        }
+    }
+    classes
+}
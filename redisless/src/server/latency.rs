@@ -0,0 +1,55 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+const BUCKETS: usize = 64;
+
+/// Latency histograms indexed by lowercase command name
+pub type LatencyHistograms = HashMap<String, LatencyHistogram>;
+
+/// Latency histogram of a command where the bucket `i` counts the calls
+/// which took more than `2^(i-1)` and at most `2^i` microseconds
+pub struct LatencyHistogram {
+    calls: u64,
+    buckets: [u64; BUCKETS],
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        Self {
+            calls: 0,
+            buckets: [0; BUCKETS],
+        }
+    }
+}
+
+impl LatencyHistogram {
+    pub fn record(&mut self, elapsed: Duration) {
+        let usec = (elapsed.as_micros() as u64).max(1);
+        let bucket = (u64::BITS - (usec - 1).leading_zeros()) as usize;
+
+        self.calls += 1;
+        self.buckets[bucket.min(BUCKETS - 1)] += 1;
+    }
+
+    pub fn calls(&self) -> u64 {
+        self.calls
+    }
+
+    /// upper bound in microseconds and cumulative number of calls of each non empty bucket
+    pub fn cumulative_buckets(&self) -> Vec<(u64, u64)> {
+        let mut cumulative_count = 0;
+
+        self.buckets
+            .iter()
+            .enumerate()
+            .filter_map(|(bucket, count)| {
+                if *count == 0 {
+                    return None;
+                }
+
+                cumulative_count += count;
+                Some((1_u64 << bucket, cumulative_count))
+            })
+            .collect()
+    }
+}
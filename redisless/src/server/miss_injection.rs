@@ -0,0 +1,54 @@
+use crate::glob::glob_match;
+use crate::storage::models::RedisString;
+
+struct MissInjection {
+    pattern: RedisString,
+    remaining: u64,
+}
+
+/// Reads forced to report a miss even though the key exists (the value is left untouched)
+#[derive(Default)]
+pub struct MissInjections {
+    injections: Vec<MissInjection>,
+}
+
+impl MissInjections {
+    /// force the next `count` reads of the keys matching the glob-style `pattern` to miss
+    pub fn add(&mut self, pattern: &[u8], count: u64) {
+        if count == 0 {
+            return;
+        }
+
+        self.injections.push(MissInjection {
+            pattern: pattern.to_vec(),
+            remaining: count,
+        });
+    }
+
+    /// consume an injected miss for `key` - return `true` if the read must report a miss
+    pub fn consume(&mut self, key: &[u8]) -> bool {
+        if self.injections.is_empty() {
+            return false;
+        }
+
+        let position = self
+            .injections
+            .iter()
+            .position(|injection| glob_match(&injection.pattern, key));
+
+        match position {
+            Some(position) => {
+                self.injections[position].remaining -= 1;
+                if self.injections[position].remaining == 0 {
+                    self.injections.remove(position);
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.injections.clear();
+    }
+}
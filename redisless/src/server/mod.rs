@@ -1,23 +1,55 @@
 use std::io::ErrorKind;
-use std::net::{IpAddr, Ipv4Addr, SocketAddr, TcpListener, TcpStream};
-use std::sync::{Arc, Mutex};
+use std::net::{IpAddr, Ipv4Addr, Shutdown, SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
 use std::thread;
-use std::time::{Duration, SystemTime};
+use std::time::{Duration, Instant};
 
 use crossbeam_channel::{Receiver, Sender};
 use mpb::MPB;
-use rayon::ThreadPool;
 use uuid::Uuid;
 
+use client::Client;
+use context::ServerContext;
 use util::*;
 
 use crate::cluster::peer::{Peer, PeersDiscovery, DEFAULT_NODE_LISTENING_PORT};
+use crate::error::Error;
+use crate::storage::models::{DefragReport, KeyInfo};
 use crate::storage::Storage;
 
 #[cfg(test)]
 mod tests;
 
+mod acl;
+mod blocking;
+mod client;
+mod command_queue;
+mod context;
+mod functions;
+mod keyspace_events;
+mod latency;
+mod miss_injection;
+mod pubsub;
+mod scripting;
+mod stats;
+mod transaction;
 mod util;
+mod watch;
+
+pub use command_queue::{CommandQueueOptions, QueueFullPolicy};
+pub use stats::ServerStats;
+
+// number of keys whose values are shrunk at once, the storage being unlocked in between
+const DEFRAG_BATCH_KEYS: usize = 100;
+// how often the defragmentation thread checks if the server has been stopped
+const DEFRAG_STOP_CHECK_MILLIS: u64 = 100;
+// how often the expired keys are looked for while their removal is notified
+const ACTIVE_EXPIRE_TICK_MILLIS: u64 = 100;
+// connections are closed after this long without receiving anything
+const CONNECTION_IDLE_TIMEOUT_SECONDS: u64 = 300;
+// how often connections waiting for a request check if the server has been stopped
+const CONNECTION_STOP_CHECK_MILLIS: u64 = 100;
 
 type CloseConnection = bool;
 type ReceivedDataLength = usize;
@@ -25,6 +57,10 @@ type ReceivedDataLength = usize;
 pub struct Server {
     server_state_bus: MPB<ServerState>,
     cluster_options: ServerClusterOptions,
+    storage: Arc<Mutex<dyn Storage + Send>>,
+    context: Arc<ServerContext>,
+    defrag_interval: Option<Duration>,
+    last_defrag_report: Arc<Mutex<Option<DefragReport>>>,
 }
 
 #[derive(Debug, Eq, PartialEq, Clone)]
@@ -33,11 +69,11 @@ pub enum ServerState {
     Started,
     Stop,
     Stopped,
-    Timeout,
-    Error(String),
+    Error(Error),
 }
 
 #[derive(Debug)]
+#[allow(dead_code)]
 pub struct ServerClusterOptions {
     group_id: String,
     peers_discovery: PeersDiscovery,
@@ -71,9 +107,25 @@ impl Default for ServerClusterOptions {
     }
 }
 
+#[derive(Debug, Default)]
+pub struct ServerOptions {
+    pub cluster: ServerClusterOptions,
+    pub command_queue: CommandQueueOptions,
+    // password the clients have to authenticate with before running any command
+    pub requirepass: Option<String>,
+    // time between two background defragmentation passes while the server is started, none being
+    // run unless it is set
+    pub defrag_interval: Option<Duration>,
+    // a MULTI within a transaction marks a savepoint: when a command fails once EXEC is called,
+    // the keys it and the commands since the latest savepoint name are put back as they were -
+    // along with the databases of the ones writing other keys, such as FLUSHALL or the scripts -
+    // and a next failure goes back to the previous savepoint
+    pub transaction_savepoints: bool,
+}
+
 impl Server {
     pub fn new<T: Storage + Send + 'static>(storage: T, port: u16) -> Self {
-        Server::new_with_cluster_options(storage, ServerClusterOptions::default(), port)
+        Server::new_with_options(storage, ServerOptions::default(), port)
     }
 
     pub fn new_with_cluster_options<T: Storage + Send + 'static>(
@@ -81,9 +133,32 @@ impl Server {
         cluster_options: ServerClusterOptions,
         port: u16,
     ) -> Self {
+        let options = ServerOptions {
+            cluster: cluster_options,
+            ..ServerOptions::default()
+        };
+
+        Server::new_with_options(storage, options, port)
+    }
+
+    pub fn new_with_options<T: Storage + Send + 'static>(
+        storage: T,
+        options: ServerOptions,
+        port: u16,
+    ) -> Self {
+        let storage = Arc::new(Mutex::new(storage));
+
         let s = Server {
             server_state_bus: MPB::new(),
-            cluster_options,
+            cluster_options: options.cluster,
+            storage: storage.clone(),
+            context: Arc::new(ServerContext::new(
+                options.command_queue,
+                options.requirepass,
+                options.transaction_savepoints,
+            )),
+            defrag_interval: options.defrag_interval,
+            last_defrag_report: Arc::new(Mutex::new(None)),
         };
 
         s._init_configuration(format!("0.0.0.0:{}", port), storage);
@@ -93,7 +168,7 @@ impl Server {
     fn _init_configuration<A: Into<String>, T: Storage + Send + 'static>(
         &self,
         addr: A,
-        storage: T,
+        storage: Arc<Mutex<T>>,
     ) {
         let addr = addr.into();
         let state_send = self.server_state_bus.sender();
@@ -102,44 +177,66 @@ impl Server {
         let id = Uuid::new_v4();
         let peer = Peer::new(
             id.to_string(),
-            PeersDiscovery::Automatic(self.cluster_options.listening_socket_addr.port()),
+            self.cluster_options.peers_discovery.clone(),
             self.cluster_options.listening_socket_addr,
         );
 
         let mut cluster_node = peer.into_cluster_node();
+        let defrag_interval = self.defrag_interval;
+        let last_defrag_report = self.last_defrag_report.clone();
+        let context = self.context.clone();
 
         let _ = thread::spawn(move || {
             let addr = addr;
-            let storage = Arc::new(Mutex::new(storage));
+
+            // remove the expired keys nobody accesses, so that their expiry is notified on time
+            start_active_expire(&storage, &context);
 
             loop {
                 if let Ok(server_state) = state_recv.recv() {
                     if server_state == ServerState::Start {
-                        // start local RESP server
-                        start_server(&addr, &state_send, &state_recv, &storage);
-
-                        // start current node listener
-                        cluster_node.start_listener();
+                        // start current node listener, making it discoverable by its peers
+                        if let Err(err) = cluster_node.start_listener() {
+                            let _ = state_send.send(ServerState::Error(err.into()));
+                            continue;
+                        }
+
+                        // start local RESP server, until it is stopped
+                        start_server(
+                            &addr,
+                            &state_send,
+                            &state_recv,
+                            &storage,
+                            &context,
+                            defrag_interval,
+                            &last_defrag_report,
+                        );
+
+                        // the server is already reported as stopped, a node which can not be
+                        // unregistered is only tried by its peers in vain
+                        let _ = cluster_node.stop_listener();
                     }
                 }
             }
         });
     }
 
-    fn change_state(&self, change_to: ServerState) -> Option<ServerState> {
+    fn change_state(&self, change_to: ServerState) -> Result<ServerState, Error> {
         let send_state_ch = self.server_state_bus.sender();
 
         let post_change_to_state = match change_to {
             ServerState::Start => ServerState::Started,
             ServerState::Stop => ServerState::Stopped,
-            ServerState::Started
-            | ServerState::Stopped
-            | ServerState::Timeout
-            | ServerState::Error(_) => return None,
+            ServerState::Started | ServerState::Stopped | ServerState::Error(_) => {
+                return Err(Error::Config(format!(
+                    "can not change the server state to {:?}",
+                    change_to
+                )))
+            }
         };
 
         let _ = thread::spawn(move || {
-            let _ = thread::sleep(Duration::from_millis(100));
+            thread::sleep(Duration::from_millis(100));
             let _ = send_state_ch.send(change_to);
         });
 
@@ -147,23 +244,151 @@ impl Server {
         let receiver = self.server_state_bus.receiver(); // TODO cache receiver to reuse it?
 
         while let Ok(server_state) = receiver.recv_timeout(Duration::from_secs(5)) {
-            if server_state == post_change_to_state {
-                return Some(server_state);
+            match server_state {
+                server_state if server_state == post_change_to_state => return Ok(server_state),
+                ServerState::Error(err) => return Err(err),
+                _ => {}
             }
         }
 
-        Some(ServerState::Timeout)
+        Err(Error::Io(
+            ErrorKind::TimedOut,
+            format!(
+                "the server did not reach the {:?} state",
+                post_change_to_state
+            ),
+        ))
     }
 
     /// start server
-    pub fn start(&self) -> Option<ServerState> {
+    pub fn start(&self) -> Result<ServerState, Error> {
         self.change_state(ServerState::Start)
     }
 
     /// stop server
-    pub fn stop(&self) -> Option<ServerState> {
+    pub fn stop(&self) -> Result<ServerState, Error> {
         self.change_state(ServerState::Stop)
     }
+
+    /// remove all the keys of all the databases
+    pub fn flush(&self) -> Result<(), Error> {
+        self.storage.lock()?.flush_all(false);
+        Ok(())
+    }
+
+    /// force the next `count` GETs of the keys matching the glob-style `pattern` to report a miss,
+    /// even though the values exist - they are not deleted
+    pub fn inject_misses(&self, pattern: &[u8], count: u64) -> Result<(), Error> {
+        self.context.miss_injections.lock()?.add(pattern, count);
+        Ok(())
+    }
+
+    /// cancel all the pending injected misses
+    pub fn clear_injected_misses(&self) -> Result<(), Error> {
+        self.context.miss_injections.lock()?.clear();
+        Ok(())
+    }
+
+    /// type, ttl, number of elements and approximate size of `key` in the first database - `None`
+    /// if it does not exist
+    pub fn key_info(&self, key: &[u8]) -> Result<Option<KeyInfo>, Error> {
+        let mut storage = self.storage.lock()?;
+        storage.select(0);
+        Ok(storage.key_info(key))
+    }
+
+    /// command queue depth and high watermark
+    pub fn stats(&self) -> ServerStats {
+        self.context.stats()
+    }
+
+    /// memory allocated before and after the last background defragmentation pass
+    pub fn last_defrag_report(&self) -> Option<DefragReport> {
+        match self.last_defrag_report.lock() {
+            Ok(report) => *report,
+            Err(_) => None,
+        }
+    }
+}
+
+/// shrink over-allocated data structures every `interval`, until the server is stopped
+fn start_defrag<T: Storage + Send + 'static>(
+    storage: &Arc<Mutex<T>>,
+    stopped: &Arc<AtomicBool>,
+    interval: Duration,
+    last_defrag_report: &Arc<Mutex<Option<DefragReport>>>,
+) {
+    let storage = storage.clone();
+    let stopped = stopped.clone();
+    let last_defrag_report = last_defrag_report.clone();
+
+    let _ = thread::spawn(move || {
+        let mut last_pass = Instant::now();
+
+        loop {
+            thread::sleep(interval.min(Duration::from_millis(DEFRAG_STOP_CHECK_MILLIS)));
+
+            if stopped.load(Ordering::Relaxed) {
+                break;
+            }
+
+            if last_pass.elapsed() < interval {
+                continue;
+            }
+
+            let report = match defrag_pass(&storage, &stopped) {
+                Some(report) => report,
+                None => break,
+            };
+            if let Ok(mut last_report) = last_defrag_report.lock() {
+                *last_report = Some(report);
+            }
+            last_pass = Instant::now();
+        }
+    });
+}
+
+/// shrink the data structures of every database a few keys at a time, so that the commands are not
+/// held up for a whole pass - `None` if the server is stopped in the meantime
+fn defrag_pass<T: Storage>(storage: &Arc<Mutex<T>>, stopped: &AtomicBool) -> Option<DefragReport> {
+    let mut report = DefragReport::default();
+    let databases = lock_then_release(storage).databases();
+
+    for db in 0..databases {
+        let mut cursor = 0;
+        loop {
+            let (next_cursor, batch_report) =
+                lock_then_release(storage).defrag(db, cursor, DEFRAG_BATCH_KEYS);
+            report += batch_report;
+
+            if stopped.load(Ordering::Relaxed) {
+                return None;
+            }
+            if next_cursor == 0 {
+                break;
+            }
+            cursor = next_cursor;
+        }
+    }
+
+    Some(report)
+}
+
+fn start_active_expire<T: Storage + Send + 'static>(
+    storage: &Arc<Mutex<T>>,
+    context: &Arc<ServerContext>,
+) {
+    let storage = storage.clone();
+    let context = context.clone();
+
+    let _ = thread::spawn(move || loop {
+        thread::sleep(Duration::from_millis(ACTIVE_EXPIRE_TICK_MILLIS));
+
+        if context.pubsub.keyspace_events() & keyspace_events::EXPIRED != 0 {
+            lock_then_release(&storage).remove_expired_keys();
+            notify_expired_keys(&storage, &context);
+        }
+    });
 }
 
 fn start_server<T: Storage + Send + 'static>(
@@ -171,27 +396,38 @@ fn start_server<T: Storage + Send + 'static>(
     state_send: &Sender<ServerState>,
     state_recv: &Receiver<ServerState>,
     storage: &Arc<Mutex<T>>,
+    context: &Arc<ServerContext>,
+    defrag_interval: Option<Duration>,
+    last_defrag_report: &Arc<Mutex<Option<DefragReport>>>,
 ) {
+    // set once the server is stopped, so that the connections, the workers and the
+    // defragmentation exit
+    let stopped = Arc::new(AtomicBool::new(false));
+
+    if let Err(err) = context.command_queue.start_workers(&stopped) {
+        stopped.store(true, Ordering::Relaxed);
+        let _ = state_send.send(ServerState::Error(err));
+        return;
+    }
+
     let listener = match TcpListener::bind(addr) {
         Ok(listener) => {
             // notify that the server has been started
             let _ = state_send.send(ServerState::Started);
             let _ = listener.set_nonblocking(true);
+            // shrink over-allocated data structures in the background, if asked to
+            if let Some(defrag_interval) = defrag_interval {
+                start_defrag(storage, &stopped, defrag_interval, last_defrag_report);
+            }
             listener
         }
-        Err(_) => {
-            thread::sleep(Duration::from_millis(10));
-            return;
-        }
-    };
-
-    let thread_pool = match rayon::ThreadPoolBuilder::new()
-        .thread_name(|_| "request handler".to_string())
-        .build()
-    {
-        Ok(pool) => pool,
         Err(err) => {
-            panic!("{:?}", err);
+            stopped.store(true, Ordering::Relaxed);
+            let _ = state_send.send(ServerState::Error(Error::Bind(
+                addr.to_string(),
+                err.kind(),
+            )));
+            return;
         }
     };
 
@@ -199,7 +435,7 @@ fn start_server<T: Storage + Send + 'static>(
     for stream in listener.incoming() {
         match stream {
             Ok(tcp_stream) => {
-                handle_tcp_stream(tcp_stream, &thread_pool, &state_send, &state_recv, &storage);
+                handle_tcp_stream(tcp_stream, &stopped, storage, context);
             }
             Err(err) if err.kind() == ErrorKind::WouldBlock => {
                 thread::sleep(Duration::from_millis(10));
@@ -209,53 +445,94 @@ fn start_server<T: Storage + Send + 'static>(
             }
         }
 
-        if stop_sig_received(&state_recv, &state_send) {
+        if stop_sig_received(state_recv, state_send) {
             // let's gracefully shutdown the server
             break;
         }
     }
+
+    stopped.store(true, Ordering::Relaxed);
 }
 
+/// read the requests of the connection on its own thread, the commands are executed by the workers
 fn handle_tcp_stream<T: Storage + Send + 'static>(
     tcp_stream: TcpStream,
-    thread_pool: &ThreadPool,
-    state_send: &Sender<ServerState>,
-    state_recv: &Receiver<ServerState>,
+    stopped: &Arc<AtomicBool>,
     storage: &Arc<Mutex<T>>,
+    context: &Arc<ServerContext>,
 ) {
     let storage = storage.clone();
-    let state_recv = state_recv.clone();
-    let state_send = state_send.clone();
-
-    let _ = thread_pool.spawn(move || {
-        let mut last_update = SystemTime::now();
-
-        loop {
-            let (close_connection, received_data_length) = handle_request(&storage, &tcp_stream);
-
-            if received_data_length > 0 {
-                // reset the last time we received data
-                last_update = SystemTime::now();
-            } else {
-                // delay the loop
-                thread::sleep(Duration::from_millis(10));
-            }
-
-            if stop_sig_received(&state_recv, &state_send) || close_connection {
-                // let's close the connection
+    let context = context.clone();
+    let stopped = stopped.clone();
+
+    let _ = thread::Builder::new()
+        .name("connection".to_string())
+        .spawn(move || {
+            // the socket may inherit the non-blocking mode of the listener
+            let _ = tcp_stream.set_nonblocking(false);
+            let _ = tcp_stream
+                .set_read_timeout(Some(Duration::from_millis(CONNECTION_STOP_CHECK_MILLIS)));
+
+            let mut client = Client {
+                authenticated: !context.acl.requires_auth(),
+                user: acl::DEFAULT_USER.to_string(),
+                ..Client::default()
+            };
+            if context.clients.register(&mut client, &tcp_stream).is_err() {
+                // the client is gone already
                 return;
             }
+            let killed = client.killed.clone();
+            let client = Arc::new(Mutex::new(client));
+            let pushes = client
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .subscriptions
+                .pushes();
+            let mut last_update = Instant::now();
+            // the requests received but not run yet
+            let mut requests = vec![];
+
+            while !stopped.load(Ordering::Relaxed) {
+                let (close_connection, received_data_length) = handle_request(
+                    &storage,
+                    &context,
+                    &client,
+                    &mut requests,
+                    &tcp_stream,
+                    &stopped,
+                );
+
+                // the messages published before the connection is closed are still written
+                if !write_pushes(&pushes, &tcp_stream)
+                    || close_connection
+                    || killed.load(Ordering::Relaxed)
+                {
+                    break;
+                }
 
-            if let Ok(duration) = last_update.duration_since(SystemTime::now()) {
-                if duration.as_secs() >= 300 {
-                    // close the connection after 300 secs of inactivity
-                    return;
+                if received_data_length > 0 {
+                    // reset the last time we received data
+                    last_update = Instant::now();
+                } else if last_update.elapsed().as_secs() >= CONNECTION_IDLE_TIMEOUT_SECONDS
+                    && !client
+                        .lock()
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .subscriptions
+                        .is_subscribed()
+                {
+                    // close the connection after some inactivity, subscribers only listening
+                    break;
                 }
             }
 
-            if close_connection {
-                return;
-            }
-        }
-    });
+            // the replies already written are received before the end of the connection
+            let _ = tcp_stream.shutdown(Shutdown::Write);
+
+            // nothing is published to a client once it is gone
+            let mut client = client.lock().unwrap_or_else(PoisonError::into_inner);
+            context.pubsub.unsubscribe_all(&mut client.subscriptions);
+            context.watched_keys.unwatch_all(&mut client.watch);
+            context.clients.unregister(&client);
+        });
 }
@@ -0,0 +1,289 @@
+use std::collections::{BTreeSet, HashMap};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Mutex;
+
+use crossbeam_channel::{unbounded, Receiver, Sender};
+
+use super::keyspace_events::{KEYEVENT, KEYSPACE};
+use crate::glob::glob_match;
+use crate::protocol::response::RedisResponseType::{self, BulkString};
+use crate::storage::models::RedisString;
+
+// clients subscribed to each channel, or to each pattern
+type Registry = HashMap<RedisString, Vec<Sender<Vec<u8>>>>;
+
+/// Channels, patterns and shard channels a client is subscribed to, along with the queue of the
+/// messages pushed to it out of its replies
+pub struct Subscriptions {
+    sender: Sender<Vec<u8>>,
+    receiver: Receiver<Vec<u8>>,
+    pub channels: BTreeSet<RedisString>,
+    pub patterns: BTreeSet<RedisString>,
+    pub shard_channels: BTreeSet<RedisString>,
+}
+
+impl Default for Subscriptions {
+    fn default() -> Self {
+        let (sender, receiver) = unbounded();
+        Subscriptions {
+            sender,
+            receiver,
+            channels: BTreeSet::new(),
+            patterns: BTreeSet::new(),
+            shard_channels: BTreeSet::new(),
+        }
+    }
+}
+
+impl Subscriptions {
+    /// number of subscriptions to channels and patterns, as replied to each subscription change
+    pub fn count(&self) -> usize {
+        self.channels.len() + self.patterns.len()
+    }
+
+    /// number of subscriptions to shard channels, counted apart from the other ones
+    pub fn shard_count(&self) -> usize {
+        self.shard_channels.len()
+    }
+
+    /// whether the client is subscribed to anything, so that it is only listening
+    pub fn is_subscribed(&self) -> bool {
+        self.count() + self.shard_count() > 0
+    }
+
+    /// queue of the messages to write to the client, already encoded
+    pub fn pushes(&self) -> Receiver<Vec<u8>> {
+        self.receiver.clone()
+    }
+}
+
+/// Clients subscribed to channels, or to the channels matching glob-style patterns, and to shard
+/// channels - a single shard holding all of them
+#[derive(Default)]
+pub struct PubSub {
+    channels: Mutex<Registry>,
+    patterns: Mutex<Registry>,
+    shard_channels: Mutex<Registry>,
+    // classes of the keyspace events published, none by default
+    keyspace_events: AtomicU32,
+}
+
+impl PubSub {
+    /// subscribe a client to `channel`, false if it already was
+    pub fn subscribe(&self, subscriptions: &mut Subscriptions, channel: &[u8]) -> bool {
+        if !subscriptions.channels.insert(channel.to_vec()) {
+            return false;
+        }
+
+        register(&self.channels, &subscriptions.sender, channel);
+        true
+    }
+
+    /// unsubscribe a client from `channel`, false if it was not subscribed to it
+    pub fn unsubscribe(&self, subscriptions: &mut Subscriptions, channel: &[u8]) -> bool {
+        if !subscriptions.channels.remove(channel) {
+            return false;
+        }
+
+        unregister(&self.channels, &subscriptions.sender, channel);
+        true
+    }
+
+    /// subscribe a client to the channels matching `pattern`, false if it already was
+    pub fn psubscribe(&self, subscriptions: &mut Subscriptions, pattern: &[u8]) -> bool {
+        if !subscriptions.patterns.insert(pattern.to_vec()) {
+            return false;
+        }
+
+        register(&self.patterns, &subscriptions.sender, pattern);
+        true
+    }
+
+    /// unsubscribe a client from `pattern`, false if it was not subscribed to it
+    pub fn punsubscribe(&self, subscriptions: &mut Subscriptions, pattern: &[u8]) -> bool {
+        if !subscriptions.patterns.remove(pattern) {
+            return false;
+        }
+
+        unregister(&self.patterns, &subscriptions.sender, pattern);
+        true
+    }
+
+    /// subscribe a client to the shard channel `channel`, false if it already was
+    pub fn ssubscribe(&self, subscriptions: &mut Subscriptions, channel: &[u8]) -> bool {
+        if !subscriptions.shard_channels.insert(channel.to_vec()) {
+            return false;
+        }
+
+        register(&self.shard_channels, &subscriptions.sender, channel);
+        true
+    }
+
+    /// unsubscribe a client from the shard channel `channel`, false if it was not subscribed to
+    /// it
+    pub fn sunsubscribe(&self, subscriptions: &mut Subscriptions, channel: &[u8]) -> bool {
+        if !subscriptions.shard_channels.remove(channel) {
+            return false;
+        }
+
+        unregister(&self.shard_channels, &subscriptions.sender, channel);
+        true
+    }
+
+    /// unsubscribe a client from all its channels, patterns and shard channels, once it is gone
+    pub fn unsubscribe_all(&self, subscriptions: &mut Subscriptions) {
+        for channel in std::mem::take(&mut subscriptions.channels) {
+            unregister(&self.channels, &subscriptions.sender, &channel);
+        }
+        for pattern in std::mem::take(&mut subscriptions.patterns) {
+            unregister(&self.patterns, &subscriptions.sender, &pattern);
+        }
+        for channel in std::mem::take(&mut subscriptions.shard_channels) {
+            unregister(&self.shard_channels, &subscriptions.sender, &channel);
+        }
+    }
+
+    /// push `message` to the clients subscribed to `channel` or to a pattern matching it, return
+    /// how many received it - a client receiving it once per matching subscription
+    pub fn publish(&self, channel: &[u8], message: &[u8]) -> usize {
+        let mut receivers = publish_to(&self.channels, b"message", channel, message);
+
+        let patterns = self.patterns.lock().unwrap();
+        for (pattern, senders) in patterns.iter() {
+            if !glob_match(pattern, channel) {
+                continue;
+            }
+
+            let push = push_reply(vec![
+                BulkString(b"pmessage".to_vec()),
+                BulkString(pattern.clone()),
+                BulkString(channel.to_vec()),
+                BulkString(message.to_vec()),
+            ]);
+            receivers += push_to(senders, &push);
+        }
+
+        receivers
+    }
+
+    /// push `message` to the clients subscribed to the shard channel `channel`, return how many
+    /// received it
+    pub fn spublish(&self, channel: &[u8], message: &[u8]) -> usize {
+        publish_to(&self.shard_channels, b"smessage", channel, message)
+    }
+
+    /// classes of the keyspace events published, as flags of `keyspace_events`
+    pub fn keyspace_events(&self) -> u32 {
+        self.keyspace_events.load(Ordering::Relaxed)
+    }
+
+    pub fn set_keyspace_events(&self, flags: u32) {
+        self.keyspace_events.store(flags, Ordering::Relaxed);
+    }
+
+    /// publish `event` on `key` of the database `db` to the keyspace and keyevent channels, when
+    /// its class is enabled
+    pub fn notify_keyspace_event(&self, class: u32, event: &str, db: usize, key: &[u8]) {
+        let flags = self.keyspace_events();
+        if flags & class == 0 {
+            return;
+        }
+
+        if flags & KEYSPACE != 0 {
+            let mut channel = format!("__keyspace@{}__:", db).into_bytes();
+            channel.extend_from_slice(key);
+            self.publish(&channel, event.as_bytes());
+        }
+        if flags & KEYEVENT != 0 {
+            let channel = format!("__keyevent@{}__:{}", db, event);
+            self.publish(channel.as_bytes(), key);
+        }
+    }
+
+    /// channels with at least one subscriber, the ones matching `pattern` if given
+    pub fn channels(&self, pattern: Option<&[u8]>) -> Vec<RedisString> {
+        active_channels(&self.channels, pattern)
+    }
+
+    /// number of clients subscribed to `channel`, not counting the patterns matching it
+    pub fn subscribers(&self, channel: &[u8]) -> usize {
+        subscriber_count(&self.channels, channel)
+    }
+
+    /// shard channels with at least one subscriber, the ones matching `pattern` if given
+    pub fn shard_channels(&self, pattern: Option<&[u8]>) -> Vec<RedisString> {
+        active_channels(&self.shard_channels, pattern)
+    }
+
+    /// number of clients subscribed to the shard channel `channel`
+    pub fn shard_subscribers(&self, channel: &[u8]) -> usize {
+        subscriber_count(&self.shard_channels, channel)
+    }
+
+    /// number of distinct patterns subscribed to
+    pub fn pattern_count(&self) -> usize {
+        self.patterns.lock().unwrap().len()
+    }
+}
+
+fn register(registry: &Mutex<Registry>, sender: &Sender<Vec<u8>>, name: &[u8]) {
+    let mut registry = registry.lock().unwrap();
+    registry
+        .entry(name.to_vec())
+        .or_default()
+        .push(sender.clone());
+}
+
+fn unregister(registry: &Mutex<Registry>, sender: &Sender<Vec<u8>>, name: &[u8]) {
+    let mut registry = registry.lock().unwrap();
+    if let Some(senders) = registry.get_mut(name) {
+        senders.retain(|other| !other.same_channel(sender));
+        if senders.is_empty() {
+            registry.remove(name);
+        }
+    }
+}
+
+/// push a message to the clients subscribed to `channel` in `registry`, framed as `kind`
+fn publish_to(registry: &Mutex<Registry>, kind: &[u8], channel: &[u8], message: &[u8]) -> usize {
+    let registry = registry.lock().unwrap();
+    match registry.get(channel) {
+        Some(senders) => {
+            let push = push_reply(vec![
+                BulkString(kind.to_vec()),
+                BulkString(channel.to_vec()),
+                BulkString(message.to_vec()),
+            ]);
+            push_to(senders, &push)
+        }
+        None => 0,
+    }
+}
+
+fn active_channels(registry: &Mutex<Registry>, pattern: Option<&[u8]>) -> Vec<RedisString> {
+    let registry = registry.lock().unwrap();
+    let mut channels: Vec<RedisString> = registry
+        .keys()
+        .filter(|channel| pattern.is_none_or(|pattern| glob_match(pattern, channel)))
+        .cloned()
+        .collect();
+    channels.sort();
+    channels
+}
+
+fn subscriber_count(registry: &Mutex<Registry>, channel: &[u8]) -> usize {
+    let registry = registry.lock().unwrap();
+    registry.get(channel).map_or(0, |senders| senders.len())
+}
+
+fn push_reply(fields: Vec<RedisResponseType>) -> Vec<u8> {
+    RedisResponseType::Array(fields).get_formatted()
+}
+
+/// send an encoded message to clients, return how many are still there to receive it
+fn push_to(senders: &[Sender<Vec<u8>>], push: &[u8]) -> usize {
+    senders
+        .iter()
+        .filter(|sender| sender.send(push.to_vec()).is_ok())
+        .count()
+}
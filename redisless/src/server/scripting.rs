@@ -0,0 +1,645 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::command::command_error::RedisCommandError;
+use crate::storage::models::RedisString;
+
+/// How long a script runs before the other clients are replied -BUSY, by default
+pub const DEFAULT_BUSY_THRESHOLD_MILLIS: u64 = 5000;
+
+#[cfg(feature = "lua")]
+pub use lua::{compile, eval, fcall, register};
+
+/// Scripts run by EVAL, by the SHA1 digest of their body so that EVALSHA can run them again
+#[derive(Default)]
+pub struct Scripts {
+    bodies: Mutex<HashMap<String, RedisString>>,
+}
+
+impl Scripts {
+    /// cache `script`, return the hexadecimal SHA1 digest it is known by
+    pub fn add(&self, script: &[u8]) -> String {
+        let sha = sha1_smol::Sha1::from(script).digest().to_string();
+        let mut bodies = self.bodies.lock().unwrap();
+        bodies.entry(sha.clone()).or_insert_with(|| script.to_vec());
+        sha
+    }
+
+    /// body of the script of digest `sha`, whatever its case
+    pub fn get(&self, sha: &[u8]) -> Option<RedisString> {
+        let sha = String::from_utf8_lossy(sha).to_ascii_lowercase();
+        self.bodies.lock().unwrap().get(&sha).cloned()
+    }
+
+    pub fn contains(&self, sha: &[u8]) -> bool {
+        let sha = String::from_utf8_lossy(sha).to_ascii_lowercase();
+        self.bodies.lock().unwrap().contains_key(&sha)
+    }
+
+    pub fn flush(&self) {
+        self.bodies.lock().unwrap().clear();
+    }
+}
+
+/// Script being run, so that the other clients are replied -BUSY once it has run for longer than
+/// the busy threshold, until it returns or is killed with SCRIPT KILL
+pub struct RunningScript {
+    started_at: Mutex<Option<Instant>>,
+    // set by SCRIPT KILL, checked by the script as it runs
+    killed: AtomicBool,
+    // set once the script called a write command, so that it can not be killed anymore
+    written: AtomicBool,
+    // 0 for a script never to be considered busy
+    busy_threshold_millis: AtomicU64,
+}
+
+impl Default for RunningScript {
+    fn default() -> Self {
+        RunningScript {
+            started_at: Mutex::new(None),
+            killed: AtomicBool::new(false),
+            written: AtomicBool::new(false),
+            busy_threshold_millis: AtomicU64::new(DEFAULT_BUSY_THRESHOLD_MILLIS),
+        }
+    }
+}
+
+impl RunningScript {
+    pub fn busy_threshold_millis(&self) -> u64 {
+        self.busy_threshold_millis.load(Ordering::Relaxed)
+    }
+
+    pub fn set_busy_threshold_millis(&self, millis: u64) {
+        self.busy_threshold_millis.store(millis, Ordering::Relaxed);
+    }
+
+    /// whether a script has been running for longer than the busy threshold
+    pub fn is_busy(&self) -> bool {
+        let threshold = self.busy_threshold_millis();
+        let started_at = *self.started_at.lock().unwrap();
+        match started_at {
+            Some(started_at) if threshold > 0 => {
+                started_at.elapsed() >= Duration::from_millis(threshold)
+            }
+            _ => false,
+        }
+    }
+
+    /// stop the busy script, as SCRIPT KILL does, unless it already wrote to the data
+    pub fn kill(&self) -> Result<(), RedisCommandError> {
+        if !self.is_busy() {
+            return Err(RedisCommandError::NotBusy);
+        }
+        if self.written.load(Ordering::Relaxed) {
+            return Err(RedisCommandError::Unkillable);
+        }
+
+        self.killed.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+
+    #[cfg(feature = "lua")]
+    fn start(&self) {
+        self.killed.store(false, Ordering::Relaxed);
+        self.written.store(false, Ordering::Relaxed);
+        *self.started_at.lock().unwrap() = Some(Instant::now());
+    }
+
+    #[cfg(feature = "lua")]
+    fn finish(&self) {
+        *self.started_at.lock().unwrap() = None;
+    }
+
+    #[cfg(feature = "lua")]
+    fn is_killed(&self) -> bool {
+        self.killed.load(Ordering::Relaxed)
+    }
+
+    #[cfg(feature = "lua")]
+    fn record_write(&self) {
+        self.written.store(true, Ordering::Relaxed);
+    }
+}
+
+#[cfg(not(feature = "lua"))]
+pub fn eval<T: crate::storage::Storage>(
+    _storage: &std::sync::Arc<Mutex<T>>,
+    _context: &std::sync::Arc<super::context::ServerContext>,
+    _client: &mut super::client::Client,
+    _script: &[u8],
+    _keys: Vec<RedisString>,
+    _args: Vec<RedisString>,
+) -> crate::protocol::response::RedisResponse {
+    use crate::protocol::response::RedisResponse;
+
+    RedisResponse::error(RedisCommandError::NotSupported("EVAL".to_string()))
+}
+
+#[cfg(not(feature = "lua"))]
+pub fn compile(_script: &[u8]) -> Result<(), RedisCommandError> {
+    Err(RedisCommandError::NotSupported("SCRIPT LOAD".to_string()))
+}
+
+#[cfg(not(feature = "lua"))]
+pub fn register(_body: &[u8]) -> Result<Vec<super::functions::Function>, RedisCommandError> {
+    Err(RedisCommandError::NotSupported("FUNCTION LOAD".to_string()))
+}
+
+#[cfg(not(feature = "lua"))]
+pub fn fcall<T: crate::storage::Storage>(
+    _storage: &std::sync::Arc<Mutex<T>>,
+    _context: &std::sync::Arc<super::context::ServerContext>,
+    _client: &mut super::client::Client,
+    _code: &[u8],
+    _function: &super::functions::Function,
+    _keys: Vec<RedisString>,
+    _args: Vec<RedisString>,
+) -> crate::protocol::response::RedisResponse {
+    use crate::protocol::response::RedisResponse;
+
+    RedisResponse::error(RedisCommandError::NotSupported("FCALL".to_string()))
+}
+
+#[cfg(feature = "lua")]
+mod lua {
+    use std::cell::RefCell;
+    use std::fmt;
+    use std::rc::Rc;
+    use std::sync::{Arc, Mutex};
+
+    use mlua::{HookTriggers, Lua, LuaOptions, StdLib, Table, Value, Variadic, VmState};
+
+    use crate::command::command_error::RedisCommandError;
+    use crate::command::{category, Command};
+    use crate::protocol::parser::RedisProtocolParser;
+    use crate::protocol::response::{RedisResponse, RedisResponseType};
+    use crate::protocol::Resp;
+    use crate::server::client::Client;
+    use crate::server::context::ServerContext;
+    use crate::server::functions::{self, Function, FUNCTION_FLAGS};
+    use crate::server::util::run_without_blocking;
+    use crate::storage::models::RedisString;
+    use crate::storage::Storage;
+
+    // name of the chunk of a script, as found in its error messages
+    const CHUNK_NAME: &str = "=user_script";
+    // how often a script checks whether it has been killed
+    const KILL_CHECK_INSTRUCTIONS: u32 = 1000;
+    // how deeply nested the tables converted from and to replies can be
+    const MAX_NESTING_DEPTH: usize = 500;
+    const NESTING_DEPTH_ERROR: &str = "ERR reached lua stack limit";
+
+    // functions registered by a library, along with their callbacks
+    type Registered = Rc<RefCell<Vec<(Function, mlua::Function)>>>;
+
+    /// Error raised to fail a script with it as is, as the one replied by a command called with
+    /// `redis.call`
+    #[derive(Debug)]
+    struct ReplyError(String);
+
+    impl fmt::Display for ReplyError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "{}", self.0)
+        }
+    }
+
+    impl std::error::Error for ReplyError {}
+
+    fn reply_error(message: impl Into<String>) -> mlua::Error {
+        mlua::Error::external(ReplyError(message.into()))
+    }
+
+    /// run `script` in a fresh Lua interpreter, `keys` and `args` being its KEYS and ARGV
+    /// tables, and reply what it returns
+    pub fn eval<T: Storage>(
+        storage: &Arc<Mutex<T>>,
+        context: &Arc<ServerContext>,
+        client: &mut Client,
+        script: &[u8],
+        keys: Vec<RedisString>,
+        args: Vec<RedisString>,
+    ) -> RedisResponse {
+        run(storage, context, client, false, |lua, _| {
+            let globals = lua.globals();
+            globals.set("KEYS", strings(lua, keys)?)?;
+            globals.set("ARGV", strings(lua, args)?)?;
+
+            lua.load(script).set_name(CHUNK_NAME).call(())
+        })
+    }
+
+    /// run `function` of the library of code `code` in a fresh Lua interpreter, called with the
+    /// tables of `keys` and `args`, and reply what it returns
+    pub fn fcall<T: Storage>(
+        storage: &Arc<Mutex<T>>,
+        context: &Arc<ServerContext>,
+        client: &mut Client,
+        code: &[u8],
+        function: &Function,
+        keys: Vec<RedisString>,
+        args: Vec<RedisString>,
+    ) -> RedisResponse {
+        let body = match functions::parse_metadata(code) {
+            Ok((_, body)) => body,
+            Err(err) => return RedisResponse::error(err),
+        };
+
+        run(
+            storage,
+            context,
+            client,
+            function.is_read_only(),
+            |lua, redis| {
+                let registered = register_function(lua, redis)?;
+                lua.load(body).set_name(CHUNK_NAME).exec()?;
+
+                let callback = registered
+                    .borrow()
+                    .iter()
+                    .find(|(registered, _)| registered.name == function.name)
+                    .map(|(_, callback)| callback.clone());
+                match callback {
+                    Some(callback) => callback.call((strings(lua, keys)?, strings(lua, args)?)),
+                    None => Err(reply_error(RedisCommandError::FunctionNotFound.to_string())),
+                }
+            },
+        )
+    }
+
+    /// functions registered by the body of a library, as run by FUNCTION LOAD
+    pub fn register(body: &[u8]) -> Result<Vec<Function>, RedisCommandError> {
+        let lua = new_lua().map_err(script_error)?;
+        let redis = lua.create_table().map_err(script_error)?;
+        let registered = register_function(&lua, &redis).map_err(script_error)?;
+        lua.globals().set("redis", redis).map_err(script_error)?;
+        lua.load(body)
+            .set_name(CHUNK_NAME)
+            .exec()
+            .map_err(script_error)?;
+
+        Ok(registered
+            .take()
+            .into_iter()
+            .map(|(function, _)| function)
+            .collect())
+    }
+
+    fn new_lua() -> mlua::Result<Lua> {
+        Lua::new_with(
+            StdLib::TABLE | StdLib::STRING | StdLib::MATH,
+            LuaOptions::default(),
+        )
+    }
+
+    /// run `script` with the `redis` table giving access to the data, and reply the value it
+    /// returns - a read only script not being able to call write commands
+    fn run<T: Storage>(
+        storage: &Arc<Mutex<T>>,
+        context: &Arc<ServerContext>,
+        client: &mut Client,
+        read_only: bool,
+        script: impl FnOnce(&Lua, &Table) -> mlua::Result<Value>,
+    ) -> RedisResponse {
+        let lua = match new_lua() {
+            Ok(lua) => lua,
+            Err(err) => return RedisResponse::error(script_error(err)),
+        };
+
+        let running_context = context.clone();
+        lua.set_hook(
+            HookTriggers::new().every_nth_instruction(KILL_CHECK_INSTRUCTIONS),
+            move |_, _| match running_context.running_script.is_killed() {
+                true => Err(reply_error(RedisCommandError::ScriptKilled.to_string())),
+                false => Ok(VmState::Continue),
+            },
+        );
+
+        // a database selected by the script is not the one of the client anymore once it is done
+        let db = client.db;
+        context.running_script.start();
+        let response = run_script(storage, context, client, read_only, &lua, script);
+        context.running_script.finish();
+        client.db = db;
+        response
+    }
+
+    fn run_script<T: Storage>(
+        storage: &Arc<Mutex<T>>,
+        context: &Arc<ServerContext>,
+        client: &mut Client,
+        read_only: bool,
+        lua: &Lua,
+        script: impl FnOnce(&Lua, &Table) -> mlua::Result<Value>,
+    ) -> RedisResponse {
+        let client = RefCell::new(client);
+        let result = lua.scope(|scope| {
+            let redis = lua.create_table()?;
+            redis.set(
+                "call",
+                scope.create_function(|lua, args: Variadic<Value>| {
+                    let mut client = client.borrow_mut();
+                    let reply = call(lua, storage, context, &mut client, read_only, args)?;
+                    match error_message(&reply) {
+                        Some(message) => Err(reply_error(message)),
+                        None => Ok(reply),
+                    }
+                })?,
+            )?;
+            redis.set(
+                "pcall",
+                scope.create_function(|lua, args: Variadic<Value>| {
+                    let mut client = client.borrow_mut();
+                    call(lua, storage, context, &mut client, read_only, args)
+                })?,
+            )?;
+            redis.set(
+                "error_reply",
+                lua.create_function(|lua, message: mlua::String| {
+                    lua.create_table_from([("err", message)])
+                })?,
+            )?;
+            redis.set(
+                "status_reply",
+                lua.create_function(|lua, status: mlua::String| {
+                    lua.create_table_from([("ok", status)])
+                })?,
+            )?;
+            redis.set(
+                "sha1hex",
+                lua.create_function(|_, string: mlua::String| {
+                    Ok(sha1_smol::Sha1::from(string.as_bytes())
+                        .digest()
+                        .to_string())
+                })?,
+            )?;
+            lua.globals().set("redis", redis.clone())?;
+
+            let value = script(lua, &redis)?;
+            Ok(script_reply(value, 0))
+        });
+
+        match result {
+            Ok(response) => response,
+            Err(err) => RedisResponse::error(script_error(err)),
+        }
+    }
+
+    /// add `redis.register_function` to `redis`, called either with the name and the callback
+    /// of a function or with a table of its definition, return the functions it registers
+    fn register_function(lua: &Lua, redis: &Table) -> mlua::Result<Registered> {
+        let registered = Registered::default();
+        let functions = registered.clone();
+        let register = lua.create_function(move |_, args: Variadic<Value>| {
+            let (name, callback, flags, description) = match args.as_slice() {
+                [Value::String(name), Value::Function(callback)] => {
+                    (name.to_string_lossy(), callback.clone(), vec![], None)
+                }
+                [Value::Table(definition)] => (
+                    definition.get::<String>("function_name")?,
+                    definition.get::<mlua::Function>("callback")?,
+                    definition
+                        .get::<Option<Vec<String>>>("flags")?
+                        .unwrap_or_default(),
+                    definition.get::<Option<String>>("description")?,
+                ),
+                _ => {
+                    return Err(reply_error(
+                        "ERR wrong number of arguments to redis.register_function",
+                    ))
+                }
+            };
+
+            if !functions::is_valid_name(&name) {
+                return Err(reply_error(
+                    RedisCommandError::InvalidName("Function").to_string(),
+                ));
+            }
+            if functions
+                .borrow()
+                .iter()
+                .any(|(function, _)| function.name == name)
+            {
+                return Err(reply_error("ERR Function already exists in the library"));
+            }
+            if !flags
+                .iter()
+                .all(|flag| FUNCTION_FLAGS.contains(&flag.as_str()))
+            {
+                return Err(reply_error("ERR unknown flag given"));
+            }
+
+            let function = Function {
+                name,
+                description,
+                flags,
+            };
+            functions.borrow_mut().push((function, callback));
+            Ok(())
+        })?;
+
+        redis.set("register_function", register)?;
+        Ok(registered)
+    }
+
+    /// check that `script` compiles, as SCRIPT LOAD does before caching it
+    pub fn compile(script: &[u8]) -> Result<(), RedisCommandError> {
+        let lua = Lua::new_with(StdLib::NONE, LuaOptions::default()).map_err(script_error)?;
+        lua.load(script)
+            .set_name(CHUNK_NAME)
+            .into_function()
+            .map(|_| ())
+            .map_err(script_error)
+    }
+
+    fn strings(lua: &Lua, strings: Vec<RedisString>) -> mlua::Result<Table> {
+        let strings = strings
+            .into_iter()
+            .map(|string| lua.create_string(string))
+            .collect::<mlua::Result<Vec<_>>>()?;
+        lua.create_sequence_from(strings)
+    }
+
+    /// run a command called by a script, its reply converted to a Lua value - an error becoming
+    /// an `{err = message}` table
+    fn call<T: Storage>(
+        lua: &Lua,
+        storage: &Arc<Mutex<T>>,
+        context: &Arc<ServerContext>,
+        client: &mut Client,
+        read_only: bool,
+        args: Variadic<Value>,
+    ) -> mlua::Result<Value> {
+        if args.is_empty() {
+            return Err(reply_error(
+                "ERR Please specify at least one argument for this redis lib call",
+            ));
+        }
+
+        let mut arguments = Vec::<RedisString>::with_capacity(args.len());
+        for arg in args {
+            match arg {
+                Value::String(_) | Value::Integer(_) | Value::Number(_) => {
+                    let arg = lua.coerce_string(arg)?.unwrap_or_else(|| unreachable!());
+                    arguments.push(arg.as_bytes().to_vec());
+                }
+                _ => {
+                    return Err(reply_error(
+                        "ERR Lua redis lib command arguments must be strings or integers",
+                    ))
+                }
+            }
+        }
+
+        let name = String::from_utf8_lossy(&arguments[0]).to_ascii_lowercase();
+        if category::category_commands("write").contains(&name.as_str()) {
+            if read_only {
+                return Err(reply_error(
+                    RedisCommandError::WriteFromReadOnlyScript.to_string(),
+                ));
+            }
+            context.running_script.record_write();
+        }
+
+        let resps = arguments
+            .iter()
+            .map(|argument| Resp::BulkString(argument))
+            .collect();
+        let (response, reads_values) = match Command::parse(resps) {
+            Ok(command) if !command.is_allowed_in_script() => (
+                RedisResponse::error(RedisCommandError::NotAllowedFromScript),
+                false,
+            ),
+            Ok(command) => {
+                // the permissions of the user running the script apply to its commands
+                let permitted = context
+                    .acl
+                    .check_permissions(&client.user, &name, &command.keys());
+                match permitted {
+                    Ok(()) => {
+                        let reads_values = reads_values(&command);
+                        let response = run_without_blocking(storage, context, client, command);
+                        (response, reads_values)
+                    }
+                    Err(err) => (RedisResponse::error(err), false),
+                }
+            }
+            Err(_) if !category::command_names().any(|command| command == name) => (
+                RedisResponse::error(RedisCommandError::UnknownCommandFromScript),
+                false,
+            ),
+            Err(err) => (RedisResponse::error(err), false),
+        };
+
+        let reply = response.reply();
+        match RedisProtocolParser::parse(&reply) {
+            Ok((resp, _)) => lua_value(lua, resp, reads_values, 0),
+            Err(_) => Ok(Value::Nil),
+        }
+    }
+
+    /// whether the command replies the values it reads as simple strings, converted to Lua
+    /// strings as bulk strings are rather than to status tables
+    fn reads_values(command: &Command) -> bool {
+        matches!(
+            command,
+            Command::Get(_) | Command::GetSet(..) | Command::MGet(_) | Command::HGet(..)
+        )
+    }
+
+    /// convert the reply of a command to a Lua value, as Redis does
+    fn lua_value(lua: &Lua, resp: Resp, reads_values: bool, depth: usize) -> mlua::Result<Value> {
+        if depth > MAX_NESTING_DEPTH {
+            return Err(mlua::Error::external(ReplyError(
+                NESTING_DEPTH_ERROR.to_string(),
+            )));
+        }
+
+        Ok(match resp {
+            Resp::Integer(integer) => {
+                let integer = std::str::from_utf8(integer)
+                    .ok()
+                    .and_then(|integer| integer.parse::<i64>().ok())
+                    .unwrap_or_default();
+                Value::Integer(integer)
+            }
+            Resp::BulkString(string) => Value::String(lua.create_string(string)?),
+            Resp::String(value) if reads_values => Value::String(lua.create_string(value)?),
+            Resp::String(status) => {
+                Value::Table(lua.create_table_from([("ok", lua.create_string(status)?)])?)
+            }
+            Resp::Error(message) => {
+                Value::Table(lua.create_table_from([("err", lua.create_string(message)?)])?)
+            }
+            Resp::Array(resps) => {
+                let values = resps
+                    .into_iter()
+                    .map(|resp| lua_value(lua, resp, reads_values, depth + 1))
+                    .collect::<mlua::Result<Vec<_>>>()?;
+                Value::Table(lua.create_sequence_from(values)?)
+            }
+            Resp::Nil => Value::Boolean(false),
+        })
+    }
+
+    /// message of an `{err = message}` table
+    fn error_message(value: &Value) -> Option<String> {
+        match value {
+            Value::Table(table) => match table.raw_get::<Value>("err") {
+                Ok(Value::String(message)) => Some(message.to_string_lossy()),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// convert the value returned by a script to a reply, as Redis does - a table being replied
+    /// as an array up to its first nil, and an error past `MAX_NESTING_DEPTH` nested tables
+    fn script_reply(value: Value, depth: usize) -> RedisResponse {
+        use RedisResponseType::*;
+        match value {
+            Value::Table(_) if depth > MAX_NESTING_DEPTH => {
+                RedisResponse::error(RedisCommandError::Script(NESTING_DEPTH_ERROR.to_string()))
+            }
+            Value::Integer(integer) => RedisResponse::single(Integer(integer)),
+            Value::Number(number) => RedisResponse::single(Integer(number as i64)),
+            Value::String(string) => RedisResponse::single(BulkString(string.as_bytes().to_vec())),
+            Value::Boolean(true) => RedisResponse::single(Integer(1)),
+            Value::Table(table) => {
+                if let Some(message) = error_message(&Value::Table(table.clone())) {
+                    return RedisResponse::error(RedisCommandError::Script(message));
+                }
+                if let Ok(Value::String(status)) = table.raw_get::<Value>("ok") {
+                    return RedisResponse::single(SimpleString(status.as_bytes().to_vec()));
+                }
+
+                let replies = table
+                    .sequence_values::<Value>()
+                    .map_while(Result::ok)
+                    .map(|value| script_reply(value, depth + 1))
+                    .collect();
+                RedisResponse::replies(replies)
+            }
+            _ => RedisResponse::single(Nil),
+        }
+    }
+
+    /// error replied by a script which failed, a `ReplyError` being kept as is
+    fn script_error(err: mlua::Error) -> RedisCommandError {
+        match err {
+            mlua::Error::CallbackError { cause, .. } => script_error((*cause).clone()),
+            mlua::Error::ExternalError(err) => match err.downcast_ref::<ReplyError>() {
+                Some(ReplyError(message)) => RedisCommandError::Script(message.clone()),
+                None => RedisCommandError::Script(format!("ERR {}", err)),
+            },
+            mlua::Error::SyntaxError { message, .. } => {
+                RedisCommandError::Script(format!("ERR Error compiling script: {}", message))
+            }
+            mlua::Error::RuntimeError(message) => {
+                RedisCommandError::Script(format!("ERR {}", message))
+            }
+            err => RedisCommandError::Script(format!("ERR Error running script: {}", err)),
+        }
+    }
+}
@@ -0,0 +1,8 @@
+/// Runtime statistics of a server
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ServerStats {
+    // number of commands waiting to be executed
+    pub command_queue_depth: usize,
+    // highest number of commands which have been waiting to be executed at once
+    pub command_queue_high_watermark: usize,
+}
@@ -0,0 +1,143 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+
+use crate::command::Command;
+use crate::server::context::ServerContext;
+use crate::server::keyspace_events;
+use crate::server::util::{lock_db, notify};
+use crate::storage::models::{Expiry, RedisString, RedisValue};
+use crate::storage::Storage;
+
+// value and expiry of a key, `None` when it does not exist
+type SavedValue = Option<(RedisValue, Option<Expiry>)>;
+// keys of a database along with their values and expiries
+type SavedDatabase = Vec<(RedisString, RedisValue, Option<Expiry>)>;
+
+/// Commands queued by a client between MULTI and EXEC
+#[derive(Default)]
+pub struct Transaction {
+    pub commands: Vec<Command>,
+    // set once a command could not be queued, so that EXEC discards the transaction
+    pub aborted: bool,
+    // number of commands queued before each savepoint marked by a nested MULTI
+    pub savepoints: Vec<usize>,
+}
+
+/// Values of the keys written since a savepoint, as they were when it was marked
+#[derive(Default)]
+pub struct Savepoint {
+    values: HashMap<(usize, RedisString), SavedValue>,
+    // databases saved entirely before a command writing keys it does not name, by index
+    databases: HashMap<usize, SavedDatabase>,
+}
+
+impl Savepoint {
+    /// remember what `command` may write when run against the database `db`, unless it has been
+    /// written since the savepoint already
+    pub fn save<T: Storage + ?Sized>(&mut self, storage: &mut T, db: usize, command: &Command) {
+        match command {
+            Command::FlushDb(_) => self.save_database(storage, db),
+            Command::SwapDb(db, other_db) => {
+                self.save_database(storage, *db as usize);
+                self.save_database(storage, *other_db as usize);
+            }
+            // FLUSHALL writes every database, and a script can select any of them to write keys it
+            // has not been given
+            Command::FlushAll(_)
+            | Command::Eval(..)
+            | Command::EvalSha(..)
+            | Command::FCall(..) => {
+                for db in 0..storage.databases() {
+                    self.save_database(storage, db);
+                }
+            }
+            Command::Move(key, target_db) => self.save_key(storage, *target_db as usize, key),
+            _ => {}
+        }
+
+        for key in command.keys() {
+            self.save_key(storage, db, key);
+        }
+        storage.select(db);
+    }
+
+    fn save_key<T: Storage + ?Sized>(&mut self, storage: &mut T, db: usize, key: &RedisString) {
+        // the database saved entirely holds the key as it was already
+        if self.databases.contains_key(&db) || !storage.select(db) {
+            return;
+        }
+
+        self.values
+            .entry((db, key.clone()))
+            .or_insert_with(|| saved_value(storage, key));
+    }
+
+    fn save_database<T: Storage + ?Sized>(&mut self, storage: &mut T, db: usize) {
+        if self.databases.contains_key(&db) || !storage.select(db) {
+            return;
+        }
+
+        let database = storage
+            .keys(b"*")
+            .into_iter()
+            .filter_map(|key| {
+                let (value, expiry) = saved_value(storage, &key)?;
+                Some((key, value, expiry))
+            })
+            .collect();
+        self.databases.insert(db, database);
+    }
+
+    /// put the keys back as they were when the savepoint was marked, the ones saved along with
+    /// their database having been saved before it
+    pub fn rollback<T: Storage>(self, storage: &Arc<Mutex<T>>, context: &ServerContext) {
+        for (db, database) in self.databases {
+            let mut storage = lock_db(storage, db);
+            let saved_keys = database.iter().map(|(key, ..)| key).collect::<HashSet<_>>();
+            for key in storage.keys(b"*") {
+                if !saved_keys.contains(&key) {
+                    rollback_key(&mut *storage, context, db, &key, None);
+                }
+            }
+            for (key, value, expiry) in database {
+                rollback_key(&mut *storage, context, db, &key, Some((value, expiry)));
+            }
+        }
+
+        for ((db, key), value) in self.values {
+            let mut storage = lock_db(storage, db);
+            rollback_key(&mut *storage, context, db, &key, value);
+        }
+    }
+}
+
+fn saved_value<T: Storage + ?Sized>(storage: &mut T, key: &[u8]) -> SavedValue {
+    let value = storage.value(key)?;
+    let expiry = storage.meta(key).and_then(|meta| meta.expiry);
+    Some((value, expiry))
+}
+
+/// put `key` back as `value` if it has been modified since, which is signaled as a RESTORE or a
+/// DEL would be
+fn rollback_key<T: Storage + ?Sized>(
+    storage: &mut T,
+    context: &ServerContext,
+    db: usize,
+    key: &[u8],
+    value: SavedValue,
+) {
+    if saved_value(storage, key) == value {
+        return;
+    }
+
+    match value {
+        Some((value, expiry)) => {
+            storage.restore(key, value, expiry);
+            notify(context, db, keyspace_events::GENERIC, "restore", key);
+        }
+        None => {
+            storage.remove(key);
+            notify(context, db, keyspace_events::GENERIC, "del", key);
+        }
+    }
+}
@@ -1,16 +1,23 @@
 mod run_command;
 // re-export run_command
-use crossbeam_channel::{Receiver, Sender};
+use crossbeam_channel::{bounded, Receiver, RecvTimeoutError, Sender};
 pub use run_command::*;
 
+use crate::server::blocking::Waiter;
+use crate::server::client::Client;
+use crate::server::context::ServerContext;
+use crate::server::keyspace_events;
 use crate::server::ServerState;
 
 use std::{
-    io::{BufReader, Read, Write},
+    io::{self, ErrorKind, Read, Write},
     net::TcpStream,
-    sync::{Arc, Mutex, MutexGuard},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, MutexGuard, PoisonError,
+    },
     thread,
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 use crate::{
@@ -21,19 +28,37 @@ use crate::{
 
 use super::{CloseConnection, ReceivedDataLength};
 
-pub fn lock_then_release<T: Storage>(storage: &Arc<Mutex<T>>) -> MutexGuard<T> {
-    loop {
-        match storage.lock() {
-            Ok(storage) => {
-                return storage;
-            }
-            Err(_) => {
-                thread::sleep(Duration::from_millis(10));
-            }
-        }
+// how often a blocked client checks if it is gone or if the server has been stopped
+const BLOCKED_CHECK_MILLIS: u64 = 100;
+// how many bytes are read from a connection at once
+const READ_CHUNK_SIZE: usize = 16 * 1024;
+// size of the requests received but not yet complete past which the connection is closed, as
+// Redis does with its client-query-buffer-limit
+const MAX_PENDING_REQUEST_SIZE: usize = 1024 * 1024 * 1024;
+
+pub fn lock_then_release<T: Storage + ?Sized>(storage: &Arc<Mutex<T>>) -> MutexGuard<'_, T> {
+    // the storage is still usable after a command panicked while holding the lock
+    storage.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// publish the keys removed because they expired since the last time
+pub fn notify_expired_keys<T: Storage + ?Sized>(storage: &Arc<Mutex<T>>, context: &ServerContext) {
+    let expired_keys = lock_then_release(storage).take_expired_keys();
+    for (db, key) in expired_keys {
+        context.watched_keys.touch(db, &key);
+        context
+            .pubsub
+            .notify_keyspace_event(keyspace_events::EXPIRED, "expired", db, &key);
     }
 }
 
+/// lock the storage and select the database `db`
+pub fn lock_db<T: Storage + ?Sized>(storage: &Arc<Mutex<T>>, db: usize) -> MutexGuard<'_, T> {
+    let mut storage = lock_then_release(storage);
+    storage.select(db);
+    storage
+}
+
 pub fn stop_sig_received(recv: &Receiver<ServerState>, sender: &Sender<ServerState>) -> bool {
     if let Ok(recv_state) = recv.try_recv() {
         if recv_state == ServerState::Stop {
@@ -46,51 +71,235 @@ pub fn stop_sig_received(recv: &Receiver<ServerState>, sender: &Sender<ServerSta
     false
 }
 
-pub fn get_command(bytes: &[u8; 512]) -> Result<Command, RedisCommandError> {
+/// parse the command and return it along with its lowercase name
+pub fn get_command(bytes: &[u8]) -> Result<(Command, String), RedisCommandError> {
     match RedisProtocolParser::parse(bytes) {
-        Ok((Resp::Array(v), _)) => match Command::parse(v) {
-            Ok(command) => Ok(command),
-            Err(err) => Err(err),
-        },
+        Ok((Resp::Array(v), _)) => {
+            let command_name = match v.first() {
+                Some(Resp::BulkString(name)) => String::from_utf8_lossy(name).to_lowercase(),
+                _ => String::new(),
+            };
+
+            Command::parse(v).map(|command| (command, command_name))
+        }
         Err(err) => Err(RedisCommandError::ProtocolParse(err)),
         _ => Err(RedisCommandError::CommandNotFound),
     }
 }
 
-fn get_bytes_from_request(stream: &TcpStream) -> ([u8; 512], usize) {
-    let mut buf_reader = BufReader::new(stream);
-    let mut buf = [0; 512];
-    let mut buf_length = 0_usize;
+/// append the bytes received from the connection to `requests`, return how many there are - none
+/// when nothing has been received before the read timeout, and an error once the connection has
+/// been closed
+fn read_requests(mut stream: &TcpStream, requests: &mut Vec<u8>) -> io::Result<usize> {
+    let mut buf = [0; READ_CHUNK_SIZE];
 
-    while let Ok(s) = buf_reader.read(&mut buf) {
-        buf_length += s;
+    match stream.read(&mut buf) {
+        Ok(0) => Err(io::Error::from(ErrorKind::UnexpectedEof)),
+        Ok(buf_length) => {
+            requests.extend_from_slice(&buf[..buf_length]);
+            Ok(buf_length)
+        }
+        Err(err)
+            if matches!(
+                err.kind(),
+                ErrorKind::WouldBlock | ErrorKind::TimedOut | ErrorKind::Interrupted
+            ) =>
+        {
+            Ok(0)
+        }
+        Err(err) => Err(err),
+    }
+}
 
-        if s < 512 {
-            break;
+/// length of the first request of `requests`, `None` until it has been received entirely - a
+/// request which can not be parsed spanning all of them
+fn request_length(requests: &[u8]) -> Option<usize> {
+    match RedisProtocolParser::parse(requests) {
+        Ok((_, left)) => Some(requests.len() - left.len()),
+        Err(err) if err.is_incomplete() => None,
+        Err(_) => Some(requests.len()),
+    }
+}
+
+/// read from the connection, then run each of the requests received entirely - pipelined by the
+/// client or not - and write their replies, `requests` keeping the beginning of the next one
+pub fn handle_request<T: Storage + Send + 'static>(
+    storage: &Arc<Mutex<T>>,
+    context: &Arc<ServerContext>,
+    client: &Arc<Mutex<Client>>,
+    requests: &mut Vec<u8>,
+    stream: &TcpStream,
+    stopped: &AtomicBool,
+) -> (CloseConnection, ReceivedDataLength) {
+    let buf_length = match read_requests(stream, requests) {
+        Ok(buf_length) => buf_length,
+        // the client is gone
+        Err(_) => return (true, 0),
+    };
+
+    if buf_length == 0 {
+        return (false, 0);
+    }
+
+    while let Some(length) = request_length(requests) {
+        let request = requests.drain(..length).collect::<Vec<_>>();
+        if run_request(storage, context, client, &request, stream, stopped) {
+            return (true, buf_length);
+        }
+
+        // a killed client does not run the rest of its pipelined requests
+        let killed = client
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .killed
+            .load(Ordering::Relaxed);
+        if killed {
+            return (true, buf_length);
         }
     }
 
-    (buf, buf_length)
+    // the client is not sending a request but filling the memory
+    (requests.len() > MAX_PENDING_REQUEST_SIZE, buf_length)
 }
 
-pub fn handle_request<T: Storage>(
+/// queue the command of `request` and write its reply once it has been executed, return whether
+/// the connection is to be closed
+fn run_request<T: Storage + Send + 'static>(
     storage: &Arc<Mutex<T>>,
+    context: &Arc<ServerContext>,
+    client: &Arc<Mutex<Client>>,
+    request: &[u8],
     mut stream: &TcpStream,
-) -> (CloseConnection, ReceivedDataLength) {
-    let (buf, buf_length) = get_bytes_from_request(stream);
+    stopped: &AtomicBool,
+) -> CloseConnection {
+    let mut deadline = None;
+    let (quit, reply) = loop {
+        let (quit, reply) = match execute_request(storage, context, client, request) {
+            Some(reply) => reply,
+            None => return true,
+        };
+
+        // a blocking command is run again each time one of its keys is pushed to
+        let waiter = client
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .blocked
+            .take();
+        let waiter = match waiter {
+            Some(waiter) => waiter,
+            None => break (quit, reply),
+        };
 
-    match buf.get(0) {
-        Some(x) if *x == 0 => {
-            return (false, buf_length);
+        let deadline =
+            *deadline.get_or_insert_with(|| waiter.timeout.map(|timeout| Instant::now() + timeout));
+        let woken_up = wait_until_woken_up(&waiter, deadline, stream, stopped);
+        context.blocked_clients.cancel(&waiter);
+
+        // the reply of a blocked command is the one to send once it times out
+        match woken_up {
+            Some(true) => continue,
+            Some(false) => break (quit, reply),
+            None => return true,
         }
-        _ => {}
+    };
+
+    if deadline.is_some() {
+        let mut client = client.lock().unwrap_or_else(PoisonError::into_inner);
+        client.stream_read_ids = None;
+    }
+
+    // the client is gone when its reply can not be written
+    // and QUIT closes it once replied
+    let written = stream
+        .write_all(&reply)
+        .and_then(|_| stream.flush())
+        .is_ok();
+
+    quit || !written
+}
+
+/// write the messages published to the client since its last request, false if it is gone
+pub fn write_pushes(pushes: &Receiver<Vec<u8>>, mut stream: &TcpStream) -> bool {
+    pushes
+        .try_iter()
+        .all(|push| stream.write_all(&push).is_ok())
+}
+
+/// run the request on the command queue, `None` if it could not be executed
+fn execute_request<T: Storage + Send + 'static>(
+    storage: &Arc<Mutex<T>>,
+    context: &Arc<ServerContext>,
+    client: &Arc<Mutex<Client>>,
+    request: &[u8],
+) -> Option<(bool, Vec<u8>)> {
+    let (reply_send, reply_recv) = bounded(1);
+    let job = {
+        let storage = storage.clone();
+        let context = context.clone();
+        let client = client.clone();
+        let request = request.to_vec();
+
+        move || {
+            let mut client = client.lock().unwrap_or_else(PoisonError::into_inner);
+            let res = run_command_and_get_response(&storage, &context, &mut client, &request);
+            context.clients.update(&client);
+            let _ = reply_send.send((res.is_quit(), res.reply()));
+        }
+    };
+
+    match context.command_queue.submit(job) {
+        // the job is dropped without replying when the command panics
+        true => Some(
+            reply_recv
+                .recv()
+                .unwrap_or_else(|_| (false, RedisCommandError::CommandPanicked.to_vec())),
+        ),
+        false => Some((false, RedisCommandError::Busy.to_vec())),
+    }
+}
+
+/// Wait for an element to be pushed to one of the keys of `waiter`. Return `Some(false)` once the
+/// deadline is passed and `None` if the client is gone or the server has been stopped.
+fn wait_until_woken_up(
+    waiter: &Waiter,
+    deadline: Option<Instant>,
+    stream: &TcpStream,
+    stopped: &AtomicBool,
+) -> Option<bool> {
+    let tick = Duration::from_millis(BLOCKED_CHECK_MILLIS);
+
+    loop {
+        let wait = match deadline {
+            Some(deadline) => match deadline.checked_duration_since(Instant::now()) {
+                Some(left) => left.min(tick),
+                None => return Some(false),
+            },
+            None => tick,
+        };
+
+        match waiter.wakeup.recv_timeout(wait) {
+            Ok(_) => return Some(true),
+            Err(RecvTimeoutError::Disconnected) => return None,
+            Err(RecvTimeoutError::Timeout) => {}
+        }
+
+        if stopped.load(Ordering::Relaxed) || is_closed(stream) {
+            return None;
+        }
+    }
+}
+
+/// check without blocking if the client has closed the connection
+fn is_closed(stream: &TcpStream) -> bool {
+    if stream.set_nonblocking(true).is_err() {
+        return true;
     }
 
-    let res = run_command_and_get_response(storage, &buf);
-    let quit = if res.is_quit() { true } else { false };
-    let reply = res.reply();
-    //eprintln!("?{}", std::str::from_utf8(&reply).unwrap());
-    let _ = stream.write(&reply);
+    let closed = match stream.peek(&mut [0; 1]) {
+        Ok(0) => true,
+        Ok(_) => false,
+        Err(err) => err.kind() != ErrorKind::WouldBlock,
+    };
 
-    (quit, 1)
+    stream.set_nonblocking(false).is_err() || closed
 }
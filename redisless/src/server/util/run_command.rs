@@ -1,211 +1,4699 @@
+use chrono::Utc;
+use rand::{
+    rngs::OsRng,
+    seq::{IteratorRandom, SliceRandom},
+    RngCore,
+};
 use std::{
-    collections::HashMap,
-    sync::{Arc, Mutex},
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex, TryLockError},
+    time::{Duration, Instant},
 };
 
-use chrono::format::format;
-
 use crate::{
-    command::Command,
+    command::{category, ClientKillFilter, Command, SortOptions},
+    glob::glob_match,
     protocol::response::{RedisResponse, RedisResponseType},
-    storage::{models::RedisString, Storage},
+    storage::{
+        dump,
+        models::{
+            bitmap::{self, BitFieldOperation, BitOverflow},
+            geo::{self, GeoMatch, GeoOrigin, GeoQuery},
+            Aggregate, Claim, ConsumerEntry, ConsumerGroup, Expiry, HyperLogLog, ListEnd,
+            RedisSortedSet, RedisStream, RedisString, RedisType, ScoreEnd, StreamFields, StreamId,
+            ZRangeBy, ZRangeQuery,
+        },
+        scan::scan,
+        Storage,
+    },
 };
 
 use super::*;
+use crate::server::acl;
+use crate::server::client::Client;
+use crate::server::context::ServerContext;
+use crate::server::functions::{self, Library};
+use crate::server::keyspace_events;
+use crate::server::scripting;
+use crate::server::transaction::{Savepoint, Transaction};
+
+// how often a command waiting for a transaction or a script to end checks whether it is busy
+const EXEC_LOCK_RETRY_MILLIS: u64 = 1;
 
 pub fn run_command_and_get_response<T: Storage>(
     storage: &Arc<Mutex<T>>,
-    bytes: &[u8; 512],
+    context: &Arc<ServerContext>,
+    client: &mut Client,
+    bytes: &[u8],
 ) -> RedisResponse {
-    use protocol::response::RedisResponseType::*;
-    let command = get_command(bytes);
-    let response = match command {
-        Ok(command) => match command {
-            Command::Set(k, v) => {
-                lock_then_release(storage).write(k.as_slice(), v.as_slice());
-                RedisResponse::okay()
+    match get_command(bytes) {
+        Ok((command, command_name)) => {
+            if !command.is_allowed_without_auth() {
+                if !client.authenticated {
+                    if context.acl.requires_auth() {
+                        return RedisResponse::error(RedisCommandError::NoAuth);
+                    }
+                    // the password of the default user has been removed since
+                    client.authenticated = true;
+                }
+
+                let permitted =
+                    context
+                        .acl
+                        .check_permissions(&client.user, &command_name, &command.keys());
+                if let Err(err) = permitted {
+                    return RedisResponse::error(err);
+                }
             }
-            Command::Append(k, v) => {
-                let len = lock_then_release(storage).extend(k.as_slice(), v.as_slice());
-                RedisResponse::single(Integer(len as i64))
+
+            if client.subscriptions.is_subscribed() && !command.is_allowed_when_subscribed() {
+                return RedisResponse::error(RedisCommandError::NotAllowedWhenSubscribed(
+                    command_name,
+                ));
             }
-            Command::Setex(k, expiry, v) | Command::PSetex(k, expiry, v) => {
-                let mut storage = lock_then_release(storage);
 
-                storage.write(k.as_slice(), v.as_slice());
-                storage.expire(k.as_slice(), expiry);
+            if let Some(transaction) = &mut client.transaction {
+                if command.is_queued_in_transaction() {
+                    transaction.commands.push(command);
+                    return RedisResponse::single(RedisResponseType::SimpleString(
+                        b"QUEUED".to_vec(),
+                    ));
+                }
+            }
 
-                RedisResponse::okay()
+            let started_at = Instant::now();
+            // SCRIPT KILL stops the running script rather than waiting for it
+            if let Command::ScriptKill = command {
+                return match context.running_script.kill() {
+                    Ok(()) => RedisResponse::okay(),
+                    Err(err) => RedisResponse::error(err),
+                };
             }
-            Command::Setnx(k, v) => {
-                let mut storage = lock_then_release(storage);
-                match storage.contains(&k[..]) {
-                    // Key exists, will not re set key
-                    true => RedisResponse::single(Integer(0)),
-                    // Key does not exist, will set key
-                    false => {
-                        storage.write(&k, &v);
-                        RedisResponse::single(Integer(1))
+
+            // the lock is held for reading, or for writing by an exclusive command, the other
+            // clients being replied -BUSY rather than waiting for a script running for too long
+            let exclusive = command.is_exclusive();
+            let (running, running_exclusive) = loop {
+                if exclusive {
+                    match context.exec_lock.try_write() {
+                        Ok(lock) => break (None, Some(lock)),
+                        Err(TryLockError::Poisoned(err)) => break (None, Some(err.into_inner())),
+                        Err(TryLockError::WouldBlock) => {}
                     }
+                } else {
+                    match context.exec_lock.try_read() {
+                        Ok(lock) => break (Some(lock), None),
+                        Err(TryLockError::Poisoned(err)) => break (Some(err.into_inner()), None),
+                        Err(TryLockError::WouldBlock) => {}
+                    }
+                }
+
+                if context.running_script.is_busy() {
+                    return RedisResponse::error(RedisCommandError::BusyScript);
                 }
+                thread::sleep(Duration::from_millis(EXEC_LOCK_RETRY_MILLIS));
+            };
+            let response = run_command(storage, context, client, command);
+            drop(running);
+            drop(running_exclusive);
+            notify_expired_keys(storage, context);
+
+            if let Ok(mut latency_histograms) = context.latency_histograms.lock() {
+                latency_histograms
+                    .entry(command_name)
+                    .or_default()
+                    .record(started_at.elapsed());
             }
-            Command::MSet(items) => {
-                let mut storage = lock_then_release(storage);
-                items.iter().for_each(|(k, v)| storage.write(k, v));
-                RedisResponse::okay()
+
+            response
+        }
+        Err(err) => {
+            // a transaction with a command that could not be queued is discarded by EXEC
+            if let Some(transaction) = &mut client.transaction {
+                transaction.aborted = true;
             }
-            Command::MSetnx(items) => {
-                // Either set all or not set any at all if any already exist
-                let mut storage = lock_then_release(storage);
-                match items.iter().all(|(key, _)| !storage.contains(key)) {
-                    // None of the keys already exist in the storage
-                    true => {
-                        items.iter().for_each(|(k, v)| storage.write(k, v));
-                        RedisResponse::single(Integer(1))
-                    }
-                    // Some key exists, don't write any of the keys
-                    false => RedisResponse::single(Integer(0)),
+            RedisResponse::error(err)
+        }
+    }
+}
+
+/// run a command of a transaction or of a script, a blocking command replying right away rather
+/// than blocking the client
+pub fn run_without_blocking<T: Storage>(
+    storage: &Arc<Mutex<T>>,
+    context: &Arc<ServerContext>,
+    client: &mut Client,
+    command: Command,
+) -> RedisResponse {
+    let response = run_command(storage, context, client, command);
+    if let Some(waiter) = client.blocked.take() {
+        context.blocked_clients.cancel(&waiter);
+        client.stream_read_ids = None;
+    }
+    response
+}
+
+fn run_command<T: Storage>(
+    storage: &Arc<Mutex<T>>,
+    context: &Arc<ServerContext>,
+    client: &mut Client,
+    command: Command,
+) -> RedisResponse {
+    use protocol::response::RedisResponseType::*;
+    match command {
+        Command::Set(k, v) => {
+            lock_db(storage, client.db).write(k.as_slice(), v.as_slice());
+            notify(context, client.db, keyspace_events::STRING, "set", &k);
+            RedisResponse::okay()
+        }
+        Command::Append(k, v) => {
+            let mut storage = lock_db(storage, client.db);
+            if is_wrong_type(&mut *storage, &k, RedisType::String) {
+                return RedisResponse::error(RedisCommandError::WrongType);
+            }
+
+            let len = storage.extend(k.as_slice(), v.as_slice());
+            notify(context, client.db, keyspace_events::STRING, "append", &k);
+            RedisResponse::single(Integer(len as i64))
+        }
+        Command::SetBit(k, offset, bit) => {
+            let mut storage = lock_db(storage, client.db);
+            match storage.string_mut(&k) {
+                Some(value) => {
+                    let previous = bitmap::set_bit(value, offset, bit);
+                    notify(context, client.db, keyspace_events::STRING, "setbit", &k);
+                    RedisResponse::single(Integer(previous as i64))
                 }
+                None => RedisResponse::error(RedisCommandError::WrongType),
             }
-            Command::Expire(k, expiry) | Command::PExpire(k, expiry) => {
-                let e = lock_then_release(storage).expire(k.as_slice(), expiry);
-                RedisResponse::single(Integer(e as i64))
+        }
+        Command::GetBit(k, offset) => {
+            let mut storage = lock_db(storage, client.db);
+            if is_wrong_type(&mut *storage, &k, RedisType::String) {
+                return RedisResponse::error(RedisCommandError::WrongType);
             }
-            Command::Get(k) => match lock_then_release(storage).read(k.as_slice()) {
-                Some(value) => RedisResponse::single(SimpleString(value.to_vec())),
-                None => RedisResponse::single(Nil),
-            },
-            Command::GetSet(k, v) => {
-                let mut storage = lock_then_release(storage);
 
-                let response = match storage.read(k.as_slice()) {
-                    Some(value) => RedisResponse::single(SimpleString(value.to_vec())),
-                    None => RedisResponse::single(Nil),
-                };
-                storage.write(k.as_slice(), v.as_slice());
-                response
-            }
-            Command::MGet(keys) => {
-                let mut storage = lock_then_release(storage);
-                let mut responses = Vec::<RedisResponseType>::with_capacity(keys.len());
-                for key in keys {
-                    let response = match storage.read(key.as_slice()) {
-                        Some(value) => RedisResponseType::SimpleString(value.to_vec()),
-                        None => RedisResponseType::Nil,
-                    };
-                    responses.push(response);
-                }
-                RedisResponse::array(responses)
+            let value = storage.read(&k).unwrap_or_default();
+            RedisResponse::single(Integer(bitmap::get_bit(value, offset) as i64))
+        }
+        Command::BitCount(k, range) => {
+            let mut storage = lock_db(storage, client.db);
+            if is_wrong_type(&mut *storage, &k, RedisType::String) {
+                return RedisResponse::error(RedisCommandError::WrongType);
             }
-            Command::HSet(map_key, items) => {
-                let mut hash_map = HashMap::<RedisString, RedisString>::with_capacity(items.len());
 
-                for (k, v) in items {
-                    hash_map.insert(k.to_vec(), v.to_vec());
-                }
+            let value = storage.read(&k).unwrap_or_default();
+            let range = match range {
+                Some(range) => range.bits(value.len()),
+                None if value.is_empty() => None,
+                None => Some((0, value.len() as u64 * 8 - 1)),
+            };
+            let count = range.map_or(0, |(start, end)| bitmap::count_bits(value, start, end));
+            RedisResponse::single(Integer(count as i64))
+        }
+        Command::BitPos(k, bit, start, end, unit) => {
+            let mut storage = lock_db(storage, client.db);
+            if is_wrong_type(&mut *storage, &k, RedisType::String) {
+                return RedisResponse::error(RedisCommandError::WrongType);
+            }
 
-                let mut storage = lock_then_release(storage);
-                storage.hwrite(&map_key, hash_map);
-                RedisResponse::okay()
+            let value = storage.read(&k).unwrap_or_default();
+            // a missing key is an empty string, which is made of clear bits
+            if value.is_empty() {
+                let position = match bit {
+                    0 => 0,
+                    _ => -1,
+                };
+                return RedisResponse::single(Integer(position));
             }
-            Command::HGet(map_key, field_key) => {
-                match lock_then_release(storage).hread(map_key.as_slice(), field_key.as_slice()) {
-                    Some(value) => RedisResponse::single(SimpleString(value.to_vec())),
-                    None => RedisResponse::single(Nil),
-                }
+
+            let range = bitmap::BitRange {
+                start,
+                end: end.unwrap_or(-1),
+                unit,
+            };
+            let position = match range.bits(value.len()) {
+                Some((first, last)) => match bitmap::position(value, bit, first, last) {
+                    Some(position) => position as i64,
+                    // with no end given the string is considered padded with clear bits
+                    None if bit == 0 && end.is_none() => value.len() as i64 * 8,
+                    None => -1,
+                },
+                None => -1,
+            };
+            RedisResponse::single(Integer(position))
+        }
+        Command::BitOp(operation, destination, source_keys) => {
+            let mut storage = lock_db(storage, client.db);
+            if source_keys
+                .iter()
+                .any(|key| is_wrong_type(&mut *storage, key, RedisType::String))
+            {
+                return RedisResponse::error(RedisCommandError::WrongType);
             }
-            Command::Del(k) => {
-                let d = lock_then_release(storage).remove(k.as_slice());
-                RedisResponse::single(Integer(d as i64))
+
+            let sources: Vec<_> = source_keys
+                .iter()
+                .map(|key| storage.read(key).unwrap_or_default().to_vec())
+                .collect();
+            let sources: Vec<_> = sources.iter().map(|source| source.as_slice()).collect();
+            let result = operation.apply(&sources);
+
+            // the destination is overwritten whatever its type, and deleted by an empty result
+            let removed = storage.remove(&destination);
+            if !result.is_empty() {
+                storage.write(&destination, &result);
+                notify(
+                    context,
+                    client.db,
+                    keyspace_events::STRING,
+                    "set",
+                    &destination,
+                );
+            } else if removed > 0 {
+                notify(
+                    context,
+                    client.db,
+                    keyspace_events::GENERIC,
+                    "del",
+                    &destination,
+                );
             }
-            Command::Incr(k) => {
-                let mut storage = lock_then_release(storage);
+            RedisResponse::single(Integer(result.len() as i64))
+        }
+        Command::BitField(k, operations) => {
+            let mut storage = lock_db(storage, client.db);
+            if is_wrong_type(&mut *storage, &k, RedisType::String) {
+                return RedisResponse::error(RedisCommandError::WrongType);
+            }
+
+            // the string is grown up front to hold every field written, while reading
+            // only leaves it untouched
+            let written = operations
+                .iter()
+                .filter_map(|operation| match operation {
+                    BitFieldOperation::Set(field, offset, _)
+                    | BitFieldOperation::IncrBy(field, offset, _) => {
+                        Some(offset + field.bits as u64)
+                    }
+                    _ => None,
+                })
+                .max();
+            let mut read_only;
+            let bytes = match written {
+                Some(end) => {
+                    let bytes = storage.string_mut(&k).unwrap();
+                    let len = end.div_ceil(8) as usize;
+                    if bytes.len() < len {
+                        bytes.resize(len, 0);
+                    }
+                    bytes
+                }
+                None => {
+                    read_only = storage.read(&k).unwrap_or_default().to_vec();
+                    &mut read_only
+                }
+            };
 
-                match storage.read(k.as_slice()) {
+            let mut overflow = BitOverflow::Wrap;
+            let mut replies = vec![];
+            for operation in operations {
+                let (field, offset, value) = match operation {
+                    BitFieldOperation::Get(field, offset) => {
+                        replies.push(Integer(bitmap::get_field(bytes, offset, field)));
+                        continue;
+                    }
+                    BitFieldOperation::Overflow(behavior) => {
+                        overflow = behavior;
+                        continue;
+                    }
+                    BitFieldOperation::Set(field, offset, value) => {
+                        (field, offset, field.fit(value as i128, overflow))
+                    }
+                    BitFieldOperation::IncrBy(field, offset, increment) => {
+                        let value = bitmap::get_field(bytes, offset, field) as i128;
+                        (
+                            field,
+                            offset,
+                            field.fit(value + increment as i128, overflow),
+                        )
+                    }
+                };
+
+                match value {
                     Some(value) => {
-                        if let Ok(mut int_val) = std::str::from_utf8(value).unwrap().parse::<i64>()
-                        {
-                            int_val += 1;
-                            let new_value = int_val.to_string().into_bytes();
-                            storage.write(k.as_slice(), new_value.as_slice());
-                            RedisResponse::single(Integer(int_val as i64))
-                        } else {
-                            // handle this error
-                            unimplemented!()
+                        let previous = bitmap::get_field(bytes, offset, field);
+                        bitmap::set_field(bytes, offset, field, value);
+                        // SET replies with the previous value and INCRBY with the new one
+                        match operation {
+                            BitFieldOperation::Set(..) => replies.push(Integer(previous)),
+                            _ => replies.push(Integer(value)),
                         }
                     }
-                    None => {
-                        let val = "1";
-                        storage.write(&k, val.as_bytes());
-                        RedisResponse::single(Integer(1))
+                    None => replies.push(Nil),
+                }
+            }
+            if written.is_some() {
+                notify(context, client.db, keyspace_events::STRING, "setbit", &k);
+            }
+            RedisResponse::array(replies)
+        }
+        Command::PfAdd(k, elements) => {
+            let mut storage = lock_db(storage, client.db);
+            let (mut hll, mut changed) = match read_hyperloglog(&mut *storage, &k) {
+                Ok(Some(hll)) => (hll, false),
+                Ok(None) => (HyperLogLog::new(), true),
+                Err(err) => return RedisResponse::error(err),
+            };
+
+            for element in elements {
+                changed |= hll.add(&element);
+            }
+            if changed {
+                *storage.string_mut(&k).unwrap() = hll.to_bytes();
+                notify(context, client.db, keyspace_events::STRING, "pfadd", &k);
+            }
+            RedisResponse::single(Integer(changed as i64))
+        }
+        Command::PfCount(keys) => {
+            let mut storage = lock_db(storage, client.db);
+            // several keys are counted as their union
+            let mut union = HyperLogLog::new();
+            for key in keys {
+                match read_hyperloglog(&mut *storage, &key) {
+                    Ok(Some(hll)) => union.merge(&hll),
+                    Ok(None) => {}
+                    Err(err) => return RedisResponse::error(err),
+                }
+            }
+            RedisResponse::single(Integer(union.count() as i64))
+        }
+        Command::PfMerge(destination, source_keys) => {
+            let mut storage = lock_db(storage, client.db);
+            // the destination is part of the union
+            let mut union = HyperLogLog::new();
+            for key in std::iter::once(&destination).chain(&source_keys) {
+                match read_hyperloglog(&mut *storage, key) {
+                    Ok(Some(hll)) => union.merge(&hll),
+                    Ok(None) => {}
+                    Err(err) => return RedisResponse::error(err),
+                }
+            }
+
+            *storage.string_mut(&destination).unwrap() = union.to_bytes();
+            notify(
+                context,
+                client.db,
+                keyspace_events::STRING,
+                "pfadd",
+                &destination,
+            );
+            RedisResponse::okay()
+        }
+        Command::XAdd(stream_key, no_mkstream, trim, id, fields) => {
+            let mut storage = lock_db(storage, client.db);
+            if is_wrong_type(&mut *storage, &stream_key, RedisType::Stream) {
+                return RedisResponse::error(RedisCommandError::WrongType);
+            }
+            if no_mkstream && !storage.contains(&stream_key) {
+                return RedisResponse::single(Nil);
+            }
+
+            let stream = storage.stream_mut(&stream_key).unwrap();
+            if stream.last_id() == StreamId::MAX {
+                return RedisResponse::error(RedisCommandError::StreamExhausted);
+            }
+            let id = match stream.next_id(id, now_millis()) {
+                Some(id) => id,
+                None => {
+                    if stream.is_empty() {
+                        storage.remove(&stream_key);
                     }
+                    return RedisResponse::error(RedisCommandError::StreamIdTooSmall);
                 }
+            };
+
+            stream.add(id, fields);
+            let trimmed = trim.map_or(0, |trim| stream.trim(trim));
+            context.blocked_clients.notify(client.db, &stream_key);
+            notify(
+                context,
+                client.db,
+                keyspace_events::STREAM,
+                "xadd",
+                &stream_key,
+            );
+            if trimmed > 0 {
+                notify(
+                    context,
+                    client.db,
+                    keyspace_events::STREAM,
+                    "xtrim",
+                    &stream_key,
+                );
+            }
+            RedisResponse::single(BulkString(id.to_string().into_bytes()))
+        }
+        Command::XTrim(stream_key, trim) => {
+            let mut storage = lock_db(storage, client.db);
+            if is_wrong_type(&mut *storage, &stream_key, RedisType::Stream) {
+                return RedisResponse::error(RedisCommandError::WrongType);
             }
-            Command::IncrBy(k, increment) => {
-                let mut storage = lock_then_release(storage);
 
-                match storage.read(k.as_slice()) {
-                    Some(value) => {
-                        if let Ok(mut int_val) = std::str::from_utf8(value).unwrap().parse::<i64>()
-                        {
-                            int_val += increment;
-                            let new_value = int_val.to_string().into_bytes();
-                            storage.write(k.as_slice(), new_value.as_slice());
-                            RedisResponse::single(Integer(int_val as i64))
-                        } else {
-                            //RedisResponse::error(...)
-                            unimplemented!()
-                        }
+            let trimmed = match storage.contains(&stream_key) {
+                true => storage.stream_mut(&stream_key).unwrap().trim(trim),
+                false => 0,
+            };
+            if trimmed > 0 {
+                notify(
+                    context,
+                    client.db,
+                    keyspace_events::STREAM,
+                    "xtrim",
+                    &stream_key,
+                );
+            }
+            RedisResponse::single(Integer(trimmed as i64))
+        }
+        Command::XDel(stream_key, ids) => {
+            let mut storage = lock_db(storage, client.db);
+            if is_wrong_type(&mut *storage, &stream_key, RedisType::Stream) {
+                return RedisResponse::error(RedisCommandError::WrongType);
+            }
+
+            let deleted = match storage.contains(&stream_key) {
+                true => {
+                    let stream = storage.stream_mut(&stream_key).unwrap();
+                    ids.iter().filter(|id| stream.delete(id)).count()
+                }
+                false => 0,
+            };
+            if deleted > 0 {
+                notify(
+                    context,
+                    client.db,
+                    keyspace_events::STREAM,
+                    "xdel",
+                    &stream_key,
+                );
+            }
+            RedisResponse::single(Integer(deleted as i64))
+        }
+        Command::XLen(stream_key) => {
+            let mut storage = lock_db(storage, client.db);
+            if is_wrong_type(&mut *storage, &stream_key, RedisType::Stream) {
+                return RedisResponse::error(RedisCommandError::WrongType);
+            }
+
+            let len = storage.stream(&stream_key).map_or(0, RedisStream::len);
+            RedisResponse::single(Integer(len as i64))
+        }
+        Command::XRead(stream_keys, ids, count, block) => {
+            let mut storage = lock_db(storage, client.db);
+            let ids = match client.stream_read_ids.take() {
+                Some(ids) => ids,
+                None => match stream_read_ids(&mut *storage, &stream_keys, ids) {
+                    Ok(ids) => ids,
+                    Err(err) => return RedisResponse::error(err),
+                },
+            };
+
+            let streams = match stream_read(&mut *storage, &stream_keys, &ids, count) {
+                Ok(streams) => streams,
+                Err(err) => return RedisResponse::error(err),
+            };
+            match (block, streams.is_empty()) {
+                (Some(timeout), true) => {
+                    // registered while the storage is locked so that no addition can be missed
+                    client.blocked = Some(context.blocked_clients.wait(
+                        client.db,
+                        &stream_keys,
+                        timeout,
+                    ));
+                    client.stream_read_ids = Some(ids);
+                    RedisResponse::single(NilArray)
+                }
+                (None, true) => RedisResponse::single(NilArray),
+                (_, false) => RedisResponse::array(streams),
+            }
+        }
+        Command::XGroupCreate(stream_key, group, id, mk_stream) => {
+            let mut storage = lock_db(storage, client.db);
+            if is_wrong_type(&mut *storage, &stream_key, RedisType::Stream) {
+                return RedisResponse::error(RedisCommandError::WrongType);
+            }
+            if !mk_stream && !storage.contains(&stream_key) {
+                return RedisResponse::error(RedisCommandError::GroupKeyMissing);
+            }
+
+            let stream = storage.stream_mut(&stream_key).unwrap();
+            if stream.groups.contains_key(&group) {
+                return RedisResponse::error(RedisCommandError::BusyGroup);
+            }
+
+            let last_delivered_id = id.unwrap_or_else(|| stream.last_id());
+            let entries_read = stream.entries_read_until(last_delivered_id);
+            stream
+                .groups
+                .insert(group, ConsumerGroup::new(last_delivered_id, entries_read));
+            notify(
+                context,
+                client.db,
+                keyspace_events::STREAM,
+                "xgroup-create",
+                &stream_key,
+            );
+            RedisResponse::okay()
+        }
+        Command::XGroupSetId(stream_key, group, id) => {
+            let mut storage = lock_db(storage, client.db);
+            match xgroup_stream(&mut *storage, &stream_key, &group) {
+                Ok(stream) => {
+                    let last_delivered_id = id.unwrap_or_else(|| stream.last_id());
+                    let entries_read = stream.entries_read_until(last_delivered_id);
+                    let group = stream.groups.get_mut(&group).unwrap();
+                    group.last_delivered_id = last_delivered_id;
+                    group.entries_read = entries_read;
+                    notify(
+                        context,
+                        client.db,
+                        keyspace_events::STREAM,
+                        "xgroup-setid",
+                        &stream_key,
+                    );
+                    RedisResponse::okay()
+                }
+                Err(err) => RedisResponse::error(err),
+            }
+        }
+        Command::XGroupDestroy(stream_key, group) => {
+            let mut storage = lock_db(storage, client.db);
+            match xgroup_stream(&mut *storage, &stream_key, &group) {
+                Ok(stream) => {
+                    stream.groups.remove(&group);
+                    // the clients blocked on the group get an error
+                    context.blocked_clients.notify(client.db, &stream_key);
+                    notify(
+                        context,
+                        client.db,
+                        keyspace_events::STREAM,
+                        "xgroup-destroy",
+                        &stream_key,
+                    );
+                    RedisResponse::single(Integer(1))
+                }
+                Err(RedisCommandError::NoGroup(_, _)) => RedisResponse::single(Integer(0)),
+                Err(err) => RedisResponse::error(err),
+            }
+        }
+        Command::XGroupCreateConsumer(stream_key, group, consumer) => {
+            let mut storage = lock_db(storage, client.db);
+            match xgroup_stream(&mut *storage, &stream_key, &group) {
+                Ok(stream) => {
+                    let group = stream.groups.get_mut(&group).unwrap();
+                    let created = group.create_consumer(&consumer, now_millis());
+                    if created {
+                        let event = "xgroup-createconsumer";
+                        notify(
+                            context,
+                            client.db,
+                            keyspace_events::STREAM,
+                            event,
+                            &stream_key,
+                        );
                     }
-                    None => {
-                        let val = increment.to_string();
-                        storage.write(&k, val.as_bytes());
-                        RedisResponse::single(Integer(increment))
+                    RedisResponse::single(Integer(created as i64))
+                }
+                Err(err) => RedisResponse::error(err),
+            }
+        }
+        Command::XGroupDelConsumer(stream_key, group, consumer) => {
+            let mut storage = lock_db(storage, client.db);
+            match xgroup_stream(&mut *storage, &stream_key, &group) {
+                Ok(stream) => {
+                    let group = stream.groups.get_mut(&group).unwrap();
+                    let pending = group.delete_consumer(&consumer);
+                    if pending.is_some() {
+                        let event = "xgroup-delconsumer";
+                        notify(
+                            context,
+                            client.db,
+                            keyspace_events::STREAM,
+                            event,
+                            &stream_key,
+                        );
                     }
+                    RedisResponse::single(Integer(pending.unwrap_or(0) as i64))
                 }
+                Err(err) => RedisResponse::error(err),
             }
-            Command::Exists(k) => {
-                let exists = lock_then_release(storage).contains(&k);
-                let exists: i64 = match exists {
-                    true => 1,
-                    false => 0,
-                };
-                RedisResponse::single(Integer(exists))
+        }
+        Command::XReadGroup(group, consumer, stream_keys, ids, count, block, no_ack) => {
+            let mut storage = lock_db(storage, client.db);
+            for stream_key in &stream_keys {
+                if let Err(err) = group_stream(&mut *storage, stream_key, &group) {
+                    return RedisResponse::error(err);
+                }
+            }
+
+            // the pending entries of the consumer are read right away, even if there are none
+            let history = ids.iter().any(Option::is_some);
+            let now = now_millis();
+            let mut streams = vec![];
+            for (stream_key, id) in stream_keys.iter().zip(ids) {
+                let stream = storage.stream_mut(stream_key).unwrap();
+                let entries = stream
+                    .read_group(&group, &consumer, id, count, no_ack, now)
+                    .unwrap();
+                if history || !entries.is_empty() {
+                    streams.push(Array(vec![
+                        BulkString(stream_key.to_vec()),
+                        Array(consumer_entries_reply(entries)),
+                    ]));
+                }
+            }
+
+            match (block, streams.is_empty()) {
+                (Some(timeout), true) if !history => {
+                    // registered while the storage is locked so that no addition can be missed
+                    client.blocked = Some(context.blocked_clients.wait(
+                        client.db,
+                        &stream_keys,
+                        timeout,
+                    ));
+                    RedisResponse::single(NilArray)
+                }
+                (_, true) => RedisResponse::single(NilArray),
+                (_, false) => RedisResponse::array(streams),
+            }
+        }
+        Command::XAck(stream_key, group, ids) => {
+            let mut storage = lock_db(storage, client.db);
+            match group_stream(&mut *storage, &stream_key, &group) {
+                Ok(stream) => {
+                    let group = stream.groups.get_mut(&group).unwrap();
+                    let acknowledged = ids.into_iter().filter(|id| group.ack(*id)).count();
+                    RedisResponse::single(Integer(acknowledged as i64))
+                }
+                Err(RedisCommandError::NoGroup(_, _)) => RedisResponse::single(Integer(0)),
+                Err(err) => RedisResponse::error(err),
+            }
+        }
+        Command::XInfoStream(stream_key) => {
+            let mut storage = lock_db(storage, client.db);
+            match info_stream(&mut *storage, &stream_key) {
+                Ok(stream) => RedisResponse::array(stream_info_reply(stream, None)),
+                Err(err) => RedisResponse::error(err),
+            }
+        }
+        Command::XInfoStreamFull(stream_key, count) => {
+            let mut storage = lock_db(storage, client.db);
+            match info_stream(&mut *storage, &stream_key) {
+                Ok(stream) => RedisResponse::array(stream_info_reply(stream, Some(count))),
+                Err(err) => RedisResponse::error(err),
+            }
+        }
+        Command::XInfoGroups(stream_key) => {
+            let mut storage = lock_db(storage, client.db);
+            let stream = match info_stream(&mut *storage, &stream_key) {
+                Ok(stream) => stream,
+                Err(err) => return RedisResponse::error(err),
+            };
+
+            let groups = stream
+                .groups
+                .iter()
+                .map(|(name, group)| {
+                    Array(vec![
+                        BulkString(b"name".to_vec()),
+                        BulkString(name.to_vec()),
+                        BulkString(b"consumers".to_vec()),
+                        Integer(group.consumers.len() as i64),
+                        BulkString(b"pending".to_vec()),
+                        Integer(group.pending.len() as i64),
+                        BulkString(b"last-delivered-id".to_vec()),
+                        BulkString(group.last_delivered_id.to_string().into_bytes()),
+                        BulkString(b"entries-read".to_vec()),
+                        group.entries_read.map_or(Nil, |read| Integer(read as i64)),
+                        BulkString(b"lag".to_vec()),
+                        stream.lag(group).map_or(Nil, |lag| Integer(lag as i64)),
+                    ])
+                })
+                .collect();
+            RedisResponse::array(groups)
+        }
+        Command::XInfoConsumers(stream_key, group) => {
+            let mut storage = lock_db(storage, client.db);
+            let stream = match group_stream(&mut *storage, &stream_key, &group) {
+                Ok(stream) => stream,
+                Err(err) => return RedisResponse::error(err),
+            };
+
+            let now = now_millis();
+            let consumers = stream.groups[&group]
+                .consumers
+                .iter()
+                .map(|(name, consumer)| {
+                    let inactive = consumer
+                        .active_time
+                        .map_or(-1, |active_time| now.saturating_sub(active_time) as i64);
+                    Array(vec![
+                        BulkString(b"name".to_vec()),
+                        BulkString(name.to_vec()),
+                        BulkString(b"pending".to_vec()),
+                        Integer(consumer.pending.len() as i64),
+                        BulkString(b"idle".to_vec()),
+                        Integer(now.saturating_sub(consumer.seen_time) as i64),
+                        BulkString(b"inactive".to_vec()),
+                        Integer(inactive),
+                    ])
+                })
+                .collect();
+            RedisResponse::array(consumers)
+        }
+        Command::XSetId(stream_key, last_id, entries_added, max_deleted_id) => {
+            let mut storage = lock_db(storage, client.db);
+            let stream = match info_stream(&mut *storage, &stream_key) {
+                Ok(stream) => stream,
+                Err(err) => return RedisResponse::error(err),
+            };
+
+            if stream.last_entry().is_some_and(|(id, _)| last_id < *id) {
+                return RedisResponse::error(RedisCommandError::SetIdTooSmall);
+            }
+            if entries_added.is_some_and(|added| added < stream.len() as u64) {
+                return RedisResponse::error(RedisCommandError::SetIdEntriesAdded);
+            }
+            if max_deleted_id.is_some_and(|id| last_id < id) {
+                return RedisResponse::error(RedisCommandError::SetIdMaxDeleted);
+            }
+
+            storage
+                .stream_mut(&stream_key)
+                .unwrap()
+                .set_id(last_id, entries_added, max_deleted_id);
+            notify(
+                context,
+                client.db,
+                keyspace_events::STREAM,
+                "xsetid",
+                &stream_key,
+            );
+            RedisResponse::okay()
+        }
+        Command::XPending(stream_key, group) => {
+            let mut storage = lock_db(storage, client.db);
+            let stream = match group_stream(&mut *storage, &stream_key, &group) {
+                Ok(stream) => stream,
+                Err(err) => return RedisResponse::error(err),
+            };
+
+            let group = &stream.groups[&group];
+            let (first, last) = match (group.pending.keys().next(), group.pending.keys().last()) {
+                (Some(first), Some(last)) => (first, last),
+                _ => return RedisResponse::array(vec![Integer(0), Nil, Nil, Nil]),
+            };
+            let consumers = group
+                .consumers
+                .iter()
+                .filter(|(_, consumer)| !consumer.pending.is_empty())
+                .map(|(name, consumer)| {
+                    Array(vec![
+                        BulkString(name.to_vec()),
+                        BulkString(consumer.pending.len().to_string().into_bytes()),
+                    ])
+                })
+                .collect();
+
+            RedisResponse::array(vec![
+                Integer(group.pending.len() as i64),
+                BulkString(first.to_string().into_bytes()),
+                BulkString(last.to_string().into_bytes()),
+                Array(consumers),
+            ])
+        }
+        Command::XPendingRange(stream_key, group, start, end, count, min_idle, consumer) => {
+            let mut storage = lock_db(storage, client.db);
+            let stream = match group_stream(&mut *storage, &stream_key, &group) {
+                Ok(stream) => stream,
+                Err(err) => return RedisResponse::error(err),
+            };
+            if start > end {
+                return RedisResponse::array(vec![]);
+            }
+
+            let now = now_millis();
+            let entries = stream.groups[&group]
+                .pending
+                .range(start..=end)
+                .filter(|(_, entry)| consumer.as_ref().is_none_or(|name| entry.consumer == *name))
+                .map(|(id, entry)| (id, entry, now.saturating_sub(entry.delivery_time)))
+                .filter(|(_, _, idle)| min_idle.is_none_or(|min_idle| *idle >= min_idle))
+                .take(count)
+                .map(|(id, entry, idle)| {
+                    Array(vec![
+                        BulkString(id.to_string().into_bytes()),
+                        BulkString(entry.consumer.to_vec()),
+                        Integer(idle as i64),
+                        Integer(entry.delivery_count as i64),
+                    ])
+                })
+                .collect();
+            RedisResponse::array(entries)
+        }
+        Command::XClaim(stream_key, group, consumer, ids, options, last_id) => {
+            let mut storage = lock_db(storage, client.db);
+            let stream = match group_stream(&mut *storage, &stream_key, &group) {
+                Ok(stream) => stream,
+                Err(err) => return RedisResponse::error(err),
+            };
+
+            let now = now_millis();
+            stream
+                .groups
+                .get_mut(&group)
+                .unwrap()
+                .touch_consumer(&consumer, now);
+            let claims = ids
+                .into_iter()
+                .map(|id| stream.claim(&group, &consumer, id, now, &options).unwrap())
+                .collect::<Vec<_>>();
+
+            let group = stream.groups.get_mut(&group).unwrap();
+            if let Some(last_id) = last_id.filter(|id| *id > group.last_delivered_id) {
+                group.last_delivered_id = last_id;
             }
-            Command::Ttl(k) => {
-                let ttl = if let Some(meta) = lock_then_release(storage).meta(&k) {
-                    if let Some(expiry) = meta.expiry {
-                        expiry.duration_left_millis() / 1000
-                    } else {
-                        -1
+
+            RedisResponse::array(claims_reply(claims, options.just_id).0)
+        }
+        Command::XAutoClaim(stream_key, group, consumer, start, count, options) => {
+            let mut storage = lock_db(storage, client.db);
+            let stream = match group_stream(&mut *storage, &stream_key, &group) {
+                Ok(stream) => stream,
+                Err(err) => return RedisResponse::error(err),
+            };
+
+            let now = now_millis();
+            stream
+                .groups
+                .get_mut(&group)
+                .unwrap()
+                .touch_consumer(&consumer, now);
+            let (next, claims) = stream
+                .auto_claim(&group, &consumer, start, count, now, &options)
+                .unwrap();
+
+            let (claimed, deleted) = claims_reply(claims, options.just_id);
+            RedisResponse::array(vec![
+                BulkString(next.to_string().into_bytes()),
+                Array(claimed),
+                Array(deleted),
+            ])
+        }
+        Command::XRange(stream_key, start, end, count) => stream_range(
+            &mut *lock_db(storage, client.db),
+            &stream_key,
+            start,
+            end,
+            count,
+            false,
+        ),
+        Command::XRevRange(stream_key, start, end, count) => stream_range(
+            &mut *lock_db(storage, client.db),
+            &stream_key,
+            start,
+            end,
+            count,
+            true,
+        ),
+        Command::Setex(k, expiry, v) | Command::PSetex(k, expiry, v) => {
+            let mut storage = lock_db(storage, client.db);
+
+            storage.write(k.as_slice(), v.as_slice());
+            storage.expire(k.as_slice(), expiry);
+            notify(context, client.db, keyspace_events::STRING, "set", &k);
+            notify(context, client.db, keyspace_events::GENERIC, "expire", &k);
+
+            RedisResponse::okay()
+        }
+        Command::Setnx(k, v) => {
+            let mut storage = lock_db(storage, client.db);
+            match storage.contains(&k[..]) {
+                // Key exists, will not re set key
+                true => RedisResponse::single(Integer(0)),
+                // Key does not exist, will set key
+                false => {
+                    storage.write(&k, &v);
+                    notify(context, client.db, keyspace_events::STRING, "set", &k);
+                    RedisResponse::single(Integer(1))
+                }
+            }
+        }
+        Command::MSet(items) => {
+            let mut storage = lock_db(storage, client.db);
+            for (k, v) in &items {
+                storage.write(k, v);
+                notify(context, client.db, keyspace_events::STRING, "set", k);
+            }
+            RedisResponse::okay()
+        }
+        Command::MSetnx(items) => {
+            // Either set all or not set any at all if any already exist
+            let mut storage = lock_db(storage, client.db);
+            match items.iter().all(|(key, _)| !storage.contains(key)) {
+                // None of the keys already exist in the storage
+                true => {
+                    for (k, v) in &items {
+                        storage.write(k, v);
+                        notify(context, client.db, keyspace_events::STRING, "set", k);
                     }
-                } else {
-                    -2
+                    RedisResponse::single(Integer(1))
+                }
+                // Some key exists, don't write any of the keys
+                false => RedisResponse::single(Integer(0)),
+            }
+        }
+        Command::Expire(k, expiry) | Command::PExpire(k, expiry) => {
+            let e = lock_db(storage, client.db).expire(k.as_slice(), expiry);
+            if e > 0 {
+                notify(context, client.db, keyspace_events::GENERIC, "expire", &k);
+            }
+            RedisResponse::single(Integer(e as i64))
+        }
+        Command::Get(k) => {
+            if injected_miss(context, &k) {
+                return RedisResponse::single(Nil);
+            }
+
+            let mut storage = lock_db(storage, client.db);
+            if is_wrong_type(&mut *storage, &k, RedisType::String) {
+                return RedisResponse::error(RedisCommandError::WrongType);
+            }
+
+            match storage.read(k.as_slice()) {
+                Some(value) => RedisResponse::single(SimpleString(value.to_vec())),
+                None => RedisResponse::single(Nil),
+            }
+        }
+        Command::GetSet(k, v) => {
+            let mut storage = lock_db(storage, client.db);
+            if is_wrong_type(&mut *storage, &k, RedisType::String) {
+                return RedisResponse::error(RedisCommandError::WrongType);
+            }
+
+            let response = match storage.read(k.as_slice()) {
+                Some(value) => RedisResponse::single(SimpleString(value.to_vec())),
+                None => RedisResponse::single(Nil),
+            };
+            storage.write(k.as_slice(), v.as_slice());
+            notify(context, client.db, keyspace_events::STRING, "set", &k);
+            response
+        }
+        Command::MGet(keys) => {
+            let mut storage = lock_db(storage, client.db);
+            let mut responses = Vec::<RedisResponseType>::with_capacity(keys.len());
+            for key in keys {
+                if injected_miss(context, &key) {
+                    responses.push(RedisResponseType::Nil);
+                    continue;
+                }
+
+                let response = match storage.read(key.as_slice()) {
+                    Some(value) => RedisResponseType::SimpleString(value.to_vec()),
+                    None => RedisResponseType::Nil,
                 };
-                RedisResponse::single(Integer(ttl))
+                responses.push(response);
+            }
+            RedisResponse::array(responses)
+        }
+        Command::HSet(map_key, items) => {
+            let mut storage = lock_db(storage, client.db);
+            let hash = match storage.hash_mut(&map_key) {
+                Some(hash) => hash,
+                None => return RedisResponse::error(RedisCommandError::WrongType),
+            };
+
+            let added = items
+                .into_iter()
+                .filter(|(k, v)| hash.data.insert(k.to_vec(), v.to_vec()).is_none())
+                .count();
+            notify(context, client.db, keyspace_events::HASH, "hset", &map_key);
+            RedisResponse::single(Integer(added as i64))
+        }
+        Command::HMSet(map_key, items) => {
+            let mut storage = lock_db(storage, client.db);
+            let hash = match storage.hash_mut(&map_key) {
+                Some(hash) => hash,
+                None => return RedisResponse::error(RedisCommandError::WrongType),
+            };
+
+            hash.data.extend(items);
+            notify(context, client.db, keyspace_events::HASH, "hset", &map_key);
+            RedisResponse::okay()
+        }
+        Command::HSetnx(map_key, field_key, value) => {
+            let mut storage = lock_db(storage, client.db);
+            let hash = match storage.hash_mut(&map_key) {
+                Some(hash) => hash,
+                None => return RedisResponse::error(RedisCommandError::WrongType),
+            };
+
+            match hash.data.contains_key(&field_key) {
+                true => RedisResponse::single(Integer(0)),
+                false => {
+                    hash.data.insert(field_key, value);
+                    notify(context, client.db, keyspace_events::HASH, "hset", &map_key);
+                    RedisResponse::single(Integer(1))
+                }
+            }
+        }
+        Command::HGet(map_key, field_key) => {
+            let mut storage = lock_db(storage, client.db);
+            if is_wrong_type(&mut *storage, &map_key, RedisType::Hash) {
+                return RedisResponse::error(RedisCommandError::WrongType);
+            }
+
+            match storage.hread(map_key.as_slice(), field_key.as_slice()) {
+                Some(value) => RedisResponse::single(SimpleString(value.to_vec())),
+                None => RedisResponse::single(Nil),
+            }
+        }
+        Command::HDel(map_key, field_keys) => {
+            let mut storage = lock_db(storage, client.db);
+            if !storage.contains(&map_key) {
+                return RedisResponse::single(Integer(0));
+            }
+
+            let hash = match storage.hash_mut(&map_key) {
+                Some(hash) => hash,
+                None => return RedisResponse::error(RedisCommandError::WrongType),
+            };
+            let removed = field_keys
+                .iter()
+                .filter(|field_key| hash.data.remove(*field_key).is_some())
+                .count();
+
+            if removed > 0 {
+                notify(context, client.db, keyspace_events::HASH, "hdel", &map_key);
+            }
+            // a hash without fields does not exist
+            if hash.data.is_empty() {
+                storage.remove(&map_key);
+                notify(
+                    context,
+                    client.db,
+                    keyspace_events::GENERIC,
+                    "del",
+                    &map_key,
+                );
+            }
+            RedisResponse::single(Integer(removed as i64))
+        }
+        Command::HMGet(map_key, field_keys) => {
+            let mut storage = lock_db(storage, client.db);
+            if is_wrong_type(&mut *storage, &map_key, RedisType::Hash) {
+                return RedisResponse::error(RedisCommandError::WrongType);
+            }
+
+            let hash = storage.hash(&map_key);
+            let values = field_keys
+                .iter()
+                .map(
+                    |field_key| match hash.and_then(|hash| hash.data.get(field_key)) {
+                        Some(value) => BulkString(value.to_vec()),
+                        None => Nil,
+                    },
+                )
+                .collect();
+            RedisResponse::array(values)
+        }
+        Command::HIncrBy(map_key, field_key, increment) => {
+            let mut storage = lock_db(storage, client.db);
+            let hash = match storage.hash_mut(&map_key) {
+                Some(hash) => hash,
+                None => return RedisResponse::error(RedisCommandError::WrongType),
+            };
+
+            let value = match hash.data.get(&field_key) {
+                Some(value) => match std::str::from_utf8(value).map(str::parse::<i64>) {
+                    Ok(Ok(value)) => value,
+                    _ => return RedisResponse::error(RedisCommandError::HashValueNotInteger),
+                },
+                None => 0,
+            };
+
+            match value.checked_add(increment) {
+                Some(value) => {
+                    hash.data.insert(field_key, value.to_string().into_bytes());
+                    notify(
+                        context,
+                        client.db,
+                        keyspace_events::HASH,
+                        "hincrby",
+                        &map_key,
+                    );
+                    RedisResponse::single(Integer(value))
+                }
+                None => RedisResponse::error(RedisCommandError::IncrementOverflow),
+            }
+        }
+        Command::HIncrByFloat(map_key, field_key, increment) => {
+            let mut storage = lock_db(storage, client.db);
+            let hash = match storage.hash_mut(&map_key) {
+                Some(hash) => hash,
+                None => return RedisResponse::error(RedisCommandError::WrongType),
+            };
+
+            let value = match hash.data.get(&field_key) {
+                Some(value) => match std::str::from_utf8(value).map(str::parse::<f64>) {
+                    Ok(Ok(value)) if !value.is_nan() => value,
+                    _ => return RedisResponse::error(RedisCommandError::HashValueNotFloat),
+                },
+                None => 0.0,
+            };
+
+            let value = value + increment;
+            if !value.is_finite() {
+                return RedisResponse::error(RedisCommandError::NanOrInfinity);
+            }
+
+            let value = value.to_string().into_bytes();
+            hash.data.insert(field_key, value.clone());
+            notify(
+                context,
+                client.db,
+                keyspace_events::HASH,
+                "hincrbyfloat",
+                &map_key,
+            );
+            RedisResponse::single(BulkString(value))
+        }
+        Command::HGetAll(map_key) => {
+            let mut storage = lock_db(storage, client.db);
+            if is_wrong_type(&mut *storage, &map_key, RedisType::Hash) {
+                return RedisResponse::error(RedisCommandError::WrongType);
+            }
+
+            let items = match storage.hash(&map_key) {
+                Some(hash) => hash
+                    .data
+                    .iter()
+                    .flat_map(|(k, v)| vec![BulkString(k.to_vec()), BulkString(v.to_vec())])
+                    .collect(),
+                None => vec![],
+            };
+            RedisResponse::array(items)
+        }
+        Command::HKeys(map_key) => {
+            let mut storage = lock_db(storage, client.db);
+            if is_wrong_type(&mut *storage, &map_key, RedisType::Hash) {
+                return RedisResponse::error(RedisCommandError::WrongType);
+            }
+
+            let fields = match storage.hash(&map_key) {
+                Some(hash) => hash.data.keys().map(|k| BulkString(k.to_vec())).collect(),
+                None => vec![],
+            };
+            RedisResponse::array(fields)
+        }
+        Command::HVals(map_key) => {
+            let mut storage = lock_db(storage, client.db);
+            if is_wrong_type(&mut *storage, &map_key, RedisType::Hash) {
+                return RedisResponse::error(RedisCommandError::WrongType);
+            }
+
+            let values = match storage.hash(&map_key) {
+                Some(hash) => hash.data.values().map(|v| BulkString(v.to_vec())).collect(),
+                None => vec![],
+            };
+            RedisResponse::array(values)
+        }
+        Command::HLen(map_key) => {
+            let mut storage = lock_db(storage, client.db);
+            if is_wrong_type(&mut *storage, &map_key, RedisType::Hash) {
+                return RedisResponse::error(RedisCommandError::WrongType);
+            }
+
+            let len = storage.hash(&map_key).map_or(0, |hash| hash.data.len());
+            RedisResponse::single(Integer(len as i64))
+        }
+        Command::HExists(map_key, field_key) => {
+            let mut storage = lock_db(storage, client.db);
+            if is_wrong_type(&mut *storage, &map_key, RedisType::Hash) {
+                return RedisResponse::error(RedisCommandError::WrongType);
+            }
+
+            let exists = storage
+                .hash(&map_key)
+                .is_some_and(|hash| hash.data.contains_key(&field_key));
+            RedisResponse::single(Integer(exists as i64))
+        }
+        Command::HStrLen(map_key, field_key) => {
+            let mut storage = lock_db(storage, client.db);
+            if is_wrong_type(&mut *storage, &map_key, RedisType::Hash) {
+                return RedisResponse::error(RedisCommandError::WrongType);
+            }
+
+            let len = storage
+                .hash(&map_key)
+                .and_then(|hash| hash.data.get(&field_key))
+                .map_or(0, |value| value.len());
+            RedisResponse::single(Integer(len as i64))
+        }
+        Command::HScan(map_key, cursor, pattern, count, no_values) => {
+            let mut storage = lock_db(storage, client.db);
+            if is_wrong_type(&mut *storage, &map_key, RedisType::Hash) {
+                return RedisResponse::error(RedisCommandError::WrongType);
+            }
+
+            let (cursor, items) = match storage.hash(&map_key) {
+                Some(hash) => {
+                    let fields = hash
+                        .data
+                        .iter()
+                        .filter(|(k, _)| pattern.as_ref().is_none_or(|p| glob_match(p, k)))
+                        .map(|(k, v)| (k.as_slice(), (k, v)));
+                    scan(fields, cursor, count)
+                }
+                None => (0, vec![]),
+            };
+
+            let items = items
+                .into_iter()
+                .flat_map(|(k, v)| match no_values {
+                    true => vec![BulkString(k.to_vec())],
+                    false => vec![BulkString(k.to_vec()), BulkString(v.to_vec())],
+                })
+                .collect();
+            RedisResponse::array(vec![
+                BulkString(cursor.to_string().into_bytes()),
+                RedisResponseType::Array(items),
+            ])
+        }
+        Command::HRandField(map_key, count, with_values) => {
+            let mut storage = lock_db(storage, client.db);
+            if is_wrong_type(&mut *storage, &map_key, RedisType::Hash) {
+                return RedisResponse::error(RedisCommandError::WrongType);
             }
-            Command::Pttl(k) => {
-                let ttl = if let Some(meta) = lock_then_release(storage).meta(&k) {
-                    if let Some(expiry) = meta.expiry {
-                        expiry.duration_left_millis()
-                    } else {
-                        -1
+
+            let mut rng = rand::thread_rng();
+            let hash = storage.hash(&map_key);
+            let count = match count {
+                Some(count) => count,
+                None => {
+                    return match hash.and_then(|hash| hash.data.keys().choose(&mut rng)) {
+                        Some(field) => RedisResponse::single(BulkString(field.to_vec())),
+                        None => RedisResponse::single(Nil),
                     }
-                } else {
-                    -2
-                };
-                RedisResponse::single(Integer(ttl))
+                }
+            };
+
+            let fields: Vec<_> = hash.map_or(vec![], |hash| hash.data.iter().collect());
+            // a negative count allows the same field to be returned several times
+            let picked: Vec<_> = match count >= 0 {
+                true => fields.choose_multiple(&mut rng, count as usize).collect(),
+                false if fields.is_empty() => vec![],
+                false => (0..count.unsigned_abs())
+                    .filter_map(|_| fields.choose(&mut rng))
+                    .collect(),
+            };
+
+            let items = picked
+                .into_iter()
+                .flat_map(|(k, v)| match with_values {
+                    true => vec![BulkString(k.to_vec()), BulkString(v.to_vec())],
+                    false => vec![BulkString(k.to_vec())],
+                })
+                .collect();
+            RedisResponse::array(items)
+        }
+        Command::LPush(list_key, values) => {
+            let mut storage = lock_db(storage, client.db);
+            let response = push(
+                &mut *storage,
+                context,
+                client.db,
+                &list_key,
+                values,
+                ListEnd::Left,
+            );
+            context.blocked_clients.notify(client.db, &list_key);
+            response
+        }
+        Command::RPush(list_key, values) => {
+            let mut storage = lock_db(storage, client.db);
+            let response = push(
+                &mut *storage,
+                context,
+                client.db,
+                &list_key,
+                values,
+                ListEnd::Right,
+            );
+            context.blocked_clients.notify(client.db, &list_key);
+            response
+        }
+        Command::LPop(list_key, count) => pop(
+            &mut *lock_db(storage, client.db),
+            context,
+            client.db,
+            &list_key,
+            count,
+            ListEnd::Left,
+        ),
+        Command::RPop(list_key, count) => pop(
+            &mut *lock_db(storage, client.db),
+            context,
+            client.db,
+            &list_key,
+            count,
+            ListEnd::Right,
+        ),
+        Command::LLen(list_key) => {
+            let mut storage = lock_db(storage, client.db);
+            if is_wrong_type(&mut *storage, &list_key, RedisType::List) {
+                return RedisResponse::error(RedisCommandError::WrongType);
             }
-            Command::Info => RedisResponse::single(BulkString("".as_bytes().to_vec())),
-            Command::Ping => RedisResponse::pong(),
-            Command::Dbsize => {
-                let storage = lock_then_release(storage);
-                let size = storage.size() as i64;
-                RedisResponse::single(Integer(size))
+
+            let len = storage.list(&list_key).map_or(0, |list| list.data.len());
+            RedisResponse::single(Integer(len as i64))
+        }
+        Command::LRange(list_key, start, stop) => {
+            let mut storage = lock_db(storage, client.db);
+            if is_wrong_type(&mut *storage, &list_key, RedisType::List) {
+                return RedisResponse::error(RedisCommandError::WrongType);
             }
-            Command::Quit => RedisResponse::quit(),
-        },
-        Err(err) => RedisResponse::error(err),
-    };
-    response
+
+            let elements = match storage.list(&list_key) {
+                Some(list) => match list.range(start, stop) {
+                    Some(range) => list
+                        .data
+                        .range(range)
+                        .map(|v| BulkString(v.to_vec()))
+                        .collect(),
+                    None => vec![],
+                },
+                None => vec![],
+            };
+            RedisResponse::array(elements)
+        }
+        Command::LIndex(list_key, index) => {
+            let mut storage = lock_db(storage, client.db);
+            if is_wrong_type(&mut *storage, &list_key, RedisType::List) {
+                return RedisResponse::error(RedisCommandError::WrongType);
+            }
+
+            let element = storage
+                .list(&list_key)
+                .and_then(|list| list.index(index).map(|index| &list.data[index]));
+            match element {
+                Some(element) => RedisResponse::single(BulkString(element.to_vec())),
+                None => RedisResponse::single(Nil),
+            }
+        }
+        Command::LSet(list_key, index, value) => {
+            let mut storage = lock_db(storage, client.db);
+            if !storage.contains(&list_key) {
+                return RedisResponse::error(RedisCommandError::NoSuchKey);
+            }
+
+            let list = match storage.list_mut(&list_key) {
+                Some(list) => list,
+                None => return RedisResponse::error(RedisCommandError::WrongType),
+            };
+            match list.index(index) {
+                Some(index) => {
+                    list.data[index] = value;
+                    notify(context, client.db, keyspace_events::LIST, "lset", &list_key);
+                    RedisResponse::okay()
+                }
+                None => RedisResponse::error(RedisCommandError::IndexOutOfRange),
+            }
+        }
+        Command::LTrim(list_key, start, stop) => {
+            let mut storage = lock_db(storage, client.db);
+            if !storage.contains(&list_key) {
+                return RedisResponse::okay();
+            }
+
+            let list = match storage.list_mut(&list_key) {
+                Some(list) => list,
+                None => return RedisResponse::error(RedisCommandError::WrongType),
+            };
+            match list.range(start, stop) {
+                Some(range) => {
+                    list.data.truncate(range.end() + 1);
+                    list.data.drain(..range.start());
+                }
+                None => list.data.clear(),
+            }
+            // trimming every element deletes the key
+            let emptied = list.data.is_empty();
+            if emptied {
+                storage.remove(&list_key);
+            }
+            notify_pop(
+                context,
+                client.db,
+                keyspace_events::LIST,
+                "ltrim",
+                &list_key,
+                emptied,
+            );
+            RedisResponse::okay()
+        }
+        Command::LInsert(list_key, before, pivot, value) => {
+            let mut storage = lock_db(storage, client.db);
+            if !storage.contains(&list_key) {
+                return RedisResponse::single(Integer(0));
+            }
+
+            let list = match storage.list_mut(&list_key) {
+                Some(list) => list,
+                None => return RedisResponse::error(RedisCommandError::WrongType),
+            };
+            match list.data.iter().position(|element| *element == pivot) {
+                Some(index) => {
+                    let index = if before { index } else { index + 1 };
+                    list.data.insert(index, value);
+                    let len = list.data.len();
+                    notify(
+                        context,
+                        client.db,
+                        keyspace_events::LIST,
+                        "linsert",
+                        &list_key,
+                    );
+                    RedisResponse::single(Integer(len as i64))
+                }
+                None => RedisResponse::single(Integer(-1)),
+            }
+        }
+        Command::LRem(list_key, count, value) => {
+            let mut storage = lock_db(storage, client.db);
+            if !storage.contains(&list_key) {
+                return RedisResponse::single(Integer(0));
+            }
+
+            let list = match storage.list_mut(&list_key) {
+                Some(list) => list,
+                None => return RedisResponse::error(RedisCommandError::WrongType),
+            };
+
+            // a negative count removes the elements starting from the tail, 0 removes them all
+            let limit = match count {
+                0 => usize::MAX,
+                count => count.unsigned_abs() as usize,
+            };
+            let from_tail = count < 0;
+            if from_tail {
+                list.data.make_contiguous().reverse();
+            }
+            let mut removed = 0;
+            list.data.retain(|element| {
+                let remove = removed < limit && *element == value;
+                removed += remove as usize;
+                !remove
+            });
+            if from_tail {
+                list.data.make_contiguous().reverse();
+            }
+
+            let emptied = list.data.is_empty();
+            if emptied {
+                storage.remove(&list_key);
+            }
+            if removed > 0 {
+                notify_pop(
+                    context,
+                    client.db,
+                    keyspace_events::LIST,
+                    "lrem",
+                    &list_key,
+                    emptied,
+                );
+            }
+            RedisResponse::single(Integer(removed as i64))
+        }
+        Command::LPos(list_key, value, rank, count, max_len) => {
+            let mut storage = lock_db(storage, client.db);
+            if is_wrong_type(&mut *storage, &list_key, RedisType::List) {
+                return RedisResponse::error(RedisCommandError::WrongType);
+            }
+
+            let positions: Vec<_> = match storage.list(&list_key) {
+                Some(list) => {
+                    // a negative rank looks for the matches starting from the tail
+                    let indexes: Box<dyn Iterator<Item = usize>> = match rank < 0 {
+                        true => Box::new((0..list.data.len()).rev()),
+                        false => Box::new(0..list.data.len()),
+                    };
+                    let max_len = if max_len == 0 { usize::MAX } else { max_len };
+                    // COUNT 0 returns all the matches
+                    let count = match count {
+                        Some(0) => usize::MAX,
+                        count => count.unwrap_or(1),
+                    };
+
+                    indexes
+                        .take(max_len)
+                        .filter(|index| list.data[*index] == value)
+                        .skip(rank.unsigned_abs() as usize - 1)
+                        .take(count)
+                        .map(|index| Integer(index as i64))
+                        .collect()
+                }
+                None => vec![],
+            };
+
+            match count {
+                Some(_) => RedisResponse::array(positions),
+                None => RedisResponse::single(positions.into_iter().next().unwrap_or(Nil)),
+            }
+        }
+        Command::LMove(source, destination, from, to) => {
+            let mut storage = lock_db(storage, client.db);
+            match list_move(
+                &mut *storage,
+                context,
+                client.db,
+                &source,
+                &destination,
+                from,
+                to,
+            ) {
+                Ok(Some(element)) => {
+                    context.blocked_clients.notify(client.db, &destination);
+                    RedisResponse::single(BulkString(element))
+                }
+                Ok(None) => RedisResponse::single(Nil),
+                Err(err) => RedisResponse::error(err),
+            }
+        }
+        Command::BLPop(list_keys, timeout) => blocking_pop(
+            storage,
+            context,
+            client,
+            list_keys,
+            ListEnd::Left,
+            None,
+            timeout,
+        ),
+        Command::BRPop(list_keys, timeout) => blocking_pop(
+            storage,
+            context,
+            client,
+            list_keys,
+            ListEnd::Right,
+            None,
+            timeout,
+        ),
+        Command::LMPop(list_keys, end, count) => {
+            let mut storage = lock_db(storage, client.db);
+            match multi_pop(&mut *storage, context, client.db, &list_keys, end, count) {
+                Ok(Some((list_key, elements))) => {
+                    RedisResponse::array(pop_reply(list_key, elements, Some(count)))
+                }
+                Ok(None) => RedisResponse::single(NilArray),
+                Err(err) => RedisResponse::error(err),
+            }
+        }
+        Command::BLMPop(timeout, list_keys, end, count) => blocking_pop(
+            storage,
+            context,
+            client,
+            list_keys,
+            end,
+            Some(count),
+            timeout,
+        ),
+        Command::BLMove(source, destination, from, to, timeout) => {
+            let mut storage = lock_db(storage, client.db);
+            match list_move(
+                &mut *storage,
+                context,
+                client.db,
+                &source,
+                &destination,
+                from,
+                to,
+            ) {
+                Ok(Some(element)) => {
+                    context.blocked_clients.notify(client.db, &destination);
+                    RedisResponse::single(BulkString(element))
+                }
+                Ok(None) => {
+                    // registered while the storage is locked so that no push can be missed
+                    let waiter = context.blocked_clients.wait(client.db, &[source], timeout);
+                    client.blocked = Some(waiter);
+                    RedisResponse::single(Nil)
+                }
+                Err(err) => RedisResponse::error(err),
+            }
+        }
+        Command::SAdd(set_key, members) => {
+            let mut storage = lock_db(storage, client.db);
+            let set = match storage.set_mut(&set_key) {
+                Some(set) => set,
+                None => return RedisResponse::error(RedisCommandError::WrongType),
+            };
+
+            let added = members
+                .into_iter()
+                .filter(|member| set.data.insert(member.to_vec()))
+                .count();
+            if added > 0 {
+                notify(context, client.db, keyspace_events::SET, "sadd", &set_key);
+            }
+            RedisResponse::single(Integer(added as i64))
+        }
+        Command::SRem(set_key, members) => {
+            let mut storage = lock_db(storage, client.db);
+            if !storage.contains(&set_key) {
+                return RedisResponse::single(Integer(0));
+            }
+
+            let set = match storage.set_mut(&set_key) {
+                Some(set) => set,
+                None => return RedisResponse::error(RedisCommandError::WrongType),
+            };
+            let removed = members
+                .iter()
+                .filter(|member| set.data.remove(*member))
+                .count();
+            let emptied = set.data.is_empty();
+            if emptied {
+                storage.remove(&set_key);
+            }
+            if removed > 0 {
+                notify_pop(
+                    context,
+                    client.db,
+                    keyspace_events::SET,
+                    "srem",
+                    &set_key,
+                    emptied,
+                );
+            }
+            RedisResponse::single(Integer(removed as i64))
+        }
+        Command::SMembers(set_key) => {
+            let mut storage = lock_db(storage, client.db);
+            if is_wrong_type(&mut *storage, &set_key, RedisType::Set) {
+                return RedisResponse::error(RedisCommandError::WrongType);
+            }
+
+            let members = match storage.set(&set_key) {
+                Some(set) => set.data.iter().map(|m| BulkString(m.to_vec())).collect(),
+                None => vec![],
+            };
+            RedisResponse::array(members)
+        }
+        Command::SIsMember(set_key, member) => {
+            let mut storage = lock_db(storage, client.db);
+            if is_wrong_type(&mut *storage, &set_key, RedisType::Set) {
+                return RedisResponse::error(RedisCommandError::WrongType);
+            }
+
+            let is_member = storage
+                .set(&set_key)
+                .is_some_and(|set| set.data.contains(&member));
+            RedisResponse::single(Integer(is_member as i64))
+        }
+        Command::SMIsMember(set_key, members) => {
+            let mut storage = lock_db(storage, client.db);
+            if is_wrong_type(&mut *storage, &set_key, RedisType::Set) {
+                return RedisResponse::error(RedisCommandError::WrongType);
+            }
+
+            let set = storage.set(&set_key);
+            let flags = members
+                .iter()
+                .map(|member| set.is_some_and(|set| set.data.contains(member)))
+                .map(|is_member| Integer(is_member as i64))
+                .collect();
+            RedisResponse::array(flags)
+        }
+        Command::SCard(set_key) => {
+            let mut storage = lock_db(storage, client.db);
+            if is_wrong_type(&mut *storage, &set_key, RedisType::Set) {
+                return RedisResponse::error(RedisCommandError::WrongType);
+            }
+
+            let len = storage.set(&set_key).map_or(0, |set| set.data.len());
+            RedisResponse::single(Integer(len as i64))
+        }
+        Command::SPop(set_key, count) => {
+            let mut storage = lock_db(storage, client.db);
+            if is_wrong_type(&mut *storage, &set_key, RedisType::Set) {
+                return RedisResponse::error(RedisCommandError::WrongType);
+            }
+
+            let popped = storage.set(&set_key).map_or(vec![], |set| {
+                let mut rng = rand::thread_rng();
+                set.data
+                    .iter()
+                    .cloned()
+                    .choose_multiple(&mut rng, count.unwrap_or(1))
+            });
+            if !popped.is_empty() {
+                let set = storage.set_mut(&set_key).unwrap();
+                popped.iter().for_each(|member| {
+                    set.data.remove(member);
+                });
+                let emptied = set.data.is_empty();
+                if emptied {
+                    storage.remove(&set_key);
+                }
+                notify_pop(
+                    context,
+                    client.db,
+                    keyspace_events::SET,
+                    "spop",
+                    &set_key,
+                    emptied,
+                );
+            }
+
+            let mut popped = popped.into_iter().map(BulkString);
+            match count {
+                Some(_) => RedisResponse::array(popped.collect()),
+                None => RedisResponse::single(popped.next().unwrap_or(Nil)),
+            }
+        }
+        Command::SRandMember(set_key, count) => {
+            let mut storage = lock_db(storage, client.db);
+            if is_wrong_type(&mut *storage, &set_key, RedisType::Set) {
+                return RedisResponse::error(RedisCommandError::WrongType);
+            }
+
+            let mut rng = rand::thread_rng();
+            let set = storage.set(&set_key);
+            let count = match count {
+                Some(count) => count,
+                None => {
+                    return match set.and_then(|set| set.data.iter().choose(&mut rng)) {
+                        Some(member) => RedisResponse::single(BulkString(member.to_vec())),
+                        None => RedisResponse::single(Nil),
+                    }
+                }
+            };
+
+            let members: Vec<_> = set.map_or(vec![], |set| set.data.iter().collect());
+            // a negative count allows the same member to be returned several times
+            let picked: Vec<_> = match count >= 0 {
+                true => members.choose_multiple(&mut rng, count as usize).collect(),
+                false if members.is_empty() => vec![],
+                false => (0..count.unsigned_abs())
+                    .filter_map(|_| members.choose(&mut rng))
+                    .collect(),
+            };
+            RedisResponse::array(picked.into_iter().map(|m| BulkString(m.to_vec())).collect())
+        }
+        Command::SMove(source, destination, member) => {
+            let mut storage = lock_db(storage, client.db);
+            if is_wrong_type(&mut *storage, &source, RedisType::Set)
+                || is_wrong_type(&mut *storage, &destination, RedisType::Set)
+            {
+                return RedisResponse::error(RedisCommandError::WrongType);
+            }
+
+            let is_member = storage
+                .set(&source)
+                .is_some_and(|set| set.data.contains(&member));
+            if !is_member {
+                return RedisResponse::single(Integer(0));
+            }
+
+            if source != destination {
+                let set = storage.set_mut(&source).unwrap();
+                set.data.remove(&member);
+                let emptied = set.data.is_empty();
+                if emptied {
+                    storage.remove(&source);
+                }
+                notify_pop(
+                    context,
+                    client.db,
+                    keyspace_events::SET,
+                    "srem",
+                    &source,
+                    emptied,
+                );
+                storage.set_mut(&destination).unwrap().data.insert(member);
+                notify(
+                    context,
+                    client.db,
+                    keyspace_events::SET,
+                    "sadd",
+                    &destination,
+                );
+            }
+            RedisResponse::single(Integer(1))
+        }
+        Command::SInter(set_keys) => set_operation_reply(
+            &mut *lock_db(storage, client.db),
+            &set_keys,
+            SetOperation::Inter,
+        ),
+        Command::SUnion(set_keys) => set_operation_reply(
+            &mut *lock_db(storage, client.db),
+            &set_keys,
+            SetOperation::Union,
+        ),
+        Command::SDiff(set_keys) => set_operation_reply(
+            &mut *lock_db(storage, client.db),
+            &set_keys,
+            SetOperation::Diff,
+        ),
+        Command::SInterCard(set_keys, limit) => {
+            set_intersection_card(&mut *lock_db(storage, client.db), &set_keys, limit)
+        }
+        Command::SScan(set_key, cursor, pattern, count) => {
+            let mut storage = lock_db(storage, client.db);
+            if is_wrong_type(&mut *storage, &set_key, RedisType::Set) {
+                return RedisResponse::error(RedisCommandError::WrongType);
+            }
+
+            let (cursor, members) = match storage.set(&set_key) {
+                Some(set) => {
+                    let members = set
+                        .data
+                        .iter()
+                        .filter(|m| pattern.as_ref().is_none_or(|p| glob_match(p, m)))
+                        .map(|m| (m.as_slice(), m));
+                    scan(members, cursor, count)
+                }
+                None => (0, vec![]),
+            };
+
+            RedisResponse::array(vec![
+                BulkString(cursor.to_string().into_bytes()),
+                RedisResponseType::Array(
+                    members
+                        .into_iter()
+                        .map(|m| BulkString(m.to_vec()))
+                        .collect(),
+                ),
+            ])
+        }
+        Command::SInterStore(destination, set_keys) => set_operation_store(
+            &mut *lock_db(storage, client.db),
+            context,
+            client.db,
+            &destination,
+            &set_keys,
+            SetOperation::Inter,
+        ),
+        Command::SUnionStore(destination, set_keys) => set_operation_store(
+            &mut *lock_db(storage, client.db),
+            context,
+            client.db,
+            &destination,
+            &set_keys,
+            SetOperation::Union,
+        ),
+        Command::SDiffStore(destination, set_keys) => set_operation_store(
+            &mut *lock_db(storage, client.db),
+            context,
+            client.db,
+            &destination,
+            &set_keys,
+            SetOperation::Diff,
+        ),
+        Command::ZAdd(zset_key, options, members) => {
+            let mut storage = lock_db(storage, client.db);
+            let zset = match storage.zset_mut(&zset_key) {
+                Some(zset) => zset,
+                None => return RedisResponse::error(RedisCommandError::WrongType),
+            };
+
+            let mut added = 0;
+            let mut updated = 0;
+            // score of the last member added or updated
+            let mut new_score = None;
+            for (score, member) in members {
+                let current = zset.score(&member);
+                let score = match options.incr {
+                    true => current.unwrap_or(0.0) + score,
+                    false => score,
+                };
+                if score.is_nan() {
+                    if zset.is_empty() {
+                        storage.remove(&zset_key);
+                    }
+                    return RedisResponse::error(RedisCommandError::ScoreNaN);
+                }
+
+                match current {
+                    None if options.xx => continue,
+                    None => added += 1,
+                    Some(_) if options.nx => continue,
+                    Some(current) if options.gt && score <= current => continue,
+                    Some(current) if options.lt && score >= current => continue,
+                    Some(current) if current != score => updated += 1,
+                    Some(_) => {}
+                }
+                zset.insert(member, score);
+                new_score = Some(score);
+            }
+
+            // XX may have left the created sorted set empty
+            if zset.is_empty() {
+                storage.remove(&zset_key);
+            }
+            context.blocked_clients.notify(client.db, &zset_key);
+            if added + updated > 0 {
+                let event = if options.incr { "zincr" } else { "zadd" };
+                notify(context, client.db, keyspace_events::ZSET, event, &zset_key);
+            }
+
+            match (options.incr, new_score) {
+                (true, Some(score)) => {
+                    RedisResponse::single(BulkString(score.to_string().into_bytes()))
+                }
+                (true, None) => RedisResponse::single(Nil),
+                (false, _) if options.ch => RedisResponse::single(Integer(added + updated)),
+                (false, _) => RedisResponse::single(Integer(added)),
+            }
+        }
+        Command::ZScore(zset_key, member) => {
+            let mut storage = lock_db(storage, client.db);
+            if is_wrong_type(&mut *storage, &zset_key, RedisType::ZSet) {
+                return RedisResponse::error(RedisCommandError::WrongType);
+            }
+
+            match storage.zset(&zset_key).and_then(|zset| zset.score(&member)) {
+                Some(score) => RedisResponse::single(BulkString(score.to_string().into_bytes())),
+                None => RedisResponse::single(Nil),
+            }
+        }
+        Command::GeoPos(zset_key, members) => {
+            let mut storage = lock_db(storage, client.db);
+            if is_wrong_type(&mut *storage, &zset_key, RedisType::ZSet) {
+                return RedisResponse::error(RedisCommandError::WrongType);
+            }
+
+            let zset = storage.zset(&zset_key);
+            let positions = members
+                .iter()
+                .map(|member| match zset.and_then(|zset| zset.score(member)) {
+                    Some(score) => {
+                        let (longitude, latitude) = geo::decode(score as u64);
+                        Array(vec![
+                            BulkString(longitude.to_string().into_bytes()),
+                            BulkString(latitude.to_string().into_bytes()),
+                        ])
+                    }
+                    None => Nil,
+                })
+                .collect();
+            RedisResponse::array(positions)
+        }
+        Command::GeoDist(zset_key, member, other, unit) => {
+            let mut storage = lock_db(storage, client.db);
+            if is_wrong_type(&mut *storage, &zset_key, RedisType::ZSet) {
+                return RedisResponse::error(RedisCommandError::WrongType);
+            }
+
+            let zset = storage.zset(&zset_key);
+            let position = |member: &[u8]| {
+                zset.and_then(|zset| zset.score(member))
+                    .map(|score| geo::decode(score as u64))
+            };
+            match (position(&member), position(&other)) {
+                (Some(from), Some(to)) => {
+                    let distance = geo::distance(from, to) / unit.meters();
+                    RedisResponse::single(BulkString(format!("{:.4}", distance).into_bytes()))
+                }
+                _ => RedisResponse::single(Nil),
+            }
+        }
+        Command::GeoHash(zset_key, members) => {
+            let mut storage = lock_db(storage, client.db);
+            if is_wrong_type(&mut *storage, &zset_key, RedisType::ZSet) {
+                return RedisResponse::error(RedisCommandError::WrongType);
+            }
+
+            let zset = storage.zset(&zset_key);
+            let hashes = members
+                .iter()
+                .map(|member| match zset.and_then(|zset| zset.score(member)) {
+                    Some(score) => {
+                        let (longitude, latitude) = geo::decode(score as u64);
+                        BulkString(geo::geohash_string(longitude, latitude).into_bytes())
+                    }
+                    None => Nil,
+                })
+                .collect();
+            RedisResponse::array(hashes)
+        }
+        Command::GeoSearch(zset_key, query) => {
+            let mut storage = lock_db(storage, client.db);
+            if is_wrong_type(&mut *storage, &zset_key, RedisType::ZSet) {
+                return RedisResponse::error(RedisCommandError::WrongType);
+            }
+
+            let zset = match storage.zset(&zset_key) {
+                Some(zset) => zset,
+                None => return RedisResponse::array(vec![]),
+            };
+            let matches = match geo_search(zset, &query) {
+                Ok(matches) => matches,
+                Err(err) => return RedisResponse::error(err),
+            };
+            let with_options = query.with_dist || query.with_hash || query.with_coord;
+            let reply = matches
+                .into_iter()
+                .map(|found| {
+                    let member = BulkString(found.member.clone());
+                    if !with_options {
+                        return member;
+                    }
+
+                    let mut fields = vec![member];
+                    if query.with_dist {
+                        let distance = found.distance / query.unit.meters();
+                        fields.push(BulkString(format!("{:.4}", distance).into_bytes()));
+                    }
+                    if query.with_hash {
+                        fields.push(Integer(found.score as i64));
+                    }
+                    if query.with_coord {
+                        let (longitude, latitude) = found.position;
+                        fields.push(Array(vec![
+                            BulkString(longitude.to_string().into_bytes()),
+                            BulkString(latitude.to_string().into_bytes()),
+                        ]));
+                    }
+                    Array(fields)
+                })
+                .collect();
+            RedisResponse::array(reply)
+        }
+        Command::GeoSearchStore(destination, source, query, store_dist) => {
+            let mut storage = lock_db(storage, client.db);
+            if is_wrong_type(&mut *storage, &source, RedisType::ZSet) {
+                return RedisResponse::error(RedisCommandError::WrongType);
+            }
+
+            let members: Vec<(RedisString, f64)> = match storage.zset(&source) {
+                Some(zset) => match geo_search(zset, &query) {
+                    Ok(matches) => matches
+                        .into_iter()
+                        .map(|found| match store_dist {
+                            true => (found.member.clone(), found.distance / query.unit.meters()),
+                            false => (found.member.clone(), found.score),
+                        })
+                        .collect(),
+                    Err(err) => return RedisResponse::error(err),
+                },
+                None => vec![],
+            };
+
+            let mut zset = RedisSortedSet::new();
+            for (member, score) in members {
+                zset.insert(member, score);
+            }
+            let len = store_zset(
+                &mut *storage,
+                context,
+                client.db,
+                &destination,
+                zset,
+                "geosearchstore",
+            );
+            RedisResponse::single(Integer(len as i64))
+        }
+        Command::ZMScore(zset_key, members) => {
+            let mut storage = lock_db(storage, client.db);
+            if is_wrong_type(&mut *storage, &zset_key, RedisType::ZSet) {
+                return RedisResponse::error(RedisCommandError::WrongType);
+            }
+
+            let zset = storage.zset(&zset_key);
+            let scores = members
+                .iter()
+                .map(|member| match zset.and_then(|zset| zset.score(member)) {
+                    Some(score) => BulkString(score.to_string().into_bytes()),
+                    None => Nil,
+                })
+                .collect();
+            RedisResponse::array(scores)
+        }
+        Command::ZRandMember(zset_key, count, with_scores) => {
+            let mut storage = lock_db(storage, client.db);
+            if is_wrong_type(&mut *storage, &zset_key, RedisType::ZSet) {
+                return RedisResponse::error(RedisCommandError::WrongType);
+            }
+
+            let mut rng = rand::thread_rng();
+            let zset = storage.zset(&zset_key);
+            let count = match count {
+                Some(count) => count,
+                None => {
+                    return match zset.and_then(|zset| zset.iter().choose(&mut rng)) {
+                        Some((member, _)) => RedisResponse::single(BulkString(member.to_vec())),
+                        None => RedisResponse::single(Nil),
+                    }
+                }
+            };
+
+            let members: Vec<_> = zset.map_or(vec![], |zset| zset.iter().collect());
+            // a negative count allows the same member to be returned several times
+            let picked = match count >= 0 {
+                true => members
+                    .choose_multiple(&mut rng, count as usize)
+                    .copied()
+                    .collect(),
+                false if members.is_empty() => vec![],
+                false => (0..count.unsigned_abs())
+                    .filter_map(|_| members.choose(&mut rng).copied())
+                    .collect(),
+            };
+            RedisResponse::array(scored_members_reply(picked, with_scores))
+        }
+        Command::ZScan(zset_key, cursor, pattern, count) => {
+            let mut storage = lock_db(storage, client.db);
+            if is_wrong_type(&mut *storage, &zset_key, RedisType::ZSet) {
+                return RedisResponse::error(RedisCommandError::WrongType);
+            }
+
+            let (cursor, members) = match storage.zset(&zset_key) {
+                Some(zset) => {
+                    let members = zset
+                        .iter()
+                        .filter(|(m, _)| pattern.as_ref().is_none_or(|p| glob_match(p, m)))
+                        .map(|(m, s)| (m.as_slice(), (m, s)));
+                    scan(members, cursor, count)
+                }
+                None => (0, vec![]),
+            };
+
+            RedisResponse::array(vec![
+                BulkString(cursor.to_string().into_bytes()),
+                RedisResponseType::Array(scored_members_reply(members, true)),
+            ])
+        }
+        Command::ZRem(zset_key, members) => {
+            let mut storage = lock_db(storage, client.db);
+            if !storage.contains(&zset_key) {
+                return RedisResponse::single(Integer(0));
+            }
+
+            let zset = match storage.zset_mut(&zset_key) {
+                Some(zset) => zset,
+                None => return RedisResponse::error(RedisCommandError::WrongType),
+            };
+            let removed = members
+                .iter()
+                .filter(|member| zset.remove(member).is_some())
+                .count();
+            let emptied = zset.is_empty();
+            if emptied {
+                storage.remove(&zset_key);
+            }
+            if removed > 0 {
+                notify_pop(
+                    context,
+                    client.db,
+                    keyspace_events::ZSET,
+                    "zrem",
+                    &zset_key,
+                    emptied,
+                );
+            }
+            RedisResponse::single(Integer(removed as i64))
+        }
+        Command::ZCard(zset_key) => {
+            let mut storage = lock_db(storage, client.db);
+            if is_wrong_type(&mut *storage, &zset_key, RedisType::ZSet) {
+                return RedisResponse::error(RedisCommandError::WrongType);
+            }
+
+            let len = storage.zset(&zset_key).map_or(0, |zset| zset.len());
+            RedisResponse::single(Integer(len as i64))
+        }
+        Command::ZRange(zset_key, query, with_scores) => {
+            let mut storage = lock_db(storage, client.db);
+            if is_wrong_type(&mut *storage, &zset_key, RedisType::ZSet) {
+                return RedisResponse::error(RedisCommandError::WrongType);
+            }
+
+            let members = match storage.zset(&zset_key) {
+                Some(zset) => zset.query(&query),
+                None => vec![],
+            };
+            RedisResponse::array(scored_members_reply(members, with_scores))
+        }
+        Command::ZRangeStore(destination, source, query) => {
+            let mut storage = lock_db(storage, client.db);
+            if is_wrong_type(&mut *storage, &source, RedisType::ZSet) {
+                return RedisResponse::error(RedisCommandError::WrongType);
+            }
+
+            let members = match storage.zset(&source) {
+                Some(zset) => zset
+                    .query(&query)
+                    .into_iter()
+                    .map(|(member, score)| (member.clone(), score))
+                    .collect(),
+                None => vec![],
+            };
+
+            let mut zset = RedisSortedSet::new();
+            for (member, score) in members {
+                zset.insert(member, score);
+            }
+            let len = store_zset(
+                &mut *storage,
+                context,
+                client.db,
+                &destination,
+                zset,
+                "zrangestore",
+            );
+            RedisResponse::single(Integer(len as i64))
+        }
+        Command::ZIncrBy(zset_key, increment, member) => {
+            let mut storage = lock_db(storage, client.db);
+            let zset = match storage.zset_mut(&zset_key) {
+                Some(zset) => zset,
+                None => return RedisResponse::error(RedisCommandError::WrongType),
+            };
+
+            let score = zset.score(&member).unwrap_or(0.0) + increment;
+            if score.is_nan() {
+                if zset.is_empty() {
+                    storage.remove(&zset_key);
+                }
+                return RedisResponse::error(RedisCommandError::ScoreNaN);
+            }
+
+            zset.insert(member, score);
+            context.blocked_clients.notify(client.db, &zset_key);
+            notify(
+                context,
+                client.db,
+                keyspace_events::ZSET,
+                "zincr",
+                &zset_key,
+            );
+            RedisResponse::single(BulkString(score.to_string().into_bytes()))
+        }
+        Command::ZPopMin(zset_key, count) => zset_pop(
+            &mut *lock_db(storage, client.db),
+            context,
+            client.db,
+            zset_key,
+            count,
+            ScoreEnd::Min,
+        ),
+        Command::ZPopMax(zset_key, count) => zset_pop(
+            &mut *lock_db(storage, client.db),
+            context,
+            client.db,
+            zset_key,
+            count,
+            ScoreEnd::Max,
+        ),
+        Command::BZPopMin(zset_keys, timeout) => blocking_zset_pop(
+            storage,
+            context,
+            client,
+            zset_keys,
+            ScoreEnd::Min,
+            None,
+            timeout,
+        ),
+        Command::BZPopMax(zset_keys, timeout) => blocking_zset_pop(
+            storage,
+            context,
+            client,
+            zset_keys,
+            ScoreEnd::Max,
+            None,
+            timeout,
+        ),
+        Command::BZMPop(timeout, zset_keys, end, count) => blocking_zset_pop(
+            storage,
+            context,
+            client,
+            zset_keys,
+            end,
+            Some(count),
+            timeout,
+        ),
+        Command::ZMPop(zset_keys, end, count) => {
+            let mut storage = lock_db(storage, client.db);
+            match zset_multi_pop(&mut *storage, context, client.db, &zset_keys, end, count) {
+                Ok(Some((zset_key, members))) => {
+                    RedisResponse::array(zset_pop_reply(zset_key, members))
+                }
+                Ok(None) => RedisResponse::single(NilArray),
+                Err(err) => RedisResponse::error(err),
+            }
+        }
+        Command::ZRank(zset_key, member, with_score) => zrank_reply(
+            &mut *lock_db(storage, client.db),
+            &zset_key,
+            &member,
+            false,
+            with_score,
+        ),
+        Command::ZRevRank(zset_key, member, with_score) => zrank_reply(
+            &mut *lock_db(storage, client.db),
+            &zset_key,
+            &member,
+            true,
+            with_score,
+        ),
+        Command::ZUnion(zset_keys, weights, aggregate, with_scores) => zset_operation_reply(
+            &mut *lock_db(storage, client.db),
+            &zset_keys,
+            &weights,
+            aggregate,
+            SetOperation::Union,
+            with_scores,
+        ),
+        Command::ZInter(zset_keys, weights, aggregate, with_scores) => zset_operation_reply(
+            &mut *lock_db(storage, client.db),
+            &zset_keys,
+            &weights,
+            aggregate,
+            SetOperation::Inter,
+            with_scores,
+        ),
+        Command::ZDiff(zset_keys, with_scores) => zset_operation_reply(
+            &mut *lock_db(storage, client.db),
+            &zset_keys,
+            &vec![1.0; zset_keys.len()],
+            Aggregate::Sum,
+            SetOperation::Diff,
+            with_scores,
+        ),
+        Command::ZUnionStore(destination, zset_keys, weights, aggregate) => {
+            let mut storage = lock_db(storage, client.db);
+            match zset_operation(
+                &mut *storage,
+                &zset_keys,
+                &weights,
+                aggregate,
+                SetOperation::Union,
+            ) {
+                Ok(zset) => {
+                    let len = store_zset(
+                        &mut *storage,
+                        context,
+                        client.db,
+                        &destination,
+                        zset,
+                        "zunionstore",
+                    );
+                    RedisResponse::single(Integer(len as i64))
+                }
+                Err(err) => RedisResponse::error(err),
+            }
+        }
+        Command::ZInterStore(destination, zset_keys, weights, aggregate) => {
+            let mut storage = lock_db(storage, client.db);
+            match zset_operation(
+                &mut *storage,
+                &zset_keys,
+                &weights,
+                aggregate,
+                SetOperation::Inter,
+            ) {
+                Ok(zset) => {
+                    let len = store_zset(
+                        &mut *storage,
+                        context,
+                        client.db,
+                        &destination,
+                        zset,
+                        "zinterstore",
+                    );
+                    RedisResponse::single(Integer(len as i64))
+                }
+                Err(err) => RedisResponse::error(err),
+            }
+        }
+        Command::ZDiffStore(destination, zset_keys) => {
+            let mut storage = lock_db(storage, client.db);
+            match zset_operation(
+                &mut *storage,
+                &zset_keys,
+                &vec![1.0; zset_keys.len()],
+                Aggregate::Sum,
+                SetOperation::Diff,
+            ) {
+                Ok(zset) => {
+                    let len = store_zset(
+                        &mut *storage,
+                        context,
+                        client.db,
+                        &destination,
+                        zset,
+                        "zdiffstore",
+                    );
+                    RedisResponse::single(Integer(len as i64))
+                }
+                Err(err) => RedisResponse::error(err),
+            }
+        }
+        Command::ZCount(zset_key, min, max) => {
+            let mut storage = lock_db(storage, client.db);
+            if is_wrong_type(&mut *storage, &zset_key, RedisType::ZSet) {
+                return RedisResponse::error(RedisCommandError::WrongType);
+            }
+
+            let query = ZRangeQuery::new(ZRangeBy::Score(min, max), false);
+            let count = storage
+                .zset(&zset_key)
+                .map_or(0, |zset| zset.query(&query).len());
+            RedisResponse::single(Integer(count as i64))
+        }
+        Command::ZLexCount(zset_key, min, max) => {
+            let mut storage = lock_db(storage, client.db);
+            if is_wrong_type(&mut *storage, &zset_key, RedisType::ZSet) {
+                return RedisResponse::error(RedisCommandError::WrongType);
+            }
+
+            let query = ZRangeQuery::new(ZRangeBy::Lex(min, max), false);
+            let count = storage
+                .zset(&zset_key)
+                .map_or(0, |zset| zset.query(&query).len());
+            RedisResponse::single(Integer(count as i64))
+        }
+        Command::Del(k) => {
+            let d = lock_db(storage, client.db).remove(k.as_slice());
+            if d > 0 {
+                notify(context, client.db, keyspace_events::GENERIC, "del", &k);
+            }
+            RedisResponse::single(Integer(d as i64))
+        }
+        Command::Unlink(keys) => {
+            let mut storage = lock_db(storage, client.db);
+            let mut unlinked = 0;
+            for key in &keys {
+                if storage.unlink(key) > 0 {
+                    notify(context, client.db, keyspace_events::GENERIC, "del", key);
+                    unlinked += 1;
+                }
+            }
+            RedisResponse::single(Integer(unlinked as i64))
+        }
+        Command::Incr(k) => incr_by(storage, context, client, &k, 1),
+        Command::IncrBy(k, increment) => incr_by(storage, context, client, &k, increment),
+        Command::Exists(k) => {
+            let exists = lock_db(storage, client.db).contains(&k);
+            let exists: i64 = match exists {
+                true => 1,
+                false => 0,
+            };
+            RedisResponse::single(Integer(exists))
+        }
+        Command::Touch(keys) => {
+            let mut storage = lock_db(storage, client.db);
+            let touched = keys.iter().filter(|key| storage.touch(key)).count();
+            RedisResponse::single(Integer(touched as i64))
+        }
+        Command::Type(k) => {
+            let mut storage = lock_db(storage, client.db);
+            let data_type = match storage.contains(&k) {
+                true => storage.meta(&k).map(|meta| meta.data_type.name()),
+                false => None,
+            };
+            let data_type = data_type.unwrap_or("none");
+            RedisResponse::single(SimpleString(data_type.as_bytes().to_vec()))
+        }
+        Command::Sort(key, options, destination) => {
+            let mut storage = lock_db(storage, client.db);
+            let values = match sort(&mut *storage, &key, &options) {
+                Ok(values) => values,
+                Err(err) => return RedisResponse::error(err),
+            };
+
+            let destination = match destination {
+                Some(destination) => destination,
+                None => return RedisResponse::array(sort_reply(values)),
+            };
+
+            // the values which do not exist are stored as empty strings
+            let len = values.len();
+            let existed = storage.remove(&destination) > 0;
+            if len > 0 {
+                storage.list_mut(&destination).unwrap().data =
+                    values.into_iter().map(Option::unwrap_or_default).collect();
+                context.blocked_clients.notify(client.db, &destination);
+                notify(
+                    context,
+                    client.db,
+                    keyspace_events::LIST,
+                    "sortstore",
+                    &destination,
+                );
+            } else if existed {
+                notify(
+                    context,
+                    client.db,
+                    keyspace_events::GENERIC,
+                    "del",
+                    &destination,
+                );
+            }
+            RedisResponse::single(Integer(len as i64))
+        }
+        Command::SortRo(key, options) => {
+            let mut storage = lock_db(storage, client.db);
+            match sort(&mut *storage, &key, &options) {
+                Ok(values) => RedisResponse::array(sort_reply(values)),
+                Err(err) => RedisResponse::error(err),
+            }
+        }
+        Command::Keys(pattern) => {
+            let keys = lock_db(storage, client.db).keys(&pattern);
+            let keys = keys.into_iter().map(BulkString).collect();
+            RedisResponse::array(keys)
+        }
+        Command::Scan(cursor, pattern, count, data_type) => {
+            let (cursor, keys) = lock_db(storage, client.db).scan(
+                cursor,
+                count,
+                pattern.as_deref(),
+                data_type.as_deref(),
+            );
+            let keys = keys.into_iter().map(BulkString).collect();
+            RedisResponse::array(vec![
+                BulkString(cursor.to_string().into_bytes()),
+                RedisResponseType::Array(keys),
+            ])
+        }
+        Command::RandomKey => match lock_db(storage, client.db).random_key() {
+            Some(key) => RedisResponse::single(BulkString(key)),
+            None => RedisResponse::single(Nil),
+        },
+        Command::Rename(k, new_k) => match lock_db(storage, client.db).rename(&k, &new_k) {
+            true => {
+                notify_rename(context, client.db, &k, &new_k);
+                RedisResponse::okay()
+            }
+            false => RedisResponse::error(RedisCommandError::NoSuchKey),
+        },
+        Command::RenameNx(k, new_k) => {
+            let mut storage = lock_db(storage, client.db);
+            match (storage.contains(&k), storage.contains(&new_k)) {
+                (false, _) => RedisResponse::error(RedisCommandError::NoSuchKey),
+                // destination already exists, will not rename
+                (true, true) => RedisResponse::single(Integer(0)),
+                (true, false) => {
+                    storage.rename(&k, &new_k);
+                    notify_rename(context, client.db, &k, &new_k);
+                    RedisResponse::single(Integer(1))
+                }
+            }
+        }
+        Command::Copy(k, new_k, db, replace) => {
+            let mut storage = lock_db(storage, client.db);
+            let db = db.map_or(client.db, |db| db as usize);
+            if db >= storage.databases() {
+                return RedisResponse::error(RedisCommandError::DbIndexOutOfRange);
+            }
+
+            if k == new_k && db == client.db {
+                return RedisResponse::error(RedisCommandError::SameObject);
+            }
+
+            if db != client.db {
+                let (value, expiry) = match storage.value(&k) {
+                    Some(value) => (value, storage.meta(&k).unwrap().expiry),
+                    None => return RedisResponse::single(Integer(0)),
+                };
+
+                storage.select(db);
+                if !replace && storage.contains(&new_k) {
+                    return RedisResponse::single(Integer(0));
+                }
+
+                storage.restore(&new_k, value, expiry);
+                notify(context, db, keyspace_events::GENERIC, "copy_to", &new_k);
+                return RedisResponse::single(Integer(1));
+            }
+
+            match (storage.contains(&k), storage.contains(&new_k)) {
+                // destination already exists, will not copy without REPLACE
+                (true, true) if !replace => RedisResponse::single(Integer(0)),
+                (true, _) => {
+                    storage.copy(&k, &new_k);
+                    notify(context, db, keyspace_events::GENERIC, "copy_to", &new_k);
+                    RedisResponse::single(Integer(1))
+                }
+                (false, _) => RedisResponse::single(Integer(0)),
+            }
+        }
+        Command::Dump(k) => match lock_db(storage, client.db).value(&k) {
+            Some(value) => match dump::serialize(&value) {
+                Some(payload) => RedisResponse::single(BulkString(payload)),
+                None => RedisResponse::error(RedisCommandError::DumpStream),
+            },
+            None => RedisResponse::single(Nil),
+        },
+        Command::Restore(k, ttl, payload, replace, absolute_ttl) => {
+            let value = match dump::deserialize(&payload) {
+                Ok(value) => value,
+                Err(err) => return RedisResponse::error(err.into()),
+            };
+
+            let expiry = match (ttl, absolute_ttl) {
+                (0, _) => None,
+                (timestamp, true) => Some(Expiry {
+                    timestamp: timestamp as i64,
+                }),
+                (ttl, false) => match Expiry::new_from_millis(ttl) {
+                    Ok(expiry) => Some(expiry),
+                    Err(err) => return RedisResponse::error(err.into()),
+                },
+            };
+
+            let mut storage = lock_db(storage, client.db);
+            if !replace && storage.contains(&k) {
+                return RedisResponse::error(RedisCommandError::BusyKey);
+            }
+
+            // an absolute ttl in the past restores nothing
+            if expiry.is_some_and(|expiry| expiry.duration_left_millis() <= 0) {
+                storage.remove(&k);
+            } else {
+                storage.restore(&k, value, expiry);
+                notify(context, client.db, keyspace_events::GENERIC, "restore", &k);
+            }
+            RedisResponse::okay()
+        }
+        Command::ObjectEncoding(k) => match lock_db(storage, client.db).encoding(&k) {
+            Some(encoding) => RedisResponse::single(BulkString(encoding.as_bytes().to_vec())),
+            None => RedisResponse::single(Nil),
+        },
+        Command::ObjectRefCount(k) => match lock_db(storage, client.db).contains(&k) {
+            // values are never shared between keys
+            true => RedisResponse::single(Integer(1)),
+            false => RedisResponse::single(Nil),
+        },
+        Command::ObjectIdleTime(k) => {
+            let mut storage = lock_db(storage, client.db);
+            match storage.contains(&k) {
+                true => {
+                    let idle_millis = storage.meta(&k).unwrap().idle_millis();
+                    RedisResponse::single(Integer(idle_millis / 1000))
+                }
+                false => RedisResponse::single(Nil),
+            }
+        }
+        Command::ObjectFreq(k) => {
+            let mut storage = lock_db(storage, client.db);
+            match storage.contains(&k) {
+                true => {
+                    let access_frequency = storage.meta(&k).unwrap().access_frequency();
+                    RedisResponse::single(Integer(access_frequency as i64))
+                }
+                false => RedisResponse::single(Nil),
+            }
+        }
+        Command::Ttl(k) => {
+            let ttl = if let Some(meta) = lock_db(storage, client.db).meta(&k) {
+                if let Some(expiry) = meta.expiry {
+                    expiry.duration_left_millis() / 1000
+                } else {
+                    -1
+                }
+            } else {
+                -2
+            };
+            RedisResponse::single(Integer(ttl))
+        }
+        Command::Pttl(k) => {
+            let ttl = if let Some(meta) = lock_db(storage, client.db).meta(&k) {
+                if let Some(expiry) = meta.expiry {
+                    expiry.duration_left_millis()
+                } else {
+                    -1
+                }
+            } else {
+                -2
+            };
+            RedisResponse::single(Integer(ttl))
+        }
+        Command::Info => {
+            let stats = context.stats();
+            let info = format!(
+                "# Stats\r\ncommand_queue_depth:{}\r\ncommand_queue_high_watermark:{}\r\n",
+                stats.command_queue_depth, stats.command_queue_high_watermark
+            );
+            RedisResponse::single(BulkString(info.into_bytes()))
+        }
+        // subscribed clients are replied like a message, so that they can tell it from one
+        Command::Ping(message) if client.subscriptions.is_subscribed() => {
+            RedisResponse::array(vec![
+                BulkString(b"pong".to_vec()),
+                BulkString(message.unwrap_or_default()),
+            ])
+        }
+        Command::Ping(None) => RedisResponse::pong(),
+        Command::Ping(Some(message)) => RedisResponse::single(BulkString(message)),
+        Command::Echo(message) => RedisResponse::single(BulkString(message)),
+        Command::Dbsize => {
+            let storage = lock_db(storage, client.db);
+            let size = storage.size() as i64;
+            RedisResponse::single(Integer(size))
+        }
+        Command::Time => {
+            let now = Utc::now();
+            RedisResponse::array(vec![
+                BulkString(now.timestamp().to_string().into_bytes()),
+                BulkString(now.timestamp_subsec_micros().to_string().into_bytes()),
+            ])
+        }
+        Command::FlushDb(lazy) => {
+            lock_db(storage, client.db).flush(lazy);
+            context.watched_keys.touch_db(client.db);
+            RedisResponse::okay()
+        }
+        Command::FlushAll(lazy) => {
+            let mut storage = lock_db(storage, client.db);
+            storage.flush_all(lazy);
+            (0..storage.databases()).for_each(|db| context.watched_keys.touch_db(db));
+            RedisResponse::okay()
+        }
+        Command::Move(k, db) => {
+            let mut storage = lock_db(storage, client.db);
+            let db = db as usize;
+            if db >= storage.databases() {
+                return RedisResponse::error(RedisCommandError::DbIndexOutOfRange);
+            }
+
+            if db == client.db {
+                return RedisResponse::error(RedisCommandError::SameObject);
+            }
+
+            let (value, expiry) = match storage.value(&k) {
+                Some(value) => (value, storage.meta(&k).unwrap().expiry),
+                None => return RedisResponse::single(Integer(0)),
+            };
+
+            storage.select(db);
+            if storage.contains(&k) {
+                // will not overwrite the key of the target database
+                return RedisResponse::single(Integer(0));
+            }
+            storage.restore(&k, value, expiry);
+
+            storage.select(client.db);
+            storage.remove(&k);
+            notify(
+                context,
+                client.db,
+                keyspace_events::GENERIC,
+                "move_from",
+                &k,
+            );
+            notify(context, db, keyspace_events::GENERIC, "move_to", &k);
+            RedisResponse::single(Integer(1))
+        }
+        Command::SwapDb(db, other_db) => {
+            match lock_db(storage, client.db).swap_db(db as usize, other_db as usize) {
+                true => {
+                    context.watched_keys.touch_db(db as usize);
+                    context.watched_keys.touch_db(other_db as usize);
+                    RedisResponse::okay()
+                }
+                false => RedisResponse::error(RedisCommandError::DbIndexOutOfRange),
+            }
+        }
+        Command::Select(db) => {
+            if db >= lock_db(storage, client.db).databases() as u64 {
+                return RedisResponse::error(RedisCommandError::DbIndexOutOfRange);
+            }
+
+            client.db = db as usize;
+            RedisResponse::okay()
+        }
+        Command::LatencyHistogram(command_names) => {
+            let latency_histograms = match context.latency_histograms.lock() {
+                Ok(latency_histograms) => latency_histograms,
+                Err(_) => return RedisResponse::array(vec![]),
+            };
+
+            let mut command_names = command_names
+                .into_iter()
+                .map(|name| String::from_utf8_lossy(&name).to_lowercase())
+                .collect::<Vec<_>>();
+            if command_names.is_empty() {
+                command_names = latency_histograms.keys().cloned().collect();
+            }
+            command_names.sort();
+            command_names.dedup();
+
+            let mut responses = vec![];
+            for command_name in command_names {
+                if let Some(histogram) = latency_histograms.get(&command_name) {
+                    let buckets = histogram
+                        .cumulative_buckets()
+                        .into_iter()
+                        .flat_map(|(bucket, count)| {
+                            vec![Integer(bucket as i64), Integer(count as i64)]
+                        })
+                        .collect();
+
+                    responses.push(BulkString(command_name.into_bytes()));
+                    responses.push(RedisResponseType::Array(vec![
+                        BulkString(b"calls".to_vec()),
+                        Integer(histogram.calls() as i64),
+                        BulkString(b"histogram_usec".to_vec()),
+                        RedisResponseType::Array(buckets),
+                    ]));
+                }
+            }
+
+            RedisResponse::array(responses)
+        }
+        Command::ConfigGet(patterns) => {
+            // the only parameters that can be configured, lua-time-limit being the former name of
+            // busy-reply-threshold
+            let busy_threshold = context.running_script.busy_threshold_millis().to_string();
+            let parameters = [
+                ("busy-reply-threshold", busy_threshold.clone()),
+                ("lua-time-limit", busy_threshold),
+                (
+                    "requirepass",
+                    String::from_utf8_lossy(&context.acl.requirepass()).to_string(),
+                ),
+                (
+                    "notify-keyspace-events",
+                    keyspace_events::format(context.pubsub.keyspace_events()),
+                ),
+            ];
+
+            let mut reply = vec![];
+            for (parameter, value) in parameters {
+                let matched = patterns
+                    .iter()
+                    .any(|pattern| glob_match(&pattern.to_ascii_lowercase(), parameter.as_bytes()));
+                if matched {
+                    reply.push(BulkString(parameter.as_bytes().to_vec()));
+                    reply.push(BulkString(value.into_bytes()));
+                }
+            }
+            RedisResponse::array(reply)
+        }
+        Command::ConfigSet(items) => {
+            // every parameter is checked before any of them is set
+            let mut flags = None;
+            let mut busy_threshold = None;
+            let mut requirepass = None;
+            for (parameter, value) in &items {
+                let parameter = String::from_utf8_lossy(parameter).to_lowercase();
+                match parameter.as_str() {
+                    "notify-keyspace-events" => match keyspace_events::parse(value) {
+                        Some(parsed) => flags = Some(parsed),
+                        None => {
+                            return RedisResponse::error(RedisCommandError::InvalidKeyspaceEvents)
+                        }
+                    },
+                    "busy-reply-threshold" | "lua-time-limit" => {
+                        match std::str::from_utf8(value).ok().and_then(|v| v.parse().ok()) {
+                            Some(millis) => busy_threshold = Some(millis),
+                            None => {
+                                return RedisResponse::error(
+                                    RedisCommandError::InvalidConfigInteger(parameter),
+                                )
+                            }
+                        }
+                    }
+                    "requirepass" => requirepass = Some(value.clone()),
+                    _ => {
+                        return RedisResponse::error(RedisCommandError::UnsupportedConfig(
+                            parameter,
+                        ))
+                    }
+                }
+            }
+
+            if let Some(flags) = flags {
+                context.pubsub.set_keyspace_events(flags);
+            }
+            if let Some(millis) = busy_threshold {
+                context.running_script.set_busy_threshold_millis(millis);
+            }
+            if let Some(password) = requirepass {
+                context.acl.set_requirepass(password);
+            }
+            RedisResponse::okay()
+        }
+        Command::AclCat(category) => match category {
+            None => RedisResponse::array(
+                category::CATEGORIES
+                    .iter()
+                    .map(|category| BulkString(category.as_bytes().to_vec()))
+                    .collect(),
+            ),
+            Some(category) => {
+                let category = String::from_utf8_lossy(&category).to_lowercase();
+                if !category::is_category(&category) {
+                    return RedisResponse::error(RedisCommandError::UnknownCategory(category));
+                }
+
+                RedisResponse::array(
+                    category::category_commands(&category)
+                        .into_iter()
+                        .map(|name| BulkString(name.as_bytes().to_vec()))
+                        .collect(),
+                )
+            }
+        },
+        Command::AclGenPass(bits) => {
+            RedisResponse::single(BulkString(generate_password(bits).into_bytes()))
+        }
+        // the clients of a downgraded user are killed, as they were allowed more than it is now
+        Command::AclSetUser(user, rules) => match context.acl.set_user(&user, &rules) {
+            Ok(downgraded) => {
+                if downgraded {
+                    context.clients.kill_user(&String::from_utf8_lossy(&user));
+                }
+                RedisResponse::okay()
+            }
+            Err(err) => RedisResponse::error(err),
+        },
+        Command::AclDelUser(users) => match context.acl.delete_users(&users) {
+            Ok(deleted) => {
+                for user in &deleted {
+                    context.clients.kill_user(user);
+                }
+                RedisResponse::single(Integer(deleted.len() as i64))
+            }
+            Err(err) => RedisResponse::error(err),
+        },
+        Command::AclGetUser(user) => match context.acl.user(&user) {
+            Some(user) => {
+                let mut flags = vec![BulkString(
+                    (if user.enabled { "on" } else { "off" })
+                        .as_bytes()
+                        .to_vec(),
+                )];
+                if user.nopass {
+                    flags.push(BulkString(b"nopass".to_vec()));
+                }
+
+                RedisResponse::array(vec![
+                    BulkString(b"flags".to_vec()),
+                    Array(flags),
+                    BulkString(b"passwords".to_vec()),
+                    Array(
+                        user.passwords
+                            .iter()
+                            .map(|hash| BulkString(hash.as_bytes().to_vec()))
+                            .collect(),
+                    ),
+                    BulkString(b"commands".to_vec()),
+                    BulkString(user.describe_commands().into_bytes()),
+                    BulkString(b"keys".to_vec()),
+                    BulkString(user.describe_keys().into_bytes()),
+                ])
+            }
+            None => RedisResponse::single(Nil),
+        },
+        Command::AclList => RedisResponse::array(
+            context
+                .acl
+                .users()
+                .iter()
+                .map(|user| BulkString(user.describe().into_bytes()))
+                .collect(),
+        ),
+        Command::AclWhoAmI => RedisResponse::single(BulkString(client.user.as_bytes().to_vec())),
+        Command::ClientId => RedisResponse::single(Integer(client.id as i64)),
+        // the client itself can be killed, its connection being closed once replied
+        Command::ClientKillAddr(addr) => {
+            let filter = ClientKillFilter {
+                addr: Some(String::from_utf8_lossy(&addr).to_string()),
+                skip_me: false,
+                ..ClientKillFilter::default()
+            };
+            match context.clients.kill(&filter, client.id) {
+                0 => RedisResponse::error(RedisCommandError::NoSuchClient),
+                _ => RedisResponse::okay(),
+            }
+        }
+        Command::ClientKill(filter) => {
+            if let Some(user) = &filter.user {
+                if context.acl.user(user).is_none() {
+                    return RedisResponse::error(RedisCommandError::NoSuchUser(
+                        String::from_utf8_lossy(user).to_string(),
+                    ));
+                }
+            }
+
+            let killed = context.clients.kill(&filter, client.id);
+            RedisResponse::single(Integer(killed as i64))
+        }
+        Command::Quit => RedisResponse::quit(),
+        // the connection is back to its initial state, as when it was opened
+        Command::Reset => {
+            client.transaction = None;
+            context.watched_keys.unwatch_all(&mut client.watch);
+            context.pubsub.unsubscribe_all(&mut client.subscriptions);
+            client.db = 0;
+            client.authenticated = !context.acl.requires_auth();
+            client.user = acl::DEFAULT_USER.to_string();
+            RedisResponse::single(SimpleString(b"RESET".to_vec()))
+        }
+        // the default user when no username is given
+        Command::Auth(username, password) => {
+            if username.is_none() && context.acl.is_default_user_nopass() {
+                return RedisResponse::error(RedisCommandError::AuthWithoutPassword);
+            }
+
+            let username = username.unwrap_or_else(|| acl::DEFAULT_USER.as_bytes().to_vec());
+            match context.acl.authenticate(&username, &password) {
+                true => {
+                    client.authenticated = true;
+                    client.user = String::from_utf8_lossy(&username).to_string();
+                    RedisResponse::okay()
+                }
+                false => RedisResponse::error(RedisCommandError::WrongPass),
+            }
+        }
+        Command::Multi => match &mut client.transaction {
+            Some(transaction) if context.transaction_savepoints => {
+                transaction.savepoints.push(transaction.commands.len());
+                RedisResponse::okay()
+            }
+            Some(_) => RedisResponse::error(RedisCommandError::NestedMulti),
+            None => {
+                client.transaction = Some(Transaction::default());
+                RedisResponse::okay()
+            }
+        },
+        Command::Exec => {
+            let response = match client.transaction.take() {
+                Some(transaction) if transaction.aborted => {
+                    RedisResponse::error(RedisCommandError::ExecAbort)
+                }
+                Some(transaction) => exec(storage, context, client, transaction),
+                None => return RedisResponse::error(RedisCommandError::WithoutMulti("EXEC")),
+            };
+            context.watched_keys.unwatch_all(&mut client.watch);
+            response
+        }
+        Command::Discard => match client.transaction.take() {
+            Some(_) => {
+                context.watched_keys.unwatch_all(&mut client.watch);
+                RedisResponse::okay()
+            }
+            None => RedisResponse::error(RedisCommandError::WithoutMulti("DISCARD")),
+        },
+        Command::Watch(keys) => {
+            if client.transaction.is_some() {
+                return RedisResponse::error(RedisCommandError::WatchInMulti);
+            }
+
+            // the keys which already expired are removed first, so that their removal does not
+            // count as a modification
+            let mut locked_storage = lock_db(storage, client.db);
+            keys.iter().for_each(|key| {
+                locked_storage.contains(key);
+            });
+            drop(locked_storage);
+            notify_expired_keys(storage, context);
+
+            for key in &keys {
+                context
+                    .watched_keys
+                    .watch(&mut client.watch, client.db, key);
+            }
+            RedisResponse::okay()
+        }
+        Command::Unwatch => {
+            context.watched_keys.unwatch_all(&mut client.watch);
+            RedisResponse::okay()
+        }
+        Command::Eval(script, keys, args) => {
+            context.scripts.add(&script);
+            scripting::eval(storage, context, client, &script, keys, args)
+        }
+        Command::EvalSha(sha, keys, args) => match context.scripts.get(&sha) {
+            Some(script) => scripting::eval(storage, context, client, &script, keys, args),
+            None => RedisResponse::error(RedisCommandError::NoScript),
+        },
+        Command::ScriptLoad(script) => match scripting::compile(&script) {
+            Ok(()) => RedisResponse::single(BulkString(context.scripts.add(&script).into_bytes())),
+            Err(err) => RedisResponse::error(err),
+        },
+        Command::ScriptExists(shas) => RedisResponse::array(
+            shas.iter()
+                .map(|sha| Integer(context.scripts.contains(sha) as i64))
+                .collect(),
+        ),
+        Command::ScriptFlush => {
+            context.scripts.flush();
+            RedisResponse::okay()
+        }
+        // SCRIPT KILL does not wait for the running script, called from a transaction
+        Command::ScriptKill => RedisResponse::error(RedisCommandError::NotBusy),
+        Command::FunctionLoad(code, replace) => {
+            let library = functions::parse_metadata(&code).and_then(|(name, body)| {
+                let functions = scripting::register(body)?;
+                if functions.is_empty() {
+                    return Err(RedisCommandError::NoFunctionsRegistered);
+                }
+
+                Ok(Library {
+                    name,
+                    code: code.clone(),
+                    functions,
+                })
+            });
+            match library.and_then(|library| context.libraries.load(library, replace)) {
+                Ok(name) => RedisResponse::single(BulkString(name.into_bytes())),
+                Err(err) => RedisResponse::error(err),
+            }
+        }
+        Command::FunctionList(pattern, with_code) => RedisResponse::array(
+            context
+                .libraries
+                .list(pattern.as_deref())
+                .into_iter()
+                .map(|library| library_reply(library, with_code))
+                .collect(),
+        ),
+        Command::FunctionDelete(library) => match context.libraries.delete(&library) {
+            true => RedisResponse::okay(),
+            false => RedisResponse::error(RedisCommandError::LibraryNotFound),
+        },
+        Command::FunctionDump => RedisResponse::single(BulkString(context.libraries.dump())),
+        Command::FCall(name, keys, args, read_only) => match context.libraries.function(&name) {
+            Some((_, function)) if read_only && !function.is_read_only() => {
+                RedisResponse::error(RedisCommandError::WriteFlagInReadOnly)
+            }
+            Some((code, function)) => {
+                scripting::fcall(storage, context, client, &code, &function, keys, args)
+            }
+            None => RedisResponse::error(RedisCommandError::FunctionNotFound),
+        },
+        Command::Subscribe(channels) => {
+            let confirmations = channels
+                .into_iter()
+                .map(|channel| {
+                    context
+                        .pubsub
+                        .subscribe(&mut client.subscriptions, &channel);
+                    subscription_reply(b"subscribe", Some(channel), client.subscriptions.count())
+                })
+                .collect();
+            RedisResponse::multiple(confirmations)
+        }
+        Command::Unsubscribe(channels) => {
+            // all the channels when none is given
+            let channels = match channels.is_empty() {
+                true => client.subscriptions.channels.iter().cloned().collect(),
+                false => channels,
+            };
+            if channels.is_empty() {
+                return RedisResponse::single(subscription_reply(
+                    b"unsubscribe",
+                    None,
+                    client.subscriptions.count(),
+                ));
+            }
+
+            let confirmations = channels
+                .into_iter()
+                .map(|channel| {
+                    context
+                        .pubsub
+                        .unsubscribe(&mut client.subscriptions, &channel);
+                    subscription_reply(b"unsubscribe", Some(channel), client.subscriptions.count())
+                })
+                .collect();
+            RedisResponse::multiple(confirmations)
+        }
+        Command::PSubscribe(patterns) => {
+            let confirmations = patterns
+                .into_iter()
+                .map(|pattern| {
+                    context
+                        .pubsub
+                        .psubscribe(&mut client.subscriptions, &pattern);
+                    subscription_reply(b"psubscribe", Some(pattern), client.subscriptions.count())
+                })
+                .collect();
+            RedisResponse::multiple(confirmations)
+        }
+        Command::PUnsubscribe(patterns) => {
+            // all the patterns when none is given
+            let patterns = match patterns.is_empty() {
+                true => client.subscriptions.patterns.iter().cloned().collect(),
+                false => patterns,
+            };
+            if patterns.is_empty() {
+                return RedisResponse::single(subscription_reply(
+                    b"punsubscribe",
+                    None,
+                    client.subscriptions.count(),
+                ));
+            }
+
+            let confirmations = patterns
+                .into_iter()
+                .map(|pattern| {
+                    context
+                        .pubsub
+                        .punsubscribe(&mut client.subscriptions, &pattern);
+                    subscription_reply(b"punsubscribe", Some(pattern), client.subscriptions.count())
+                })
+                .collect();
+            RedisResponse::multiple(confirmations)
+        }
+        Command::PubSubChannels(pattern) => {
+            let channels = context.pubsub.channels(pattern.as_deref());
+            RedisResponse::array(channels.into_iter().map(BulkString).collect())
+        }
+        Command::PubSubNumSub(channels) => {
+            let counts = channels
+                .into_iter()
+                .flat_map(|channel| {
+                    let subscribers = context.pubsub.subscribers(&channel);
+                    [BulkString(channel), Integer(subscribers as i64)]
+                })
+                .collect();
+            RedisResponse::array(counts)
+        }
+        Command::PubSubNumPat => {
+            RedisResponse::single(Integer(context.pubsub.pattern_count() as i64))
+        }
+        Command::Publish(channel, message) => {
+            let receivers = context.pubsub.publish(&channel, &message);
+            RedisResponse::single(Integer(receivers as i64))
+        }
+        Command::SSubscribe(channels) => {
+            let confirmations = channels
+                .into_iter()
+                .map(|channel| {
+                    context
+                        .pubsub
+                        .ssubscribe(&mut client.subscriptions, &channel);
+                    let count = client.subscriptions.shard_count();
+                    subscription_reply(b"ssubscribe", Some(channel), count)
+                })
+                .collect();
+            RedisResponse::multiple(confirmations)
+        }
+        Command::SUnsubscribe(channels) => {
+            // all the shard channels when none is given
+            let channels = match channels.is_empty() {
+                true => client
+                    .subscriptions
+                    .shard_channels
+                    .iter()
+                    .cloned()
+                    .collect(),
+                false => channels,
+            };
+            if channels.is_empty() {
+                return RedisResponse::single(subscription_reply(b"sunsubscribe", None, 0));
+            }
+
+            let confirmations = channels
+                .into_iter()
+                .map(|channel| {
+                    context
+                        .pubsub
+                        .sunsubscribe(&mut client.subscriptions, &channel);
+                    let count = client.subscriptions.shard_count();
+                    subscription_reply(b"sunsubscribe", Some(channel), count)
+                })
+                .collect();
+            RedisResponse::multiple(confirmations)
+        }
+        Command::SPublish(channel, message) => {
+            let receivers = context.pubsub.spublish(&channel, &message);
+            RedisResponse::single(Integer(receivers as i64))
+        }
+        Command::PubSubShardChannels(pattern) => {
+            let channels = context.pubsub.shard_channels(pattern.as_deref());
+            RedisResponse::array(channels.into_iter().map(BulkString).collect())
+        }
+        Command::PubSubShardNumSub(channels) => {
+            let counts = channels
+                .into_iter()
+                .flat_map(|channel| {
+                    let subscribers = context.pubsub.shard_subscribers(&channel);
+                    [BulkString(channel), Integer(subscribers as i64)]
+                })
+                .collect();
+            RedisResponse::array(counts)
+        }
+    }
+}
+
+/// add `increment` to the integer stored at `key`, which counts as 0 when it does not exist
+fn incr_by<T: Storage>(
+    storage: &Arc<Mutex<T>>,
+    context: &Arc<ServerContext>,
+    client: &Client,
+    key: &[u8],
+    increment: i64,
+) -> RedisResponse {
+    let mut storage = lock_db(storage, client.db);
+    if is_wrong_type(&mut *storage, key, RedisType::String) {
+        return RedisResponse::error(RedisCommandError::WrongType);
+    }
+
+    let value = match storage.read(key) {
+        Some(value) => match std::str::from_utf8(value).map(str::parse::<i64>) {
+            Ok(Ok(value)) => value,
+            _ => return RedisResponse::error(RedisCommandError::NotInteger),
+        },
+        None => 0,
+    };
+
+    match value.checked_add(increment) {
+        Some(value) => {
+            storage.write(key, value.to_string().as_bytes());
+            notify(context, client.db, keyspace_events::STRING, "incrby", key);
+            RedisResponse::single(RedisResponseType::Integer(value))
+        }
+        None => RedisResponse::error(RedisCommandError::IncrementOverflow),
+    }
+}
+
+fn injected_miss(context: &Arc<ServerContext>, key: &[u8]) -> bool {
+    match context.miss_injections.lock() {
+        Ok(mut miss_injections) => miss_injections.consume(key),
+        Err(_) => false,
+    }
+}
+
+/// hex encoded password made of `bits` random bits (rounded up to the next multiple of 4)
+fn generate_password(bits: u64) -> String {
+    let chars = bits.div_ceil(4) as usize;
+    let mut bytes = vec![0_u8; chars.div_ceil(2)];
+    OsRng.fill_bytes(&mut bytes);
+
+    let mut password = bytes
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect::<String>();
+    password.truncate(chars);
+    password
+}
+
+/// `true` if `key` exists and holds a value of another type than `data_type`
+/// push `values` one after the other at the `end` of the list, creating it when missing
+fn push<T: Storage + ?Sized>(
+    storage: &mut T,
+    context: &ServerContext,
+    db: usize,
+    list_key: &[u8],
+    values: Vec<Vec<u8>>,
+    end: ListEnd,
+) -> RedisResponse {
+    let list = match storage.list_mut(list_key) {
+        Some(list) => list,
+        None => return RedisResponse::error(RedisCommandError::WrongType),
+    };
+
+    values.into_iter().for_each(|value| list.push(end, value));
+    notify(
+        context,
+        db,
+        keyspace_events::LIST,
+        push_event(end),
+        list_key,
+    );
+    RedisResponse::single(RedisResponseType::Integer(list.data.len() as i64))
+}
+
+/// pop one element (or up to `count` ones) from the `end` of the list, deleting it once empty
+fn pop<T: Storage + ?Sized>(
+    storage: &mut T,
+    context: &ServerContext,
+    db: usize,
+    list_key: &[u8],
+    count: Option<usize>,
+    end: ListEnd,
+) -> RedisResponse {
+    if !storage.contains(list_key) {
+        return RedisResponse::single(RedisResponseType::Nil);
+    }
+
+    let list = match storage.list_mut(list_key) {
+        Some(list) => list,
+        None => return RedisResponse::error(RedisCommandError::WrongType),
+    };
+
+    let popped: Vec<_> = (0..count.unwrap_or(1))
+        .map_while(|_| list.pop(end))
+        .map(RedisResponseType::BulkString)
+        .collect();
+    let emptied = list.data.is_empty();
+    if emptied {
+        storage.remove(list_key);
+    }
+    notify_pop(
+        context,
+        db,
+        keyspace_events::LIST,
+        pop_event(end),
+        list_key,
+        emptied,
+    );
+
+    match count {
+        Some(_) => RedisResponse::array(popped),
+        None => RedisResponse::single(popped.into_iter().next().unwrap_or(RedisResponseType::Nil)),
+    }
+}
+
+// key of the list elements have been popped from, along with the elements
+type PoppedElements = (Vec<u8>, Vec<Vec<u8>>);
+
+/// pop up to `count` elements from the `end` of the first non empty list of `list_keys`, `None`
+/// when they are all empty
+fn multi_pop<T: Storage + ?Sized>(
+    storage: &mut T,
+    context: &ServerContext,
+    db: usize,
+    list_keys: &[Vec<u8>],
+    end: ListEnd,
+    count: usize,
+) -> Result<Option<PoppedElements>, RedisCommandError> {
+    if list_keys
+        .iter()
+        .any(|list_key| is_wrong_type(storage, list_key, RedisType::List))
+    {
+        return Err(RedisCommandError::WrongType);
+    }
+
+    // lists are deleted once empty, the first existing key holds an element
+    let list_key = match list_keys.iter().find(|list_key| storage.contains(list_key)) {
+        Some(list_key) => list_key,
+        None => return Ok(None),
+    };
+
+    let list = storage.list_mut(list_key).unwrap();
+    let elements = (0..count).map_while(|_| list.pop(end)).collect();
+    let emptied = list.data.is_empty();
+    if emptied {
+        storage.remove(list_key);
+    }
+    notify_pop(
+        context,
+        db,
+        keyspace_events::LIST,
+        pop_event(end),
+        list_key,
+        emptied,
+    );
+
+    Ok(Some((list_key.to_vec(), elements)))
+}
+
+/// `[key, element]` for the single element pops, `[key, [elements]]` when a count is given
+fn pop_reply(
+    list_key: Vec<u8>,
+    mut elements: Vec<Vec<u8>>,
+    count: Option<usize>,
+) -> Vec<RedisResponseType> {
+    let elements = match count {
+        Some(_) => RedisResponseType::Array(
+            elements
+                .into_iter()
+                .map(RedisResponseType::BulkString)
+                .collect(),
+        ),
+        None => RedisResponseType::BulkString(elements.remove(0)),
+    };
+
+    vec![RedisResponseType::BulkString(list_key), elements]
+}
+
+/// pop from the first non empty list of `list_keys` like `multi_pop`, or block the client until
+/// an element is pushed to one of them - replying a null array if none is before the timeout
+fn blocking_pop<T: Storage>(
+    storage: &Arc<Mutex<T>>,
+    context: &Arc<ServerContext>,
+    client: &mut Client,
+    list_keys: Vec<Vec<u8>>,
+    end: ListEnd,
+    count: Option<usize>,
+    timeout: Option<Duration>,
+) -> RedisResponse {
+    let mut storage = lock_db(storage, client.db);
+    match multi_pop(
+        &mut *storage,
+        context,
+        client.db,
+        &list_keys,
+        end,
+        count.unwrap_or(1),
+    ) {
+        Ok(Some((list_key, elements))) => {
+            RedisResponse::array(pop_reply(list_key, elements, count))
+        }
+        Ok(None) => {
+            // registered while the storage is locked so that no push can be missed
+            client.blocked = Some(context.blocked_clients.wait(client.db, &list_keys, timeout));
+            RedisResponse::single(RedisResponseType::NilArray)
+        }
+        Err(err) => RedisResponse::error(err),
+    }
+}
+
+/// pop an element from the `from` end of `source` and push it to the `to` end of `destination`,
+/// `None` when `source` does not exist
+fn list_move<T: Storage + ?Sized>(
+    storage: &mut T,
+    context: &ServerContext,
+    db: usize,
+    source: &[u8],
+    destination: &[u8],
+    from: ListEnd,
+    to: ListEnd,
+) -> Result<Option<Vec<u8>>, RedisCommandError> {
+    if is_wrong_type(storage, source, RedisType::List)
+        || is_wrong_type(storage, destination, RedisType::List)
+    {
+        return Err(RedisCommandError::WrongType);
+    }
+
+    if !storage.contains(source) {
+        return Ok(None);
+    }
+
+    let list = storage.list_mut(source).unwrap();
+    let element = list.pop(from).unwrap();
+    let emptied = list.data.is_empty();
+    if emptied {
+        storage.remove(source);
+    }
+    notify_pop(
+        context,
+        db,
+        keyspace_events::LIST,
+        pop_event(from),
+        source,
+        emptied,
+    );
+
+    storage
+        .list_mut(destination)
+        .unwrap()
+        .push(to, element.clone());
+    notify(
+        context,
+        db,
+        keyspace_events::LIST,
+        push_event(to),
+        destination,
+    );
+    Ok(Some(element))
+}
+
+enum SetOperation {
+    Inter,
+    Union,
+    Diff,
+}
+
+impl SetOperation {
+    /// keyspace event of SINTERSTORE, SUNIONSTORE and SDIFFSTORE
+    fn store_event(&self) -> &'static str {
+        match self {
+            SetOperation::Inter => "sinterstore",
+            SetOperation::Union => "sunionstore",
+            SetOperation::Diff => "sdiffstore",
+        }
+    }
+}
+
+/// combine the sets of `set_keys`, the missing keys being empty sets - a difference is made
+/// between the first set and all the others
+fn set_operation<T: Storage + ?Sized>(
+    storage: &mut T,
+    set_keys: &[Vec<u8>],
+    operation: SetOperation,
+) -> Result<HashSet<Vec<u8>>, RedisCommandError> {
+    if set_keys
+        .iter()
+        .any(|set_key| is_wrong_type(storage, set_key, RedisType::Set))
+    {
+        return Err(RedisCommandError::WrongType);
+    }
+
+    let mut sets = set_keys.iter().map(|set_key| {
+        storage
+            .set(set_key)
+            .map(|set| set.data.clone())
+            .unwrap_or_default()
+    });
+    let first = sets.next().unwrap_or_default();
+
+    Ok(sets.fold(first, |result, set| match operation {
+        SetOperation::Inter => result.intersection(&set).cloned().collect(),
+        SetOperation::Union => result.union(&set).cloned().collect(),
+        SetOperation::Diff => result.difference(&set).cloned().collect(),
+    }))
+}
+
+fn set_operation_reply<T: Storage + ?Sized>(
+    storage: &mut T,
+    set_keys: &[Vec<u8>],
+    operation: SetOperation,
+) -> RedisResponse {
+    match set_operation(storage, set_keys, operation) {
+        Ok(members) => RedisResponse::array(
+            members
+                .into_iter()
+                .map(RedisResponseType::BulkString)
+                .collect(),
+        ),
+        Err(err) => RedisResponse::error(err),
+    }
+}
+
+/// count the members of the intersection, up to `limit` unless it is 0, without building it:
+/// the members of the smallest set are looked up in the other ones
+fn set_intersection_card<T: Storage + ?Sized>(
+    storage: &mut T,
+    set_keys: &[Vec<u8>],
+    limit: usize,
+) -> RedisResponse {
+    if set_keys
+        .iter()
+        .any(|set_key| is_wrong_type(storage, set_key, RedisType::Set))
+    {
+        return RedisResponse::error(RedisCommandError::WrongType);
+    }
+
+    let mut set_keys = set_keys
+        .iter()
+        .map(|set_key| {
+            (
+                storage.set(set_key).map_or(0, |set| set.data.len()),
+                set_key,
+            )
+        })
+        .collect::<Vec<_>>();
+    set_keys.sort_unstable_by_key(|(len, _)| *len);
+
+    let (smallest_len, smallest_key) = set_keys[0];
+    let mut card = 0;
+    if smallest_len > 0 {
+        let candidates = storage.set(smallest_key).unwrap().data.clone();
+        for member in candidates {
+            if set_keys[1..].iter().all(|(_, set_key)| {
+                storage
+                    .set(set_key)
+                    .is_some_and(|set| set.data.contains(&member))
+            }) {
+                card += 1;
+                if card == limit {
+                    break;
+                }
+            }
+        }
+    }
+
+    RedisResponse::single(RedisResponseType::Integer(card as i64))
+}
+
+/// overwrite `destination` (whatever its type) with the combined sets, an empty result deletes it
+fn set_operation_store<T: Storage + ?Sized>(
+    storage: &mut T,
+    context: &ServerContext,
+    db: usize,
+    destination: &[u8],
+    set_keys: &[Vec<u8>],
+    operation: SetOperation,
+) -> RedisResponse {
+    let event = operation.store_event();
+    let members = match set_operation(storage, set_keys, operation) {
+        Ok(members) => members,
+        Err(err) => return RedisResponse::error(err),
+    };
+
+    let len = members.len();
+    let existed = storage.remove(destination) > 0;
+    if len > 0 {
+        storage.set_mut(destination).unwrap().data = members;
+        notify(context, db, keyspace_events::SET, event, destination);
+    } else if existed {
+        notify(context, db, keyspace_events::GENERIC, "del", destination);
+    }
+    RedisResponse::single(RedisResponseType::Integer(len as i64))
+}
+
+/// combine the sorted sets of `zset_keys`, the plain sets counting as sorted sets whose members
+/// all score 1 and the missing keys as empty sets - the scores are multiplied by the weight of
+/// their key and the ones of a member found in several sets are combined with `aggregate`, while
+/// a difference keeps the members of the first set with their original score
+fn zset_operation<T: Storage + ?Sized>(
+    storage: &mut T,
+    zset_keys: &[Vec<u8>],
+    weights: &[f64],
+    aggregate: Aggregate,
+    operation: SetOperation,
+) -> Result<RedisSortedSet, RedisCommandError> {
+    if zset_keys.iter().any(|zset_key| {
+        is_wrong_type(storage, zset_key, RedisType::ZSet)
+            && is_wrong_type(storage, zset_key, RedisType::Set)
+    }) {
+        return Err(RedisCommandError::WrongType);
+    }
+
+    let mut zsets = zset_keys.iter().zip(weights).map(|(zset_key, weight)| {
+        let members: HashMap<RedisString, f64> = match storage.zset(zset_key) {
+            Some(zset) => zset
+                .iter()
+                .map(|(member, score)| (member.clone(), score))
+                .collect(),
+            None => storage
+                .set(zset_key)
+                .map(|set| {
+                    set.data
+                        .iter()
+                        .map(|member| (member.clone(), 1.0))
+                        .collect()
+                })
+                .unwrap_or_default(),
+        };
+
+        members
+            .into_iter()
+            // 0 * inf gives 0 rather than NaN
+            .map(|(member, score)| match score * weight {
+                score if score.is_nan() => (member, 0.0),
+                score => (member, score),
+            })
+            .collect::<HashMap<_, _>>()
+    });
+    let first = zsets.next().unwrap_or_default();
+
+    let members = zsets.fold(first, |mut result, zset| {
+        match operation {
+            SetOperation::Inter => {
+                result.retain(|member, _| zset.contains_key(member));
+                for (member, score) in result.iter_mut() {
+                    *score = aggregate.apply(*score, zset[member]);
+                }
+            }
+            SetOperation::Union => {
+                for (member, score) in zset {
+                    result
+                        .entry(member)
+                        .and_modify(|result| *result = aggregate.apply(*result, score))
+                        .or_insert(score);
+                }
+            }
+            SetOperation::Diff => result.retain(|member, _| !zset.contains_key(member)),
+        }
+        result
+    });
+
+    let mut zset = RedisSortedSet::new();
+    for (member, score) in members {
+        zset.insert(member, score);
+    }
+    Ok(zset)
+}
+
+fn zset_operation_reply<T: Storage + ?Sized>(
+    storage: &mut T,
+    zset_keys: &[Vec<u8>],
+    weights: &[f64],
+    aggregate: Aggregate,
+    operation: SetOperation,
+    with_scores: bool,
+) -> RedisResponse {
+    match zset_operation(storage, zset_keys, weights, aggregate, operation) {
+        Ok(zset) => RedisResponse::array(scored_members_reply(zset.iter().collect(), with_scores)),
+        Err(err) => RedisResponse::error(err),
+    }
+}
+
+/// overwrite `destination` (whatever its type) with `zset`, an empty one deleting it, return
+/// the number of members stored
+fn store_zset<T: Storage + ?Sized>(
+    storage: &mut T,
+    context: &ServerContext,
+    db: usize,
+    destination: &[u8],
+    zset: RedisSortedSet,
+    event: &str,
+) -> usize {
+    let len = zset.len();
+    let existed = storage.remove(destination) > 0;
+    if len > 0 {
+        *storage.zset_mut(destination).unwrap() = zset;
+        context.blocked_clients.notify(db, destination);
+        notify(context, db, keyspace_events::ZSET, event, destination);
+    } else if existed {
+        notify(context, db, keyspace_events::GENERIC, "del", destination);
+    }
+    len
+}
+
+/// flat list of the members, each one followed by its score when `with_scores` is set
+fn scored_members_reply(
+    members: Vec<(&RedisString, f64)>,
+    with_scores: bool,
+) -> Vec<RedisResponseType> {
+    members
+        .into_iter()
+        .flat_map(|(member, score)| match with_scores {
+            true => vec![
+                RedisResponseType::BulkString(member.to_vec()),
+                RedisResponseType::BulkString(score.to_string().into_bytes()),
+            ],
+            false => vec![RedisResponseType::BulkString(member.to_vec())],
+        })
+        .collect()
+}
+
+/// flat list of the members popped from the `end` of `zset_key`, each one followed by its score
+fn zset_pop<T: Storage + ?Sized>(
+    storage: &mut T,
+    context: &ServerContext,
+    db: usize,
+    zset_key: Vec<u8>,
+    count: Option<usize>,
+    end: ScoreEnd,
+) -> RedisResponse {
+    match zset_multi_pop(storage, context, db, &[zset_key], end, count.unwrap_or(1)) {
+        Ok(Some((_, members))) => RedisResponse::array(
+            members
+                .into_iter()
+                .flat_map(|(member, score)| {
+                    vec![
+                        RedisResponseType::BulkString(member),
+                        RedisResponseType::BulkString(score.to_string().into_bytes()),
+                    ]
+                })
+                .collect(),
+        ),
+        Ok(None) => RedisResponse::array(vec![]),
+        Err(err) => RedisResponse::error(err),
+    }
+}
+
+type PoppedMembers = (Vec<u8>, Vec<(Vec<u8>, f64)>);
+
+/// pop up to `count` members from the `end` of the first non empty sorted set of `zset_keys`,
+/// `None` when they are all empty
+fn zset_multi_pop<T: Storage + ?Sized>(
+    storage: &mut T,
+    context: &ServerContext,
+    db: usize,
+    zset_keys: &[Vec<u8>],
+    end: ScoreEnd,
+    count: usize,
+) -> Result<Option<PoppedMembers>, RedisCommandError> {
+    if zset_keys
+        .iter()
+        .any(|zset_key| is_wrong_type(storage, zset_key, RedisType::ZSet))
+    {
+        return Err(RedisCommandError::WrongType);
+    }
+
+    // sorted sets are deleted once empty, the first existing key holds a member
+    let zset_key = match zset_keys.iter().find(|zset_key| storage.contains(zset_key)) {
+        Some(zset_key) => zset_key,
+        None => return Ok(None),
+    };
+
+    let zset = storage.zset_mut(zset_key).unwrap();
+    let members = (0..count).map_while(|_| zset.pop(end)).collect();
+    let emptied = zset.is_empty();
+    if emptied {
+        storage.remove(zset_key);
+    }
+    let event = match end {
+        ScoreEnd::Min => "zpopmin",
+        ScoreEnd::Max => "zpopmax",
+    };
+    notify_pop(context, db, keyspace_events::ZSET, event, zset_key, emptied);
+
+    Ok(Some((zset_key.to_vec(), members)))
+}
+
+/// `[key, [[member, score], ...]]`
+fn zset_pop_reply(zset_key: Vec<u8>, members: Vec<(Vec<u8>, f64)>) -> Vec<RedisResponseType> {
+    let members = members
+        .into_iter()
+        .map(|(member, score)| {
+            RedisResponseType::Array(vec![
+                RedisResponseType::BulkString(member),
+                RedisResponseType::BulkString(score.to_string().into_bytes()),
+            ])
+        })
+        .collect();
+
+    vec![
+        RedisResponseType::BulkString(zset_key),
+        RedisResponseType::Array(members),
+    ]
+}
+
+/// pop from the first non empty sorted set of `zset_keys` like `zset_multi_pop`, or block the
+/// client until a member is added to one of them - replying a null array if none is before the
+/// timeout. A single member is replied as `[key, member, score]` when no count is given.
+fn blocking_zset_pop<T: Storage>(
+    storage: &Arc<Mutex<T>>,
+    context: &Arc<ServerContext>,
+    client: &mut Client,
+    zset_keys: Vec<Vec<u8>>,
+    end: ScoreEnd,
+    count: Option<usize>,
+    timeout: Option<Duration>,
+) -> RedisResponse {
+    let mut storage = lock_db(storage, client.db);
+    match zset_multi_pop(
+        &mut *storage,
+        context,
+        client.db,
+        &zset_keys,
+        end,
+        count.unwrap_or(1),
+    ) {
+        Ok(Some((zset_key, members))) if count.is_some() => {
+            RedisResponse::array(zset_pop_reply(zset_key, members))
+        }
+        Ok(Some((zset_key, mut members))) => {
+            let (member, score) = members.remove(0);
+            RedisResponse::array(vec![
+                RedisResponseType::BulkString(zset_key),
+                RedisResponseType::BulkString(member),
+                RedisResponseType::BulkString(score.to_string().into_bytes()),
+            ])
+        }
+        Ok(None) => {
+            // registered while the storage is locked so that no addition can be missed
+            client.blocked = Some(context.blocked_clients.wait(client.db, &zset_keys, timeout));
+            RedisResponse::single(RedisResponseType::NilArray)
+        }
+        Err(err) => RedisResponse::error(err),
+    }
+}
+
+/// rank of `member` from the lowest score, or from the highest one when `rev` is set
+fn zrank_reply<T: Storage + ?Sized>(
+    storage: &mut T,
+    zset_key: &[u8],
+    member: &[u8],
+    rev: bool,
+    with_score: bool,
+) -> RedisResponse {
+    if is_wrong_type(storage, zset_key, RedisType::ZSet) {
+        return RedisResponse::error(RedisCommandError::WrongType);
+    }
+
+    let zset = match storage.zset(zset_key) {
+        Some(zset) => zset,
+        None => return RedisResponse::single(RedisResponseType::Nil),
+    };
+    let (rank, score) = match (zset.rank(member), zset.score(member)) {
+        (Some(rank), Some(score)) => (rank, score),
+        _ => return RedisResponse::single(RedisResponseType::Nil),
+    };
+
+    let rank = match rev {
+        true => zset.len() - 1 - rank,
+        false => rank,
+    };
+    match with_score {
+        true => RedisResponse::array(vec![
+            RedisResponseType::Integer(rank as i64),
+            RedisResponseType::BulkString(score.to_string().into_bytes()),
+        ]),
+        false => RedisResponse::single(RedisResponseType::Integer(rank as i64)),
+    }
+}
+
+/// entries of `stream_key` with an ID between `start` and `end` included
+fn stream_range<T: Storage + ?Sized>(
+    storage: &mut T,
+    stream_key: &[u8],
+    start: StreamId,
+    end: StreamId,
+    count: Option<usize>,
+    rev: bool,
+) -> RedisResponse {
+    if is_wrong_type(storage, stream_key, RedisType::Stream) {
+        return RedisResponse::error(RedisCommandError::WrongType);
+    }
+
+    let entries = match storage.stream(stream_key) {
+        Some(stream) => stream.range(start, end, count, rev),
+        None => vec![],
+    };
+    RedisResponse::array(stream_entries_reply(entries))
+}
+
+/// IDs XREAD reads `stream_keys` from, `$` (`None`) standing for the last ID of a stream
+fn stream_read_ids<T: Storage + ?Sized>(
+    storage: &mut T,
+    stream_keys: &[Vec<u8>],
+    ids: Vec<Option<StreamId>>,
+) -> Result<Vec<StreamId>, RedisCommandError> {
+    stream_keys
+        .iter()
+        .zip(ids)
+        .map(|(stream_key, id)| {
+            if is_wrong_type(storage, stream_key, RedisType::Stream) {
+                return Err(RedisCommandError::WrongType);
+            }
+
+            Ok(id.unwrap_or_else(|| {
+                storage
+                    .stream(stream_key)
+                    .map_or(StreamId::MIN, RedisStream::last_id)
+            }))
+        })
+        .collect()
+}
+
+/// `[key, [entries]]` for each of `stream_keys` having entries with an ID greater than its own
+/// in `ids`, up to `count` entries per stream
+fn stream_read<T: Storage + ?Sized>(
+    storage: &mut T,
+    stream_keys: &[Vec<u8>],
+    ids: &[StreamId],
+    count: Option<usize>,
+) -> Result<Vec<RedisResponseType>, RedisCommandError> {
+    let mut streams = vec![];
+    for (stream_key, id) in stream_keys.iter().zip(ids) {
+        if is_wrong_type(storage, stream_key, RedisType::Stream) {
+            return Err(RedisCommandError::WrongType);
+        }
+
+        let entries = match (storage.stream(stream_key), id.next()) {
+            (Some(stream), Some(start)) => stream.range(start, StreamId::MAX, count, false),
+            _ => continue,
+        };
+        if !entries.is_empty() {
+            streams.push(RedisResponseType::Array(vec![
+                RedisResponseType::BulkString(stream_key.to_vec()),
+                RedisResponseType::Array(stream_entries_reply(entries)),
+            ]));
+        }
+    }
+
+    Ok(streams)
+}
+
+/// stream at `stream_key` as XINFO and XSETID require it to exist
+fn info_stream<'a, T: Storage + ?Sized>(
+    storage: &'a mut T,
+    stream_key: &[u8],
+) -> Result<&'a RedisStream, RedisCommandError> {
+    if is_wrong_type(storage, stream_key, RedisType::Stream) {
+        return Err(RedisCommandError::WrongType);
+    }
+
+    storage
+        .stream(stream_key)
+        .ok_or(RedisCommandError::NoSuchKey)
+}
+
+/// XINFO STREAM reply, along with the entries up to `full` of them and the groups in detail for
+/// the FULL form
+fn stream_info_reply(stream: &RedisStream, full: Option<Option<usize>>) -> Vec<RedisResponseType> {
+    use RedisResponseType::*;
+
+    let entry_reply = |entry: Option<(&StreamId, &StreamFields)>| match entry {
+        Some(entry) => stream_entries_reply(vec![entry]).remove(0),
+        None => Nil,
+    };
+    let recorded_first_id = stream.first_entry().map_or(StreamId::MIN, |(id, _)| *id);
+
+    let mut reply = vec![
+        BulkString(b"length".to_vec()),
+        Integer(stream.len() as i64),
+        BulkString(b"radix-tree-keys".to_vec()),
+        Integer(stream.node_count() as i64),
+        // the nodes are under a root node
+        BulkString(b"radix-tree-nodes".to_vec()),
+        Integer(stream.node_count() as i64 + 1),
+        BulkString(b"last-generated-id".to_vec()),
+        BulkString(stream.last_id().to_string().into_bytes()),
+        BulkString(b"max-deleted-entry-id".to_vec()),
+        BulkString(stream.max_deleted_id().to_string().into_bytes()),
+        BulkString(b"entries-added".to_vec()),
+        Integer(stream.entries_added() as i64),
+        BulkString(b"recorded-first-entry-id".to_vec()),
+        BulkString(recorded_first_id.to_string().into_bytes()),
+    ];
+
+    let count = match full {
+        Some(count) => count,
+        None => {
+            reply.append(&mut vec![
+                BulkString(b"groups".to_vec()),
+                Integer(stream.groups.len() as i64),
+                BulkString(b"first-entry".to_vec()),
+                entry_reply(stream.first_entry()),
+                BulkString(b"last-entry".to_vec()),
+                entry_reply(stream.last_entry()),
+            ]);
+            return reply;
+        }
+    };
+
+    let entries = stream.range(StreamId::MIN, StreamId::MAX, count, false);
+    let count = count.unwrap_or(usize::MAX);
+    let groups = stream
+        .groups
+        .iter()
+        .map(|(name, group)| {
+            let pending = group
+                .pending
+                .iter()
+                .take(count)
+                .map(|(id, entry)| {
+                    Array(vec![
+                        BulkString(id.to_string().into_bytes()),
+                        BulkString(entry.consumer.to_vec()),
+                        Integer(entry.delivery_time as i64),
+                        Integer(entry.delivery_count as i64),
+                    ])
+                })
+                .collect();
+            let consumers = group
+                .consumers
+                .iter()
+                .map(|(name, consumer)| {
+                    let pending = consumer
+                        .pending
+                        .iter()
+                        .take(count)
+                        .map(|id| {
+                            let entry = &group.pending[id];
+                            Array(vec![
+                                BulkString(id.to_string().into_bytes()),
+                                Integer(entry.delivery_time as i64),
+                                Integer(entry.delivery_count as i64),
+                            ])
+                        })
+                        .collect();
+                    Array(vec![
+                        BulkString(b"name".to_vec()),
+                        BulkString(name.to_vec()),
+                        BulkString(b"seen-time".to_vec()),
+                        Integer(consumer.seen_time as i64),
+                        BulkString(b"active-time".to_vec()),
+                        Integer(consumer.active_time.map_or(-1, |time| time as i64)),
+                        BulkString(b"pel-count".to_vec()),
+                        Integer(consumer.pending.len() as i64),
+                        BulkString(b"pending".to_vec()),
+                        Array(pending),
+                    ])
+                })
+                .collect();
+
+            Array(vec![
+                BulkString(b"name".to_vec()),
+                BulkString(name.to_vec()),
+                BulkString(b"last-delivered-id".to_vec()),
+                BulkString(group.last_delivered_id.to_string().into_bytes()),
+                BulkString(b"entries-read".to_vec()),
+                group.entries_read.map_or(Nil, |read| Integer(read as i64)),
+                BulkString(b"lag".to_vec()),
+                stream.lag(group).map_or(Nil, |lag| Integer(lag as i64)),
+                BulkString(b"pel-count".to_vec()),
+                Integer(group.pending.len() as i64),
+                BulkString(b"pending".to_vec()),
+                Array(pending),
+                BulkString(b"consumers".to_vec()),
+                Array(consumers),
+            ])
+        })
+        .collect();
+
+    reply.append(&mut vec![
+        BulkString(b"entries".to_vec()),
+        Array(stream_entries_reply(entries)),
+        BulkString(b"groups".to_vec()),
+        Array(groups),
+    ]);
+    reply
+}
+
+/// stream at `stream_key` having the consumer group `group`
+fn group_stream<'a, T: Storage + ?Sized>(
+    storage: &'a mut T,
+    stream_key: &[u8],
+    group: &[u8],
+) -> Result<&'a mut RedisStream, RedisCommandError> {
+    if is_wrong_type(storage, stream_key, RedisType::Stream) {
+        return Err(RedisCommandError::WrongType);
+    }
+
+    let no_group = || {
+        RedisCommandError::NoGroup(
+            String::from_utf8_lossy(stream_key).into_owned(),
+            String::from_utf8_lossy(group).into_owned(),
+        )
+    };
+    if !storage.contains(stream_key) {
+        return Err(no_group());
+    }
+
+    let stream = storage.stream_mut(stream_key).unwrap();
+    match stream.groups.contains_key(group) {
+        true => Ok(stream),
+        false => Err(no_group()),
+    }
+}
+
+/// stream at `stream_key` having the consumer group `group`, for XGROUP which requires the key to
+/// exist
+fn xgroup_stream<'a, T: Storage + ?Sized>(
+    storage: &'a mut T,
+    stream_key: &[u8],
+    group: &[u8],
+) -> Result<&'a mut RedisStream, RedisCommandError> {
+    if !storage.contains(stream_key) {
+        return Err(RedisCommandError::GroupKeyMissing);
+    }
+
+    group_stream(storage, stream_key, group)
+}
+
+/// list of `[id, [field, value, ...]]` entries read by a consumer, with a nil in place of the
+/// fields of the deleted ones
+fn consumer_entries_reply(entries: Vec<ConsumerEntry>) -> Vec<RedisResponseType> {
+    entries
+        .into_iter()
+        .map(|(id, fields)| {
+            let fields = match fields {
+                Some(fields) => RedisResponseType::Array(
+                    fields
+                        .into_iter()
+                        .flat_map(|(field, value)| {
+                            vec![
+                                RedisResponseType::BulkString(field),
+                                RedisResponseType::BulkString(value),
+                            ]
+                        })
+                        .collect(),
+                ),
+                None => RedisResponseType::Nil,
+            };
+            RedisResponseType::Array(vec![
+                RedisResponseType::BulkString(id.to_string().into_bytes()),
+                fields,
+            ])
+        })
+        .collect()
+}
+
+/// claimed entries, or their IDs only when `just_id` is set, along with the IDs of the deleted
+/// ones
+fn claims_reply(
+    claims: Vec<Claim>,
+    just_id: bool,
+) -> (Vec<RedisResponseType>, Vec<RedisResponseType>) {
+    let mut claimed = vec![];
+    let mut deleted = vec![];
+    for claim in claims {
+        match claim {
+            Claim::Claimed(id, _) if just_id => {
+                claimed.push(RedisResponseType::BulkString(id.to_string().into_bytes()))
+            }
+            Claim::Claimed(id, fields) => {
+                claimed.append(&mut consumer_entries_reply(vec![(id, Some(fields))]))
+            }
+            Claim::Deleted(id) => {
+                deleted.push(RedisResponseType::BulkString(id.to_string().into_bytes()))
+            }
+            Claim::Skipped => {}
+        }
+    }
+
+    (claimed, deleted)
+}
+
+/// current time in milliseconds since the Unix epoch
+fn now_millis() -> u64 {
+    Utc::now().timestamp_millis().max(0) as u64
+}
+
+/// list of `[id, [field, value, ...]]` entries
+fn stream_entries_reply(entries: Vec<(&StreamId, &StreamFields)>) -> Vec<RedisResponseType> {
+    entries
+        .into_iter()
+        .map(|(id, fields)| {
+            RedisResponseType::Array(vec![
+                RedisResponseType::BulkString(id.to_string().into_bytes()),
+                RedisResponseType::Array(
+                    fields
+                        .iter()
+                        .flat_map(|(field, value)| {
+                            vec![
+                                RedisResponseType::BulkString(field.to_vec()),
+                                RedisResponseType::BulkString(value.to_vec()),
+                            ]
+                        })
+                        .collect(),
+                ),
+            ])
+        })
+        .collect()
+}
+
+/// HyperLogLog stored at `key`, `None` if the key does not exist
+/// elements of the list, set or sorted set `key` sorted by SORT, or by their weights when a BY
+/// pattern is given, within its LIMIT - each one replaced by the values of the GET patterns when
+/// there are any, `None` standing for the values which do not exist
+fn sort<T: Storage + ?Sized>(
+    storage: &mut T,
+    key: &[u8],
+    options: &SortOptions,
+) -> Result<Vec<Option<RedisString>>, RedisCommandError> {
+    let data_type = match storage.contains(key) {
+        true => storage.meta(key).map(|meta| meta.data_type),
+        false => None,
+    };
+    let mut elements: Vec<RedisString> = match data_type {
+        Some(RedisType::List) => storage
+            .list(key)
+            .map(|list| list.data.iter().cloned().collect())
+            .unwrap_or_default(),
+        Some(RedisType::Set) => storage
+            .set(key)
+            .map(|set| set.data.iter().cloned().collect())
+            .unwrap_or_default(),
+        Some(RedisType::ZSet) => storage
+            .zset(key)
+            .map(|zset| zset.iter().map(|(member, _)| member.clone()).collect())
+            .unwrap_or_default(),
+        Some(_) => return Err(RedisCommandError::WrongType),
+        None => vec![],
+    };
+
+    match &options.by {
+        // a BY pattern without `*` keeps the elements in their original order
+        Some(pattern) if !pattern.contains(&b'*') => {
+            if data_type == Some(RedisType::ZSet) && options.desc {
+                elements.reverse();
+            }
+        }
+        by => {
+            let weights = elements
+                .iter()
+                .map(|element| match by {
+                    Some(pattern) => sort_lookup(storage, pattern, element),
+                    None => Some(element.clone()),
+                })
+                .collect::<Vec<_>>();
+
+            let mut weighted = elements.into_iter().zip(weights).collect::<Vec<_>>();
+            if options.alpha {
+                // the missing weights come first
+                weighted.sort_by(|(element, weight), (other, other_weight)| {
+                    weight.cmp(other_weight).then_with(|| element.cmp(other))
+                });
+                elements = weighted.into_iter().map(|(element, _)| element).collect();
+            } else {
+                // the missing weights count as 0
+                let mut scored = Vec::with_capacity(weighted.len());
+                for (element, weight) in weighted {
+                    let score = match weight {
+                        Some(weight) => std::str::from_utf8(&weight)
+                            .ok()
+                            .and_then(|weight| weight.parse::<f64>().ok())
+                            .filter(|score| !score.is_nan())
+                            .ok_or(RedisCommandError::SortNotFloat)?,
+                        None => 0.0,
+                    };
+                    scored.push((element, score));
+                }
+                scored.sort_by(|(element, score), (other, other_score)| {
+                    score
+                        .total_cmp(other_score)
+                        .then_with(|| element.cmp(other))
+                });
+                elements = scored.into_iter().map(|(element, _)| element).collect();
+            }
+
+            if options.desc {
+                elements.reverse();
+            }
+        }
+    }
+
+    let (offset, count) = options.limit.unwrap_or((0, -1));
+    let count = match count {
+        count if count < 0 => elements.len(),
+        count => count as usize,
+    };
+    let elements = elements
+        .into_iter()
+        .skip(offset.max(0) as usize)
+        .take(count);
+
+    if options.get.is_empty() {
+        return Ok(elements.map(Some).collect());
+    }
+
+    let mut values = vec![];
+    for element in elements {
+        for pattern in &options.get {
+            values.push(sort_lookup(storage, pattern, &element));
+        }
+    }
+    Ok(values)
+}
+
+/// value of the key made of `pattern` with its first `*` replaced by `element`, or of the field
+/// of this hash when the pattern ends with `->field` - `#` standing for the element itself, and
+/// `None` when the pattern has no `*` or the value does not exist
+fn sort_lookup<T: Storage + ?Sized>(
+    storage: &mut T,
+    pattern: &[u8],
+    element: &[u8],
+) -> Option<RedisString> {
+    if pattern == b"#" {
+        return Some(element.to_vec());
+    }
+
+    let star = pattern.iter().position(|byte| *byte == b'*')?;
+    let (key_pattern, field) = match pattern[star + 1..]
+        .windows(2)
+        .position(|bytes| bytes == b"->")
+    {
+        Some(arrow) if star + arrow + 3 < pattern.len() => (
+            &pattern[..star + arrow + 1],
+            Some(&pattern[star + arrow + 3..]),
+        ),
+        _ => (pattern, None),
+    };
+
+    let mut key = key_pattern[..star].to_vec();
+    key.extend_from_slice(element);
+    key.extend_from_slice(&key_pattern[star + 1..]);
+
+    match field {
+        Some(field) if !is_wrong_type(storage, &key, RedisType::Hash) => {
+            storage.hread(&key, field).map(<[u8]>::to_vec)
+        }
+        None if !is_wrong_type(storage, &key, RedisType::String) => {
+            storage.read(&key).map(<[u8]>::to_vec)
+        }
+        _ => None,
+    }
+}
+
+fn sort_reply(values: Vec<Option<RedisString>>) -> Vec<RedisResponseType> {
+    values
+        .into_iter()
+        .map(|value| match value {
+            Some(value) => RedisResponseType::BulkString(value),
+            None => RedisResponseType::Nil,
+        })
+        .collect()
+}
+
+fn read_hyperloglog<T: Storage + ?Sized>(
+    storage: &mut T,
+    key: &[u8],
+) -> Result<Option<HyperLogLog>, RedisCommandError> {
+    if is_wrong_type(storage, key, RedisType::String) {
+        return Err(RedisCommandError::WrongType);
+    }
+
+    match storage.read(key) {
+        Some(bytes) => HyperLogLog::from_bytes(bytes)
+            .map(Some)
+            .ok_or(RedisCommandError::InvalidHyperLogLog),
+        None => Ok(None),
+    }
+}
+
+fn is_wrong_type<T: Storage + ?Sized>(storage: &mut T, key: &[u8], data_type: RedisType) -> bool {
+    storage.contains(key)
+        && storage
+            .meta(key)
+            .is_some_and(|meta| meta.data_type != data_type)
+}
+
+/// members of a sorted set within the area of a search, around a position or one of the members
+fn geo_search<'a>(
+    zset: &'a RedisSortedSet,
+    query: &GeoQuery,
+) -> Result<Vec<GeoMatch<'a>>, RedisCommandError> {
+    let center = match &query.origin {
+        GeoOrigin::Position(longitude, latitude) => (*longitude, *latitude),
+        GeoOrigin::Member(member) => match zset.score(member) {
+            Some(score) => geo::decode(score as u64),
+            None => return Err(RedisCommandError::UnknownGeoMember),
+        },
+    };
+    Ok(geo::search(zset, center, query))
+}
+
+/// confirmation of a subscription change, along with the number of subscriptions left
+fn subscription_reply(
+    kind: &[u8],
+    channel: Option<RedisString>,
+    count: usize,
+) -> RedisResponseType {
+    RedisResponseType::Array(vec![
+        RedisResponseType::BulkString(kind.to_vec()),
+        channel.map_or(RedisResponseType::Nil, RedisResponseType::BulkString),
+        RedisResponseType::Integer(count as i64),
+    ])
+}
+
+/// description of a library as listed by FUNCTION LIST, along with its code if `with_code`
+fn library_reply(library: Library, with_code: bool) -> RedisResponseType {
+    use RedisResponseType::*;
+
+    let functions = library
+        .functions
+        .into_iter()
+        .map(|function| {
+            Array(vec![
+                BulkString(b"name".to_vec()),
+                BulkString(function.name.into_bytes()),
+                BulkString(b"description".to_vec()),
+                function
+                    .description
+                    .map_or(Nil, |description| BulkString(description.into_bytes())),
+                BulkString(b"flags".to_vec()),
+                Array(
+                    function
+                        .flags
+                        .into_iter()
+                        .map(|flag| BulkString(flag.into_bytes()))
+                        .collect(),
+                ),
+            ])
+        })
+        .collect();
+
+    let mut reply = vec![
+        BulkString(b"library_name".to_vec()),
+        BulkString(library.name.into_bytes()),
+        BulkString(b"engine".to_vec()),
+        BulkString(b"LUA".to_vec()),
+        BulkString(b"functions".to_vec()),
+        Array(functions),
+    ];
+    if with_code {
+        reply.push(BulkString(b"library_code".to_vec()));
+        reply.push(BulkString(library.code));
+    }
+    Array(reply)
+}
+
+/// signal that `key` has been modified, to the clients watching it and to the subscribers of its
+/// keyspace events
+pub fn notify(context: &ServerContext, db: usize, class: u32, event: &str, key: &[u8]) {
+    context.watched_keys.touch(db, key);
+    context.pubsub.notify_keyspace_event(class, event, db, key);
+}
+
+/// run the commands of a transaction one after the other, EXEC being run without any command of
+/// another client in between
+fn exec<T: Storage>(
+    storage: &Arc<Mutex<T>>,
+    context: &Arc<ServerContext>,
+    client: &mut Client,
+    transaction: Transaction,
+) -> RedisResponse {
+    // checked once no other command can run anymore
+    if client.watch.is_modified() {
+        return RedisResponse::single(RedisResponseType::Nil);
+    }
+
+    let mut savepoints = transaction.savepoints.into_iter().peekable();
+    // the savepoints marked before the command being run, the latest one last
+    let mut marked: Vec<Savepoint> = vec![];
+    let mut replies = Vec::with_capacity(transaction.commands.len());
+    for (position, command) in transaction.commands.into_iter().enumerate() {
+        while savepoints.next_if_eq(&position).is_some() {
+            marked.push(Savepoint::default());
+        }
+
+        if !marked.is_empty() {
+            let mut locked_storage = lock_db(storage, client.db);
+            for savepoint in marked.iter_mut() {
+                savepoint.save(&mut *locked_storage, client.db, &command);
+            }
+        }
+
+        let reply = run_without_blocking(storage, context, client, command);
+        // a failing command undoes the writes made since the latest savepoint, which is released
+        if reply.is_error() {
+            if let Some(savepoint) = marked.pop() {
+                savepoint.rollback(storage, context);
+            }
+        }
+        replies.push(reply);
+    }
+    RedisResponse::replies(replies)
+}
+
+/// publish a pop from `key`, followed by its deletion when it was emptied
+fn notify_pop(
+    context: &ServerContext,
+    db: usize,
+    class: u32,
+    event: &str,
+    key: &[u8],
+    emptied: bool,
+) {
+    notify(context, db, class, event, key);
+    if emptied {
+        notify(context, db, keyspace_events::GENERIC, "del", key);
+    }
+}
+
+fn notify_rename(context: &ServerContext, db: usize, key: &[u8], new_key: &[u8]) {
+    notify(context, db, keyspace_events::GENERIC, "rename_from", key);
+    notify(context, db, keyspace_events::GENERIC, "rename_to", new_key);
+}
+
+fn push_event(end: ListEnd) -> &'static str {
+    match end {
+        ListEnd::Left => "lpush",
+        ListEnd::Right => "rpush",
+    }
+}
+
+fn pop_event(end: ListEnd) -> &'static str {
+    match end {
+        ListEnd::Left => "lpop",
+        ListEnd::Right => "rpop",
+    }
 }
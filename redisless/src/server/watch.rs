@@ -0,0 +1,84 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::storage::models::RedisString;
+
+// a key along with the index of its database
+type DbKey = (usize, RedisString);
+
+/// Keys watched by a client with WATCH, along with whether one of them has been modified since
+#[derive(Default)]
+pub struct Watch {
+    keys: Vec<DbKey>,
+    modified: Arc<AtomicBool>,
+}
+
+impl Watch {
+    /// whether a watched key has been modified, so that EXEC does not run the transaction
+    pub fn is_modified(&self) -> bool {
+        self.modified.load(Ordering::Relaxed)
+    }
+}
+
+/// Clients watching keys, per database and key
+#[derive(Default)]
+pub struct WatchedKeys {
+    watchers: Mutex<HashMap<DbKey, Vec<Arc<AtomicBool>>>>,
+}
+
+impl WatchedKeys {
+    /// watch `key` of the database `db` for a client
+    pub fn watch(&self, watch: &mut Watch, db: usize, key: &[u8]) {
+        let db_key = (db, key.to_vec());
+        if watch.keys.contains(&db_key) {
+            return;
+        }
+
+        let mut watchers = self.watchers.lock().unwrap();
+        watchers
+            .entry(db_key.clone())
+            .or_default()
+            .push(watch.modified.clone());
+        watch.keys.push(db_key);
+    }
+
+    /// forget all the keys watched by a client, and whether one of them has been modified
+    pub fn unwatch_all(&self, watch: &mut Watch) {
+        let mut watchers = self.watchers.lock().unwrap();
+        for db_key in std::mem::take(&mut watch.keys) {
+            if let Some(modified) = watchers.get_mut(&db_key) {
+                modified.retain(|other| !Arc::ptr_eq(other, &watch.modified));
+                if modified.is_empty() {
+                    watchers.remove(&db_key);
+                }
+            }
+        }
+        watch.modified.store(false, Ordering::Relaxed);
+    }
+
+    /// flag the clients watching `key` of the database `db`, once it has been modified
+    pub fn touch(&self, db: usize, key: &[u8]) {
+        let watchers = self.watchers.lock().unwrap();
+        if watchers.is_empty() {
+            return;
+        }
+
+        if let Some(modified) = watchers.get(&(db, key.to_vec())) {
+            modified
+                .iter()
+                .for_each(|modified| modified.store(true, Ordering::Relaxed));
+        }
+    }
+
+    /// flag the clients watching any key of the database `db`, once it has been flushed or
+    /// swapped
+    pub fn touch_db(&self, db: usize) {
+        let watchers = self.watchers.lock().unwrap();
+        watchers
+            .iter()
+            .filter(|((key_db, _), _)| *key_db == db)
+            .flat_map(|(_, modified)| modified)
+            .for_each(|modified| modified.store(true, Ordering::Relaxed));
+    }
+}
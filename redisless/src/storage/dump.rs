@@ -0,0 +1,609 @@
+//! DUMP payload codec, compatible with Redis: the value is RDB encoded and followed by a footer
+//! made of the RDB version (2 bytes) and a CRC64 (8 bytes) of everything before it.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::convert::TryInto;
+
+use super::models::{RedisHashMap, RedisList, RedisSet, RedisSortedSet, RedisString, RedisValue};
+
+/// RDB version written in the payloads - the one of Redis 6
+pub const RDB_VERSION: u16 = 9;
+/// newest RDB version of the payloads which can be restored - the one of Redis 7.4, whose
+/// values are encoded as those of Redis 7.0 but for the hashes with expiring fields
+const MAX_RDB_VERSION: u16 = 12;
+
+const RDB_TYPE_STRING: u8 = 0;
+const RDB_TYPE_LIST: u8 = 1;
+const RDB_TYPE_SET: u8 = 2;
+// scores stored as strings
+const RDB_TYPE_ZSET: u8 = 3;
+const RDB_TYPE_HASH: u8 = 4;
+// scores stored as binary doubles
+const RDB_TYPE_ZSET_2: u8 = 5;
+// compact encodings of the small values, up to Redis 6
+const RDB_TYPE_LIST_ZIPLIST: u8 = 10;
+const RDB_TYPE_SET_INTSET: u8 = 11;
+const RDB_TYPE_ZSET_ZIPLIST: u8 = 12;
+const RDB_TYPE_HASH_ZIPLIST: u8 = 13;
+// list of ziplists
+const RDB_TYPE_LIST_QUICKLIST: u8 = 14;
+// compact encodings of the small values, from Redis 7
+const RDB_TYPE_HASH_LISTPACK: u8 = 16;
+const RDB_TYPE_ZSET_LISTPACK: u8 = 17;
+// list of listpacks and of large elements stored as is
+const RDB_TYPE_LIST_QUICKLIST_2: u8 = 18;
+const RDB_TYPE_SET_LISTPACK: u8 = 20;
+// a library of functions, in a FUNCTION DUMP payload
+const RDB_OPCODE_FUNCTION2: u8 = 245;
+
+const RDB_6BIT_LENGTH: u8 = 0;
+const RDB_14BIT_LENGTH: u8 = 1;
+const RDB_32BIT_LENGTH: u8 = 0x80;
+const RDB_64BIT_LENGTH: u8 = 0x81;
+const RDB_ENCODED_VALUE: u8 = 3;
+
+const RDB_ENCODED_INT8: u8 = 0;
+const RDB_ENCODED_INT16: u8 = 1;
+const RDB_ENCODED_INT32: u8 = 2;
+const RDB_ENCODED_LZF: u8 = 3;
+
+// special lengths of the scores stored as strings
+const RDB_SCORE_NAN: u8 = 253;
+const RDB_SCORE_POSITIVE_INFINITY: u8 = 254;
+const RDB_SCORE_NEGATIVE_INFINITY: u8 = 255;
+
+// kinds of the nodes of a quicklist 2
+const QUICKLIST_NODE_PLAIN: u64 = 1;
+const QUICKLIST_NODE_PACKED: u64 = 2;
+
+// total length, offset of the last entry and number of entries
+const ZIPLIST_HEADER_LENGTH: usize = 10;
+// length of the previous entry stored on 4 bytes
+const ZIPLIST_BIG_PREVIOUS_LENGTH: u8 = 254;
+const ZIPLIST_END: u8 = 255;
+
+// total length and number of entries
+const LISTPACK_HEADER_LENGTH: usize = 6;
+const LISTPACK_END: u8 = 255;
+
+const FOOTER_LENGTH: usize = 10;
+
+// reflected polynomial of the CRC64 variant used by Redis (Jones)
+const CRC64_POLYNOMIAL: u64 = 0x95ac_9329_ac4b_c9b5;
+
+#[derive(Debug, PartialEq)]
+pub enum PayloadError {
+    // The payload comes from a newer RDB version or has been altered
+    VersionOrChecksum,
+    // The payload is truncated or holds an unsupported type or encoding
+    BadDataFormat,
+}
+
+/// DUMP payload of `value`, `None` for streams whose encoding is not supported
+pub fn serialize(value: &RedisValue) -> Option<Vec<u8>> {
+    let mut payload = vec![];
+
+    match value {
+        RedisValue::String(value) => {
+            payload.push(RDB_TYPE_STRING);
+            write_string(&mut payload, value);
+        }
+        RedisValue::Hash(value) => {
+            payload.push(RDB_TYPE_HASH);
+            write_length(&mut payload, value.data.len() as u64);
+            for (field, value) in &value.data {
+                write_string(&mut payload, field);
+                write_string(&mut payload, value);
+            }
+        }
+        RedisValue::List(value) => {
+            payload.push(RDB_TYPE_LIST);
+            write_length(&mut payload, value.data.len() as u64);
+            for element in &value.data {
+                write_string(&mut payload, element);
+            }
+        }
+        RedisValue::Set(value) => {
+            payload.push(RDB_TYPE_SET);
+            write_length(&mut payload, value.data.len() as u64);
+            for member in &value.data {
+                write_string(&mut payload, member);
+            }
+        }
+        RedisValue::ZSet(value) => {
+            payload.push(RDB_TYPE_ZSET_2);
+            write_length(&mut payload, value.len() as u64);
+            // highest scores first, like Redis
+            for (member, score) in value.iter().rev() {
+                write_string(&mut payload, member);
+                payload.extend_from_slice(&score.to_le_bytes());
+            }
+        }
+        RedisValue::Stream(_) => return None,
+    }
+
+    write_footer(&mut payload);
+    Some(payload)
+}
+
+/// FUNCTION DUMP payload of the libraries of code `codes`
+pub fn serialize_functions<'a>(codes: impl IntoIterator<Item = &'a [u8]>) -> Vec<u8> {
+    let mut payload = vec![];
+    for code in codes {
+        payload.push(RDB_OPCODE_FUNCTION2);
+        write_string(&mut payload, code);
+    }
+
+    write_footer(&mut payload);
+    payload
+}
+
+pub fn deserialize(payload: &[u8]) -> Result<RedisValue, PayloadError> {
+    if payload.len() < FOOTER_LENGTH {
+        return Err(PayloadError::VersionOrChecksum);
+    }
+
+    let (body, checksum) = payload.split_at(payload.len() - 8);
+    let version = u16::from_le_bytes([body[body.len() - 2], body[body.len() - 1]]);
+    let checksum = u64::from_le_bytes(checksum.try_into().unwrap());
+    if version > MAX_RDB_VERSION || crc64(body) != checksum {
+        return Err(PayloadError::VersionOrChecksum);
+    }
+
+    let mut reader = Reader {
+        input: &body[..body.len() - 2],
+        position: 0,
+    };
+
+    let value_type = reader.byte()?;
+    let value = match value_type {
+        RDB_TYPE_STRING => RedisValue::String(reader.string()?),
+        RDB_TYPE_HASH => {
+            let length = reader.length()?;
+            let mut data = HashMap::new();
+            for _ in 0..length {
+                let field = reader.string()?;
+                let value = reader.string()?;
+                data.insert(field, value);
+            }
+            RedisValue::Hash(RedisHashMap::new(data))
+        }
+        RDB_TYPE_LIST => {
+            let length = reader.length()?;
+            let mut data = VecDeque::new();
+            for _ in 0..length {
+                data.push_back(reader.string()?);
+            }
+            RedisValue::List(RedisList::new(data))
+        }
+        RDB_TYPE_SET => {
+            let length = reader.length()?;
+            let mut data = HashSet::new();
+            for _ in 0..length {
+                data.insert(reader.string()?);
+            }
+            RedisValue::Set(RedisSet::new(data))
+        }
+        RDB_TYPE_ZSET | RDB_TYPE_ZSET_2 => {
+            let length = reader.length()?;
+            let mut data = RedisSortedSet::new();
+            for _ in 0..length {
+                let member = reader.string()?;
+                let score = match value_type {
+                    RDB_TYPE_ZSET => reader.string_double()?,
+                    _ => reader.double()?,
+                };
+                data.insert(member, score);
+            }
+            RedisValue::ZSet(data)
+        }
+        RDB_TYPE_LIST_ZIPLIST => list(ziplist_entries(&reader.string()?)?),
+        RDB_TYPE_LIST_QUICKLIST => {
+            let length = reader.length()?;
+            let mut elements = vec![];
+            for _ in 0..length {
+                elements.extend(ziplist_entries(&reader.string()?)?);
+            }
+            list(elements)
+        }
+        RDB_TYPE_LIST_QUICKLIST_2 => {
+            let length = reader.length()?;
+            let mut elements = vec![];
+            for _ in 0..length {
+                match reader.length()? {
+                    QUICKLIST_NODE_PLAIN => elements.push(reader.string()?),
+                    QUICKLIST_NODE_PACKED => elements.extend(listpack_entries(&reader.string()?)?),
+                    _ => return Err(PayloadError::BadDataFormat),
+                }
+            }
+            list(elements)
+        }
+        RDB_TYPE_SET_INTSET => set(intset_members(&reader.string()?)?),
+        RDB_TYPE_SET_LISTPACK => set(listpack_entries(&reader.string()?)?),
+        RDB_TYPE_HASH_ZIPLIST => hash(ziplist_entries(&reader.string()?)?)?,
+        RDB_TYPE_HASH_LISTPACK => hash(listpack_entries(&reader.string()?)?)?,
+        RDB_TYPE_ZSET_ZIPLIST => zset(ziplist_entries(&reader.string()?)?)?,
+        RDB_TYPE_ZSET_LISTPACK => zset(listpack_entries(&reader.string()?)?)?,
+        _ => return Err(PayloadError::BadDataFormat),
+    };
+
+    match reader.is_empty() {
+        true => Ok(value),
+        false => Err(PayloadError::BadDataFormat),
+    }
+}
+
+fn list(elements: Vec<RedisString>) -> RedisValue {
+    RedisValue::List(RedisList::new(elements.into_iter().collect()))
+}
+
+fn set(members: Vec<RedisString>) -> RedisValue {
+    RedisValue::Set(RedisSet::new(members.into_iter().collect()))
+}
+
+/// hash of the fields and values of `entries`, one after the other
+fn hash(entries: Vec<RedisString>) -> Result<RedisValue, PayloadError> {
+    let data = pairs(entries)?.collect();
+    Ok(RedisValue::Hash(RedisHashMap::new(data)))
+}
+
+/// sorted set of the members and scores of `entries`, one after the other
+fn zset(entries: Vec<RedisString>) -> Result<RedisValue, PayloadError> {
+    let mut data = RedisSortedSet::new();
+    for (member, score) in pairs(entries)? {
+        let score = std::str::from_utf8(&score)
+            .ok()
+            .and_then(|score| score.parse::<f64>().ok())
+            .filter(|score| !score.is_nan())
+            .ok_or(PayloadError::BadDataFormat)?;
+        data.insert(member, score);
+    }
+    Ok(RedisValue::ZSet(data))
+}
+
+fn pairs(
+    entries: Vec<RedisString>,
+) -> Result<impl Iterator<Item = (RedisString, RedisString)>, PayloadError> {
+    if !entries.len().is_multiple_of(2) {
+        return Err(PayloadError::BadDataFormat);
+    }
+
+    let mut entries = entries.into_iter();
+    Ok(std::iter::from_fn(move || {
+        Some((entries.next()?, entries.next()?))
+    }))
+}
+
+fn integer_string(integer: i64) -> RedisString {
+    integer.to_string().into_bytes()
+}
+
+/// entries of a ziplist, the integers being converted to strings
+fn ziplist_entries(ziplist: &[u8]) -> Result<Vec<RedisString>, PayloadError> {
+    let mut reader = Reader {
+        input: ziplist,
+        position: 0,
+    };
+    reader.bytes(ZIPLIST_HEADER_LENGTH)?;
+
+    let mut entries = vec![];
+    loop {
+        // each entry starts with the length of the previous one
+        match reader.byte()? {
+            ZIPLIST_END => break,
+            ZIPLIST_BIG_PREVIOUS_LENGTH => {
+                reader.bytes(4)?;
+            }
+            _ => {}
+        }
+
+        let encoding = reader.byte()?;
+        let entry = match encoding >> 6 {
+            0 => reader.bytes((encoding & 0x3f) as usize)?.to_vec(),
+            1 => {
+                let length = (((encoding & 0x3f) as usize) << 8) | reader.byte()? as usize;
+                reader.bytes(length)?.to_vec()
+            }
+            2 => {
+                let length = u32::from_be_bytes(reader.bytes(4)?.try_into().unwrap());
+                reader.bytes(length as usize)?.to_vec()
+            }
+            _ => integer_string(match encoding {
+                0xc0 => reader.signed(2)?,
+                0xd0 => reader.signed(4)?,
+                0xe0 => reader.signed(8)?,
+                0xf0 => reader.signed(3)?,
+                0xfe => reader.signed(1)?,
+                // the integers from 0 to 12 are stored in the encoding itself
+                0xf1..=0xfd => (encoding & 0x0f) as i64 - 1,
+                _ => return Err(PayloadError::BadDataFormat),
+            }),
+        };
+        entries.push(entry);
+    }
+
+    match reader.is_empty() {
+        true => Ok(entries),
+        false => Err(PayloadError::BadDataFormat),
+    }
+}
+
+/// entries of a listpack, the integers being converted to strings
+fn listpack_entries(listpack: &[u8]) -> Result<Vec<RedisString>, PayloadError> {
+    let mut reader = Reader {
+        input: listpack,
+        position: 0,
+    };
+    reader.bytes(LISTPACK_HEADER_LENGTH)?;
+
+    let mut entries = vec![];
+    loop {
+        let start = reader.position;
+        let encoding = reader.byte()?;
+        let entry = match encoding {
+            LISTPACK_END => break,
+            0x00..=0x7f => integer_string(encoding as i64),
+            0x80..=0xbf => reader.bytes((encoding & 0x3f) as usize)?.to_vec(),
+            0xc0..=0xdf => {
+                let integer = (((encoding & 0x1f) as i64) << 8) | reader.byte()? as i64;
+                // 13 bits two's complement
+                integer_string(match integer >= 1 << 12 {
+                    true => integer - (1 << 13),
+                    false => integer,
+                })
+            }
+            0xe0..=0xef => {
+                let length = (((encoding & 0x0f) as usize) << 8) | reader.byte()? as usize;
+                reader.bytes(length)?.to_vec()
+            }
+            0xf0 => {
+                let length = u32::from_le_bytes(reader.bytes(4)?.try_into().unwrap());
+                reader.bytes(length as usize)?.to_vec()
+            }
+            0xf1 => integer_string(reader.signed(2)?),
+            0xf2 => integer_string(reader.signed(3)?),
+            0xf3 => integer_string(reader.signed(4)?),
+            0xf4 => integer_string(reader.signed(8)?),
+            _ => return Err(PayloadError::BadDataFormat),
+        };
+
+        // each entry ends with its length, to be iterated backwards
+        let length = reader.position - start;
+        let backward_length = match length {
+            0..=127 => 1,
+            128..=16382 => 2,
+            16383..=2097150 => 3,
+            2097151..=268435454 => 4,
+            _ => 5,
+        };
+        reader.bytes(backward_length)?;
+        entries.push(entry);
+    }
+
+    match reader.is_empty() {
+        true => Ok(entries),
+        false => Err(PayloadError::BadDataFormat),
+    }
+}
+
+/// members of an intset, converted to strings
+fn intset_members(intset: &[u8]) -> Result<Vec<RedisString>, PayloadError> {
+    let mut reader = Reader {
+        input: intset,
+        position: 0,
+    };
+
+    // size of each integer, then number of integers
+    let size = u32::from_le_bytes(reader.bytes(4)?.try_into().unwrap()) as usize;
+    let length = u32::from_le_bytes(reader.bytes(4)?.try_into().unwrap());
+    if !matches!(size, 2 | 4 | 8) {
+        return Err(PayloadError::BadDataFormat);
+    }
+
+    let members = (0..length)
+        .map(|_| reader.signed(size).map(integer_string))
+        .collect::<Result<Vec<_>, _>>()?;
+    match reader.is_empty() {
+        true => Ok(members),
+        false => Err(PayloadError::BadDataFormat),
+    }
+}
+
+fn write_footer(payload: &mut Vec<u8>) {
+    payload.extend_from_slice(&RDB_VERSION.to_le_bytes());
+    let checksum = crc64(payload);
+    payload.extend_from_slice(&checksum.to_le_bytes());
+}
+
+fn write_length(payload: &mut Vec<u8>, length: u64) {
+    if length < 1 << 6 {
+        payload.push((RDB_6BIT_LENGTH << 6) | length as u8);
+    } else if length < 1 << 14 {
+        payload.push((RDB_14BIT_LENGTH << 6) | (length >> 8) as u8);
+        payload.push(length as u8);
+    } else if length <= u32::MAX as u64 {
+        payload.push(RDB_32BIT_LENGTH);
+        payload.extend_from_slice(&(length as u32).to_be_bytes());
+    } else {
+        payload.push(RDB_64BIT_LENGTH);
+        payload.extend_from_slice(&length.to_be_bytes());
+    }
+}
+
+fn write_string(payload: &mut Vec<u8>, value: &[u8]) {
+    write_length(payload, value.len() as u64);
+    payload.extend_from_slice(value);
+}
+
+pub fn crc64(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0_u64, |mut crc, byte| {
+        crc ^= *byte as u64;
+        for _ in 0..8 {
+            crc = match crc & 1 {
+                1 => (crc >> 1) ^ CRC64_POLYNOMIAL,
+                _ => crc >> 1,
+            };
+        }
+        crc
+    })
+}
+
+enum Length {
+    Plain(u64),
+    Encoded(u8),
+}
+
+struct Reader<'a> {
+    input: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn is_empty(&self) -> bool {
+        self.position == self.input.len()
+    }
+
+    fn bytes(&mut self, length: usize) -> Result<&'a [u8], PayloadError> {
+        let end = self
+            .position
+            .checked_add(length)
+            .filter(|end| *end <= self.input.len())
+            .ok_or(PayloadError::BadDataFormat)?;
+
+        let bytes = &self.input[self.position..end];
+        self.position = end;
+        Ok(bytes)
+    }
+
+    fn byte(&mut self) -> Result<u8, PayloadError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    /// little endian two's complement integer of `size` bytes
+    fn signed(&mut self, size: usize) -> Result<i64, PayloadError> {
+        let bytes = self.bytes(size)?;
+        let integer = bytes
+            .iter()
+            .rev()
+            .fold(0_u64, |integer, byte| (integer << 8) | *byte as u64);
+        let unused_bits = 64 - 8 * size as u32;
+        Ok(((integer << unused_bits) as i64) >> unused_bits)
+    }
+
+    /// score stored as a string, prefixed by its length or by a special one for NaN and infinity
+    fn string_double(&mut self) -> Result<f64, PayloadError> {
+        match self.byte()? {
+            RDB_SCORE_NAN => Err(PayloadError::BadDataFormat),
+            RDB_SCORE_POSITIVE_INFINITY => Ok(f64::INFINITY),
+            RDB_SCORE_NEGATIVE_INFINITY => Ok(f64::NEG_INFINITY),
+            length => std::str::from_utf8(self.bytes(length as usize)?)
+                .ok()
+                .and_then(|score| score.parse::<f64>().ok())
+                .filter(|score| !score.is_nan())
+                .ok_or(PayloadError::BadDataFormat),
+        }
+    }
+
+    fn double(&mut self) -> Result<f64, PayloadError> {
+        let bytes = self.bytes(8)?.try_into().unwrap();
+        match f64::from_le_bytes(bytes) {
+            double if double.is_nan() => Err(PayloadError::BadDataFormat),
+            double => Ok(double),
+        }
+    }
+
+    fn raw_length(&mut self) -> Result<Length, PayloadError> {
+        let first = self.byte()?;
+        match first >> 6 {
+            RDB_6BIT_LENGTH => Ok(Length::Plain((first & 0x3f) as u64)),
+            RDB_14BIT_LENGTH => {
+                let second = self.byte()?;
+                Ok(Length::Plain(
+                    (((first & 0x3f) as u64) << 8) | second as u64,
+                ))
+            }
+            RDB_ENCODED_VALUE => Ok(Length::Encoded(first & 0x3f)),
+            _ => match first {
+                RDB_32BIT_LENGTH => {
+                    let bytes = self.bytes(4)?.try_into().unwrap();
+                    Ok(Length::Plain(u32::from_be_bytes(bytes) as u64))
+                }
+                RDB_64BIT_LENGTH => {
+                    let bytes = self.bytes(8)?.try_into().unwrap();
+                    Ok(Length::Plain(u64::from_be_bytes(bytes)))
+                }
+                _ => Err(PayloadError::BadDataFormat),
+            },
+        }
+    }
+
+    fn length(&mut self) -> Result<u64, PayloadError> {
+        match self.raw_length()? {
+            Length::Plain(length) => Ok(length),
+            Length::Encoded(_) => Err(PayloadError::BadDataFormat),
+        }
+    }
+
+    fn string(&mut self) -> Result<RedisString, PayloadError> {
+        let integer = match self.raw_length()? {
+            Length::Plain(length) => return Ok(self.bytes(length as usize)?.to_vec()),
+            Length::Encoded(RDB_ENCODED_INT8) => self.byte()? as i8 as i64,
+            Length::Encoded(RDB_ENCODED_INT16) => {
+                i16::from_le_bytes(self.bytes(2)?.try_into().unwrap()) as i64
+            }
+            Length::Encoded(RDB_ENCODED_INT32) => {
+                i32::from_le_bytes(self.bytes(4)?.try_into().unwrap()) as i64
+            }
+            Length::Encoded(RDB_ENCODED_LZF) => {
+                let compressed_length = self.length()? as usize;
+                let length = self.length()? as usize;
+                let compressed = self.bytes(compressed_length)?;
+                return lzf_decompress(compressed, length).ok_or(PayloadError::BadDataFormat);
+            }
+            Length::Encoded(_) => return Err(PayloadError::BadDataFormat),
+        };
+
+        Ok(integer.to_string().into_bytes())
+    }
+}
+
+/// decompress the LZF `input` which must expand to exactly `length` bytes
+fn lzf_decompress(input: &[u8], length: usize) -> Option<Vec<u8>> {
+    let mut output = vec![];
+    let mut position = 0;
+
+    while position < input.len() {
+        let control = input[position] as usize;
+        position += 1;
+
+        if control < 1 << 5 {
+            // literal run
+            let literal = input.get(position..position + control + 1)?;
+            output.extend_from_slice(literal);
+            position += control + 1;
+        } else {
+            // back reference
+            let mut run = control >> 5;
+            if run == 7 {
+                run += *input.get(position)? as usize;
+                position += 1;
+            }
+            let offset = ((control & 0x1f) << 8) + *input.get(position)? as usize + 1;
+            position += 1;
+
+            let start = output.len().checked_sub(offset)?;
+            for index in start..start + run + 2 {
+                output.push(output[index]);
+            }
+        }
+
+        if output.len() > length {
+            return None;
+        }
+    }
+
+    match output.len() == length {
+        true => Some(output),
+        false => None,
+    }
+}
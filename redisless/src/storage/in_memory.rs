@@ -1,28 +1,73 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::mem::size_of;
+use std::thread;
 
 use prost::bytes::BufMut;
+use rand::seq::IteratorRandom;
 
 use super::models::*;
+use super::scan::scan;
+use crate::glob::glob_match;
 use crate::storage::Storage;
 
+// values bigger than this are freed on a background thread when unlinked
+const LAZY_FREE_THRESHOLD_BYTES: usize = 64 * 1024;
+
+/// number of databases created by `InMemoryStorage::new()`
+pub const DEFAULT_DATABASES: usize = 16;
+
 pub struct InMemoryStorage {
+    databases: Vec<Keyspace>,
+    selected: usize,
+}
+
+/// Keys of a single database
+#[derive(Default)]
+struct Keyspace {
     data_mapper: HashMap<RedisString, RedisMeta>,
     string_store: HashMap<RedisString, RedisString>,
     hash_store: HashMap<RedisString, RedisHashMap>,
+    list_store: HashMap<RedisString, RedisList>,
+    set_store: HashMap<RedisString, RedisSet>,
+    zset_store: HashMap<RedisString, RedisSortedSet>,
+    stream_store: HashMap<RedisString, RedisStream>,
+    // keys removed since they expired, until they are taken to be notified
+    expired: Vec<RedisString>,
+}
+
+impl Default for InMemoryStorage {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl InMemoryStorage {
     pub fn new() -> Self {
+        Self::with_databases(DEFAULT_DATABASES)
+    }
+
+    /// storage with `databases` numbered databases (at least one), the first one being selected
+    pub fn with_databases(databases: usize) -> Self {
         Self {
-            data_mapper: HashMap::new(),
-            string_store: HashMap::new(),
-            hash_store: HashMap::new(),
+            databases: (0..databases.max(1)).map(|_| Keyspace::default()).collect(),
+            selected: 0,
         }
     }
+
+    fn keyspace(&self) -> &Keyspace {
+        &self.databases[self.selected]
+    }
+
+    fn keyspace_mut(&mut self) -> &mut Keyspace {
+        &mut self.databases[self.selected]
+    }
 }
 
-impl Storage for InMemoryStorage {
+impl Keyspace {
     fn write(&mut self, key: &[u8], value: &[u8]) {
+        // the previous value may be of another type
+        self.remove(key);
+
         let meta = RedisMeta::new(RedisType::String, None);
         self.data_mapper.insert(key.to_vec(), meta);
         self.string_store.insert(key.to_vec(), value.to_vec());
@@ -30,6 +75,9 @@ impl Storage for InMemoryStorage {
     fn extend(&mut self, key: &[u8], tail: &[u8]) -> u64 {
         match self.string_store.get_mut(key) {
             Some(v) => {
+                if let Some(meta) = self.data_mapper.get_mut(key) {
+                    meta.touch();
+                }
                 v.put_slice(tail);
                 v.len() as u64
             }
@@ -50,23 +98,218 @@ impl Storage for InMemoryStorage {
     }
 
     fn read(&mut self, key: &[u8]) -> Option<&[u8]> {
-        if let Some(value) = self.data_mapper.get(key) {
+        if let Some(value) = self.data_mapper.get_mut(key) {
             match value.is_expired() {
                 true => {
-                    self.remove(key);
+                    self.remove_expired(key);
                     None
                 }
-                false => Some(self.string_store.get(key).unwrap()),
+                false => {
+                    value.touch();
+                    // `None` when the key holds another type
+                    self.string_store.get(key).map(|value| value.as_slice())
+                }
             }
         } else {
             None
         }
     }
 
+    fn string_mut(&mut self, key: &[u8]) -> Option<&mut RedisString> {
+        if !self.contains(key) {
+            self.write(key, &[]);
+        }
+
+        let meta = self.data_mapper.get_mut(key)?;
+        meta.touch();
+        self.string_store.get_mut(key)
+    }
+
     fn meta(&self, key: &[u8]) -> Option<&RedisMeta> {
         self.data_mapper.get(key)
     }
 
+    fn key_info(&mut self, key: &[u8]) -> Option<KeyInfo> {
+        if !self.contains(key) {
+            return None;
+        }
+
+        let meta = self.data_mapper.get(key)?;
+        let ttl = match meta.expiry {
+            Some(expiry) => expiry.duration_left_millis(),
+            None => -1,
+        };
+
+        let (elements, size) = match meta.data_type {
+            RedisType::String => {
+                let value = self.string_store.get(key)?;
+                (1, value.capacity())
+            }
+            RedisType::Hash => {
+                let value = self.hash_store.get(key)?;
+                (value.data.len() as u64, value.allocated_memory())
+            }
+            RedisType::List => {
+                let value = self.list_store.get(key)?;
+                (value.data.len() as u64, value.allocated_memory())
+            }
+            RedisType::Set => {
+                let value = self.set_store.get(key)?;
+                (value.data.len() as u64, value.allocated_memory())
+            }
+            RedisType::ZSet => {
+                let value = self.zset_store.get(key)?;
+                (value.len() as u64, value.allocated_memory())
+            }
+            RedisType::Stream => {
+                let value = self.stream_store.get(key)?;
+                (value.len() as u64, value.allocated_memory())
+            }
+        };
+
+        Some(KeyInfo {
+            data_type: meta.data_type,
+            ttl,
+            elements,
+            size: (key.len() + size) as u64,
+        })
+    }
+
+    /// Internal representation of the value of `key`, as reported by `OBJECT ENCODING`
+    fn encoding(&mut self, key: &[u8]) -> Option<&'static str> {
+        if !self.contains(key) {
+            return None;
+        }
+
+        match self.data_mapper.get(key)?.data_type {
+            // strings are always stored as plain bytes
+            RedisType::String => Some("raw"),
+            RedisType::Hash => Some("hashtable"),
+            RedisType::List => Some("quicklist"),
+            RedisType::Set => Some("hashtable"),
+            RedisType::ZSet => Some("skiplist"),
+            RedisType::Stream => Some("stream"),
+        }
+    }
+
+    fn allocated_memory(&self) -> usize {
+        self.data_mapper.capacity() * size_of::<(RedisString, RedisMeta)>()
+            + self.data_mapper.keys().map(|k| k.capacity()).sum::<usize>()
+            + self.string_store.capacity() * size_of::<(RedisString, RedisString)>()
+            + self
+                .string_store
+                .iter()
+                .map(|(k, v)| k.capacity() + v.capacity())
+                .sum::<usize>()
+            + self.hash_store.capacity() * size_of::<(RedisString, RedisHashMap)>()
+            + self
+                .hash_store
+                .iter()
+                .map(|(k, v)| k.capacity() + v.allocated_memory())
+                .sum::<usize>()
+            + self.list_store.capacity() * size_of::<(RedisString, RedisList)>()
+            + self
+                .list_store
+                .iter()
+                .map(|(k, v)| k.capacity() + v.allocated_memory())
+                .sum::<usize>()
+            + self.set_store.capacity() * size_of::<(RedisString, RedisSet)>()
+            + self
+                .set_store
+                .iter()
+                .map(|(k, v)| k.capacity() + v.allocated_memory())
+                .sum::<usize>()
+            + self.zset_store.capacity() * size_of::<(RedisString, RedisSortedSet)>()
+            + self
+                .zset_store
+                .iter()
+                .map(|(k, v)| k.capacity() + v.allocated_memory())
+                .sum::<usize>()
+            + self.stream_store.capacity() * size_of::<(RedisString, RedisStream)>()
+            + self
+                .stream_store
+                .iter()
+                .map(|(k, v)| k.capacity() + v.allocated_memory())
+                .sum::<usize>()
+    }
+
+    /// memory allocated by the tables holding the keys, their values aside
+    fn tables_memory(&self) -> usize {
+        self.data_mapper.capacity() * size_of::<(RedisString, RedisMeta)>()
+            + self.string_store.capacity() * size_of::<(RedisString, RedisString)>()
+            + self.hash_store.capacity() * size_of::<(RedisString, RedisHashMap)>()
+            + self.list_store.capacity() * size_of::<(RedisString, RedisList)>()
+            + self.set_store.capacity() * size_of::<(RedisString, RedisSet)>()
+            + self.zset_store.capacity() * size_of::<(RedisString, RedisSortedSet)>()
+            + self.stream_store.capacity() * size_of::<(RedisString, RedisStream)>()
+    }
+
+    /// memory allocated by the value stored at `key`
+    fn value_memory(&self, key: &[u8]) -> usize {
+        let data_type = match self.data_mapper.get(key) {
+            Some(meta) => meta.data_type,
+            None => return 0,
+        };
+
+        match data_type {
+            RedisType::String => self.string_store.get(key).map(|v| v.capacity()),
+            RedisType::Hash => self.hash_store.get(key).map(|v| v.allocated_memory()),
+            RedisType::List => self.list_store.get(key).map(|v| v.allocated_memory()),
+            RedisType::Set => self.set_store.get(key).map(|v| v.allocated_memory()),
+            RedisType::ZSet => self.zset_store.get(key).map(|v| v.allocated_memory()),
+            RedisType::Stream => self.stream_store.get(key).map(|v| v.allocated_memory()),
+        }
+        .unwrap_or(0)
+    }
+
+    fn shrink_value(&mut self, key: &[u8]) {
+        let data_type = match self.data_mapper.get(key) {
+            Some(meta) => meta.data_type,
+            None => return,
+        };
+
+        match data_type {
+            RedisType::String => self.string_store.get_mut(key).map(|v| v.shrink_to_fit()),
+            RedisType::Hash => self.hash_store.get_mut(key).map(|v| v.shrink_to_fit()),
+            RedisType::List => self.list_store.get_mut(key).map(|v| v.shrink_to_fit()),
+            RedisType::Set => self.set_store.get_mut(key).map(|v| v.shrink_to_fit()),
+            RedisType::ZSet => self.zset_store.get_mut(key).map(|v| v.shrink_to_fit()),
+            RedisType::Stream => self.stream_store.get_mut(key).map(|v| v.shrink_to_fit()),
+        };
+    }
+
+    /// shrink the values of up to `count` keys from `cursor`, and the tables holding the keys once
+    /// they have all been gone through
+    fn defrag(&mut self, cursor: u64, count: usize) -> (u64, DefragReport) {
+        let (next_cursor, keys) = scan(
+            self.data_mapper.keys().map(|key| (key.as_slice(), key)),
+            cursor,
+            count,
+        );
+        let keys = keys.into_iter().cloned().collect::<Vec<_>>();
+
+        let mut allocated_before = keys.iter().map(|key| self.value_memory(key)).sum::<usize>();
+        keys.iter().for_each(|key| self.shrink_value(key));
+        let mut allocated_after = keys.iter().map(|key| self.value_memory(key)).sum::<usize>();
+
+        if next_cursor == 0 {
+            allocated_before += self.tables_memory();
+            self.data_mapper.shrink_to_fit();
+            self.string_store.shrink_to_fit();
+            self.hash_store.shrink_to_fit();
+            self.list_store.shrink_to_fit();
+            self.set_store.shrink_to_fit();
+            self.zset_store.shrink_to_fit();
+            self.stream_store.shrink_to_fit();
+            allocated_after += self.tables_memory();
+        }
+
+        (
+            next_cursor,
+            DefragReport::new(allocated_before, allocated_after),
+        )
+    }
+
     fn remove(&mut self, key: &[u8]) -> u32 {
         use RedisType::*;
         match self.data_mapper.remove_entry(key) {
@@ -79,13 +322,76 @@ impl Storage for InMemoryStorage {
                     Some(_) => 1,
                     None => 0,
                 },
-                List => unimplemented!(),
-                Set => unimplemented!(),
+                List => match self.list_store.remove(&key) {
+                    Some(_) => 1,
+                    None => 0,
+                },
+                Set => match self.set_store.remove(&key) {
+                    Some(_) => 1,
+                    None => 0,
+                },
+                ZSet => match self.zset_store.remove(&key) {
+                    Some(_) => 1,
+                    None => 0,
+                },
+                Stream => match self.stream_store.remove(&key) {
+                    Some(_) => 1,
+                    None => 0,
+                },
             },
             None => 0,
         }
     }
 
+    /// Remove `key` right away but free its value on a background thread when it is big
+    fn unlink(&mut self, key: &[u8]) -> u32 {
+        if !self.contains(key) {
+            return 0;
+        }
+
+        let meta = self.data_mapper.remove(key).unwrap();
+        match meta.data_type {
+            RedisType::String => {
+                let value = self.string_store.remove(key).unwrap();
+                if value.capacity() > LAZY_FREE_THRESHOLD_BYTES {
+                    drop_in_background(value);
+                }
+            }
+            RedisType::Hash => {
+                let value = self.hash_store.remove(key).unwrap();
+                if value.allocated_memory() > LAZY_FREE_THRESHOLD_BYTES {
+                    drop_in_background(value);
+                }
+            }
+            RedisType::List => {
+                let value = self.list_store.remove(key).unwrap();
+                if value.allocated_memory() > LAZY_FREE_THRESHOLD_BYTES {
+                    drop_in_background(value);
+                }
+            }
+            RedisType::Set => {
+                let value = self.set_store.remove(key).unwrap();
+                if value.allocated_memory() > LAZY_FREE_THRESHOLD_BYTES {
+                    drop_in_background(value);
+                }
+            }
+            RedisType::ZSet => {
+                let value = self.zset_store.remove(key).unwrap();
+                if value.allocated_memory() > LAZY_FREE_THRESHOLD_BYTES {
+                    drop_in_background(value);
+                }
+            }
+            RedisType::Stream => {
+                let value = self.stream_store.remove(key).unwrap();
+                if value.allocated_memory() > LAZY_FREE_THRESHOLD_BYTES {
+                    drop_in_background(value);
+                }
+            }
+        }
+
+        1
+    }
+
     /// If the key was present **and** the key was not expired, return `true`
     ///
     /// If the key present but was expired, remove the key and return `false`
@@ -95,7 +401,7 @@ impl Storage for InMemoryStorage {
         if let Some(meta) = self.data_mapper.get(key) {
             match meta.is_expired() {
                 true => {
-                    self.remove(key);
+                    self.remove_expired(key);
                     false
                 }
                 false => true,
@@ -105,28 +411,225 @@ impl Storage for InMemoryStorage {
         }
     }
 
+    /// remove `key` once its expiry has passed, keeping track of it
+    fn remove_expired(&mut self, key: &[u8]) {
+        self.remove(key);
+        self.expired.push(key.to_vec());
+    }
+
+    /// remove all the keys whose expiry has passed
+    fn remove_expired_keys(&mut self) {
+        let keys: Vec<RedisString> = self
+            .data_mapper
+            .iter()
+            .filter(|(_, meta)| meta.is_expired())
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in keys {
+            self.remove_expired(&key);
+        }
+    }
+
+    /// Update the last access time of `key`, return `false` if the key does not exist
+    fn touch(&mut self, key: &[u8]) -> bool {
+        if !self.contains(key) {
+            return false;
+        }
+
+        match self.data_mapper.get_mut(key) {
+            Some(meta) => {
+                meta.touch();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Move the value of `key` (with its type and expiry) to `new_key`, overwriting `new_key`.
+    /// Return `false` if `key` does not exist.
+    fn rename(&mut self, key: &[u8], new_key: &[u8]) -> bool {
+        if !self.contains(key) {
+            return false;
+        }
+
+        if key == new_key {
+            return true;
+        }
+
+        self.remove(new_key);
+
+        let meta = self.data_mapper.remove(key).unwrap();
+        match meta.data_type {
+            RedisType::String => {
+                let value = self.string_store.remove(key).unwrap();
+                self.string_store.insert(new_key.to_vec(), value);
+            }
+            RedisType::Hash => {
+                let value = self.hash_store.remove(key).unwrap();
+                self.hash_store.insert(new_key.to_vec(), value);
+            }
+            RedisType::List => {
+                let value = self.list_store.remove(key).unwrap();
+                self.list_store.insert(new_key.to_vec(), value);
+            }
+            RedisType::Set => {
+                let value = self.set_store.remove(key).unwrap();
+                self.set_store.insert(new_key.to_vec(), value);
+            }
+            RedisType::ZSet => {
+                let value = self.zset_store.remove(key).unwrap();
+                self.zset_store.insert(new_key.to_vec(), value);
+            }
+            RedisType::Stream => {
+                let value = self.stream_store.remove(key).unwrap();
+                self.stream_store.insert(new_key.to_vec(), value);
+            }
+        }
+        self.data_mapper.insert(new_key.to_vec(), meta);
+
+        true
+    }
+
+    /// Deep copy the value of `key` (with its type and expiry) to `new_key`, overwriting `new_key`.
+    /// Return `false` if `key` does not exist.
+    fn copy(&mut self, key: &[u8], new_key: &[u8]) -> bool {
+        if !self.contains(key) {
+            return false;
+        }
+
+        if key == new_key {
+            return true;
+        }
+
+        self.remove(new_key);
+
+        let meta = self.data_mapper.get(key).unwrap();
+        let meta = RedisMeta::new(meta.data_type, meta.expiry);
+        match meta.data_type {
+            RedisType::String => {
+                let value = self.string_store.get(key).unwrap().clone();
+                self.string_store.insert(new_key.to_vec(), value);
+            }
+            RedisType::Hash => {
+                let value = self.hash_store.get(key).unwrap().clone();
+                self.hash_store.insert(new_key.to_vec(), value);
+            }
+            RedisType::List => {
+                let value = self.list_store.get(key).unwrap().clone();
+                self.list_store.insert(new_key.to_vec(), value);
+            }
+            RedisType::Set => {
+                let value = self.set_store.get(key).unwrap().clone();
+                self.set_store.insert(new_key.to_vec(), value);
+            }
+            RedisType::ZSet => {
+                let value = self.zset_store.get(key).unwrap().clone();
+                self.zset_store.insert(new_key.to_vec(), value);
+            }
+            RedisType::Stream => {
+                let value = self.stream_store.get(key).unwrap().clone();
+                self.stream_store.insert(new_key.to_vec(), value);
+            }
+        }
+        self.data_mapper.insert(new_key.to_vec(), meta);
+
+        true
+    }
+
+    /// Copy of the value of `key`, `None` if it does not exist
+    fn value(&mut self, key: &[u8]) -> Option<RedisValue> {
+        if !self.contains(key) {
+            return None;
+        }
+
+        match self.data_mapper.get(key)?.data_type {
+            RedisType::String => self.string_store.get(key).cloned().map(RedisValue::String),
+            RedisType::Hash => self.hash_store.get(key).cloned().map(RedisValue::Hash),
+            RedisType::List => self.list_store.get(key).cloned().map(RedisValue::List),
+            RedisType::Set => self.set_store.get(key).cloned().map(RedisValue::Set),
+            RedisType::ZSet => self.zset_store.get(key).cloned().map(RedisValue::ZSet),
+            RedisType::Stream => self.stream_store.get(key).cloned().map(RedisValue::Stream),
+        }
+    }
+
+    /// Set `key` to `value` with the given expiry, overwriting any existing key
+    fn restore(&mut self, key: &[u8], value: RedisValue, expiry: Option<Expiry>) {
+        self.remove(key);
+
+        let meta = RedisMeta::new(value.data_type(), expiry);
+        match value {
+            RedisValue::String(value) => {
+                self.string_store.insert(key.to_vec(), value);
+            }
+            RedisValue::Hash(value) => {
+                self.hash_store.insert(key.to_vec(), value);
+            }
+            RedisValue::List(value) => {
+                self.list_store.insert(key.to_vec(), value);
+            }
+            RedisValue::Set(value) => {
+                self.set_store.insert(key.to_vec(), value);
+            }
+            RedisValue::ZSet(value) => {
+                self.zset_store.insert(key.to_vec(), value);
+            }
+            RedisValue::Stream(value) => {
+                self.stream_store.insert(key.to_vec(), value);
+            }
+        }
+        self.data_mapper.insert(key.to_vec(), meta);
+    }
+
     fn hwrite(&mut self, key: &[u8], value: HashMap<RedisString, RedisString>) {
+        // the previous value may be of another type
+        self.remove(key);
+
         let meta = RedisMeta::new(RedisType::Hash, None);
         self.data_mapper.insert(key.to_vec(), meta);
         self.hash_store
             .insert(key.to_vec(), RedisHashMap::new(value));
     }
 
+    /// Hash stored at `key`, `None` if it does not exist or holds another type
+    fn hash(&mut self, key: &[u8]) -> Option<&RedisHashMap> {
+        if !self.contains(key) {
+            return None;
+        }
+
+        let meta = self.data_mapper.get_mut(key)?;
+        meta.touch();
+        self.hash_store.get(key)
+    }
+
+    /// Hash stored at `key`, created empty when the key does not exist - `None` if the key holds
+    /// another type
+    fn hash_mut(&mut self, key: &[u8]) -> Option<&mut RedisHashMap> {
+        if !self.contains(key) {
+            self.hwrite(key, HashMap::new());
+        }
+
+        let meta = self.data_mapper.get_mut(key)?;
+        if meta.data_type != RedisType::Hash {
+            return None;
+        }
+        meta.touch();
+        self.hash_store.get_mut(key)
+    }
+
     fn hread(&mut self, key: &[u8], field_key: &[u8]) -> Option<&[u8]> {
-        if let Some(meta) = self.data_mapper.get(key) {
+        if let Some(meta) = self.data_mapper.get_mut(key) {
             match meta.is_expired() {
                 true => {
-                    self.remove(key);
+                    self.remove_expired(key);
                     None
                 }
                 // good to go
                 false => {
-                    // will never panic since we already checked if the key existed in data_mapper
-                    if let Some(field_value) = self.hash_store.get(key).unwrap().data.get(field_key)
-                    {
-                        Some(field_value)
-                    } else {
-                        None
+                    meta.touch();
+                    // `None` when the key holds another type
+                    match self.hash_store.get(key)?.data.get(field_key) {
+                        Some(field_value) => Some(field_value),
+                        None => None,
                     }
                 }
             }
@@ -135,7 +638,374 @@ impl Storage for InMemoryStorage {
         }
     }
 
+    /// List stored at `key`, `None` if it does not exist or holds another type
+    fn list(&mut self, key: &[u8]) -> Option<&RedisList> {
+        if !self.contains(key) {
+            return None;
+        }
+
+        let meta = self.data_mapper.get_mut(key)?;
+        meta.touch();
+        self.list_store.get(key)
+    }
+
+    /// List stored at `key`, created empty when the key does not exist - `None` if the key holds
+    /// another type
+    fn list_mut(&mut self, key: &[u8]) -> Option<&mut RedisList> {
+        if !self.contains(key) {
+            let meta = RedisMeta::new(RedisType::List, None);
+            self.data_mapper.insert(key.to_vec(), meta);
+            self.list_store
+                .insert(key.to_vec(), RedisList::new(VecDeque::new()));
+        }
+
+        let meta = self.data_mapper.get_mut(key)?;
+        if meta.data_type != RedisType::List {
+            return None;
+        }
+        meta.touch();
+        self.list_store.get_mut(key)
+    }
+
+    /// Set stored at `key`, `None` if it does not exist or holds another type
+    fn set(&mut self, key: &[u8]) -> Option<&RedisSet> {
+        if !self.contains(key) {
+            return None;
+        }
+
+        let meta = self.data_mapper.get_mut(key)?;
+        meta.touch();
+        self.set_store.get(key)
+    }
+
+    /// Set stored at `key`, created empty when the key does not exist - `None` if the key holds
+    /// another type
+    fn set_mut(&mut self, key: &[u8]) -> Option<&mut RedisSet> {
+        if !self.contains(key) {
+            let meta = RedisMeta::new(RedisType::Set, None);
+            self.data_mapper.insert(key.to_vec(), meta);
+            self.set_store
+                .insert(key.to_vec(), RedisSet::new(HashSet::new()));
+        }
+
+        let meta = self.data_mapper.get_mut(key)?;
+        if meta.data_type != RedisType::Set {
+            return None;
+        }
+        meta.touch();
+        self.set_store.get_mut(key)
+    }
+
+    /// Sorted set stored at `key`, `None` if it does not exist or holds another type
+    fn zset(&mut self, key: &[u8]) -> Option<&RedisSortedSet> {
+        if !self.contains(key) {
+            return None;
+        }
+
+        let meta = self.data_mapper.get_mut(key)?;
+        meta.touch();
+        self.zset_store.get(key)
+    }
+
+    /// Sorted set stored at `key`, created empty when the key does not exist - `None` if the key
+    /// holds another type
+    fn zset_mut(&mut self, key: &[u8]) -> Option<&mut RedisSortedSet> {
+        if !self.contains(key) {
+            let meta = RedisMeta::new(RedisType::ZSet, None);
+            self.data_mapper.insert(key.to_vec(), meta);
+            self.zset_store.insert(key.to_vec(), RedisSortedSet::new());
+        }
+
+        let meta = self.data_mapper.get_mut(key)?;
+        if meta.data_type != RedisType::ZSet {
+            return None;
+        }
+        meta.touch();
+        self.zset_store.get_mut(key)
+    }
+
+    /// Stream stored at `key`, `None` if it does not exist or holds another type
+    fn stream(&mut self, key: &[u8]) -> Option<&RedisStream> {
+        if !self.contains(key) {
+            return None;
+        }
+
+        let meta = self.data_mapper.get_mut(key)?;
+        meta.touch();
+        self.stream_store.get(key)
+    }
+
+    /// Stream stored at `key`, created empty when the key does not exist - `None` if the key holds
+    /// another type
+    fn stream_mut(&mut self, key: &[u8]) -> Option<&mut RedisStream> {
+        if !self.contains(key) {
+            let meta = RedisMeta::new(RedisType::Stream, None);
+            self.data_mapper.insert(key.to_vec(), meta);
+            self.stream_store.insert(key.to_vec(), RedisStream::new());
+        }
+
+        let meta = self.data_mapper.get_mut(key)?;
+        meta.touch();
+        self.stream_store.get_mut(key)
+    }
+
+    fn keys(&self, pattern: &[u8]) -> Vec<RedisString> {
+        self.data_mapper
+            .iter()
+            .filter(|(key, meta)| !meta.is_expired() && glob_match(pattern, key))
+            .map(|(key, _)| key.clone())
+            .collect()
+    }
+
+    fn scan(
+        &self,
+        cursor: u64,
+        count: usize,
+        pattern: Option<&[u8]>,
+        data_type: Option<&[u8]>,
+    ) -> (u64, Vec<RedisString>) {
+        let keys = self
+            .data_mapper
+            .iter()
+            .filter(|(key, meta)| {
+                !meta.is_expired()
+                    && pattern.is_none_or(|pattern| glob_match(pattern, key))
+                    && data_type.is_none_or(|data_type| {
+                        data_type.eq_ignore_ascii_case(meta.data_type.name().as_bytes())
+                    })
+            })
+            .map(|(key, _)| (key.as_slice(), key.clone()));
+
+        scan(keys, cursor, count)
+    }
+
+    fn random_key(&self) -> Option<RedisString> {
+        self.data_mapper
+            .iter()
+            .filter(|(_, meta)| !meta.is_expired())
+            .map(|(key, _)| key.clone())
+            .choose(&mut rand::thread_rng())
+    }
+
+    /// Remove all the keys. When `lazy` is set, the memory is freed on a background thread.
+    fn flush(&mut self, lazy: bool) {
+        let flushed = std::mem::take(self);
+
+        if lazy {
+            drop_in_background(flushed);
+        }
+    }
+
+    /// number of keys, keys which are logically expired are not counted
+    fn size(&self) -> u64 {
+        self.data_mapper
+            .values()
+            .filter(|meta| !meta.is_expired())
+            .count() as u64
+    }
+}
+
+impl Storage for InMemoryStorage {
+    fn select(&mut self, db: usize) -> bool {
+        if db >= self.databases.len() {
+            return false;
+        }
+
+        self.selected = db;
+        true
+    }
+
+    fn databases(&self) -> usize {
+        self.databases.len()
+    }
+
+    fn swap_db(&mut self, db: usize, other_db: usize) -> bool {
+        if db >= self.databases.len() || other_db >= self.databases.len() {
+            return false;
+        }
+
+        self.databases.swap(db, other_db);
+        true
+    }
+
+    fn write(&mut self, key: &[u8], value: &[u8]) {
+        self.keyspace_mut().write(key, value)
+    }
+
+    fn extend(&mut self, key: &[u8], value: &[u8]) -> u64 {
+        self.keyspace_mut().extend(key, value)
+    }
+
+    fn expire(&mut self, key: &[u8], expiry: Expiry) -> u32 {
+        self.keyspace_mut().expire(key, expiry)
+    }
+
+    fn read(&mut self, key: &[u8]) -> Option<&[u8]> {
+        self.keyspace_mut().read(key)
+    }
+
+    fn string_mut(&mut self, key: &[u8]) -> Option<&mut RedisString> {
+        self.keyspace_mut().string_mut(key)
+    }
+
+    fn remove(&mut self, key: &[u8]) -> u32 {
+        self.keyspace_mut().remove(key)
+    }
+
+    fn unlink(&mut self, key: &[u8]) -> u32 {
+        self.keyspace_mut().unlink(key)
+    }
+
+    fn contains(&mut self, key: &[u8]) -> bool {
+        self.keyspace_mut().contains(key)
+    }
+
+    fn touch(&mut self, key: &[u8]) -> bool {
+        self.keyspace_mut().touch(key)
+    }
+
+    fn rename(&mut self, key: &[u8], new_key: &[u8]) -> bool {
+        self.keyspace_mut().rename(key, new_key)
+    }
+
+    fn copy(&mut self, key: &[u8], new_key: &[u8]) -> bool {
+        self.keyspace_mut().copy(key, new_key)
+    }
+
+    fn value(&mut self, key: &[u8]) -> Option<RedisValue> {
+        self.keyspace_mut().value(key)
+    }
+
+    fn restore(&mut self, key: &[u8], value: RedisValue, expiry: Option<Expiry>) {
+        self.keyspace_mut().restore(key, value, expiry)
+    }
+
+    fn hwrite(&mut self, key: &[u8], value: HashMap<RedisString, RedisString>) {
+        self.keyspace_mut().hwrite(key, value)
+    }
+
+    fn hread(&mut self, key: &[u8], field_key: &[u8]) -> Option<&[u8]> {
+        self.keyspace_mut().hread(key, field_key)
+    }
+
+    fn hash(&mut self, key: &[u8]) -> Option<&RedisHashMap> {
+        self.keyspace_mut().hash(key)
+    }
+
+    fn hash_mut(&mut self, key: &[u8]) -> Option<&mut RedisHashMap> {
+        self.keyspace_mut().hash_mut(key)
+    }
+
+    fn list(&mut self, key: &[u8]) -> Option<&RedisList> {
+        self.keyspace_mut().list(key)
+    }
+
+    fn list_mut(&mut self, key: &[u8]) -> Option<&mut RedisList> {
+        self.keyspace_mut().list_mut(key)
+    }
+
+    fn set(&mut self, key: &[u8]) -> Option<&RedisSet> {
+        self.keyspace_mut().set(key)
+    }
+
+    fn set_mut(&mut self, key: &[u8]) -> Option<&mut RedisSet> {
+        self.keyspace_mut().set_mut(key)
+    }
+
+    fn zset(&mut self, key: &[u8]) -> Option<&RedisSortedSet> {
+        self.keyspace_mut().zset(key)
+    }
+
+    fn zset_mut(&mut self, key: &[u8]) -> Option<&mut RedisSortedSet> {
+        self.keyspace_mut().zset_mut(key)
+    }
+
+    fn stream(&mut self, key: &[u8]) -> Option<&RedisStream> {
+        self.keyspace_mut().stream(key)
+    }
+
+    fn stream_mut(&mut self, key: &[u8]) -> Option<&mut RedisStream> {
+        self.keyspace_mut().stream_mut(key)
+    }
+
+    fn keys(&self, pattern: &[u8]) -> Vec<RedisString> {
+        self.keyspace().keys(pattern)
+    }
+
+    fn scan(
+        &self,
+        cursor: u64,
+        count: usize,
+        pattern: Option<&[u8]>,
+        data_type: Option<&[u8]>,
+    ) -> (u64, Vec<RedisString>) {
+        self.keyspace().scan(cursor, count, pattern, data_type)
+    }
+
+    fn random_key(&self) -> Option<RedisString> {
+        self.keyspace().random_key()
+    }
+
     fn size(&self) -> u64 {
-        self.data_mapper.len() as u64
+        self.keyspace().size()
+    }
+
+    fn flush(&mut self, lazy: bool) {
+        self.keyspace_mut().flush(lazy)
+    }
+
+    fn flush_all(&mut self, lazy: bool) {
+        self.databases
+            .iter_mut()
+            .for_each(|keyspace| keyspace.flush(lazy));
     }
+
+    fn meta(&self, key: &[u8]) -> Option<&RedisMeta> {
+        self.keyspace().meta(key)
+    }
+
+    fn key_info(&mut self, key: &[u8]) -> Option<KeyInfo> {
+        self.keyspace_mut().key_info(key)
+    }
+
+    fn encoding(&mut self, key: &[u8]) -> Option<&'static str> {
+        self.keyspace_mut().encoding(key)
+    }
+
+    /// memory allocated by all the databases
+    fn allocated_memory(&self) -> usize {
+        self.databases
+            .iter()
+            .map(|keyspace| keyspace.allocated_memory())
+            .sum()
+    }
+
+    fn defrag(&mut self, db: usize, cursor: u64, count: usize) -> (u64, DefragReport) {
+        match self.databases.get_mut(db) {
+            Some(keyspace) => keyspace.defrag(cursor, count),
+            None => (0, DefragReport::default()),
+        }
+    }
+
+    fn remove_expired_keys(&mut self) {
+        self.databases
+            .iter_mut()
+            .for_each(|keyspace| keyspace.remove_expired_keys());
+    }
+
+    fn take_expired_keys(&mut self) -> Vec<(usize, RedisString)> {
+        self.databases
+            .iter_mut()
+            .enumerate()
+            .flat_map(|(db, keyspace)| {
+                std::mem::take(&mut keyspace.expired)
+                    .into_iter()
+                    .map(move |key| (db, key))
+            })
+            .collect()
+    }
+}
+
+fn drop_in_background<T: Send + 'static>(value: T) {
+    let _ = thread::spawn(move || drop(value));
 }
@@ -1,25 +1,79 @@
 #[cfg(test)]
 mod tests;
 
+pub mod dump;
 pub mod in_memory;
 pub mod models;
+pub mod scan;
 
 use std::collections::HashMap;
 
 use models::expiry::Expiry;
 use models::RedisString;
 
-use self::models::RedisMeta;
+use self::models::{
+    DefragReport, KeyInfo, RedisHashMap, RedisList, RedisMeta, RedisSet, RedisSortedSet,
+    RedisStream, RedisValue,
+};
 
 pub trait Storage {
+    /// run the next calls against the database `db`, return `false` if it does not exist
+    fn select(&mut self, db: usize) -> bool;
+    fn databases(&self) -> usize;
+    /// exchange the keys of two databases, return `false` if one of them does not exist
+    fn swap_db(&mut self, db: usize, other_db: usize) -> bool;
     fn write(&mut self, key: &[u8], value: &[u8]);
     fn extend(&mut self, key: &[u8], value: &[u8]) -> u64;
     fn expire(&mut self, key: &[u8], expiry: Expiry) -> u32;
     fn read(&mut self, key: &[u8]) -> Option<&[u8]>;
+    /// string stored at `key`, created empty when missing - `None` if the key holds another type
+    fn string_mut(&mut self, key: &[u8]) -> Option<&mut RedisString>;
     fn remove(&mut self, key: &[u8]) -> u32;
+    fn unlink(&mut self, key: &[u8]) -> u32;
     fn contains(&mut self, key: &[u8]) -> bool;
+    fn touch(&mut self, key: &[u8]) -> bool;
+    fn rename(&mut self, key: &[u8], new_key: &[u8]) -> bool;
+    fn copy(&mut self, key: &[u8], new_key: &[u8]) -> bool;
+    fn value(&mut self, key: &[u8]) -> Option<RedisValue>;
+    fn restore(&mut self, key: &[u8], value: RedisValue, expiry: Option<Expiry>);
     fn hwrite(&mut self, key: &[u8], value: HashMap<RedisString, RedisString>);
     fn hread(&mut self, key: &[u8], field_key: &[u8]) -> Option<&[u8]>;
+    fn hash(&mut self, key: &[u8]) -> Option<&RedisHashMap>;
+    fn hash_mut(&mut self, key: &[u8]) -> Option<&mut RedisHashMap>;
+    fn list(&mut self, key: &[u8]) -> Option<&RedisList>;
+    /// list stored at `key`, created empty when missing - `None` if the key holds another type
+    fn list_mut(&mut self, key: &[u8]) -> Option<&mut RedisList>;
+    fn set(&mut self, key: &[u8]) -> Option<&RedisSet>;
+    /// set stored at `key`, created empty when missing - `None` if the key holds another type
+    fn set_mut(&mut self, key: &[u8]) -> Option<&mut RedisSet>;
+    fn zset(&mut self, key: &[u8]) -> Option<&RedisSortedSet>;
+    /// sorted set stored at `key`, created empty when missing - `None` if the key holds another
+    /// type
+    fn zset_mut(&mut self, key: &[u8]) -> Option<&mut RedisSortedSet>;
+    fn stream(&mut self, key: &[u8]) -> Option<&RedisStream>;
+    /// stream stored at `key`, created empty when missing - `None` if the key holds another type
+    fn stream_mut(&mut self, key: &[u8]) -> Option<&mut RedisStream>;
+    fn keys(&self, pattern: &[u8]) -> Vec<RedisString>;
+    fn scan(
+        &self,
+        cursor: u64,
+        count: usize,
+        pattern: Option<&[u8]>,
+        data_type: Option<&[u8]>,
+    ) -> (u64, Vec<RedisString>);
+    fn random_key(&self) -> Option<RedisString>;
     fn size(&self) -> u64;
+    fn flush(&mut self, lazy: bool);
+    fn flush_all(&mut self, lazy: bool);
     fn meta(&self, key: &[u8]) -> Option<&RedisMeta>;
+    fn key_info(&mut self, key: &[u8]) -> Option<KeyInfo>;
+    fn encoding(&mut self, key: &[u8]) -> Option<&'static str>;
+    fn allocated_memory(&self) -> usize;
+    /// shrink the values of up to `count` keys of the database `db` from `cursor`, then the tables
+    /// holding its keys once the returned cursor is back to 0
+    fn defrag(&mut self, db: usize, cursor: u64, count: usize) -> (u64, DefragReport);
+    /// remove the keys of all the databases whose expiry has passed
+    fn remove_expired_keys(&mut self);
+    /// keys removed because they expired since the last call, along with their database
+    fn take_expired_keys(&mut self) -> Vec<(usize, RedisString)>;
 }
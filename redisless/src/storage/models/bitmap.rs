@@ -0,0 +1,237 @@
+//! Bit level access to string values, the bit at offset 0 being the most significant bit of the
+//! first byte
+
+/// Unit of the offsets of a bit range
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum BitUnit {
+    Byte,
+    Bit,
+}
+
+/// Bitwise operation of BITOP
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum BitOperation {
+    And,
+    Or,
+    Xor,
+    Not,
+}
+
+impl BitOperation {
+    /// combine `sources` byte by byte, the shorter ones being padded with zeros up to the length
+    /// of the longest - NOT only applies to the first source
+    pub fn apply(self, sources: &[&[u8]]) -> Vec<u8> {
+        let len = sources.iter().map(|source| source.len()).max().unwrap_or(0);
+        let byte = |source: &[u8], index: usize| source.get(index).copied().unwrap_or(0);
+
+        (0..len)
+            .map(|index| {
+                let mut bytes = sources.iter().map(|source| byte(source, index));
+                let first = bytes.next().unwrap_or(0);
+                match self {
+                    BitOperation::And => bytes.fold(first, |result, byte| result & byte),
+                    BitOperation::Or => bytes.fold(first, |result, byte| result | byte),
+                    BitOperation::Xor => bytes.fold(first, |result, byte| result ^ byte),
+                    BitOperation::Not => !first,
+                }
+            })
+            .collect()
+    }
+}
+
+/// Integer encoding of a BITFIELD field
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct BitFieldType {
+    pub signed: bool,
+    /// width in bits, up to 64 for signed integers and 63 for unsigned ones
+    pub bits: u8,
+}
+
+impl BitFieldType {
+    fn min(&self) -> i128 {
+        match self.signed {
+            true => -(1 << (self.bits - 1)),
+            false => 0,
+        }
+    }
+
+    fn max(&self) -> i128 {
+        match self.signed {
+            true => (1 << (self.bits - 1)) - 1,
+            false => (1 << self.bits) - 1,
+        }
+    }
+
+    /// `value` made to fit in the field according to `overflow`, `None` when it does not fit
+    /// and overflows fail
+    pub fn fit(&self, value: i128, overflow: BitOverflow) -> Option<i64> {
+        let (min, max) = (self.min(), self.max());
+        if (min..=max).contains(&value) {
+            return Some(value as i64);
+        }
+
+        match overflow {
+            BitOverflow::Wrap => {
+                let modulus = 1 << self.bits;
+                match value.rem_euclid(modulus) {
+                    value if value > max => Some((value - modulus) as i64),
+                    value => Some(value as i64),
+                }
+            }
+            BitOverflow::Sat => Some(value.clamp(min, max) as i64),
+            BitOverflow::Fail => None,
+        }
+    }
+}
+
+/// Behavior of the BITFIELD writes which overflow their field
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum BitOverflow {
+    /// keep the lowest bits of the value
+    Wrap,
+    /// stick to the minimum or maximum value of the field
+    Sat,
+    /// leave the field unchanged
+    Fail,
+}
+
+/// Subcommand of BITFIELD, the fields being given with their offset in bits
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum BitFieldOperation {
+    Get(BitFieldType, u64),
+    Set(BitFieldType, u64, i64),
+    IncrBy(BitFieldType, u64, i64),
+    Overflow(BitOverflow),
+}
+
+/// Range of a string given by its first and last offsets included, negative offsets counting
+/// from the end
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct BitRange {
+    pub start: i64,
+    pub end: i64,
+    pub unit: BitUnit,
+}
+
+impl BitRange {
+    /// offsets of the first and last bits of the range over `len` bytes, out of range offsets
+    /// being clamped - `None` when the range is empty
+    pub fn bits(&self, len: usize) -> Option<(u64, u64)> {
+        let len = match self.unit {
+            BitUnit::Byte => len as i64,
+            BitUnit::Bit => len as i64 * 8,
+        };
+        let start = match self.start {
+            start if start < 0 => (len + start).max(0),
+            start => start,
+        };
+        let end = match self.end {
+            end if end < 0 => (len + end).max(0),
+            end => end.min(len - 1),
+        };
+
+        if start > end || start >= len {
+            return None;
+        }
+        match self.unit {
+            BitUnit::Byte => Some((start as u64 * 8, end as u64 * 8 + 7)),
+            BitUnit::Bit => Some((start as u64, end as u64)),
+        }
+    }
+}
+
+/// bit at `offset`, 0 past the end of `bytes`
+pub fn get_bit(bytes: &[u8], offset: u64) -> u8 {
+    match bytes.get((offset / 8) as usize) {
+        Some(byte) => (byte >> (7 - offset % 8)) & 1,
+        None => 0,
+    }
+}
+
+/// set the bit at `offset` to `bit`, padding `bytes` with zeros to reach it, return the previous
+/// bit
+pub fn set_bit(bytes: &mut Vec<u8>, offset: u64, bit: u8) -> u8 {
+    let index = (offset / 8) as usize;
+    if bytes.len() <= index {
+        bytes.resize(index + 1, 0);
+    }
+
+    let previous = get_bit(bytes, offset);
+    let mask = 1 << (7 - offset % 8);
+    match bit {
+        0 => bytes[index] &= !mask,
+        _ => bytes[index] |= mask,
+    }
+    previous
+}
+
+/// number of bits set between the offsets `start` and `end` included, which must be within
+/// `bytes`
+pub fn count_bits(bytes: &[u8], start: u64, end: u64) -> u64 {
+    let first = (start / 8) as usize;
+    let last = (end / 8) as usize;
+
+    bytes[first..=last]
+        .iter()
+        .enumerate()
+        .map(|(index, byte)| {
+            let mut byte = *byte;
+            // leave out the bits of the first and last bytes which are not in range
+            if index == 0 {
+                byte &= 0xff >> (start % 8);
+            }
+            if index == last - first {
+                byte &= 0xff << (7 - end % 8);
+            }
+            byte.count_ones() as u64
+        })
+        .sum()
+}
+
+/// offset of the first bit equal to `bit` between the offsets `start` and `end` included
+pub fn position(bytes: &[u8], bit: u8, start: u64, end: u64) -> Option<u64> {
+    // whole bytes which can not hold the bit are skipped
+    let skipped = match bit {
+        0 => 0xff,
+        _ => 0x00,
+    };
+
+    let mut offset = start;
+    while offset <= end {
+        if offset.is_multiple_of(8) && offset + 7 <= end && bytes[(offset / 8) as usize] == skipped
+        {
+            offset += 8;
+            continue;
+        }
+        if get_bit(bytes, offset) == bit {
+            return Some(offset);
+        }
+        offset += 1;
+    }
+    None
+}
+
+/// integer stored in the field of type `field` at `offset`, the bits past the end of `bytes`
+/// being clear
+pub fn get_field(bytes: &[u8], offset: u64, field: BitFieldType) -> i64 {
+    let bits = field.bits as u64;
+    let value = (offset..offset + bits).fold(0u64, |value, offset| {
+        value << 1 | get_bit(bytes, offset) as u64
+    });
+
+    match field.signed && bits < 64 && value >> (bits - 1) == 1 {
+        // extend the sign
+        true => (value | !0 << bits) as i64,
+        false => value as i64,
+    }
+}
+
+/// store the lowest bits of `value` in the field of type `field` at `offset`, padding `bytes`
+/// with zeros to reach it
+pub fn set_field(bytes: &mut Vec<u8>, offset: u64, field: BitFieldType, value: i64) {
+    let bits = field.bits as u64;
+    for index in 0..bits {
+        let bit = (value as u64 >> (bits - 1 - index)) & 1;
+        set_bit(bytes, offset + index, bit as u8);
+    }
+}
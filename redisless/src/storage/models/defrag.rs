@@ -0,0 +1,29 @@
+use std::ops::AddAssign;
+
+/// Approximate memory allocated by the data structures shrunk by a defragmentation pass, before
+/// and after it
+#[derive(Debug, Default, PartialEq, Copy, Clone)]
+pub struct DefragReport {
+    pub allocated_before: usize,
+    pub allocated_after: usize,
+}
+
+impl DefragReport {
+    pub fn new(allocated_before: usize, allocated_after: usize) -> Self {
+        Self {
+            allocated_before,
+            allocated_after,
+        }
+    }
+
+    pub fn freed(&self) -> usize {
+        self.allocated_before.saturating_sub(self.allocated_after)
+    }
+}
+
+impl AddAssign for DefragReport {
+    fn add_assign(&mut self, other: Self) {
+        self.allocated_before += other.allocated_before;
+        self.allocated_after += other.allocated_after;
+    }
+}
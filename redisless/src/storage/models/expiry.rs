@@ -1,4 +1,5 @@
 use chrono::{offset::Utc, Duration};
+use std::convert::TryFrom;
 
 #[derive(Debug, PartialEq, Copy, Clone)]
 pub struct Expiry {
@@ -10,8 +11,9 @@ pub struct TimeOverflow {}
 
 impl Expiry {
     pub fn new_from_millis(duration: u64) -> Result<Self, TimeOverflow> {
+        let duration = i64::try_from(duration).map_err(|_| TimeOverflow {})?;
         Utc::now()
-            .checked_add_signed(Duration::milliseconds(duration as i64))
+            .checked_add_signed(Duration::milliseconds(duration))
             .map(|t| Self {
                 timestamp: t.timestamp_millis(),
             })
@@ -19,12 +21,10 @@ impl Expiry {
     }
 
     pub fn new_from_secs(duration: u64) -> Result<Self, TimeOverflow> {
-        Utc::now()
-            .checked_add_signed(Duration::seconds(duration as i64))
-            .map(|t| Self {
-                timestamp: t.timestamp_millis(),
-            })
+        duration
+            .checked_mul(1000)
             .ok_or(TimeOverflow {})
+            .and_then(Self::new_from_millis)
     }
 
     pub fn duration_left_millis(&self) -> i64 {
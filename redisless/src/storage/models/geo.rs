@@ -0,0 +1,230 @@
+//! Positions stored in sorted sets as 52 bits geohashes, the score of each member interleaving
+//! the bits of its latitude and longitude
+
+use super::{RedisSortedSet, RedisString};
+
+const LONGITUDE_MIN: f64 = -180.0;
+const LONGITUDE_MAX: f64 = 180.0;
+// limits of the Web Mercator projection, the poles being left out
+const LATITUDE_MIN: f64 = -85.05112878;
+const LATITUDE_MAX: f64 = 85.05112878;
+// bits per coordinate
+const STEP: u32 = 26;
+const EARTH_RADIUS_IN_METERS: f64 = 6372797.560856;
+const GEOHASH_ALPHABET: &[u8] = b"0123456789bcdefghjkmnpqrstuvwxyz";
+
+/// Unit of a distance
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum GeoUnit {
+    Meters,
+    Kilometers,
+    Miles,
+    Feet,
+}
+
+impl GeoUnit {
+    /// length of the unit in meters
+    pub fn meters(self) -> f64 {
+        match self {
+            GeoUnit::Meters => 1.0,
+            GeoUnit::Kilometers => 1000.0,
+            GeoUnit::Miles => 1609.34,
+            GeoUnit::Feet => 0.3048,
+        }
+    }
+}
+
+/// Center of a search
+#[derive(Debug, PartialEq, Clone)]
+pub enum GeoOrigin {
+    /// position of a member of the searched set
+    Member(RedisString),
+    /// longitude and latitude
+    Position(f64, f64),
+}
+
+/// Area of a search around its center, sizes being in meters
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum GeoShape {
+    Radius(f64),
+    /// width and height
+    Box(f64, f64),
+}
+
+impl GeoShape {
+    /// distance in meters from the center to a position within the area, None when it lies
+    /// outside of it
+    pub fn distance(self, center: (f64, f64), position: (f64, f64)) -> Option<f64> {
+        let within = match self {
+            GeoShape::Radius(radius) => distance(center, position) <= radius,
+            GeoShape::Box(width, height) => {
+                // distances along a parallel are measured at the latitude of the position
+                distance_between_latitudes(center.1, position.1) <= height / 2.0
+                    && distance((center.0, position.1), position) <= width / 2.0
+            }
+        };
+        match within {
+            true => Some(distance(center, position)),
+            false => None,
+        }
+    }
+}
+
+/// Order of the results of a search by distance to the center
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum GeoSort {
+    Asc,
+    Desc,
+}
+
+/// Search of the members of a sorted set within an area, as expressed by GEOSEARCH
+#[derive(Debug, PartialEq, Clone)]
+pub struct GeoQuery {
+    pub origin: GeoOrigin,
+    pub shape: GeoShape,
+    /// unit of the sizes of the area and of the distances in replies
+    pub unit: GeoUnit,
+    pub sort: Option<GeoSort>,
+    /// maximum number of members, the nearest ones unless ANY stops at the first ones found
+    pub count: Option<usize>,
+    pub any: bool,
+    pub with_coord: bool,
+    pub with_dist: bool,
+    pub with_hash: bool,
+}
+
+/// Member of a sorted set found by a search
+#[derive(Debug, PartialEq, Clone)]
+pub struct GeoMatch<'a> {
+    pub member: &'a RedisString,
+    pub score: f64,
+    /// distance in meters to the center
+    pub distance: f64,
+    pub position: (f64, f64),
+}
+
+/// members of a sorted set within the area of a query around a center
+pub fn search<'a>(
+    zset: &'a RedisSortedSet,
+    center: (f64, f64),
+    query: &GeoQuery,
+) -> Vec<GeoMatch<'a>> {
+    let mut matches = vec![];
+    for (member, score) in zset.iter() {
+        let position = decode(score as u64);
+        if let Some(distance) = query.shape.distance(center, position) {
+            matches.push(GeoMatch {
+                member,
+                score,
+                distance,
+                position,
+            });
+            if query.any && query.count == Some(matches.len()) {
+                break;
+            }
+        }
+    }
+
+    // only the nearest members are kept when counting them, unless any of them will do
+    let sort = match (query.sort, query.count) {
+        (None, Some(_)) if !query.any => Some(GeoSort::Asc),
+        (sort, _) => sort,
+    };
+    match sort {
+        Some(GeoSort::Asc) => matches.sort_by(|a, b| a.distance.total_cmp(&b.distance)),
+        Some(GeoSort::Desc) => matches.sort_by(|a, b| b.distance.total_cmp(&a.distance)),
+        None => {}
+    }
+    if let Some(count) = query.count {
+        matches.truncate(count);
+    }
+    matches
+}
+
+/// whether a position can be geohashed
+pub fn is_valid(longitude: f64, latitude: f64) -> bool {
+    (LONGITUDE_MIN..=LONGITUDE_MAX).contains(&longitude)
+        && (LATITUDE_MIN..=LATITUDE_MAX).contains(&latitude)
+}
+
+/// geohash of a position, to be used as a score
+pub fn encode(longitude: f64, latitude: f64) -> u64 {
+    encode_in(longitude, latitude, LATITUDE_MIN, LATITUDE_MAX)
+}
+
+/// position at the center of the area of a geohash
+pub fn decode(hash: u64) -> (f64, f64) {
+    let (latitude, longitude) = deinterleave(hash);
+    let center = |offset: u32, min: f64, max: f64| {
+        let cells = (1u64 << STEP) as f64;
+        let low = min + (max - min) * offset as f64 / cells;
+        let high = min + (max - min) * (offset as f64 + 1.0) / cells;
+        ((low + high) / 2.0).clamp(min, max)
+    };
+
+    (
+        center(longitude, LONGITUDE_MIN, LONGITUDE_MAX),
+        center(latitude, LATITUDE_MIN, LATITUDE_MAX),
+    )
+}
+
+/// distance in meters between two positions along the surface of the Earth
+pub fn distance(from: (f64, f64), to: (f64, f64)) -> f64 {
+    let (longitude, latitude) = (from.0.to_radians(), from.1.to_radians());
+    let (other_longitude, other_latitude) = (to.0.to_radians(), to.1.to_radians());
+
+    let u = ((other_latitude - latitude) / 2.0).sin();
+    let v = ((other_longitude - longitude) / 2.0).sin();
+    2.0 * EARTH_RADIUS_IN_METERS
+        * (u * u + latitude.cos() * other_latitude.cos() * v * v)
+            .sqrt()
+            .asin()
+}
+
+/// distance in meters between two parallels
+fn distance_between_latitudes(latitude: f64, other_latitude: f64) -> f64 {
+    EARTH_RADIUS_IN_METERS * (other_latitude.to_radians() - latitude.to_radians()).abs()
+}
+
+/// standard 11 characters geohash of a position, over latitudes from -90 to 90 degrees
+pub fn geohash_string(longitude: f64, latitude: f64) -> String {
+    let hash = encode_in(longitude, latitude, -90.0, 90.0);
+    (0..11)
+        .map(|i| {
+            // the 52 bits make up 10 characters and a half, the last one being padded with zeros
+            let index = match i {
+                10 => 0,
+                i => (hash >> (52 - (i + 1) * 5)) & 0x1f,
+            };
+            GEOHASH_ALPHABET[index as usize] as char
+        })
+        .collect()
+}
+
+fn encode_in(longitude: f64, latitude: f64, latitude_min: f64, latitude_max: f64) -> u64 {
+    let offset = |value: f64, min: f64, max: f64| {
+        let offset = (value - min) / (max - min) * (1u64 << STEP) as f64;
+        (offset as u64).min((1 << STEP) - 1) as u32
+    };
+
+    interleave(
+        offset(latitude, latitude_min, latitude_max),
+        offset(longitude, LONGITUDE_MIN, LONGITUDE_MAX),
+    )
+}
+
+/// bits of `even` at the even positions and bits of `odd` at the odd ones
+fn interleave(even: u32, odd: u32) -> u64 {
+    (0..STEP).fold(0, |hash, bit| {
+        hash | ((even as u64 >> bit) & 1) << (2 * bit) | ((odd as u64 >> bit) & 1) << (2 * bit + 1)
+    })
+}
+
+fn deinterleave(hash: u64) -> (u32, u32) {
+    (0..STEP).fold((0, 0), |(even, odd), bit| {
+        (
+            even | (((hash >> (2 * bit)) & 1) as u32) << bit,
+            odd | (((hash >> (2 * bit + 1)) & 1) as u32) << bit,
+        )
+    })
+}
@@ -1,7 +1,8 @@
 use super::RedisString;
 use std::collections::HashMap;
+use std::mem::size_of;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub struct RedisHashMap {
     pub data: HashMap<RedisString, RedisString>,
 }
@@ -10,4 +11,18 @@ impl RedisHashMap {
     pub fn new(data: HashMap<RedisString, RedisString>) -> Self {
         Self { data }
     }
+
+    pub fn allocated_memory(&self) -> usize {
+        self.data.capacity() * size_of::<(RedisString, RedisString)>()
+            + self
+                .data
+                .iter()
+                .map(|(k, v)| k.capacity() + v.capacity())
+                .sum::<usize>()
+    }
+
+    pub fn shrink_to_fit(&mut self) {
+        self.data.shrink_to_fit();
+        self.data.values_mut().for_each(|v| v.shrink_to_fit());
+    }
 }
@@ -0,0 +1,127 @@
+//! HyperLogLog cardinality estimation, stored in string values made of a magic header followed by
+//! one byte per register
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::Hasher;
+
+const MAGIC: &[u8] = b"HYLL";
+// bits of the hash selecting a register
+const PRECISION: u32 = 14;
+const REGISTERS: usize = 1 << PRECISION;
+// bits of the hash left to count the run of zeros
+const Q: u32 = 64 - PRECISION;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct HyperLogLog {
+    registers: Vec<u8>,
+}
+
+impl Default for HyperLogLog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HyperLogLog {
+    pub fn new() -> Self {
+        Self {
+            registers: vec![0; REGISTERS],
+        }
+    }
+
+    /// HyperLogLog stored in a string value, `None` if the string is not one
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let registers = bytes.strip_prefix(MAGIC)?;
+        match registers.len() == REGISTERS && registers.iter().all(|r| *r as u32 <= Q + 1) {
+            true => Some(Self {
+                registers: registers.to_vec(),
+            }),
+            false => None,
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        [MAGIC, &self.registers].concat()
+    }
+
+    /// count `element`, return `true` if a register has changed
+    pub fn add(&mut self, element: &[u8]) -> bool {
+        let mut hasher = DefaultHasher::new();
+        hasher.write(element);
+        let hash = hasher.finish();
+
+        let index = (hash & (REGISTERS as u64 - 1)) as usize;
+        // the bit past the last one counted bounds the run of zeros
+        let rank = ((hash >> PRECISION) | 1 << Q).trailing_zeros() as u8 + 1;
+        match rank > self.registers[index] {
+            true => {
+                self.registers[index] = rank;
+                true
+            }
+            false => false,
+        }
+    }
+
+    /// count the elements of `other` as well
+    pub fn merge(&mut self, other: &Self) {
+        for (register, other) in self.registers.iter_mut().zip(&other.registers) {
+            *register = (*register).max(*other);
+        }
+    }
+
+    /// estimated number of distinct elements, using the estimator of Otmar Ertl's "New
+    /// cardinality estimation algorithms for HyperLogLog sketches" as Redis does
+    pub fn count(&self) -> u64 {
+        let mut histogram = [0u32; Q as usize + 2];
+        for register in &self.registers {
+            histogram[*register as usize] += 1;
+        }
+
+        let m = REGISTERS as f64;
+        let mut z = m * tau((m - histogram[Q as usize + 1] as f64) / m);
+        for k in (1..=Q as usize).rev() {
+            z += histogram[k] as f64;
+            z *= 0.5;
+        }
+        z += m * sigma(histogram[0] as f64 / m);
+
+        let alpha = 0.5 / 2f64.ln();
+        (alpha * m * m / z).round() as u64
+    }
+}
+
+fn sigma(mut x: f64) -> f64 {
+    if x == 1.0 {
+        return f64::INFINITY;
+    }
+
+    let mut y = 1.0;
+    let mut z = x;
+    loop {
+        x *= x;
+        let previous = z;
+        z += x * y;
+        y += y;
+        if previous == z {
+            return z;
+        }
+    }
+}
+
+fn tau(mut x: f64) -> f64 {
+    if x == 0.0 || x == 1.0 {
+        return 0.0;
+    }
+
+    let mut y = 1.0;
+    let mut z = 1.0 - x;
+    loop {
+        x = x.sqrt();
+        let previous = z;
+        y *= 0.5;
+        z -= (1.0 - x).powi(2) * y;
+        if previous == z {
+            return z / 3.0;
+        }
+    }
+}
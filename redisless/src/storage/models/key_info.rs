@@ -0,0 +1,12 @@
+use super::RedisType;
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct KeyInfo {
+    pub data_type: RedisType,
+    /// milliseconds left before the key expires, `-1` when it has no expiry
+    pub ttl: i64,
+    /// number of elements held by the value, `1` for strings
+    pub elements: u64,
+    /// approximate number of bytes used by the key and its value
+    pub size: u64,
+}
@@ -0,0 +1,78 @@
+use super::RedisString;
+use std::collections::VecDeque;
+use std::mem::size_of;
+use std::ops::RangeInclusive;
+
+/// Side of a list, `Left` being the head
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum ListEnd {
+    Left,
+    Right,
+}
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct RedisList {
+    pub data: VecDeque<RedisString>,
+}
+
+impl RedisList {
+    pub fn new(data: VecDeque<RedisString>) -> Self {
+        Self { data }
+    }
+
+    pub fn push(&mut self, end: ListEnd, value: RedisString) {
+        match end {
+            ListEnd::Left => self.data.push_front(value),
+            ListEnd::Right => self.data.push_back(value),
+        }
+    }
+
+    pub fn pop(&mut self, end: ListEnd) -> Option<RedisString> {
+        match end {
+            ListEnd::Left => self.data.pop_front(),
+            ListEnd::Right => self.data.pop_back(),
+        }
+    }
+
+    /// Position of the element at `index`, negative indexes counting from the tail
+    pub fn index(&self, index: i64) -> Option<usize> {
+        let len = self.data.len() as i64;
+        let index = if index < 0 { len + index } else { index };
+
+        match (0..len).contains(&index) {
+            true => Some(index as usize),
+            false => None,
+        }
+    }
+
+    /// Positions between `start` and `stop` included, negative offsets counting from the tail.
+    /// Out of range offsets are clamped, `None` when the range is empty.
+    pub fn range(&self, start: i64, stop: i64) -> Option<RangeInclusive<usize>> {
+        let len = self.data.len() as i64;
+        let start = if start < 0 {
+            (len + start).max(0)
+        } else {
+            start
+        };
+        let stop = if stop < 0 {
+            len + stop
+        } else {
+            stop.min(len - 1)
+        };
+
+        match start > stop || start >= len {
+            true => None,
+            false => Some(start as usize..=stop as usize),
+        }
+    }
+
+    pub fn allocated_memory(&self) -> usize {
+        self.data.capacity() * size_of::<RedisString>()
+            + self.data.iter().map(|v| v.capacity()).sum::<usize>()
+    }
+
+    pub fn shrink_to_fit(&mut self) {
+        self.data.shrink_to_fit();
+        self.data.iter_mut().for_each(|v| v.shrink_to_fit());
+    }
+}
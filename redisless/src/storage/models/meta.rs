@@ -1,13 +1,59 @@
+use chrono::Utc;
+use rand::Rng;
+
 use super::{Expiry, RedisType};
 
+// access frequency of a new key, so that it is not evicted before having a chance to be accessed
+const LFU_INIT_VAL: u8 = 5;
+// the higher, the more accesses are needed to increment the access frequency
+const LFU_LOG_FACTOR: f64 = 10.0;
+// the access frequency is decremented for each period of inactivity
+const LFU_DECAY_MILLIS: i64 = 60 * 1000;
+
 pub struct RedisMeta {
     pub data_type: RedisType,
     pub expiry: Option<Expiry>,
+    // timestamp in millis of the last access to the key
+    pub last_access: i64,
+    // logarithmic access counter, like the one of the Redis LFU eviction policies
+    access_frequency: u8,
 }
 
 impl RedisMeta {
     pub fn new(data_type: RedisType, expiry: Option<Expiry>) -> Self {
-        Self { data_type, expiry }
+        Self {
+            data_type,
+            expiry,
+            last_access: Utc::now().timestamp_millis(),
+            access_frequency: LFU_INIT_VAL,
+        }
+    }
+
+    pub fn touch(&mut self) {
+        let mut access_frequency = self.access_frequency();
+        if access_frequency < u8::MAX {
+            let base = access_frequency.saturating_sub(LFU_INIT_VAL) as f64;
+            if rand::thread_rng().gen::<f64>() < 1.0 / (base * LFU_LOG_FACTOR + 1.0) {
+                access_frequency += 1;
+            }
+        }
+
+        self.access_frequency = access_frequency;
+        self.last_access = Utc::now().timestamp_millis();
+    }
+
+    /// access frequency counter (0-255), decayed by the time elapsed since the last access
+    pub fn access_frequency(&self) -> u8 {
+        let periods = self.idle_millis() / LFU_DECAY_MILLIS;
+        match periods > self.access_frequency as i64 {
+            true => 0,
+            false => self.access_frequency - periods as u8,
+        }
+    }
+
+    /// time elapsed since the last access to the key
+    pub fn idle_millis(&self) -> i64 {
+        (Utc::now().timestamp_millis() - self.last_access).max(0)
     }
 
     pub fn is_expired(&self) -> bool {
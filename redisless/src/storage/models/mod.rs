@@ -1,18 +1,58 @@
+pub mod bitmap;
+pub mod defrag;
 pub mod expiry;
+pub mod geo;
 pub mod hash;
+pub mod hyperloglog;
+pub mod key_info;
+pub mod list;
 pub mod meta;
+pub mod set;
+pub mod sorted_set;
+pub mod stream;
+pub mod value;
 
 // re-export so one can use with models::Expiry
 // rather than models::expiry::Expiry
+pub use defrag::DefragReport;
 pub use expiry::Expiry;
 pub use hash::RedisHashMap;
+pub use hyperloglog::HyperLogLog;
+pub use key_info::KeyInfo;
+pub use list::{ListEnd, RedisList};
 pub use meta::RedisMeta;
+pub use set::RedisSet;
+pub use sorted_set::{
+    Aggregate, LexBound, RedisSortedSet, ScoreBound, ScoreEnd, ZRangeBy, ZRangeQuery,
+};
+pub use stream::{
+    Claim, ClaimOptions, ConsumerEntry, ConsumerGroup, NewStreamId, PendingEntry, RedisStream,
+    StreamConsumer, StreamFields, StreamId, StreamTrim, TrimStrategy,
+};
+pub use value::RedisValue;
 
 pub type RedisString = Vec<u8>;
 
+#[derive(Debug, PartialEq, Copy, Clone)]
 pub enum RedisType {
     String,
     List,
     Set,
+    ZSet,
     Hash,
+    Stream,
+}
+
+impl RedisType {
+    /// name of the type as reported by the `TYPE` command
+    pub fn name(&self) -> &'static str {
+        match self {
+            RedisType::String => "string",
+            RedisType::List => "list",
+            RedisType::Set => "set",
+            RedisType::ZSet => "zset",
+            RedisType::Hash => "hash",
+            RedisType::Stream => "stream",
+        }
+    }
 }
@@ -0,0 +1,23 @@
+use super::RedisString;
+use std::collections::HashSet;
+use std::mem::size_of;
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct RedisSet {
+    pub data: HashSet<RedisString>,
+}
+
+impl RedisSet {
+    pub fn new(data: HashSet<RedisString>) -> Self {
+        Self { data }
+    }
+
+    pub fn allocated_memory(&self) -> usize {
+        self.data.capacity() * size_of::<RedisString>()
+            + self.data.iter().map(|v| v.capacity()).sum::<usize>()
+    }
+
+    pub fn shrink_to_fit(&mut self) {
+        self.data.shrink_to_fit();
+    }
+}
@@ -0,0 +1,287 @@
+use super::RedisString;
+use std::cmp::Ordering;
+use std::collections::{BTreeSet, HashMap};
+use std::mem::size_of;
+use std::ops::RangeInclusive;
+
+/// Score of a sorted set member, never NaN so that scores are totally ordered
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Score(pub f64);
+
+impl Eq for Score {}
+
+impl PartialOrd for Score {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Score {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.partial_cmp(&other.0).unwrap()
+    }
+}
+
+/// Side of a sorted set
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum ScoreEnd {
+    Min,
+    Max,
+}
+
+/// How the scores of a member found in several sorted sets are combined
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Aggregate {
+    Sum,
+    Min,
+    Max,
+}
+
+impl Aggregate {
+    pub fn apply(self, score: f64, other: f64) -> f64 {
+        match self {
+            // adding opposite infinities gives 0 rather than NaN
+            Aggregate::Sum => match score + other {
+                sum if sum.is_nan() => 0.0,
+                sum => sum,
+            },
+            Aggregate::Min => score.min(other),
+            Aggregate::Max => score.max(other),
+        }
+    }
+}
+
+/// Bound of a score range
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct ScoreBound {
+    pub value: f64,
+    pub exclusive: bool,
+}
+
+impl ScoreBound {
+    fn is_below(&self, score: f64) -> bool {
+        match self.exclusive {
+            true => self.value < score,
+            false => self.value <= score,
+        }
+    }
+
+    fn is_above(&self, score: f64) -> bool {
+        match self.exclusive {
+            true => self.value > score,
+            false => self.value >= score,
+        }
+    }
+}
+
+/// Bound of a lexicographic range
+#[derive(Debug, PartialEq, Clone)]
+pub enum LexBound {
+    /// before any member
+    Min,
+    /// after any member
+    Max,
+    Inclusive(RedisString),
+    Exclusive(RedisString),
+}
+
+impl LexBound {
+    fn is_below(&self, member: &[u8]) -> bool {
+        match self {
+            LexBound::Min => true,
+            LexBound::Max => false,
+            LexBound::Inclusive(bound) => bound.as_slice() <= member,
+            LexBound::Exclusive(bound) => bound.as_slice() < member,
+        }
+    }
+
+    fn is_above(&self, member: &[u8]) -> bool {
+        match self {
+            LexBound::Min => false,
+            LexBound::Max => true,
+            LexBound::Inclusive(bound) => bound.as_slice() >= member,
+            LexBound::Exclusive(bound) => bound.as_slice() > member,
+        }
+    }
+}
+
+/// How the members of a range query are selected
+#[derive(Debug, PartialEq, Clone)]
+pub enum ZRangeBy {
+    /// ranks between two indexes included, negative indexes counting from the end
+    Index(i64, i64),
+    /// scores between a minimum and a maximum
+    Score(ScoreBound, ScoreBound),
+    /// members between a minimum and a maximum, when all the scores are equal
+    Lex(LexBound, LexBound),
+}
+
+/// Range query over a sorted set, as expressed by ZRANGE
+#[derive(Debug, PartialEq, Clone)]
+pub struct ZRangeQuery {
+    pub by: ZRangeBy,
+    /// walk the members from the highest score to the lowest
+    pub rev: bool,
+    /// offset and count of the members to return among the ones in range, a negative count
+    /// returning all of them
+    pub limit: Option<(i64, i64)>,
+}
+
+impl ZRangeQuery {
+    pub fn new(by: ZRangeBy, rev: bool) -> Self {
+        Self {
+            by,
+            rev,
+            limit: None,
+        }
+    }
+}
+
+/// Members ordered by score, then lexicographically for equal scores
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct RedisSortedSet {
+    scores: HashMap<RedisString, f64>,
+    ordered: BTreeSet<(Score, RedisString)>,
+}
+
+impl RedisSortedSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.scores.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.scores.is_empty()
+    }
+
+    pub fn score(&self, member: &[u8]) -> Option<f64> {
+        self.scores.get(member).copied()
+    }
+
+    /// add `member` or update its score, return `true` if it has been added
+    pub fn insert(&mut self, member: RedisString, score: f64) -> bool {
+        debug_assert!(!score.is_nan());
+
+        match self.scores.insert(member.clone(), score) {
+            Some(previous) => {
+                self.ordered.remove(&(Score(previous), member.clone()));
+                self.ordered.insert((Score(score), member));
+                false
+            }
+            None => {
+                self.ordered.insert((Score(score), member));
+                true
+            }
+        }
+    }
+
+    /// remove `member`, return its score if it was present
+    pub fn remove(&mut self, member: &[u8]) -> Option<f64> {
+        let (member, score) = self.scores.remove_entry(member)?;
+        self.ordered.remove(&(Score(score), member));
+        Some(score)
+    }
+
+    /// remove the member with the lowest or the highest score
+    pub fn pop(&mut self, end: ScoreEnd) -> Option<(RedisString, f64)> {
+        let (score, member) = match end {
+            ScoreEnd::Min => self.ordered.pop_first()?,
+            ScoreEnd::Max => self.ordered.pop_last()?,
+        };
+        self.scores.remove(&member);
+        Some((member, score.0))
+    }
+
+    /// position of `member` from the lowest score, starting at 0
+    pub fn rank(&self, member: &[u8]) -> Option<usize> {
+        let score = self.score(member)?;
+        Some(
+            self.ordered
+                .range(..(Score(score), member.to_vec()))
+                .count(),
+        )
+    }
+
+    /// members along with their scores, from the lowest score to the highest
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = (&RedisString, f64)> {
+        self.ordered.iter().map(|(score, member)| (member, score.0))
+    }
+
+    /// Ranks between `start` and `stop` included, negative offsets counting from the end.
+    /// Out of range offsets are clamped, `None` when the range is empty.
+    pub fn range(&self, start: i64, stop: i64) -> Option<RangeInclusive<usize>> {
+        let len = self.len() as i64;
+        let start = if start < 0 {
+            (len + start).max(0)
+        } else {
+            start
+        };
+        let stop = if stop < 0 {
+            len + stop
+        } else {
+            stop.min(len - 1)
+        };
+
+        match start > stop || start >= len {
+            true => None,
+            false => Some(start as usize..=stop as usize),
+        }
+    }
+
+    /// members selected by `query`, in the order it walks them
+    pub fn query(&self, query: &ZRangeQuery) -> Vec<(&RedisString, f64)> {
+        let members: Box<dyn Iterator<Item = (&RedisString, f64)>> = match query.rev {
+            true => Box::new(self.iter().rev()),
+            false => Box::new(self.iter()),
+        };
+
+        let members: Box<dyn Iterator<Item = (&RedisString, f64)>> = match &query.by {
+            ZRangeBy::Index(start, stop) => match self.range(*start, *stop) {
+                Some(range) => Box::new(members.skip(*range.start()).take(range.count())),
+                None => return vec![],
+            },
+            ZRangeBy::Score(min, max) => {
+                let in_range = move |(_, score): &(&RedisString, f64)| {
+                    min.is_below(*score) && max.is_above(*score)
+                };
+                Box::new(
+                    members
+                        .skip_while(move |m| !in_range(m))
+                        .take_while(in_range),
+                )
+            }
+            ZRangeBy::Lex(min, max) => {
+                let in_range = move |(member, _): &(&RedisString, f64)| {
+                    min.is_below(member) && max.is_above(member)
+                };
+                Box::new(
+                    members
+                        .skip_while(move |m| !in_range(m))
+                        .take_while(in_range),
+                )
+            }
+        };
+
+        match query.limit {
+            Some((offset, _)) if offset < 0 => vec![],
+            Some((offset, count)) if count >= 0 => {
+                members.skip(offset as usize).take(count as usize).collect()
+            }
+            Some((offset, _)) => members.skip(offset as usize).collect(),
+            None => members.collect(),
+        }
+    }
+
+    pub fn allocated_memory(&self) -> usize {
+        self.scores.capacity() * size_of::<(RedisString, f64)>()
+            + self.ordered.len() * size_of::<(Score, RedisString)>()
+            + 2 * self.scores.keys().map(|k| k.capacity()).sum::<usize>()
+    }
+
+    pub fn shrink_to_fit(&mut self) {
+        self.scores.shrink_to_fit();
+    }
+}
@@ -0,0 +1,605 @@
+use super::RedisString;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::mem::size_of;
+
+/// ID of a stream entry: a timestamp in milliseconds and a sequence number among the entries of
+/// the same millisecond
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Copy, Clone, Default)]
+pub struct StreamId {
+    pub ms: u64,
+    pub seq: u64,
+}
+
+impl StreamId {
+    pub const MIN: StreamId = StreamId { ms: 0, seq: 0 };
+    pub const MAX: StreamId = StreamId {
+        ms: u64::MAX,
+        seq: u64::MAX,
+    };
+
+    pub fn new(ms: u64, seq: u64) -> Self {
+        Self { ms, seq }
+    }
+
+    /// smallest ID greater than this one, `None` for the maximum ID
+    pub fn next(&self) -> Option<StreamId> {
+        match self.seq.checked_add(1) {
+            Some(seq) => Some(StreamId::new(self.ms, seq)),
+            None => Some(StreamId::new(self.ms.checked_add(1)?, 0)),
+        }
+    }
+
+    /// greatest ID less than this one, `None` for the minimum ID
+    pub fn previous(&self) -> Option<StreamId> {
+        match self.seq.checked_sub(1) {
+            Some(seq) => Some(StreamId::new(self.ms, seq)),
+            None => Some(StreamId::new(self.ms.checked_sub(1)?, u64::MAX)),
+        }
+    }
+}
+
+impl fmt::Display for StreamId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.ms, self.seq)
+    }
+}
+
+/// ID requested for a new entry by XADD
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum NewStreamId {
+    /// `*`, generated from the current time
+    Auto,
+    /// `ms-*`, the sequence number being generated
+    Partial(u64),
+    Explicit(StreamId),
+}
+
+/// Fields of a stream entry along with their values, in the order they were given
+pub type StreamFields = Vec<(RedisString, RedisString)>;
+
+/// Entry delivered to a consumer of a group and not acknowledged yet
+#[derive(Debug, PartialEq, Clone)]
+pub struct PendingEntry {
+    pub consumer: RedisString,
+    /// time of the last delivery in milliseconds
+    pub delivery_time: u64,
+    pub delivery_count: u64,
+}
+
+/// Consumer of a group, known from the first time it read from the group or was created
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct StreamConsumer {
+    /// time the consumer last read from the group in milliseconds
+    pub seen_time: u64,
+    /// time entries were last delivered to the consumer or claimed by it in milliseconds
+    pub active_time: Option<u64>,
+    /// IDs of the entries delivered to the consumer and not acknowledged yet
+    pub pending: BTreeSet<StreamId>,
+}
+
+/// Consumers sharing the entries of a stream, each entry being delivered to a single one of them
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct ConsumerGroup {
+    /// ID of the last entry delivered to any consumer of the group
+    pub last_delivered_id: StreamId,
+    /// number of entries of the stream delivered to the group, `None` when it is not known
+    pub entries_read: Option<u64>,
+    /// entries delivered and not acknowledged yet, the pending entry list of the group
+    pub pending: BTreeMap<StreamId, PendingEntry>,
+    pub consumers: BTreeMap<RedisString, StreamConsumer>,
+}
+
+impl ConsumerGroup {
+    pub fn new(last_delivered_id: StreamId, entries_read: Option<u64>) -> Self {
+        Self {
+            last_delivered_id,
+            entries_read,
+            ..Self::default()
+        }
+    }
+
+    /// record that `consumer` is active at `now`, adding it if needed
+    pub fn touch_consumer(&mut self, name: &[u8], now: u64) {
+        self.create_consumer(name, now);
+        self.consumers.get_mut(name).unwrap().seen_time = now;
+    }
+
+    /// add a consumer seen at `now`, false if it already exists
+    pub fn create_consumer(&mut self, name: &[u8], now: u64) -> bool {
+        if self.consumers.contains_key(name) {
+            return false;
+        }
+
+        let consumer = StreamConsumer {
+            seen_time: now,
+            active_time: None,
+            pending: BTreeSet::new(),
+        };
+        self.consumers.insert(name.to_vec(), consumer);
+        true
+    }
+
+    /// remove a consumer along with its pending entries, `None` if it does not exist or the
+    /// number of pending entries removed
+    pub fn delete_consumer(&mut self, name: &[u8]) -> Option<usize> {
+        let consumer = self.consumers.remove(name)?;
+        for id in &consumer.pending {
+            self.pending.remove(id);
+        }
+        Some(consumer.pending.len())
+    }
+
+    /// record entry `id` as delivered to `consumer` at `now`, taking it from the consumer it was
+    /// pending for if any
+    pub fn deliver(&mut self, id: StreamId, consumer: &[u8], now: u64) {
+        self.assign(id, consumer, now, 1);
+    }
+
+    /// make entry `id` pending for `consumer`, taking it from the consumer it was pending for if
+    /// any
+    pub fn assign(
+        &mut self,
+        id: StreamId,
+        consumer: &[u8],
+        delivery_time: u64,
+        delivery_count: u64,
+    ) {
+        if let Some(entry) = self.pending.remove(&id) {
+            if let Some(owner) = self.consumers.get_mut(&entry.consumer) {
+                owner.pending.remove(&id);
+            }
+        }
+
+        self.consumers
+            .entry(consumer.to_vec())
+            .or_default()
+            .pending
+            .insert(id);
+        let entry = PendingEntry {
+            consumer: consumer.to_vec(),
+            delivery_time,
+            delivery_count,
+        };
+        self.pending.insert(id, entry);
+    }
+
+    /// acknowledge entry `id`, false if it was not pending
+    pub fn ack(&mut self, id: StreamId) -> bool {
+        match self.pending.remove(&id) {
+            Some(entry) => {
+                if let Some(consumer) = self.consumers.get_mut(&entry.consumer) {
+                    consumer.pending.remove(&id);
+                }
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// Number of entries in a node of the Redis representation of streams, approximate trimming
+/// removing whole nodes only
+pub const STREAM_NODE_MAX_ENTRIES: usize = 100;
+
+/// Entries removed by trimming
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum TrimStrategy {
+    /// the oldest entries beyond a number of entries
+    MaxLen(usize),
+    /// the entries with an ID less than a given one
+    MinId(StreamId),
+}
+
+/// Trimming of XTRIM and XADD
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct StreamTrim {
+    pub strategy: TrimStrategy,
+    /// `~`, removing entries by whole nodes only
+    pub approximate: bool,
+    /// maximum number of entries to remove, for approximate trimming only
+    pub limit: Option<usize>,
+}
+
+/// Options of XCLAIM and XAUTOCLAIM
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct ClaimOptions {
+    /// minimum time in milliseconds since the last delivery of the entries to claim
+    pub min_idle: u64,
+    /// time in milliseconds since the last delivery to set for the claimed entries
+    pub idle: Option<u64>,
+    /// Unix time in milliseconds of the last delivery to set for the claimed entries
+    pub time: Option<u64>,
+    /// delivery count to set for the claimed entries
+    pub retry_count: Option<u64>,
+    /// claim the entries of the stream which are not pending too
+    pub force: bool,
+    /// leave the delivery count alone, the claimed entries being replied with their ID only
+    pub just_id: bool,
+}
+
+/// Outcome of claiming a single entry
+#[derive(Debug, PartialEq, Clone)]
+pub enum Claim {
+    Claimed(StreamId, StreamFields),
+    /// the entry has been deleted from the stream and is no longer pending
+    Deleted(StreamId),
+    /// the entry is not pending or has been delivered too recently
+    Skipped,
+}
+
+/// Entry as read by a consumer, without fields when it has been deleted from the stream since it
+/// was delivered
+pub type ConsumerEntry = (StreamId, Option<StreamFields>);
+
+/// Append-only log of entries ordered by ID
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct RedisStream {
+    entries: BTreeMap<StreamId, StreamFields>,
+    /// ID of the last entry ever added, which can have been deleted since
+    last_id: StreamId,
+    /// number of entries ever added
+    entries_added: u64,
+    /// greatest ID of the deleted entries
+    max_deleted_id: StreamId,
+    pub groups: BTreeMap<RedisString, ConsumerGroup>,
+}
+
+impl RedisStream {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn last_id(&self) -> StreamId {
+        self.last_id
+    }
+
+    pub fn entries_added(&self) -> u64 {
+        self.entries_added
+    }
+
+    pub fn max_deleted_id(&self) -> StreamId {
+        self.max_deleted_id
+    }
+
+    /// number of nodes of the Redis representation of the stream, each holding up to
+    /// `STREAM_NODE_MAX_ENTRIES` entries
+    pub fn node_count(&self) -> usize {
+        self.entries.len().div_ceil(STREAM_NODE_MAX_ENTRIES)
+    }
+
+    pub fn first_entry(&self) -> Option<(&StreamId, &StreamFields)> {
+        self.entries.iter().next()
+    }
+
+    pub fn last_entry(&self) -> Option<(&StreamId, &StreamFields)> {
+        self.entries.iter().next_back()
+    }
+
+    /// set the last ID, the number of entries ever added and the greatest deleted ID as XSETID,
+    /// which checks them against the entries
+    pub fn set_id(
+        &mut self,
+        last_id: StreamId,
+        entries_added: Option<u64>,
+        max_deleted_id: Option<StreamId>,
+    ) {
+        self.last_id = last_id;
+        self.entries_added = entries_added.unwrap_or(self.entries_added);
+        self.max_deleted_id = max_deleted_id.unwrap_or(self.max_deleted_id);
+    }
+
+    /// number of entries added up to `id` included, `None` when it can not be told because of
+    /// the deleted entries
+    pub fn entries_read_until(&self, id: StreamId) -> Option<u64> {
+        let first_id = match self.entries.keys().next() {
+            Some(first_id) if id < self.last_id => *first_id,
+            // every entry has been read
+            _ => return Some(self.entries_added),
+        };
+
+        if self.max_deleted_id == StreamId::MIN {
+            Some(self.entries.range(..=id).count() as u64)
+        } else if self.max_deleted_id < first_id && id < first_id {
+            // only the oldest entries have been deleted, none of the remaining ones is read
+            Some(self.entries_added - self.entries.len() as u64)
+        } else {
+            None
+        }
+    }
+
+    /// number of entries of the stream not delivered to `group` yet, `None` when it is not known
+    pub fn lag(&self, group: &ConsumerGroup) -> Option<u64> {
+        match group.last_delivered_id >= self.last_id {
+            true => Some(0),
+            false => group
+                .entries_read
+                .map(|entries_read| self.entries_added.saturating_sub(entries_read)),
+        }
+    }
+
+    /// ID of the next entry according to `id`, `None` when it would not be greater than the last
+    /// ID - `now` being the current time in milliseconds
+    pub fn next_id(&self, id: NewStreamId, now: u64) -> Option<StreamId> {
+        let id = match id {
+            NewStreamId::Auto if now > self.last_id.ms => StreamId::new(now, 0),
+            NewStreamId::Auto => self.last_id.next()?,
+            NewStreamId::Partial(ms) if ms == self.last_id.ms => {
+                StreamId::new(ms, self.last_id.seq.checked_add(1)?)
+            }
+            // 0-0 is never a valid ID
+            NewStreamId::Partial(ms) => StreamId::new(ms, (ms == 0) as u64),
+            NewStreamId::Explicit(id) => id,
+        };
+
+        match id > self.last_id {
+            true => Some(id),
+            false => None,
+        }
+    }
+
+    /// append an entry, whose ID must be greater than the last one
+    pub fn add(&mut self, id: StreamId, fields: StreamFields) {
+        debug_assert!(id > self.last_id);
+
+        self.entries.insert(id, fields);
+        self.last_id = id;
+        self.entries_added += 1;
+    }
+
+    /// entries with an ID between `start` and `end` included, from the last one when `rev` is
+    /// set, up to `count` of them
+    pub fn range(
+        &self,
+        start: StreamId,
+        end: StreamId,
+        count: Option<usize>,
+        rev: bool,
+    ) -> Vec<(&StreamId, &StreamFields)> {
+        if start > end {
+            return vec![];
+        }
+
+        let entries = self.entries.range(start..=end);
+        let count = count.unwrap_or(usize::MAX);
+        match rev {
+            true => entries.rev().take(count).collect(),
+            false => entries.take(count).collect(),
+        }
+    }
+
+    /// Read from the group `group` as `consumer` at `now`, `None` if the group does not exist.
+    /// Entries never delivered to the group are read when `id` is `None` (`>`), and recorded as
+    /// pending for `consumer` unless `no_ack` is set. Otherwise the entries pending for
+    /// `consumer` with an ID greater than `id` are delivered again.
+    pub fn read_group(
+        &mut self,
+        group: &[u8],
+        consumer: &[u8],
+        id: Option<StreamId>,
+        count: Option<usize>,
+        no_ack: bool,
+        now: u64,
+    ) -> Option<Vec<ConsumerEntry>> {
+        let group_name = group;
+        let group = self.groups.get_mut(group_name)?;
+        group.touch_consumer(consumer, now);
+        let count = count.unwrap_or(usize::MAX);
+
+        let id = match id {
+            Some(id) => id,
+            None => {
+                let entries = match group.last_delivered_id.next() {
+                    Some(start) => self
+                        .entries
+                        .range(start..)
+                        .take(count)
+                        .map(|(id, fields)| (*id, Some(fields.clone())))
+                        .collect::<Vec<_>>(),
+                    None => vec![],
+                };
+
+                for (id, _) in &entries {
+                    group.last_delivered_id = *id;
+                    if !no_ack {
+                        group.deliver(*id, consumer, now);
+                    }
+                }
+                if !entries.is_empty() {
+                    group.consumers.get_mut(consumer).unwrap().active_time = Some(now);
+                    let last_delivered_id = group.last_delivered_id;
+                    let entries_read = self.entries_read_until(last_delivered_id);
+                    self.groups.get_mut(group_name).unwrap().entries_read = entries_read;
+                }
+                return Some(entries);
+            }
+        };
+
+        let ids = match id.next() {
+            Some(start) => group.consumers[consumer]
+                .pending
+                .range(start..)
+                .take(count)
+                .copied()
+                .collect::<Vec<_>>(),
+            None => vec![],
+        };
+
+        let stream_entries = &self.entries;
+        let entries = ids
+            .into_iter()
+            .map(|id| {
+                let entry = group.pending.get_mut(&id).unwrap();
+                entry.delivery_time = now;
+                entry.delivery_count += 1;
+                (id, stream_entries.get(&id).cloned())
+            })
+            .collect();
+        Some(entries)
+    }
+
+    /// delete entry `id`, false if it does not exist - it stays pending in the groups it has been
+    /// delivered by
+    pub fn delete(&mut self, id: &StreamId) -> bool {
+        match self.entries.remove(id) {
+            Some(_) => {
+                self.max_deleted_id = self.max_deleted_id.max(*id);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// remove the oldest entries according to `trim`, returning the number of removed entries
+    pub fn trim(&mut self, trim: StreamTrim) -> usize {
+        let mut count = match trim.strategy {
+            TrimStrategy::MaxLen(max_len) => self.entries.len().saturating_sub(max_len),
+            TrimStrategy::MinId(min_id) => self.entries.range(..min_id).count(),
+        };
+
+        if trim.approximate {
+            // a limit of 0 means no limit, the default one being 100 nodes
+            let limit = trim.limit.unwrap_or(100 * STREAM_NODE_MAX_ENTRIES);
+            if limit > 0 {
+                count = count.min(limit);
+            }
+            count -= count % STREAM_NODE_MAX_ENTRIES;
+        }
+
+        for _ in 0..count {
+            if let Some((id, _)) = self.entries.pop_first() {
+                self.max_deleted_id = self.max_deleted_id.max(id);
+            }
+        }
+        count
+    }
+
+    /// fields of entry `id`
+    pub fn get(&self, id: &StreamId) -> Option<&StreamFields> {
+        self.entries.get(id)
+    }
+
+    /// claim entry `id` of the group `group` for `consumer` at `now` according to `options`,
+    /// `None` if the group does not exist
+    pub fn claim(
+        &mut self,
+        group: &[u8],
+        consumer: &[u8],
+        id: StreamId,
+        now: u64,
+        options: &ClaimOptions,
+    ) -> Option<Claim> {
+        let group = self.groups.get_mut(group)?;
+        let fields = self.entries.get(&id);
+
+        let delivery_count = match (group.pending.get(&id), fields) {
+            (Some(_), None) => {
+                group.ack(id);
+                return Some(Claim::Deleted(id));
+            }
+            (Some(entry), Some(_))
+                if now.saturating_sub(entry.delivery_time) >= options.min_idle =>
+            {
+                entry.delivery_count
+            }
+            (None, Some(_)) if options.force => 0,
+            _ => return Some(Claim::Skipped),
+        };
+
+        let delivery_time = match (options.time, options.idle) {
+            (Some(time), _) => time,
+            (None, Some(idle)) => now.saturating_sub(idle),
+            (None, None) => now,
+        };
+        let delivery_count = match options.retry_count {
+            Some(retry_count) => retry_count,
+            None if options.just_id => delivery_count,
+            None => delivery_count + 1,
+        };
+
+        group.assign(id, consumer, delivery_time, delivery_count);
+        group.consumers.get_mut(consumer).unwrap().active_time = Some(now);
+        Some(Claim::Claimed(id, fields.unwrap().clone()))
+    }
+
+    /// Claim up to `count` pending entries of the group `group` from `start` for `consumer` like
+    /// `claim`, examining at most ten times as many. Return the ID to continue from, 0-0 once
+    /// the end of the pending entry list is reached, along with the claimed and deleted entries,
+    /// or `None` if the group does not exist.
+    pub fn auto_claim(
+        &mut self,
+        group: &[u8],
+        consumer: &[u8],
+        start: StreamId,
+        count: usize,
+        now: u64,
+        options: &ClaimOptions,
+    ) -> Option<(StreamId, Vec<Claim>)> {
+        let mut cursor = Some(start);
+        let mut attempts = count.saturating_mul(10);
+        let mut claims = vec![];
+
+        while let Some(start) = cursor {
+            if claims.len() == count || attempts == 0 {
+                break;
+            }
+
+            let id = match self.groups.get(group)?.pending.range(start..).next() {
+                Some((id, _)) => *id,
+                None => {
+                    cursor = None;
+                    break;
+                }
+            };
+            attempts -= 1;
+            cursor = id.next();
+
+            match self.claim(group, consumer, id, now, options)? {
+                Claim::Skipped => {}
+                claim => claims.push(claim),
+            }
+        }
+
+        let pending = &self.groups.get(group)?.pending;
+        let next = cursor
+            .and_then(|start| pending.range(start..).next())
+            .map_or(StreamId::MIN, |(id, _)| *id);
+        Some((next, claims))
+    }
+
+    pub fn allocated_memory(&self) -> usize {
+        self.entries.len() * size_of::<(StreamId, StreamFields)>()
+            + self
+                .entries
+                .values()
+                .flatten()
+                .map(|(field, value)| {
+                    size_of::<(RedisString, RedisString)>() + field.capacity() + value.capacity()
+                })
+                .sum::<usize>()
+            + self
+                .groups
+                .iter()
+                .map(|(name, group)| {
+                    size_of::<(RedisString, ConsumerGroup)>()
+                        + name.capacity()
+                        + group.pending.len() * size_of::<(StreamId, PendingEntry)>()
+                        + group.consumers.len() * size_of::<(RedisString, StreamConsumer)>()
+                })
+                .sum::<usize>()
+    }
+
+    pub fn shrink_to_fit(&mut self) {
+        self.entries
+            .values_mut()
+            .for_each(|fields| fields.shrink_to_fit());
+    }
+}
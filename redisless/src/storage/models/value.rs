@@ -0,0 +1,27 @@
+use super::{
+    RedisHashMap, RedisList, RedisSet, RedisSortedSet, RedisStream, RedisString, RedisType,
+};
+
+/// Owned value of a key, whatever its type
+#[derive(Debug, PartialEq, Clone)]
+pub enum RedisValue {
+    String(RedisString),
+    Hash(RedisHashMap),
+    List(RedisList),
+    Set(RedisSet),
+    ZSet(RedisSortedSet),
+    Stream(RedisStream),
+}
+
+impl RedisValue {
+    pub fn data_type(&self) -> RedisType {
+        match self {
+            RedisValue::String(_) => RedisType::String,
+            RedisValue::Hash(_) => RedisType::Hash,
+            RedisValue::List(_) => RedisType::List,
+            RedisValue::Set(_) => RedisType::Set,
+            RedisValue::ZSet(_) => RedisType::ZSet,
+            RedisValue::Stream(_) => RedisType::Stream,
+        }
+    }
+}
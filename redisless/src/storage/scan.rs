@@ -0,0 +1,45 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::Hasher;
+
+pub const DEFAULT_SCAN_COUNT: usize = 10;
+
+/// Position of `key` in the cursor space.
+///
+/// Cursors are not tied to the underlying `HashMap` layout (which changes on rehashing) but to a
+/// stable hash of the keys, so an element present during a full iteration is always returned.
+pub fn cursor_position(key: &[u8]) -> u64 {
+    // `DefaultHasher::new()` always uses the same keys, the hash is stable across calls
+    let mut hasher = DefaultHasher::new();
+    hasher.write(key);
+    hasher.finish()
+}
+
+/// Select at most `count` items (more when several items share the same position) positioned at
+/// or after `cursor`. Return the cursor to resume the iteration from - `0` when it is complete.
+pub fn scan<'a, T, I>(items: I, cursor: u64, count: usize) -> (u64, Vec<T>)
+where
+    I: Iterator<Item = (&'a [u8], T)>,
+{
+    let mut items = items
+        .map(|(key, item)| (cursor_position(key), item))
+        .filter(|(position, _)| *position >= cursor)
+        .collect::<Vec<_>>();
+
+    items.sort_by_key(|(position, _)| *position);
+
+    let count = count.max(1);
+    let mut end = count.min(items.len());
+    // never split items sharing the same position, they could not be resumed otherwise
+    while end > 0 && end < items.len() && items[end].0 == items[end - 1].0 {
+        end += 1;
+    }
+
+    let next_cursor = if end == items.len() { 0 } else { items[end].0 };
+
+    items.truncate(end);
+
+    (
+        next_cursor,
+        items.into_iter().map(|(_, item)| item).collect(),
+    )
+}
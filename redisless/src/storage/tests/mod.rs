@@ -1,5 +1,8 @@
 use std::{thread::sleep, time::Duration};
 
+use crate::storage::models::bitmap::{self, BitRange, BitUnit};
+use crate::storage::models::geo;
+use crate::storage::models::{DefragReport, HyperLogLog};
 use crate::storage::Storage;
 use crate::storage::{in_memory::InMemoryStorage, models::Expiry};
 
@@ -25,6 +28,10 @@ fn test_dbsize() {
     mem.write(b"key", b"xxx");
     mem.write(b"key2", b"xxx");
     assert_eq!(mem.size(), 2);
+
+    // expired keys are not counted even before being evicted
+    mem.expire(b"key2", Expiry::new_from_millis(0).unwrap());
+    assert_eq!(mem.size(), 1);
 }
 
 #[test]
@@ -52,14 +59,38 @@ fn test_expire() {
     }
 }
 
+#[test]
+fn expired_keys() {
+    let mut mem = InMemoryStorage::new();
+    mem.write(b"key", b"xxx");
+    mem.write(b"key2", b"xxx");
+    mem.write(b"key3", b"xxx");
+    mem.expire(b"key", Expiry::new_from_millis(0).unwrap());
+    mem.expire(b"key2", Expiry::new_from_millis(0).unwrap());
+
+    // removed when accessed
+    assert_eq!(mem.read(b"key"), None);
+    assert_eq!(mem.take_expired_keys(), vec![(0, b"key".to_vec())]);
+    assert!(mem.take_expired_keys().is_empty());
+
+    // or when looked for
+    mem.remove_expired_keys();
+    assert_eq!(mem.take_expired_keys(), vec![(0, b"key2".to_vec())]);
+    assert_eq!(mem.size(), 1);
+
+    // deleted keys did not expire
+    mem.remove(b"key3");
+    assert!(mem.take_expired_keys().is_empty());
+}
+
 #[test]
 fn contains() {
     let mut mem = InMemoryStorage::new();
     mem.write(b"key1", b"value1");
     let x = mem.contains(b"key1");
-    assert_eq!(x, true);
+    assert!(x);
     let x = mem.contains(b"key2");
-    assert_eq!(x, false);
+    assert!(!x);
 }
 
 #[test]
@@ -75,3 +106,1052 @@ fn extend() {
     assert_eq!(len, 8);
     assert_eq!(x, b"value222");
 }
+
+#[test]
+fn keys() {
+    let mut mem = InMemoryStorage::new();
+    mem.write(b"user:1:name", b"alice");
+    mem.write(b"user:2:name", b"bob");
+    mem.write(b"user:2:email", b"bob@example.com");
+    mem.write(b"session", b"xxx");
+
+    let mut keys = mem.keys(b"user:*:name");
+    keys.sort();
+    assert_eq!(keys, vec![b"user:1:name".to_vec(), b"user:2:name".to_vec()]);
+    assert_eq!(mem.keys(b"*").len(), 4);
+    assert_eq!(mem.keys(b"sess?on"), vec![b"session".to_vec()]);
+    assert_eq!(mem.keys(b"nothing*").len(), 0);
+}
+
+#[test]
+fn scan() {
+    let mut mem = InMemoryStorage::new();
+    for i in 0..100 {
+        mem.write(format!("key{}", i).as_bytes(), b"value");
+    }
+
+    let mut cursor = 0;
+    let mut keys = vec![];
+    loop {
+        let (next_cursor, mut page) = mem.scan(cursor, 7, None, None);
+        assert!(page.len() <= 7);
+        keys.append(&mut page);
+
+        // modifying the keyspace between two calls must not invalidate the cursor
+        mem.write(format!("other{}", cursor).as_bytes(), b"value");
+        mem.remove(format!("other{}", cursor).as_bytes());
+
+        if next_cursor == 0 {
+            break;
+        }
+        cursor = next_cursor;
+    }
+
+    keys.sort();
+    keys.dedup();
+    assert_eq!(keys.len(), 100);
+
+    let (_, keys) = mem.scan(0, 1000, Some(b"key1?"), None);
+    assert_eq!(keys.len(), 10);
+    let (_, keys) = mem.scan(0, 1000, None, Some(b"hash"));
+    assert_eq!(keys.len(), 0);
+    let (_, keys) = mem.scan(0, 1000, None, Some(b"string"));
+    assert_eq!(keys.len(), 100);
+}
+
+#[test]
+fn random_key() {
+    let mut mem = InMemoryStorage::new();
+    assert_eq!(mem.random_key(), None);
+
+    mem.write(b"key1", b"value1");
+    assert_eq!(mem.random_key(), Some(b"key1".to_vec()));
+
+    mem.write(b"key2", b"value2");
+    let mut seen = std::collections::HashSet::new();
+    for _ in 0..100 {
+        seen.insert(mem.random_key().unwrap());
+    }
+    assert_eq!(seen.len(), 2);
+}
+
+#[test]
+fn defrag() {
+    let mut mem = InMemoryStorage::new();
+    for i in 0..1000 {
+        mem.write(format!("key{}", i).as_bytes(), b"value");
+    }
+    for i in 0..990 {
+        mem.remove(format!("key{}", i).as_bytes());
+    }
+
+    let allocated = mem.allocated_memory();
+    let mut report = DefragReport::default();
+    let mut cursor = 0;
+    loop {
+        let (next_cursor, batch_report) = mem.defrag(0, cursor, 3);
+        report += batch_report;
+        if next_cursor == 0 {
+            break;
+        }
+        // the tables holding the keys are only shrunk once they have all been gone through
+        assert_eq!(batch_report.freed(), 0);
+        cursor = next_cursor;
+    }
+    assert_eq!(allocated - mem.allocated_memory(), report.freed());
+    assert!(report.allocated_after < report.allocated_before);
+
+    // nothing left to shrink
+    let (cursor, report) = mem.defrag(0, 0, 100);
+    assert_eq!(cursor, 0);
+    assert_eq!(report.freed(), 0);
+    assert_eq!(mem.read(b"key999"), Some(&b"value"[..]));
+
+    // no such database
+    assert_eq!(mem.defrag(16, 0, 100), (0, DefragReport::default()));
+}
+
+#[test]
+fn rename() {
+    let mut mem = InMemoryStorage::new();
+    mem.write(b"key1", b"value1");
+    mem.expire(b"key1", Expiry::new_from_secs(100).unwrap());
+    mem.write(b"key2", b"value2");
+
+    assert!(mem.rename(b"key1", b"key2"));
+    assert_eq!(mem.read(b"key1"), None);
+    assert_eq!(mem.read(b"key2"), Some(&b"value1"[..]));
+    // the expiry moves along with the value
+    assert!(mem.meta(b"key2").unwrap().expiry.is_some());
+    assert_eq!(mem.size(), 1);
+
+    assert!(!mem.rename(b"key1", b"key3"));
+    assert!(mem.rename(b"key2", b"key2"));
+    assert_eq!(mem.read(b"key2"), Some(&b"value1"[..]));
+}
+
+#[test]
+fn flush() {
+    let mut mem = InMemoryStorage::new();
+    mem.write(b"key1", b"value1");
+    mem.write(b"key2", b"value2");
+    mem.flush(false);
+    assert_eq!(mem.size(), 0);
+    assert_eq!(mem.read(b"key1"), None);
+
+    mem.write(b"key1", b"value1");
+    mem.flush(true);
+    assert_eq!(mem.size(), 0);
+    assert_eq!(mem.read(b"key1"), None);
+}
+
+#[test]
+fn touch() {
+    let mut mem = InMemoryStorage::new();
+    mem.write(b"key1", b"value1");
+    let last_access = mem.meta(b"key1").unwrap().last_access;
+
+    sleep(Duration::from_millis(5));
+    assert!(mem.touch(b"key1"));
+    assert!(mem.meta(b"key1").unwrap().last_access > last_access);
+    assert!(!mem.touch(b"key2"));
+}
+
+#[test]
+fn unlink() {
+    let mut mem = InMemoryStorage::new();
+    let big_value = vec![b'x'; 1024 * 1024];
+    mem.write(b"big", &big_value);
+    mem.write(b"small", b"value");
+
+    assert_eq!(mem.unlink(b"big"), 1);
+    assert_eq!(mem.unlink(b"small"), 1);
+    assert_eq!(mem.unlink(b"missing"), 0);
+    assert_eq!(mem.read(b"big"), None);
+    assert_eq!(mem.size(), 0);
+}
+
+#[test]
+fn copy() {
+    let mut mem = InMemoryStorage::new();
+    mem.write(b"key1", b"value1");
+    mem.expire(b"key1", Expiry::new_from_secs(100).unwrap());
+
+    assert!(mem.copy(b"key1", b"key2"));
+    assert_eq!(mem.read(b"key1"), Some(&b"value1"[..]));
+    assert_eq!(mem.read(b"key2"), Some(&b"value1"[..]));
+    assert_eq!(
+        mem.meta(b"key1").unwrap().expiry,
+        mem.meta(b"key2").unwrap().expiry
+    );
+
+    // the copy is independent from the source
+    mem.extend(b"key2", b"+");
+    assert_eq!(mem.read(b"key1"), Some(&b"value1"[..]));
+    assert!(!mem.copy(b"missing", b"key3"));
+}
+
+#[test]
+fn dump_and_restore() {
+    use crate::storage::dump::{crc64, deserialize, serialize, PayloadError};
+    use crate::storage::models::{ListEnd, RedisStream, RedisValue};
+    use std::collections::HashMap;
+
+    // check value of the CRC64 Jones variant used by Redis
+    assert_eq!(crc64(b"123456789"), 0xe9c6d914c4b8d9ca);
+
+    // `DUMP mykey` from Redis 6 after `SET mykey 10`
+    let payload = b"\x00\xc0\n\t\x00\xbem\x06\x89Z(\x00\n";
+    assert_eq!(deserialize(payload), Ok(RedisValue::String(b"10".to_vec())));
+
+    // LZF compressed string: one literal followed by a back reference
+    let mut payload = b"\x00\xc3\x05\x0a\x00a\xe0\x00\x00\x09\x00".to_vec();
+    payload.extend_from_slice(&crc64(&payload).to_le_bytes());
+    assert_eq!(
+        deserialize(&payload),
+        Ok(RedisValue::String(vec![b'a'; 10]))
+    );
+
+    let mut mem = InMemoryStorage::new();
+    mem.write(b"string", b"value");
+    let mut hash = HashMap::new();
+    hash.insert(b"field".to_vec(), vec![b'x'; 20_000]);
+    mem.hwrite(b"hash", hash);
+    let list = mem.list_mut(b"list").unwrap();
+    list.push(ListEnd::Right, b"first".to_vec());
+    list.push(ListEnd::Right, b"12".to_vec());
+    mem.set_mut(b"set").unwrap().data.insert(b"member".to_vec());
+    let zset = mem.zset_mut(b"zset").unwrap();
+    zset.insert(b"one".to_vec(), 1.0);
+    zset.insert(b"minus".to_vec(), -2.5);
+    zset.insert(b"infinite".to_vec(), f64::INFINITY);
+
+    for key in [&b"string"[..], b"hash", b"list", b"set", b"zset"] {
+        let value = mem.value(key).unwrap();
+        let payload = serialize(&value).unwrap();
+        assert_eq!(deserialize(&payload), Ok(value.clone()));
+
+        mem.restore(b"restored", value.clone(), None);
+        assert_eq!(mem.value(b"restored"), Some(value));
+    }
+    assert_eq!(mem.value(b"missing"), None);
+    assert_eq!(serialize(&RedisValue::Stream(RedisStream::new())), None);
+
+    let mut payload = serialize(&RedisValue::String(b"value".to_vec())).unwrap();
+    payload[2] = b'V';
+    assert_eq!(deserialize(&payload), Err(PayloadError::VersionOrChecksum));
+    assert_eq!(deserialize(b"short"), Err(PayloadError::VersionOrChecksum));
+}
+
+#[test]
+fn restore_compact_encodings() {
+    use crate::storage::dump::{crc64, deserialize, PayloadError};
+    use crate::storage::models::{RedisHashMap, RedisList, RedisSet, RedisSortedSet, RedisValue};
+
+    // payload of `value` with the footer of the RDB `version`
+    let payload = |value: &[u8], version: u16| {
+        let mut payload = value.to_vec();
+        payload.extend_from_slice(&version.to_le_bytes());
+        payload.extend_from_slice(&crc64(&payload).to_le_bytes());
+        payload
+    };
+    let strings = |strings: &[&str]| {
+        strings
+            .iter()
+            .map(|string| string.as_bytes().to_vec())
+            .collect::<Vec<_>>()
+    };
+    let list = |elements: &[&str]| RedisValue::List(RedisList::new(strings(elements).into()));
+    let set =
+        |members: &[&str]| RedisValue::Set(RedisSet::new(strings(members).into_iter().collect()));
+
+    // listpack of "a", "b" and "c"
+    let listpack = b"\x10\x00\x00\x00\x03\x00\x81a\x02\x81b\x02\x81c\x02\xff";
+    // quicklist of a single packed node, from Redis 7
+    let mut value = b"\x12\x01\x02\x10".to_vec();
+    value.extend_from_slice(listpack);
+    assert_eq!(
+        deserialize(&payload(&value, 10)),
+        Ok(list(&["a", "b", "c"]))
+    );
+    assert_eq!(
+        deserialize(&payload(&value, 12)),
+        Ok(list(&["a", "b", "c"]))
+    );
+    assert_eq!(
+        deserialize(&payload(&value, 13)),
+        Err(PayloadError::VersionOrChecksum)
+    );
+    // a plain node holds a single large element
+    let mut value = b"\x12\x02\x01\x03big\x02\x10".to_vec();
+    value.extend_from_slice(listpack);
+    assert_eq!(
+        deserialize(&payload(&value, 11)),
+        Ok(list(&["big", "a", "b", "c"]))
+    );
+
+    // ziplist of "a", 5 and "hello", in a quicklist from Redis 6 and as is from Redis 3
+    let ziplist = b"\x17\x00\x00\x00\x0f\x00\x00\x00\x03\x00\x00\x01a\x03\xf6\x02\x05hello\xff";
+    let mut value = b"\x0e\x01\x17".to_vec();
+    value.extend_from_slice(ziplist);
+    assert_eq!(
+        deserialize(&payload(&value, 9)),
+        Ok(list(&["a", "5", "hello"]))
+    );
+    let mut value = b"\x0a\x17".to_vec();
+    value.extend_from_slice(ziplist);
+    assert_eq!(
+        deserialize(&payload(&value, 6)),
+        Ok(list(&["a", "5", "hello"]))
+    );
+
+    // intset of the 16 bits integers 1, 2 and -3
+    let value = b"\x0b\x0e\x02\x00\x00\x00\x03\x00\x00\x00\x01\x00\x02\x00\xfd\xff";
+    assert_eq!(deserialize(&payload(value, 9)), Ok(set(&["1", "2", "-3"])));
+    // listpack of "x", 7 and -1
+    let value = b"\x14\x0f\x0f\x00\x00\x00\x03\x00\x81x\x02\x07\x01\xdf\xff\x02\xff";
+    assert_eq!(deserialize(&payload(value, 11)), Ok(set(&["x", "7", "-1"])));
+
+    let mut data = std::collections::HashMap::new();
+    data.insert(b"k".to_vec(), b"v".to_vec());
+    let hash = RedisValue::Hash(RedisHashMap::new(data));
+    let value = b"\x0d\x11\x11\x00\x00\x00\x0d\x00\x00\x00\x02\x00\x00\x01k\x03\x01v\xff";
+    assert_eq!(deserialize(&payload(value, 9)), Ok(hash.clone()));
+    let value = b"\x10\x0d\x0d\x00\x00\x00\x02\x00\x81k\x02\x81v\x02\xff";
+    assert_eq!(deserialize(&payload(value, 10)), Ok(hash));
+
+    // a member with a 16 bits integer score
+    let mut zset = RedisSortedSet::new();
+    zset.insert(b"m".to_vec(), -200.0);
+    let value = b"\x0c\x12\x12\x00\x00\x00\x0d\x00\x00\x00\x02\x00\x00\x01m\x03\xc0\x38\xff\xff";
+    assert_eq!(deserialize(&payload(value, 9)), Ok(RedisValue::ZSet(zset)));
+    let mut zset = RedisSortedSet::new();
+    zset.insert(b"a".to_vec(), 1.0);
+    zset.insert(b"b".to_vec(), 2.5);
+    let value = b"\x11\x14\x14\x00\x00\x00\x04\x00\x81a\x02\x01\x01\x81b\x02\x832.5\x04\xff";
+    assert_eq!(
+        deserialize(&payload(value, 10)),
+        Ok(RedisValue::ZSet(zset.clone()))
+    );
+    // scores stored as strings, from Redis 3
+    zset.insert(b"c".to_vec(), f64::INFINITY);
+    let value = b"\x03\x03\x01a\x011\x01b\x032.5\x01c\xfe";
+    assert_eq!(deserialize(&payload(value, 6)), Ok(RedisValue::ZSet(zset)));
+
+    // the listpack ends before its last entry
+    let value = b"\x10\x0b\x0d\x00\x00\x00\x02\x00\x81k\x02\x81v";
+    assert_eq!(
+        deserialize(&payload(value, 10)),
+        Err(PayloadError::BadDataFormat)
+    );
+}
+
+#[test]
+fn object_metadata() {
+    let mut mem = InMemoryStorage::new();
+    mem.write(b"key", b"value");
+    mem.hwrite(b"hash", std::collections::HashMap::new());
+
+    assert_eq!(mem.encoding(b"key"), Some("raw"));
+    assert_eq!(mem.encoding(b"hash"), Some("hashtable"));
+    assert_eq!(mem.encoding(b"missing"), None);
+
+    assert_eq!(mem.meta(b"key").unwrap().access_frequency(), 5);
+    for _ in 0..1000 {
+        mem.read(b"key");
+    }
+    // the access frequency grows logarithmically
+    let access_frequency = mem.meta(b"key").unwrap().access_frequency();
+    assert!(access_frequency > 5 && access_frequency < 50);
+    assert!(mem.meta(b"key").unwrap().idle_millis() < 1000);
+}
+
+#[test]
+fn databases() {
+    let mut mem = InMemoryStorage::with_databases(4);
+    assert_eq!(mem.databases(), 4);
+
+    mem.write(b"key", b"db0");
+    assert!(mem.select(3));
+    assert!(!mem.contains(b"key"));
+    mem.write(b"key", b"db3");
+    assert_eq!(mem.size(), 1);
+    assert!(!mem.select(4));
+
+    assert!(mem.select(0));
+    assert_eq!(mem.read(b"key"), Some(&b"db0"[..]));
+    mem.flush(false);
+    assert_eq!(mem.size(), 0);
+
+    mem.select(3);
+    assert_eq!(mem.read(b"key"), Some(&b"db3"[..]));
+    mem.flush_all(false);
+    assert_eq!(mem.size(), 0);
+
+    assert_eq!(InMemoryStorage::new().databases(), 16);
+}
+
+#[test]
+fn swap_db() {
+    let mut mem = InMemoryStorage::with_databases(2);
+    mem.write(b"key", b"db0");
+
+    assert!(mem.swap_db(0, 1));
+    assert!(!mem.contains(b"key"));
+    mem.select(1);
+    assert_eq!(mem.read(b"key"), Some(&b"db0"[..]));
+
+    assert!(!mem.swap_db(0, 2));
+}
+
+#[test]
+fn list() {
+    use crate::storage::models::ListEnd;
+
+    let mut mem = InMemoryStorage::new();
+    assert!(mem.list(b"list").is_none());
+
+    let list = mem.list_mut(b"list").unwrap();
+    list.push(ListEnd::Left, b"b".to_vec());
+    list.push(ListEnd::Left, b"a".to_vec());
+    list.push(ListEnd::Right, b"c".to_vec());
+    assert_eq!(mem.list(b"list").unwrap().data, [b"a", b"b", b"c"]);
+    assert_eq!(mem.meta(b"list").unwrap().data_type.name(), "list");
+    assert_eq!(mem.encoding(b"list"), Some("quicklist"));
+    assert_eq!(mem.key_info(b"list").unwrap().elements, 3);
+
+    assert!(mem.copy(b"list", b"copy"));
+    let copy = mem.list_mut(b"copy").unwrap();
+    assert_eq!(copy.pop(ListEnd::Right), Some(b"c".to_vec()));
+    assert_eq!(copy.pop(ListEnd::Left), Some(b"a".to_vec()));
+    assert_eq!(mem.list(b"list").unwrap().data.len(), 3);
+
+    assert!(mem.rename(b"list", b"renamed"));
+    assert!(mem.list(b"list").is_none());
+    assert_eq!(mem.list(b"renamed").unwrap().data.len(), 3);
+
+    // not a list
+    mem.write(b"string", b"value");
+    assert!(mem.list(b"string").is_none());
+    assert!(mem.list_mut(b"string").is_none());
+
+    assert_eq!(mem.remove(b"renamed"), 1);
+    assert_eq!(mem.unlink(b"copy"), 1);
+    assert_eq!(mem.size(), 1);
+}
+
+#[test]
+fn list_range() {
+    use crate::storage::models::RedisList;
+
+    let list = RedisList::new((0..5).map(|i| vec![i]).collect());
+    assert_eq!(list.range(0, -1), Some(0..=4));
+    assert_eq!(list.range(1, 2), Some(1..=2));
+    assert_eq!(list.range(-3, -2), Some(2..=3));
+    assert_eq!(list.range(-100, 100), Some(0..=4));
+    assert_eq!(list.range(3, 1), None);
+    assert_eq!(list.range(5, 10), None);
+    assert_eq!(list.range(0, -6), None);
+    assert_eq!(RedisList::default().range(0, -1), None);
+
+    assert_eq!(list.index(0), Some(0));
+    assert_eq!(list.index(4), Some(4));
+    assert_eq!(list.index(-1), Some(4));
+    assert_eq!(list.index(-5), Some(0));
+    assert_eq!(list.index(5), None);
+    assert_eq!(list.index(-6), None);
+}
+
+#[test]
+fn sorted_set() {
+    use crate::storage::models::ScoreEnd;
+
+    let mut mem = InMemoryStorage::new();
+    assert!(mem.zset(b"zset").is_none());
+
+    let zset = mem.zset_mut(b"zset").unwrap();
+    assert!(zset.insert(b"c".to_vec(), 1.0));
+    assert!(zset.insert(b"b".to_vec(), 1.0));
+    assert!(zset.insert(b"a".to_vec(), 2.0));
+    // update
+    assert!(!zset.insert(b"a".to_vec(), -1.0));
+    assert_eq!(zset.len(), 3);
+    assert_eq!(zset.score(b"a"), Some(-1.0));
+    assert_eq!(zset.score(b"d"), None);
+
+    // ordered by score then by member
+    let members = zset.iter().map(|(m, _)| m.clone()).collect::<Vec<_>>();
+    assert_eq!(members, [b"a", b"b", b"c"]);
+
+    assert_eq!(zset.rank(b"a"), Some(0));
+    assert_eq!(zset.rank(b"c"), Some(2));
+    assert_eq!(zset.rank(b"d"), None);
+
+    assert_eq!(zset.remove(b"b"), Some(1.0));
+    assert_eq!(zset.remove(b"b"), None);
+    assert_eq!(zset.iter().count(), 2);
+
+    let mut popped = zset.clone();
+    assert_eq!(popped.pop(ScoreEnd::Max), Some((b"c".to_vec(), 1.0)));
+    assert_eq!(popped.pop(ScoreEnd::Min), Some((b"a".to_vec(), -1.0)));
+    assert_eq!(popped.pop(ScoreEnd::Min), None);
+    assert!(popped.is_empty());
+
+    assert_eq!(mem.meta(b"zset").unwrap().data_type.name(), "zset");
+    assert_eq!(mem.encoding(b"zset"), Some("skiplist"));
+    assert_eq!(mem.key_info(b"zset").unwrap().elements, 2);
+
+    assert!(mem.copy(b"zset", b"copy"));
+    assert!(mem.rename(b"zset", b"renamed"));
+    let copy = mem.zset(b"copy").cloned();
+    assert_eq!(mem.zset(b"renamed").cloned(), copy);
+
+    mem.write(b"string", b"value");
+    assert!(mem.zset(b"string").is_none());
+    assert!(mem.zset_mut(b"string").is_none());
+
+    assert_eq!(mem.remove(b"renamed"), 1);
+    assert_eq!(mem.unlink(b"copy"), 1);
+    assert_eq!(mem.size(), 1);
+}
+
+#[test]
+fn sorted_set_query() {
+    use crate::storage::models::{LexBound, RedisSortedSet, ScoreBound, ZRangeBy, ZRangeQuery};
+
+    let mut zset = RedisSortedSet::new();
+    for (i, member) in [b"a", b"b", b"c", b"d", b"e"].iter().enumerate() {
+        zset.insert(member.to_vec(), i as f64);
+    }
+    let members = |query: ZRangeQuery| {
+        zset.query(&query)
+            .into_iter()
+            .map(|(m, _)| m.clone())
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+        members(ZRangeQuery::new(ZRangeBy::Index(0, -1), false)).len(),
+        5
+    );
+    assert_eq!(
+        members(ZRangeQuery::new(ZRangeBy::Index(1, 2), false)),
+        [b"b", b"c"]
+    );
+    assert_eq!(
+        members(ZRangeQuery::new(ZRangeBy::Index(0, 1), true)),
+        [b"e", b"d"]
+    );
+    assert_eq!(
+        members(ZRangeQuery::new(ZRangeBy::Index(-2, 100), false)),
+        [b"d", b"e"]
+    );
+    assert!(members(ZRangeQuery::new(ZRangeBy::Index(3, 1), false)).is_empty());
+
+    let mut query = ZRangeQuery::new(ZRangeBy::Index(0, -1), false);
+    query.limit = Some((1, 2));
+    assert_eq!(members(query.clone()), [b"b", b"c"]);
+    query.limit = Some((3, -1));
+    assert_eq!(members(query.clone()), [b"d", b"e"]);
+    query.limit = Some((-1, 2));
+    assert!(members(query).is_empty());
+
+    let bound = |value, exclusive| ScoreBound { value, exclusive };
+    let by_score = ZRangeBy::Score(bound(1.0, false), bound(3.0, true));
+    assert_eq!(members(ZRangeQuery::new(by_score, false)), [b"b", b"c"]);
+    let by_score = ZRangeBy::Score(bound(f64::NEG_INFINITY, false), bound(1.0, false));
+    assert_eq!(members(ZRangeQuery::new(by_score, true)), [b"b", b"a"]);
+    let by_score = ZRangeBy::Score(bound(2.0, true), bound(2.0, false));
+    assert!(members(ZRangeQuery::new(by_score, false)).is_empty());
+
+    let mut query = ZRangeQuery::new(ZRangeBy::Score(bound(0.5, false), bound(10.0, false)), true);
+    query.limit = Some((1, 2));
+    assert_eq!(members(query), [b"d", b"c"]);
+
+    let mut zset = RedisSortedSet::new();
+    for member in [b"a", b"b", b"c", b"d", b"e"] {
+        zset.insert(member.to_vec(), 0.0);
+    }
+    let members = |by: ZRangeBy, rev: bool| {
+        zset.query(&ZRangeQuery::new(by, rev))
+            .into_iter()
+            .map(|(m, _)| m.clone())
+            .collect::<Vec<_>>()
+    };
+    let by_lex = ZRangeBy::Lex(
+        LexBound::Inclusive(b"b".to_vec()),
+        LexBound::Exclusive(b"d".to_vec()),
+    );
+    assert_eq!(members(by_lex.clone(), false), [b"b", b"c"]);
+    assert_eq!(members(by_lex, true), [b"c", b"b"]);
+    assert_eq!(
+        members(ZRangeBy::Lex(LexBound::Min, LexBound::Max), false).len(),
+        5
+    );
+    assert_eq!(
+        members(
+            ZRangeBy::Lex(LexBound::Exclusive(b"d".to_vec()), LexBound::Max),
+            false
+        ),
+        [b"e"]
+    );
+    assert!(members(ZRangeBy::Lex(LexBound::Max, LexBound::Min), false).is_empty());
+}
+
+#[test]
+fn string_mut() {
+    let mut mem = InMemoryStorage::new();
+    mem.write(b"key1", b"value");
+    mem.expire(b"key1", Expiry::new_from_secs(100).unwrap());
+    mem.string_mut(b"key1").unwrap().push(b'1');
+    assert_eq!(mem.read(b"key1").unwrap(), b"value1");
+    // the value is modified in place, keeping its expiry
+    assert!(mem.meta(b"key1").unwrap().expiry.is_some());
+
+    mem.string_mut(b"key2").unwrap().extend_from_slice(b"new");
+    assert_eq!(mem.read(b"key2").unwrap(), b"new");
+
+    mem.set_mut(b"set").unwrap().data.insert(b"member".to_vec());
+    assert!(mem.string_mut(b"set").is_none());
+}
+
+#[test]
+fn bitmap() {
+    let mut bytes = vec![];
+    assert_eq!(bitmap::set_bit(&mut bytes, 10, 1), 0);
+    assert_eq!(bytes, vec![0x00, 0x20]);
+    assert_eq!(bitmap::get_bit(&bytes, 10), 1);
+    assert_eq!(bitmap::get_bit(&bytes, 100), 0);
+
+    let bytes = b"\xff\xf0\x00";
+    let range = |start, end, unit| BitRange { start, end, unit }.bits(bytes.len());
+    assert_eq!(range(0, -1, BitUnit::Byte), Some((0, 23)));
+    assert_eq!(range(-2, 10, BitUnit::Byte), Some((8, 23)));
+    assert_eq!(range(5, -3, BitUnit::Bit), Some((5, 21)));
+    assert_eq!(range(2, 1, BitUnit::Byte), None);
+    assert_eq!(range(3, 5, BitUnit::Byte), None);
+
+    assert_eq!(bitmap::count_bits(bytes, 0, 23), 12);
+    assert_eq!(bitmap::count_bits(bytes, 5, 9), 5);
+    assert_eq!(bitmap::count_bits(bytes, 10, 15), 2);
+    assert_eq!(bitmap::position(bytes, 0, 0, 23), Some(12));
+    assert_eq!(bitmap::position(bytes, 1, 13, 23), None);
+    assert_eq!(bitmap::position(bytes, 1, 3, 23), Some(3));
+}
+
+#[test]
+fn hyperloglog() {
+    let mut hll = HyperLogLog::new();
+    assert_eq!(hll.count(), 0);
+    assert!(hll.add(b"element"));
+    assert!(!hll.add(b"element"));
+    assert_eq!(hll.count(), 1);
+
+    for i in 0..100_000 {
+        hll.add(format!("element{}", i).as_bytes());
+    }
+    let error = (hll.count() as f64 - 100_001.0).abs() / 100_001.0;
+    assert!(error < 0.03, "error of {}", error);
+
+    let mut other = HyperLogLog::new();
+    for i in 50_000..150_000 {
+        other.add(format!("element{}", i).as_bytes());
+    }
+    hll.merge(&other);
+    let error = (hll.count() as f64 - 150_001.0).abs() / 150_001.0;
+    assert!(error < 0.03, "error of {}", error);
+
+    let bytes = hll.to_bytes();
+    assert_eq!(HyperLogLog::from_bytes(&bytes), Some(hll));
+    assert_eq!(HyperLogLog::from_bytes(b"HYLL"), None);
+    assert_eq!(HyperLogLog::from_bytes(&bytes[1..]), None);
+}
+
+#[test]
+fn stream() {
+    use crate::storage::models::{NewStreamId, StreamId};
+
+    let mut mem = InMemoryStorage::new();
+    let stream = mem.stream_mut(b"stream").unwrap();
+    assert_eq!(
+        stream.next_id(NewStreamId::Auto, 5),
+        Some(StreamId::new(5, 0))
+    );
+    stream.add(StreamId::new(5, 0), vec![(b"f".to_vec(), b"1".to_vec())]);
+
+    // generated IDs never go backwards, even when the clock does
+    assert_eq!(
+        stream.next_id(NewStreamId::Auto, 3),
+        Some(StreamId::new(5, 1))
+    );
+    assert_eq!(
+        stream.next_id(NewStreamId::Partial(5), 0),
+        Some(StreamId::new(5, 1))
+    );
+    assert_eq!(
+        stream.next_id(NewStreamId::Partial(7), 0),
+        Some(StreamId::new(7, 0))
+    );
+    assert_eq!(stream.next_id(NewStreamId::Partial(4), 0), None);
+    assert_eq!(
+        stream.next_id(NewStreamId::Explicit(StreamId::new(5, 0)), 0),
+        None
+    );
+
+    stream.add(StreamId::new(5, 1), vec![(b"f".to_vec(), b"2".to_vec())]);
+    stream.add(StreamId::new(9, 0), vec![(b"f".to_vec(), b"3".to_vec())]);
+    let ids =
+        |entries: Vec<(&StreamId, _)>| entries.into_iter().map(|(id, _)| *id).collect::<Vec<_>>();
+    assert_eq!(
+        ids(stream.range(StreamId::MIN, StreamId::MAX, None, false)),
+        vec![
+            StreamId::new(5, 0),
+            StreamId::new(5, 1),
+            StreamId::new(9, 0)
+        ]
+    );
+    assert_eq!(
+        ids(stream.range(StreamId::new(5, 1), StreamId::MAX, Some(1), true)),
+        vec![StreamId::new(9, 0)]
+    );
+    assert!(stream
+        .range(StreamId::new(9, 0), StreamId::new(5, 0), None, false)
+        .is_empty());
+
+    assert!(mem.copy(b"stream", b"copy"));
+    assert!(mem.rename(b"copy", b"renamed"));
+    assert_eq!(mem.stream(b"renamed").unwrap().len(), 3);
+    assert_eq!(
+        mem.stream(b"renamed").unwrap().last_id(),
+        StreamId::new(9, 0)
+    );
+    assert_eq!(mem.encoding(b"renamed"), Some("stream"));
+    mem.write(b"string", b"value");
+    assert!(mem.stream_mut(b"string").is_none());
+}
+
+#[test]
+fn stream_consumer_groups() {
+    use crate::storage::models::{ConsumerGroup, RedisStream, StreamId};
+
+    let mut stream = RedisStream::new();
+    for ms in 1..=3 {
+        stream.add(StreamId::new(ms, 0), vec![(b"f".to_vec(), b"v".to_vec())]);
+    }
+    stream.groups.insert(
+        b"group".to_vec(),
+        ConsumerGroup::new(StreamId::MIN, Some(0)),
+    );
+    assert_eq!(
+        stream.read_group(b"missing", b"alice", None, None, false, 10),
+        None
+    );
+
+    // new entries are delivered once within the group
+    let entries = stream
+        .read_group(b"group", b"alice", None, Some(2), false, 10)
+        .unwrap();
+    let ids = entries.iter().map(|(id, _)| *id).collect::<Vec<_>>();
+    assert_eq!(ids, vec![StreamId::new(1, 0), StreamId::new(2, 0)]);
+    let entries = stream
+        .read_group(b"group", b"bob", None, None, false, 20)
+        .unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].0, StreamId::new(3, 0));
+    assert!(stream
+        .read_group(b"group", b"bob", None, None, false, 20)
+        .unwrap()
+        .is_empty());
+
+    let group = &stream.groups[b"group".as_ref()];
+    assert_eq!(group.last_delivered_id, StreamId::new(3, 0));
+    assert_eq!(group.pending.len(), 3);
+    assert_eq!(group.consumers[b"alice".as_ref()].pending.len(), 2);
+    assert_eq!(
+        group.pending[&StreamId::new(3, 0)].consumer,
+        b"bob".to_vec()
+    );
+
+    // pending entries are delivered again from a given ID
+    let entries = stream
+        .read_group(
+            b"group",
+            b"alice",
+            Some(StreamId::new(1, 0)),
+            None,
+            false,
+            30,
+        )
+        .unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].0, StreamId::new(2, 0));
+    let pending = &stream.groups[b"group".as_ref()].pending[&StreamId::new(2, 0)];
+    assert_eq!(pending.delivery_count, 2);
+    assert_eq!(pending.delivery_time, 30);
+
+    let group = stream.groups.get_mut(b"group".as_ref()).unwrap();
+    assert!(group.ack(StreamId::new(1, 0)));
+    assert!(!group.ack(StreamId::new(1, 0)));
+    assert_eq!(group.consumers[b"alice".as_ref()].pending.len(), 1);
+    assert!(!group.create_consumer(b"alice", 40));
+    assert!(group.create_consumer(b"carol", 40));
+    assert_eq!(group.delete_consumer(b"alice"), Some(1));
+    assert_eq!(group.delete_consumer(b"alice"), None);
+    assert_eq!(group.pending.len(), 1);
+
+    // NOACK reads leave the pending entry list alone
+    group.last_delivered_id = StreamId::MIN;
+    let entries = stream
+        .read_group(b"group", b"carol", None, None, true, 50)
+        .unwrap();
+    assert_eq!(entries.len(), 3);
+    assert_eq!(stream.groups[b"group".as_ref()].pending.len(), 1);
+}
+
+#[test]
+fn stream_trim() {
+    use crate::storage::models::{RedisStream, StreamId, StreamTrim, TrimStrategy};
+
+    let mut stream = RedisStream::new();
+    for ms in 1..=250 {
+        stream.add(StreamId::new(ms, 0), vec![(b"f".to_vec(), b"v".to_vec())]);
+    }
+    let first_id =
+        |stream: &RedisStream| *stream.range(StreamId::MIN, StreamId::MAX, Some(1), false)[0].0;
+
+    // approximate trimming removes whole nodes of 100 entries
+    let trim = StreamTrim {
+        strategy: TrimStrategy::MaxLen(140),
+        approximate: true,
+        limit: None,
+    };
+    assert_eq!(stream.trim(trim), 100);
+    assert_eq!(stream.len(), 150);
+    assert_eq!(first_id(&stream), StreamId::new(101, 0));
+    assert_eq!(stream.trim(trim), 0);
+
+    let trim = StreamTrim {
+        strategy: TrimStrategy::MinId(StreamId::new(240, 0)),
+        approximate: true,
+        limit: Some(50),
+    };
+    assert_eq!(stream.trim(trim), 0);
+    let trim = StreamTrim {
+        limit: Some(0),
+        ..trim
+    };
+    assert_eq!(stream.trim(trim), 100);
+    assert_eq!(first_id(&stream), StreamId::new(201, 0));
+
+    let trim = StreamTrim {
+        strategy: TrimStrategy::MinId(StreamId::new(240, 0)),
+        approximate: false,
+        limit: None,
+    };
+    assert_eq!(stream.trim(trim), 39);
+    assert_eq!(first_id(&stream), StreamId::new(240, 0));
+    let trim = StreamTrim {
+        strategy: TrimStrategy::MaxLen(1),
+        ..trim
+    };
+    assert_eq!(stream.trim(trim), 10);
+    assert_eq!(first_id(&stream), StreamId::new(250, 0));
+
+    // the last ID is kept even if its entry is gone
+    assert!(stream.delete(&StreamId::new(250, 0)));
+    assert!(!stream.delete(&StreamId::new(250, 0)));
+    assert!(stream.is_empty());
+    assert_eq!(stream.last_id(), StreamId::new(250, 0));
+}
+
+#[test]
+fn stream_group_lag() {
+    use crate::storage::models::{ConsumerGroup, RedisStream, StreamId, StreamTrim, TrimStrategy};
+
+    let mut stream = RedisStream::new();
+    for ms in 1..=5 {
+        stream.add(StreamId::new(ms, 0), vec![(b"f".to_vec(), b"v".to_vec())]);
+    }
+    assert_eq!(stream.entries_read_until(StreamId::MIN), Some(0));
+    assert_eq!(stream.entries_read_until(StreamId::new(2, 0)), Some(2));
+    assert_eq!(stream.entries_read_until(StreamId::new(5, 0)), Some(5));
+
+    let group = ConsumerGroup::new(StreamId::new(2, 0), Some(2));
+    assert_eq!(stream.lag(&group), Some(3));
+    let group = ConsumerGroup::new(StreamId::new(2, 0), None);
+    assert_eq!(stream.lag(&group), None);
+    let group = ConsumerGroup::new(StreamId::new(5, 0), None);
+    assert_eq!(stream.lag(&group), Some(0));
+
+    // the oldest entries being trimmed only, the entries before the first one have all been read
+    stream.trim(StreamTrim {
+        strategy: TrimStrategy::MaxLen(3),
+        approximate: false,
+        limit: None,
+    });
+    assert_eq!(stream.max_deleted_id(), StreamId::new(2, 0));
+    assert_eq!(stream.entries_read_until(StreamId::new(1, 0)), Some(2));
+    assert_eq!(stream.entries_read_until(StreamId::new(3, 0)), None);
+
+    stream.delete(&StreamId::new(4, 0));
+    assert_eq!(stream.max_deleted_id(), StreamId::new(4, 0));
+    assert_eq!(stream.entries_added(), 5);
+    assert_eq!(stream.node_count(), 1);
+    assert_eq!(*stream.first_entry().unwrap().0, StreamId::new(3, 0));
+    assert_eq!(*stream.last_entry().unwrap().0, StreamId::new(5, 0));
+}
+
+#[test]
+fn geo() {
+    let palermo = (13.361389, 38.115556);
+    let catania = (15.087269, 37.502669);
+
+    // decoding gives back the center of the area, within less than a meter
+    let (longitude, latitude) = geo::decode(geo::encode(palermo.0, palermo.1));
+    assert!((longitude - palermo.0).abs() < 0.00001);
+    assert!((latitude - palermo.1).abs() < 0.00001);
+    assert_eq!(geo::encode(palermo.0, palermo.1), 3479099956230698);
+
+    // distances are computed between the stored positions, as redis does
+    let stored = |(longitude, latitude)| geo::decode(geo::encode(longitude, latitude));
+    assert!((geo::distance(stored(palermo), stored(catania)) - 166274.1516).abs() < 0.0001);
+    assert_eq!(geo::distance(palermo, palermo), 0.0);
+
+    assert_eq!(geo::geohash_string(palermo.0, palermo.1), "sqc8b49rny0");
+    assert_eq!(geo::geohash_string(catania.0, catania.1), "sqdtr74hyu0");
+
+    assert!(geo::is_valid(180.0, 85.05112878));
+    assert!(!geo::is_valid(180.1, 0.0));
+    assert!(!geo::is_valid(0.0, -86.0));
+}
+
+#[test]
+fn geo_search() {
+    use crate::storage::models::geo::{GeoOrigin, GeoQuery, GeoShape, GeoSort, GeoUnit};
+    use crate::storage::models::RedisSortedSet;
+
+    let mut zset = RedisSortedSet::new();
+    for (longitude, latitude, member) in [
+        (13.361389, 38.115556, "Palermo"),
+        (15.087269, 37.502669, "Catania"),
+        (12.758489, 38.788135, "edge1"),
+        (17.241510, 38.788135, "edge2"),
+    ] {
+        zset.insert(
+            member.as_bytes().to_vec(),
+            geo::encode(longitude, latitude) as f64,
+        );
+    }
+
+    let mut query = GeoQuery {
+        origin: GeoOrigin::Position(15.0, 37.0),
+        shape: GeoShape::Radius(200_000.0),
+        unit: GeoUnit::Kilometers,
+        sort: Some(GeoSort::Asc),
+        count: None,
+        any: false,
+        with_coord: false,
+        with_dist: false,
+        with_hash: false,
+    };
+    let members = |query: &GeoQuery| {
+        geo::search(&zset, (15.0, 37.0), query)
+            .into_iter()
+            .map(|found| String::from_utf8(found.member.clone()).unwrap())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(members(&query), vec!["Catania", "Palermo"]);
+
+    // the corners of a box are further than half its sides
+    query.shape = GeoShape::Box(400_000.0, 400_000.0);
+    assert_eq!(
+        members(&query),
+        vec!["Catania", "Palermo", "edge2", "edge1"]
+    );
+    query.sort = Some(GeoSort::Desc);
+    assert_eq!(
+        members(&query),
+        vec!["edge1", "edge2", "Palermo", "Catania"]
+    );
+
+    // counting the members keeps the nearest ones unless any will do
+    query.sort = None;
+    query.count = Some(2);
+    assert_eq!(members(&query), vec!["Catania", "Palermo"]);
+    query.any = true;
+    assert_eq!(members(&query).len(), 2);
+
+    assert_eq!(
+        GeoShape::Radius(100.0).distance((15.0, 37.0), (15.0, 37.0)),
+        Some(0.0)
+    );
+    assert_eq!(
+        GeoShape::Box(100.0, 100.0).distance((15.0, 37.0), (15.0, 38.0)),
+        None
+    );
+}
+
+#[test]
+fn overwrite_hash() {
+    let mut mem = InMemoryStorage::new();
+    let mut hash = std::collections::HashMap::new();
+    hash.insert(b"field".to_vec(), b"value".to_vec());
+    mem.hwrite(b"key", hash);
+
+    // the hash is gone along with its field once the key is set to a string
+    mem.write(b"key", b"value");
+    assert!(mem.hash_mut(b"key").is_none());
+    assert_eq!(mem.hread(b"key", b"field"), None);
+    assert_eq!(mem.read(b"key"), Some(&b"value"[..]));
+
+    mem.hwrite(b"key", std::collections::HashMap::new());
+    assert_eq!(mem.read(b"key"), None);
+    assert!(mem.hash_mut(b"key").is_some());
+}
+
+#[test]
+fn overwrite_list() {
+    use crate::storage::models::ListEnd;
+
+    let mut mem = InMemoryStorage::new();
+    let list = mem.list_mut(b"key").unwrap();
+    list.push(ListEnd::Right, b"a".to_vec());
+    list.push(ListEnd::Right, b"b".to_vec());
+
+    // the list is gone once the key is set to a string
+    mem.write(b"key", b"value");
+    assert!(mem.list_mut(b"key").is_none());
+    assert!(mem.list(b"key").is_none());
+    assert_eq!(mem.key_info(b"key").unwrap().elements, 1);
+
+    mem.remove(b"key");
+    assert_eq!(mem.list_mut(b"key").unwrap().data.len(), 0);
+}
+
+#[test]
+fn overwrite_set() {
+    let mut mem = InMemoryStorage::new();
+    mem.set_mut(b"key").unwrap().data.insert(b"a".to_vec());
+
+    // the set is gone once the key is set to a string
+    mem.write(b"key", b"value");
+    assert!(mem.set_mut(b"key").is_none());
+    assert!(mem.set(b"key").is_none());
+
+    mem.remove(b"key");
+    assert!(mem.set_mut(b"key").unwrap().data.is_empty());
+}
+
+#[test]
+fn overwrite_zset() {
+    let mut mem = InMemoryStorage::new();
+    mem.zset_mut(b"key").unwrap().insert(b"a".to_vec(), 1.0);
+
+    // the sorted set is gone once the key is set to a string
+    mem.write(b"key", b"value");
+    assert!(mem.zset_mut(b"key").is_none());
+    assert!(mem.zset(b"key").is_none());
+
+    mem.remove(b"key");
+    assert_eq!(mem.zset_mut(b"key").unwrap().len(), 0);
+}
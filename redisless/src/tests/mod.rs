@@ -2,13 +2,14 @@ use std::io::{Read, Write};
 use std::net::TcpStream;
 
 use crate::{
-    redisless_server_free, redisless_server_new, redisless_server_start, redisless_server_stop,
+    redisless_key_info, redisless_server_free, redisless_server_new, redisless_server_start,
+    redisless_server_stop, RedisLessKeyInfo,
 };
 
 #[test]
 #[serial]
 fn start_and_stop_server_from_c_binding() {
-    let port = 4444 as u16;
+    let port = 4444_u16;
     let server = unsafe { redisless_server_new(port) };
 
     unsafe {
@@ -44,9 +45,11 @@ fn start_and_stop_server_from_c_binding() {
 
         // run command `INFO`
         let _ = stream.write(b"*1\r\n$4\r\nINFO\r\n");
-        let mut info_res = [0; 6];
-        let _ = stream.read(&mut info_res);
-        assert_eq!(info_res, b"$0\r\n\r\n"[..]);
+        let mut info_res = [0; 512];
+        let info_length = stream.read(&mut info_res).unwrap();
+        let info = String::from_utf8_lossy(&info_res[..info_length]);
+        assert!(info.starts_with('$'));
+        assert!(info.contains("command_queue_depth:0\r\n"));
     }
 
     unsafe {
@@ -54,3 +57,46 @@ fn start_and_stop_server_from_c_binding() {
         redisless_server_free(server);
     }
 }
+
+#[test]
+#[serial]
+fn key_info_from_c_binding() {
+    let port = 4445_u16;
+    let server = unsafe { redisless_server_new(port) };
+
+    unsafe {
+        assert!(redisless_server_start(server), "server didn't start");
+    }
+
+    let mut stream = TcpStream::connect(format!("localhost:{}", port)).unwrap();
+
+    // run command `SET mykey value`
+    let _ = stream.write(b"*3\r\n$3\r\nSET\r\n$5\r\nmykey\r\n$5\r\nvalue\r\n");
+    let mut set_res = [0; 5];
+    let _ = stream.read(&mut set_res);
+    assert_eq!(set_res, b"+OK\r\n"[..]);
+
+    let mut info = RedisLessKeyInfo {
+        key_type: 0,
+        ttl: 0,
+        elements: 0,
+        size: 0,
+    };
+
+    let key = b"mykey";
+    assert!(unsafe { redisless_key_info(server, key.as_ptr(), key.len(), &mut info) });
+    assert_eq!(info.key_type, 1);
+    assert_eq!(info.ttl, -1);
+    assert_eq!(info.elements, 1);
+    assert!(info.size >= 10);
+
+    let key = b"missing";
+    assert!(!unsafe { redisless_key_info(server, key.as_ptr(), key.len(), &mut info) });
+    assert_eq!(info.key_type, 0);
+    assert_eq!(info.ttl, -2);
+
+    unsafe {
+        assert!(redisless_server_stop(server), "server didn't stop");
+        redisless_server_free(server);
+    }
+}